@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+pub use anyhow::{bail, Result};
+
+#[derive(Error, Debug)]
+pub enum AlgebraicError {
+    #[error("Malformed input, {0}")]
+    MalformedInput(String),
+
+    #[error("Unknown error, `{0}`")]
+    Unknown(String),
+}
+
+impl From<String> for AlgebraicError {
+    fn from(e: String) -> Self {
+        AlgebraicError::Unknown(e)
+    }
+}
@@ -14,6 +14,7 @@ extern crate pairing;
 extern crate rand;
 
 pub mod circom_circuit;
+pub mod errors;
 pub mod r1cs_file;
 pub mod reader;
 pub mod witness;
@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use byteorder::{LittleEndian, ReadBytesExt};
 use itertools::Itertools;
 use std::collections::BTreeMap;
@@ -9,6 +9,7 @@ use std::str;
 use ff::PrimeField;
 
 use crate::circom_circuit::{CircuitJson, R1CS};
+use crate::errors::AlgebraicError;
 
 /// get universal setup file by filename
 #[allow(dead_code)]
@@ -17,8 +18,21 @@ fn get_universal_setup_file_buff_reader(setup_file_name: &str) -> Result<BufRead
     Ok(BufReader::with_capacity(1 << 29, setup_file))
 }
 
+/// open the file, a missing file keeps the `std::io::Error` in the chain
+fn open_file(filename: &str) -> Result<BufReader<File>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .with_context(|| format!("Unable to open {}", filename))?;
+    Ok(BufReader::new(reader))
+}
+
+fn malformed(filename: &str, e: impl std::fmt::Display) -> anyhow::Error {
+    AlgebraicError::MalformedInput(format!("{}, {}", filename, e)).into()
+}
+
 /// load witness file by filename with autodetect encoding (bin or json).
-pub fn load_witness_from_file<E: PrimeField>(filename: &str) -> Vec<E> {
+pub fn load_witness_from_file<E: PrimeField>(filename: &str) -> Result<Vec<E>> {
     if filename.ends_with("json") {
         load_witness_from_json_file::<E>(filename)
     } else {
@@ -27,31 +41,24 @@ pub fn load_witness_from_file<E: PrimeField>(filename: &str) -> Vec<E> {
 }
 
 /// load witness from json file by filename
-pub fn load_witness_from_json_file<E: PrimeField>(filename: &str) -> Vec<E> {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_witness_from_json::<E, BufReader<File>>(BufReader::new(reader))
+pub fn load_witness_from_json_file<E: PrimeField>(filename: &str) -> Result<Vec<E>> {
+    load_witness_from_json::<E, BufReader<File>>(open_file(filename)?)
+        .map_err(|e| malformed(filename, e))
 }
 
 /// load witness from json by a reader
-fn load_witness_from_json<E: PrimeField, R: Read>(reader: R) -> Vec<E> {
-    let witness: Vec<String> = serde_json::from_reader(reader).expect("Unable to read.");
+fn load_witness_from_json<E: PrimeField, R: Read>(reader: R) -> Result<Vec<E>> {
+    let witness: Vec<String> = serde_json::from_reader(reader)?;
     witness
         .into_iter()
-        .map(|x| E::from_str_vartime(&x).unwrap())
-        .collect::<Vec<E>>()
+        .map(|x| E::from_str_vartime(&x).ok_or_else(|| anyhow!("invalid field element {}", x)))
+        .collect()
 }
 
 /// load witness from bin file by filename
-pub fn load_witness_from_bin_file<E: PrimeField>(filename: &str) -> Vec<E> {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_witness_from_bin_reader::<E, BufReader<File>>(BufReader::new(reader))
-        .expect("read witness failed")
+pub fn load_witness_from_bin_file<E: PrimeField>(filename: &str) -> Result<Vec<E>> {
+    load_witness_from_bin_reader::<E, BufReader<File>>(open_file(filename)?)
+        .map_err(|e| malformed(filename, e))
 }
 
 /// load witness from u8 array
@@ -101,7 +108,7 @@ pub fn load_witness_from_bin_reader<E: PrimeField, R: Read>(mut reader: R) -> Re
         bail!("invalid section type".to_string());
     }
     let sec_size = reader.read_u64::<LittleEndian>()?;
-    if sec_size != (witness_len * field_size) as u64 {
+    if sec_size != witness_len as u64 * field_size as u64 {
         bail!(format!("Invalid witness section size {}", sec_size));
     }
     let mut result = Vec::with_capacity(witness_len as usize);
@@ -124,50 +131,60 @@ pub fn load_witness_from_bin_reader<E: PrimeField, R: Read>(mut reader: R) -> Re
 }
 
 /// load r1cs file by filename with autodetect encoding (bin or json)
-pub fn load_r1cs<E: PrimeField>(filename: &str) -> R1CS<E> {
+pub fn load_r1cs<E: PrimeField>(filename: &str) -> Result<R1CS<E>> {
     if filename.ends_with("json") {
         load_r1cs_from_json_file(filename)
     } else {
-        let (r1cs, _wire_mapping) = load_r1cs_from_bin_file(filename);
-        r1cs
+        let (r1cs, _wire_mapping) = load_r1cs_from_bin_file(filename)?;
+        Ok(r1cs)
     }
 }
 
 /// load r1cs from json file by filename
-fn load_r1cs_from_json_file<E: PrimeField>(filename: &str) -> R1CS<E> {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_r1cs_from_json(BufReader::new(reader))
+fn load_r1cs_from_json_file<E: PrimeField>(filename: &str) -> Result<R1CS<E>> {
+    load_r1cs_from_json(open_file(filename)?).map_err(|e| malformed(filename, e))
 }
 
 /// load r1cs from json by a reader
-fn load_r1cs_from_json<E: PrimeField, R: Read>(reader: R) -> R1CS<E> {
-    let circuit_json: CircuitJson = serde_json::from_reader(reader).expect("Unable to read.");
+fn load_r1cs_from_json<E: PrimeField, R: Read>(reader: R) -> Result<R1CS<E>> {
+    let circuit_json: CircuitJson = serde_json::from_reader(reader)?;
 
     let num_inputs = circuit_json.num_inputs + circuit_json.num_outputs + 1;
-    let num_aux = circuit_json.num_variables - num_inputs;
+    let num_aux = circuit_json
+        .num_variables
+        .checked_sub(num_inputs)
+        .ok_or_else(|| {
+            anyhow!(
+                "the inputs exceed the {} variables",
+                circuit_json.num_variables
+            )
+        })?;
 
-    let convert_constraint = |lc: &BTreeMap<String, String>| {
+    let convert_constraint = |lc: &BTreeMap<String, String>| -> Result<Vec<(usize, E)>> {
         lc.iter()
-            .map(|(index, coeff)| (index.parse().unwrap(), E::from_str_vartime(coeff).unwrap()))
-            .collect_vec()
+            .map(|(index, coeff)| {
+                Ok((
+                    index.parse()?,
+                    E::from_str_vartime(coeff)
+                        .ok_or_else(|| anyhow!("invalid coefficient {}", coeff))?,
+                ))
+            })
+            .collect()
     };
 
     let constraints = circuit_json
         .constraints
         .iter()
         .map(|c| {
-            (
-                convert_constraint(&c[0]),
-                convert_constraint(&c[1]),
-                convert_constraint(&c[2]),
-            )
+            Ok((
+                convert_constraint(&c[0])?,
+                convert_constraint(&c[1])?,
+                convert_constraint(&c[2])?,
+            ))
         })
-        .collect_vec();
+        .collect::<Result<Vec<_>>>()?;
 
-    R1CS {
+    Ok(R1CS {
         num_inputs,
         num_aux,
         num_variables: circuit_json.num_variables,
@@ -175,25 +192,29 @@ fn load_r1cs_from_json<E: PrimeField, R: Read>(reader: R) -> R1CS<E> {
         constraints,
         custom_gates: vec![],
         custom_gates_uses: vec![],
-    }
+    })
 }
 
 /// load r1cs from bin file by filename
-fn load_r1cs_from_bin_file<E: PrimeField>(filename: &str) -> (R1CS<E>, Vec<usize>) {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_r1cs_from_bin(BufReader::new(reader))
+fn load_r1cs_from_bin_file<E: PrimeField>(filename: &str) -> Result<(R1CS<E>, Vec<usize>)> {
+    load_r1cs_from_bin(open_file(filename)?).with_context(|| format!("Unable to load {}", filename))
 }
 
 /// load r1cs from bin by a reader
-pub fn load_r1cs_from_bin<R: Read + Seek, E: PrimeField>(reader: R) -> (R1CS<E>, Vec<usize>) {
-    let file = crate::r1cs_file::from_reader::<R, E>(reader).expect("Unable to read.");
+pub fn load_r1cs_from_bin<R: Read + Seek, E: PrimeField>(
+    reader: R,
+) -> Result<(R1CS<E>, Vec<usize>)> {
+    let file = crate::r1cs_file::from_reader::<R, E>(reader)
+        .map_err(|e| AlgebraicError::MalformedInput(format!("r1cs, {}", e)))?;
     let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
     let num_variables = file.header.n_wires as usize;
-    let num_aux = num_variables - num_inputs;
-    (
+    let num_aux = num_variables.checked_sub(num_inputs).ok_or_else(|| {
+        AlgebraicError::MalformedInput(format!(
+            "r1cs, the inputs exceed the {} wires",
+            num_variables
+        ))
+    })?;
+    Ok((
         R1CS {
             num_aux,
             num_inputs,
@@ -204,5 +225,5 @@ pub fn load_r1cs_from_bin<R: Read + Seek, E: PrimeField>(reader: R) -> (R1CS<E>,
             custom_gates_uses: file.custom_gates_uses,
         },
         file.wire_mapping.iter().map(|e| *e as usize).collect_vec(),
-    )
+    ))
 }
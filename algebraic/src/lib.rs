@@ -12,6 +12,9 @@ extern crate num_traits;
 extern crate rand;
 
 pub mod circom_circuit;
+pub mod errors;
+pub mod r1cs_analysis;
+pub mod r1cs_check;
 pub mod r1cs_file;
 pub mod reader;
 pub mod utils;
@@ -0,0 +1,272 @@
+//! Static analysis of the R1CS: the statistics of the circuit, and the signals which are not, or
+//! hardly, constrained, like a signal assigned by `<--` and never used in a constraint.
+use crate::bellman_ce::ScalarEngine;
+use crate::circom_circuit::R1CS;
+use crate::errors::{AlgebraicError, Result};
+use crate::r1cs_check::load_sym;
+use crate::reader::load_r1cs;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// a signal of the circuit, named by the `.sym` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signal {
+    pub wire: usize,
+    pub name: String,
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (w{})", self.name, self.wire)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct R1csAnalysis {
+    pub n_constraints: usize,
+    /// the constraints with a constant `A` or `B`
+    pub n_linear: usize,
+    pub n_quadratic: usize,
+    /// the wires, including the constant one
+    pub n_variables: usize,
+    pub n_public_inputs: usize,
+    pub n_outputs: usize,
+    /// the private inputs and the internal signals
+    pub n_private: usize,
+    /// the custom gates and their number of uses
+    pub custom_gates: BTreeMap<String, usize>,
+    /// the signals in no constraint nor custom gate
+    pub unused_signals: Vec<Signal>,
+    /// the private signals occurring once, linearly, in the constraints, which are therefore
+    /// fixed by a single constraint and used by no other
+    pub under_constrained_signals: Vec<Signal>,
+}
+
+impl fmt::Display for R1csAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "constraints: {} ({} linear, {} quadratic)",
+            self.n_constraints, self.n_linear, self.n_quadratic
+        )?;
+        writeln!(
+            f,
+            "variables: {} ({} public inputs, {} outputs, {} private)",
+            self.n_variables, self.n_public_inputs, self.n_outputs, self.n_private
+        )?;
+        for (template, n) in self.custom_gates.iter() {
+            writeln!(f, "custom gate {}: {} uses", template, n)?;
+        }
+        writeln!(f, "unused signals: {}", self.unused_signals.len())?;
+        for s in self.unused_signals.iter() {
+            writeln!(f, "    {}", s)?;
+        }
+        write!(
+            f,
+            "under-constrained signals: {}",
+            self.under_constrained_signals.len()
+        )?;
+        for s in self.under_constrained_signals.iter() {
+            write!(f, "\n    {}", s)?;
+        }
+        Ok(())
+    }
+}
+
+/// Analyse the R1CS, naming the signals by the wire names of `load_sym`
+pub fn analyse_r1cs<E: ScalarEngine>(
+    r1cs: &R1CS<E>,
+    names: &HashMap<usize, String>,
+) -> Result<R1csAnalysis> {
+    // the inputs are the constant one, the outputs and the public inputs
+    let n_public_inputs = r1cs
+        .num_inputs
+        .checked_sub(1 + r1cs.num_outputs)
+        .ok_or_else(|| {
+            AlgebraicError::MalformedInput(format!(
+                "r1cs of {} inputs and {} outputs",
+                r1cs.num_inputs, r1cs.num_outputs
+            ))
+        })?;
+    let mut analysis = R1csAnalysis {
+        n_constraints: r1cs.constraints.len(),
+        n_variables: r1cs.num_variables,
+        n_public_inputs,
+        n_outputs: r1cs.num_outputs,
+        n_private: r1cs.num_aux,
+        ..Default::default()
+    };
+
+    // the occurrences of each wire, and whether the last one is linear
+    let mut occurrences = vec![(0usize, false); r1cs.num_variables];
+    let is_constant = |lc: &[(usize, E::Fr)]| lc.iter().all(|(wire, _)| *wire == 0);
+    for (a, b, c) in r1cs.constraints.iter() {
+        let linear = is_constant(a) || is_constant(b);
+        if linear {
+            analysis.n_linear += 1;
+        } else {
+            analysis.n_quadratic += 1;
+        }
+        for (lc, linear) in [(a, linear), (b, linear), (c, true)] {
+            for (wire, _) in lc.iter() {
+                if let Some(o) = occurrences.get_mut(*wire) {
+                    *o = (o.0 + 1, linear);
+                }
+            }
+        }
+    }
+    for u in r1cs.custom_gates_uses.iter() {
+        let template = match r1cs.custom_gates.get(u.id as usize) {
+            Some(gate) => gate.template_name.clone(),
+            None => format!("custom gate {}", u.id),
+        };
+        *analysis.custom_gates.entry(template).or_insert(0) += 1;
+        for wire in u.signals.iter() {
+            if let Some(o) = occurrences.get_mut(*wire as usize) {
+                // the custom gate fixes the signal by itself
+                *o = (o.0 + 1, false);
+            }
+        }
+    }
+
+    let signal = |wire: usize| Signal {
+        wire,
+        name: names
+            .get(&wire)
+            .cloned()
+            .unwrap_or_else(|| format!("w{}", wire)),
+    };
+    for (wire, (n, linear)) in occurrences.into_iter().enumerate().skip(1) {
+        if n == 0 {
+            analysis.unused_signals.push(signal(wire));
+        } else if n == 1 && linear && wire >= r1cs.num_inputs {
+            analysis.under_constrained_signals.push(signal(wire));
+        }
+    }
+    Ok(analysis)
+}
+
+/// Analyse the r1cs file, naming the signals by the `.sym` file if any, and write the analysis
+/// into the json file if any.
+pub fn analyse<E: ScalarEngine>(
+    r1cs_file: &str,
+    sym_file: Option<&str>,
+    json_file: Option<&str>,
+) -> Result<R1csAnalysis> {
+    let r1cs = load_r1cs::<E>(r1cs_file)?;
+    let names = match sym_file {
+        Some(sym_file) => load_sym(sym_file)?,
+        None => HashMap::new(),
+    };
+    let analysis = analyse_r1cs(&r1cs, &names)?;
+    if let Some(json_file) = json_file {
+        std::fs::write(json_file, serde_json::to_string_pretty(&analysis)?)?;
+    }
+    Ok(analysis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::bn256::{Bn256, Fr};
+    use crate::bellman_ce::{Field, PrimeField};
+    use crate::circom_circuit::{CustomGates, CustomGatesUses};
+
+    #[test]
+    fn test_analyse_multiplier() {
+        let sym_file = "/tmp/test_r1cs_analysis.sym";
+        std::fs::write(sym_file, "1,1,0,main.c\n2,2,0,main.a\n3,3,0,main.b\n").unwrap();
+        let json_file = "/tmp/test_r1cs_analysis.json";
+        let analysis = analyse::<Bn256>(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs"),
+            Some(sym_file),
+            Some(json_file),
+        )
+        .unwrap();
+        assert_eq!(
+            (
+                analysis.n_constraints,
+                analysis.n_linear,
+                analysis.n_quadratic
+            ),
+            (1, 0, 1)
+        );
+        assert_eq!(
+            (
+                analysis.n_variables,
+                analysis.n_public_inputs,
+                analysis.n_outputs,
+                analysis.n_private
+            ),
+            (4, 0, 1, 2)
+        );
+        assert!(analysis.unused_signals.is_empty());
+        assert!(analysis.under_constrained_signals.is_empty());
+
+        let json = std::fs::read_to_string(json_file).unwrap();
+        assert_eq!(
+            serde_json::from_str::<R1csAnalysis>(&json).unwrap(),
+            analysis
+        );
+    }
+
+    #[test]
+    fn test_analyse_unconstrained() {
+        let one = Fr::one();
+        // out = a * b, x = 2 * a + 1, y is unused, z is only in a custom gate
+        let r1cs = R1CS::<Bn256> {
+            num_inputs: 2,
+            num_aux: 5,
+            num_variables: 7,
+            num_outputs: 1,
+            constraints: vec![
+                (vec![(2, one)], vec![(3, one)], vec![(1, one)]),
+                (
+                    vec![(0, one)],
+                    vec![(2, Fr::from_str("2").unwrap()), (0, one)],
+                    vec![(4, one)],
+                ),
+            ],
+            custom_gates: vec![CustomGates {
+                template_name: "CMul".to_string(),
+                parameters: vec![],
+            }],
+            custom_gates_uses: vec![CustomGatesUses {
+                id: 0,
+                signals: vec![6],
+            }],
+        };
+        let names = [(4, "main.x".to_string()), (5, "main.y".to_string())]
+            .into_iter()
+            .collect();
+        let analysis = analyse_r1cs(&r1cs, &names).unwrap();
+        assert_eq!((analysis.n_linear, analysis.n_quadratic), (1, 1));
+        assert_eq!(analysis.custom_gates["CMul"], 1);
+        assert_eq!(
+            analysis.unused_signals,
+            vec![Signal {
+                wire: 5,
+                name: "main.y".to_string()
+            }]
+        );
+        assert_eq!(
+            analysis.under_constrained_signals,
+            vec![Signal {
+                wire: 4,
+                name: "main.x".to_string()
+            }]
+        );
+        assert!(analysis.to_string().contains("main.x (w4)"));
+
+        // more outputs than inputs
+        let r1cs = R1CS::<Bn256> {
+            num_outputs: 2,
+            ..r1cs
+        };
+        let err = analyse_r1cs(&r1cs, &names).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlgebraicError>(),
+            Some(AlgebraicError::MalformedInput(_))
+        ));
+    }
+}
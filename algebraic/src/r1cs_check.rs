@@ -0,0 +1,270 @@
+//! Check the witness against the constraints of the R1CS directly, so that a wrong witness is
+//! reported by constraint and signal name instead of a proof which fails to verify.
+use crate::bellman_ce::{Field, PrimeField, ScalarEngine};
+use crate::circom_circuit::R1CS;
+use crate::errors::{bail, AlgebraicError, Result};
+use crate::reader::{load_r1cs, load_witness_from_file};
+use crate::utils::repr_to_big;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// the failing constraints reported in detail
+const MAX_REPORTED_CONSTRAINTS: usize = 10;
+
+/// a constraint `A·w * B·w = C·w` which does not hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintFailure {
+    pub index: usize,
+    /// the values of `A·w`, `B·w` and `C·w`
+    pub a: String,
+    pub b: String,
+    pub c: String,
+    /// the signals of the constraint as `(wire, name, value)`
+    pub signals: Vec<(usize, String, String)>,
+}
+
+impl fmt::Display for ConstraintFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "constraint {}: {} * {} != {}",
+            self.index, self.a, self.b, self.c
+        )?;
+        for (wire, name, value) in self.signals.iter() {
+            write!(f, "\n    {} (w{}) = {}", name, wire, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// the result of checking the witness
+#[derive(Debug, Clone, Default)]
+pub struct R1csCheck {
+    pub n_constraints: usize,
+    pub n_failures: usize,
+    /// the first failing constraints, at most `MAX_REPORTED_CONSTRAINTS`
+    pub failures: Vec<ConstraintFailure>,
+    /// the custom gates and their number of uses, which are not checked as R1CS
+    pub custom_gates: Vec<(String, usize)>,
+}
+
+/// Load the signal names of the `.sym` file by wire, the lines are `label,wire,component,name`
+/// where the wire is -1 for the signals removed by the simplification.
+pub fn load_sym(sym_file: &str) -> Result<HashMap<usize, String>> {
+    let content = std::fs::read_to_string(sym_file)?;
+    let mut names = HashMap::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let fields = line.splitn(4, ',').collect::<Vec<_>>();
+        if fields.len() != 4 {
+            bail!(AlgebraicError::MalformedInput(format!(
+                "{}:{}: expect label,wire,component,name",
+                sym_file,
+                i + 1
+            )));
+        }
+        let wire = fields[1].parse::<i64>().map_err(|e| {
+            AlgebraicError::MalformedInput(format!("{}:{}: {}", sym_file, i + 1, e))
+        })?;
+        if wire >= 0 {
+            // keep the first name of the wire
+            names
+                .entry(wire as usize)
+                .or_insert_with(|| fields[3].to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Check every constraint of the R1CS over the witness, the witness is indexed by wire
+pub fn check_r1cs<E: ScalarEngine>(
+    r1cs: &R1CS<E>,
+    witness: &[E::Fr],
+    names: &HashMap<usize, String>,
+) -> Result<R1csCheck> {
+    if witness.len() != r1cs.num_variables {
+        bail!(AlgebraicError::MalformedInput(format!(
+            "the witness has {} values, the r1cs has {} wires",
+            witness.len(),
+            r1cs.num_variables
+        )));
+    }
+    let name = |wire: usize| match names.get(&wire) {
+        Some(name) => name.clone(),
+        None if wire == 0 => "one".to_string(),
+        None => format!("w{}", wire),
+    };
+    let value = |v: &E::Fr| repr_to_big(v.into_repr());
+    let eval = |lc: &[(usize, E::Fr)]| -> Result<E::Fr> {
+        let mut acc = E::Fr::zero();
+        for (wire, coeff) in lc.iter() {
+            let mut term = *witness.get(*wire).ok_or_else(|| {
+                AlgebraicError::MalformedInput(format!("wire {} out of the witness", wire))
+            })?;
+            term.mul_assign(coeff);
+            acc.add_assign(&term);
+        }
+        Ok(acc)
+    };
+
+    let mut check = R1csCheck {
+        n_constraints: r1cs.constraints.len(),
+        ..Default::default()
+    };
+    for (i, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+        let (va, vb, vc) = (eval(a)?, eval(b)?, eval(c)?);
+        let mut ab = va;
+        ab.mul_assign(&vb);
+        if ab == vc {
+            continue;
+        }
+        check.n_failures += 1;
+        if check.failures.len() < MAX_REPORTED_CONSTRAINTS {
+            let wires = a
+                .iter()
+                .chain(b.iter())
+                .chain(c.iter())
+                .map(|(wire, _)| *wire)
+                .collect::<BTreeSet<_>>();
+            check.failures.push(ConstraintFailure {
+                index: i,
+                a: value(&va),
+                b: value(&vb),
+                c: value(&vc),
+                signals: wires
+                    .into_iter()
+                    .map(|w| (w, name(w), value(&witness[w])))
+                    .collect(),
+            });
+        }
+    }
+
+    let mut uses = BTreeMap::new();
+    for u in r1cs.custom_gates_uses.iter() {
+        let template = match r1cs.custom_gates.get(u.id as usize) {
+            Some(gate) => gate.template_name.clone(),
+            None => format!("custom gate {}", u.id),
+        };
+        *uses.entry(template).or_insert(0) += 1;
+    }
+    check.custom_gates = uses.into_iter().collect();
+    Ok(check)
+}
+
+/// Check the witness file against the r1cs file, naming the signals by the `.sym` file if any
+pub fn r1cs_check<E: ScalarEngine>(
+    r1cs_file: &str,
+    witness_file: &str,
+    sym_file: Option<&str>,
+) -> Result<()> {
+    let r1cs = load_r1cs::<E>(r1cs_file)?;
+    let witness = load_witness_from_file::<E>(witness_file)?;
+    let names = match sym_file {
+        Some(sym_file) => load_sym(sym_file)?,
+        None => HashMap::new(),
+    };
+
+    let check = check_r1cs(&r1cs, &witness, &names)?;
+    for (template, n) in check.custom_gates.iter() {
+        log::warn!("{} uses of the custom gate {} are not checked", n, template);
+    }
+    if check.n_failures > 0 {
+        let report = check
+            .failures
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        bail!(AlgebraicError::UnsatisfiedConstraints(format!(
+            "{} of {} constraints fail{}:\n{}",
+            check.n_failures,
+            check.n_constraints,
+            if check.n_failures > check.failures.len() {
+                format!(", the first {} are", check.failures.len())
+            } else {
+                "".to_string()
+            },
+            report
+        )));
+    }
+    log::info!("r1cs_check passed, {} constraints", check.n_constraints);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::bn256::Bn256;
+
+    const R1CS_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+
+    fn write_witness(name: &str, witness: &[&str]) -> String {
+        let file = format!("/tmp/test_r1cs_check.{}.json", name);
+        std::fs::write(&file, serde_json::to_string(witness).unwrap()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_r1cs_check() {
+        let sym_file = "/tmp/test_r1cs_check.sym";
+        std::fs::write(sym_file, "1,1,0,main.c\n2,2,0,main.a\n3,3,0,main.b\n").unwrap();
+        let valid = write_witness("valid", &["1", "33", "3", "11"]);
+        r1cs_check::<Bn256>(R1CS_FILE, &valid, Some(sym_file)).unwrap();
+        r1cs_check::<Bn256>(R1CS_FILE, &valid, None).unwrap();
+        // the binary witness of another multiplier
+        r1cs_check::<Bn256>(
+            R1CS_FILE,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../test/single/witness.wtns"),
+            Some(sym_file),
+        )
+        .unwrap();
+
+        let invalid = write_witness("invalid", &["1", "34", "3", "11"]);
+        let r1cs = load_r1cs::<Bn256>(R1CS_FILE).unwrap();
+        let witness = load_witness_from_file::<Bn256>(&invalid).unwrap();
+        let check = check_r1cs(&r1cs, &witness, &load_sym(sym_file).unwrap()).unwrap();
+        assert_eq!((check.n_constraints, check.n_failures), (1, 1));
+        let failure = &check.failures[0];
+        assert_eq!(failure.index, 0);
+        assert_eq!(
+            failure.signals,
+            vec![
+                (1, "main.c".to_string(), "34".to_string()),
+                (2, "main.a".to_string(), "3".to_string()),
+                (3, "main.b".to_string(), "11".to_string()),
+            ]
+        );
+        assert!(check.custom_gates.is_empty());
+
+        let err = r1cs_check::<Bn256>(R1CS_FILE, &invalid, Some(sym_file)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlgebraicError>(),
+            Some(AlgebraicError::UnsatisfiedConstraints(_))
+        ));
+        assert!(err.to_string().contains("main.c (w1) = 34"));
+    }
+
+    #[test]
+    fn test_r1cs_check_invalid() {
+        let short = write_witness("short", &["1", "33", "3"]);
+        let err = r1cs_check::<Bn256>(R1CS_FILE, &short, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlgebraicError>(),
+            Some(AlgebraicError::MalformedInput(_))
+        ));
+
+        let sym_file = "/tmp/test_r1cs_check_invalid.sym";
+        std::fs::write(sym_file, "1,x,0,main.c\n").unwrap();
+        let err = load_sym(sym_file).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlgebraicError>(),
+            Some(AlgebraicError::MalformedInput(_))
+        ));
+
+        let err =
+            r1cs_check::<Bn256>(R1CS_FILE, "/tmp/test_r1cs_check.missing.wtns", None).unwrap_err();
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use byteorder::{LittleEndian, ReadBytesExt};
 use itertools::Itertools;
 use std::collections::BTreeMap;
@@ -13,38 +13,51 @@ use crate::bellman_ce::{
 };
 
 use crate::circom_circuit::{CircuitJson, R1CS};
+use crate::errors::AlgebraicError;
 
-/// get universal setup file by filename
+/// get universal setup file by filename, a missing file keeps the `std::io::Error` in the chain
 fn get_universal_setup_file_buff_reader(setup_file_name: &str) -> Result<BufReader<File>> {
-    let setup_file = File::open(setup_file_name)?;
+    let setup_file = File::open(setup_file_name)
+        .with_context(|| format!("Unable to open {}", setup_file_name))?;
     Ok(BufReader::with_capacity(1 << 29, setup_file))
 }
 
 /// load monomial form SRS by filename
-pub fn load_key_monomial_form<E: Engine>(filename: &str) -> Crs<E, CrsForMonomialForm> {
-    let mut buf_reader = get_universal_setup_file_buff_reader(filename)
-        .unwrap_or_else(|_| panic!("read key_monomial_form file err, {}", filename));
-    Crs::<E, CrsForMonomialForm>::read(&mut buf_reader).expect("read key_monomial_form err")
+pub fn load_key_monomial_form<E: Engine>(filename: &str) -> Result<Crs<E, CrsForMonomialForm>> {
+    let mut buf_reader = get_universal_setup_file_buff_reader(filename)?;
+    Crs::<E, CrsForMonomialForm>::read(&mut buf_reader).map_err(|e| malformed(filename, e))
 }
 
 /// load optional lagrange form SRS by filename
 pub fn maybe_load_key_lagrange_form<E: Engine>(
     option_filename: Option<String>,
-) -> Option<Crs<E, CrsForLagrangeForm>> {
+) -> Result<Option<Crs<E, CrsForLagrangeForm>>> {
     match option_filename {
-        None => None,
+        None => Ok(None),
         Some(filename) => {
-            let mut buf_reader = get_universal_setup_file_buff_reader(&filename)
-                .unwrap_or_else(|_| panic!("read key_lagrange_form file err, {}", filename));
+            let mut buf_reader = get_universal_setup_file_buff_reader(&filename)?;
             let key_lagrange_form = Crs::<E, CrsForLagrangeForm>::read(&mut buf_reader)
-                .expect("read key_lagrange_form err");
-            Some(key_lagrange_form)
+                .map_err(|e| malformed(&filename, e))?;
+            Ok(Some(key_lagrange_form))
         }
     }
 }
 
+/// open the file, a missing file keeps the `std::io::Error` in the chain
+fn open_file(filename: &str) -> Result<BufReader<File>> {
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(filename)
+        .with_context(|| format!("Unable to open {}", filename))?;
+    Ok(BufReader::new(reader))
+}
+
+fn malformed(filename: &str, e: impl std::fmt::Display) -> anyhow::Error {
+    AlgebraicError::MalformedInput(format!("{}, {}", filename, e)).into()
+}
+
 /// load witness file by filename with autodetect encoding (bin or json).
-pub fn load_witness_from_file<E: ScalarEngine>(filename: &str) -> Vec<E::Fr> {
+pub fn load_witness_from_file<E: ScalarEngine>(filename: &str) -> Result<Vec<E::Fr>> {
     if filename.ends_with("json") {
         load_witness_from_json_file::<E>(filename)
     } else {
@@ -53,31 +66,24 @@ pub fn load_witness_from_file<E: ScalarEngine>(filename: &str) -> Vec<E::Fr> {
 }
 
 /// load witness from json file by filename
-pub fn load_witness_from_json_file<E: ScalarEngine>(filename: &str) -> Vec<E::Fr> {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_witness_from_json::<E, BufReader<File>>(BufReader::new(reader))
+pub fn load_witness_from_json_file<E: ScalarEngine>(filename: &str) -> Result<Vec<E::Fr>> {
+    load_witness_from_json::<E, BufReader<File>>(open_file(filename)?)
+        .map_err(|e| malformed(filename, e))
 }
 
 /// load witness from json by a reader
-fn load_witness_from_json<E: ScalarEngine, R: Read>(reader: R) -> Vec<E::Fr> {
-    let witness: Vec<String> = serde_json::from_reader(reader).expect("Unable to read.");
+fn load_witness_from_json<E: ScalarEngine, R: Read>(reader: R) -> Result<Vec<E::Fr>> {
+    let witness: Vec<String> = serde_json::from_reader(reader)?;
     witness
         .into_iter()
-        .map(|x| E::Fr::from_str(&x).unwrap())
-        .collect::<Vec<E::Fr>>()
+        .map(|x| E::Fr::from_str(&x).ok_or_else(|| anyhow!("invalid field element {}", x)))
+        .collect()
 }
 
 /// load witness from bin file by filename
-pub fn load_witness_from_bin_file<E: ScalarEngine>(filename: &str) -> Vec<E::Fr> {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_witness_from_bin_reader::<E, BufReader<File>>(BufReader::new(reader))
-        .expect("read witness failed")
+pub fn load_witness_from_bin_file<E: ScalarEngine>(filename: &str) -> Result<Vec<E::Fr>> {
+    load_witness_from_bin_reader::<E, BufReader<File>>(open_file(filename)?)
+        .map_err(|e| malformed(filename, e))
 }
 
 /// load witness from u8 array
@@ -107,17 +113,20 @@ pub fn load_witness_from_bin_reader<E: ScalarEngine, R: Read>(mut reader: R) ->
     if sec_type != 1 {
         bail!("invalid section type".to_string());
     }
+    // the prime of the witness must be the one of the scalar field
+    let mut modulus = vec![];
+    E::Fr::char().write_le(&mut modulus)?;
     let sec_size = reader.read_u64::<LittleEndian>()?;
-    if sec_size != 4 + 32 + 4 {
+    if sec_size != 4 + modulus.len() as u64 + 4 {
         bail!("invalid section len".to_string());
     }
     let field_size = reader.read_u32::<LittleEndian>()?;
-    if field_size != 32 {
+    if field_size as usize != modulus.len() {
         bail!("invalid field byte size".to_string());
     }
     let mut prime = vec![0u8; field_size as usize];
     reader.read_exact(&mut prime)?;
-    if prime != hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430") {
+    if prime != modulus {
         bail!("invalid curve prime".to_string());
     }
     let witness_len = reader.read_u32::<LittleEndian>()?;
@@ -127,7 +136,7 @@ pub fn load_witness_from_bin_reader<E: ScalarEngine, R: Read>(mut reader: R) ->
         bail!("invalid section type".to_string());
     }
     let sec_size = reader.read_u64::<LittleEndian>()?;
-    if sec_size != (witness_len * field_size) as u64 {
+    if sec_size != witness_len as u64 * field_size as u64 {
         bail!(format!("Invalid witness section size {}", sec_size));
     }
     let mut result = Vec::with_capacity(witness_len as usize);
@@ -140,50 +149,60 @@ pub fn load_witness_from_bin_reader<E: ScalarEngine, R: Read>(mut reader: R) ->
 }
 
 /// load r1cs file by filename with autodetect encoding (bin or json)
-pub fn load_r1cs<E: ScalarEngine>(filename: &str) -> R1CS<E> {
+pub fn load_r1cs<E: ScalarEngine>(filename: &str) -> Result<R1CS<E>> {
     if filename.ends_with("json") {
         load_r1cs_from_json_file(filename)
     } else {
-        let (r1cs, _wire_mapping) = load_r1cs_from_bin_file(filename);
-        r1cs
+        let (r1cs, _wire_mapping) = load_r1cs_from_bin_file(filename)?;
+        Ok(r1cs)
     }
 }
 
 /// load r1cs from json file by filename
-fn load_r1cs_from_json_file<E: ScalarEngine>(filename: &str) -> R1CS<E> {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_r1cs_from_json(BufReader::new(reader))
+fn load_r1cs_from_json_file<E: ScalarEngine>(filename: &str) -> Result<R1CS<E>> {
+    load_r1cs_from_json(open_file(filename)?).map_err(|e| malformed(filename, e))
 }
 
 /// load r1cs from json by a reader
-fn load_r1cs_from_json<E: ScalarEngine, R: Read>(reader: R) -> R1CS<E> {
-    let circuit_json: CircuitJson = serde_json::from_reader(reader).expect("Unable to read.");
+fn load_r1cs_from_json<E: ScalarEngine, R: Read>(reader: R) -> Result<R1CS<E>> {
+    let circuit_json: CircuitJson = serde_json::from_reader(reader)?;
 
     let num_inputs = circuit_json.num_inputs + circuit_json.num_outputs + 1;
-    let num_aux = circuit_json.num_variables - num_inputs;
+    let num_aux = circuit_json
+        .num_variables
+        .checked_sub(num_inputs)
+        .ok_or_else(|| {
+            anyhow!(
+                "the inputs exceed the {} variables",
+                circuit_json.num_variables
+            )
+        })?;
 
-    let convert_constraint = |lc: &BTreeMap<String, String>| {
+    let convert_constraint = |lc: &BTreeMap<String, String>| -> Result<Vec<(usize, E::Fr)>> {
         lc.iter()
-            .map(|(index, coeff)| (index.parse().unwrap(), E::Fr::from_str(coeff).unwrap()))
-            .collect_vec()
+            .map(|(index, coeff)| {
+                Ok((
+                    index.parse()?,
+                    E::Fr::from_str(coeff)
+                        .ok_or_else(|| anyhow!("invalid coefficient {}", coeff))?,
+                ))
+            })
+            .collect()
     };
 
     let constraints = circuit_json
         .constraints
         .iter()
         .map(|c| {
-            (
-                convert_constraint(&c[0]),
-                convert_constraint(&c[1]),
-                convert_constraint(&c[2]),
-            )
+            Ok((
+                convert_constraint(&c[0])?,
+                convert_constraint(&c[1])?,
+                convert_constraint(&c[2])?,
+            ))
         })
-        .collect_vec();
+        .collect::<Result<Vec<_>>>()?;
 
-    R1CS {
+    Ok(R1CS {
         num_inputs,
         num_aux,
         num_variables: circuit_json.num_variables,
@@ -191,25 +210,29 @@ fn load_r1cs_from_json<E: ScalarEngine, R: Read>(reader: R) -> R1CS<E> {
         constraints,
         custom_gates: vec![],
         custom_gates_uses: vec![],
-    }
+    })
 }
 
 /// load r1cs from bin file by filename
-fn load_r1cs_from_bin_file<E: ScalarEngine>(filename: &str) -> (R1CS<E>, Vec<usize>) {
-    let reader = OpenOptions::new()
-        .read(true)
-        .open(filename)
-        .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_r1cs_from_bin(BufReader::new(reader))
+fn load_r1cs_from_bin_file<E: ScalarEngine>(filename: &str) -> Result<(R1CS<E>, Vec<usize>)> {
+    load_r1cs_from_bin(open_file(filename)?).with_context(|| format!("Unable to load {}", filename))
 }
 
 /// load r1cs from bin by a reader
-pub fn load_r1cs_from_bin<R: Read + Seek, E: ScalarEngine>(reader: R) -> (R1CS<E>, Vec<usize>) {
-    let file = crate::r1cs_file::from_reader::<R, E>(reader).expect("Unable to read.");
+pub fn load_r1cs_from_bin<R: Read + Seek, E: ScalarEngine>(
+    reader: R,
+) -> Result<(R1CS<E>, Vec<usize>)> {
+    let file = crate::r1cs_file::from_reader::<R, E>(reader)
+        .map_err(|e| AlgebraicError::MalformedInput(format!("r1cs, {}", e)))?;
     let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
     let num_variables = file.header.n_wires as usize;
-    let num_aux = num_variables - num_inputs;
-    (
+    let num_aux = num_variables.checked_sub(num_inputs).ok_or_else(|| {
+        AlgebraicError::MalformedInput(format!(
+            "r1cs, the inputs exceed the {} wires",
+            num_variables
+        ))
+    })?;
+    Ok((
         R1CS {
             num_aux,
             num_inputs,
@@ -220,5 +243,55 @@ pub fn load_r1cs_from_bin<R: Read + Seek, E: ScalarEngine>(reader: R) -> (R1CS<E
             custom_gates_uses: file.custom_gates_uses,
         },
         file.wire_mapping.iter().map(|e| *e as usize).collect_vec(),
-    )
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::bn256::Bn256;
+
+    fn assert_malformed(err: anyhow::Error) {
+        assert!(
+            matches!(
+                err.downcast_ref::<AlgebraicError>(),
+                Some(AlgebraicError::MalformedInput(_))
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_load_invalid_files() {
+        let r1cs_file = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+        assert_eq!(load_r1cs::<Bn256>(r1cs_file).unwrap().num_variables, 4);
+
+        let err = load_r1cs::<Bn256>("/tmp/test_reader_missing.r1cs").unwrap_err();
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+        let err = load_witness_from_file::<Bn256>("/tmp/test_reader_missing.wtns").unwrap_err();
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+
+        let truncated = "/tmp/test_reader_truncated.r1cs";
+        let bytes = std::fs::read(r1cs_file).unwrap();
+        std::fs::write(truncated, &bytes[..bytes.len() / 2]).unwrap();
+        assert_malformed(load_r1cs::<Bn256>(truncated).unwrap_err());
+
+        let json = "/tmp/test_reader_malformed.r1cs.json";
+        std::fs::write(json, "{").unwrap();
+        assert_malformed(load_r1cs::<Bn256>(json).unwrap_err());
+
+        let json = "/tmp/test_reader_malformed.wtns.json";
+        std::fs::write(json, r#"["1", "x"]"#).unwrap();
+        assert_malformed(load_witness_from_file::<Bn256>(json).unwrap_err());
+        let wtns = "/tmp/test_reader_malformed.wtns";
+        std::fs::write(wtns, b"wtns").unwrap();
+        assert_malformed(load_witness_from_file::<Bn256>(wtns).unwrap_err());
+
+        let err = load_key_monomial_form::<Bn256>("/tmp/test_reader_missing.srs").unwrap_err();
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+        let srs = "/tmp/test_reader_truncated.srs";
+        std::fs::write(srs, [1u8; 7]).unwrap();
+        assert_malformed(load_key_monomial_form::<Bn256>(srs).unwrap_err());
+    }
 }
@@ -1,55 +1,52 @@
-mod circom;
-pub(crate) mod memory;
-pub(super) mod witness_calculator;
-
-use fnv::FnvHasher;
+//! The witness calculators live in circom-witness, shared with algebraic-gpu; the inputs are
+//! loaded here so that a malformed input is reported as an `AlgebraicError`.
+use crate::errors::AlgebraicError;
+use anyhow::{Context, Result};
+#[cfg(feature = "wasmer")]
+pub use circom_witness::WitnessCalculator;
+pub use circom_witness::{flat_array, NativeWitnessCalculator, WitnessBackend};
 use num_bigint::BigInt;
-use num_traits::{One, Zero};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::hash::Hasher;
-use std::str::FromStr;
-pub use witness_calculator::flat_array;
-pub use witness_calculator::WitnessCalculator;
-
-pub(crate) fn fnv(inp: &str) -> (u32, u32) {
-    let mut hasher = FnvHasher::default();
-    hasher.write(inp.as_bytes());
-    let h = hasher.finish();
 
-    ((h >> 32) as u32, h as u32)
-}
-
-pub fn load_input_for_witness(input_file: &str) -> HashMap<String, Vec<BigInt>> {
+pub fn load_input_for_witness(input_file: &str) -> Result<HashMap<String, Vec<BigInt>>> {
     let inputs_str = std::fs::read_to_string(input_file)
-        .unwrap_or_else(|_| panic!("Unable to load {}", input_file));
-    let inputs: std::collections::HashMap<String, serde_json::Value> =
-        serde_json::from_str(&inputs_str).unwrap();
+        .with_context(|| format!("Unable to load {}", input_file))?;
+    let inputs: HashMap<String, Value> = serde_json::from_str(&inputs_str)
+        .map_err(|e| AlgebraicError::MalformedInput(format!("{}, {}", input_file, e)))?;
 
     inputs
         .iter()
         .map(|(key, value)| {
-            let res = match value {
-                Value::String(inner) => {
-                    vec![BigInt::from_str(inner).unwrap()]
-                }
-                Value::Bool(inner) => {
-                    if *inner {
-                        vec![BigInt::one()]
-                    } else {
-                        vec![BigInt::zero()]
-                    }
-                }
-                Value::Number(inner) => {
-                    vec![BigInt::from_str(&inner.to_string()).unwrap()]
-                    //vec![BigInt::from(inner.as_u64().expect("not a u32"))]
-                }
-                //Value::Array(inner) => inner.iter().cloned().map(value_to_bigint).collect(),
-                Value::Array(inner) => flat_array(inner),
-                _ => panic!("{:?}", value),
-            };
-
-            (key.clone(), res)
+            let value = flat_array(std::slice::from_ref(value))
+                .map_err(|e| AlgebraicError::MalformedInput(format!("{}, {}", input_file, e)))?;
+            Ok((key.clone(), value))
         })
-        .collect::<std::collections::HashMap<_, _>>()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_input_for_witness() {
+        let inputs = load_input_for_witness(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/multiplier.input.json"
+        ))
+        .unwrap();
+        assert_eq!(inputs["a"], vec![BigInt::from(3)]);
+
+        let err = load_input_for_witness("/tmp/test_witness_input_missing.json").unwrap_err();
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+
+        let input_file = "/tmp/test_witness_input_malformed.json";
+        std::fs::write(input_file, r#"{"a": "0x3", "b": 11}"#).unwrap();
+        let err = load_input_for_witness(input_file).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AlgebraicError>(),
+            Some(AlgebraicError::MalformedInput(_))
+        ));
+    }
 }
@@ -0,0 +1,96 @@
+//! Compare the native witness backend with wasmer.
+//!
+//! The toy circuits are always measured. A recursive circuit, which is what the backend is meant
+//! for, is measured too when `WITNESS_BENCH_WASM` and `WITNESS_BENCH_INPUT` are set, e.g. after
+//! `test/stark_aggregation.sh yes BN128` has produced the c12 stage of the task 0:
+//!
+//! ```bash
+//! WORKSPACE=/tmp/aggregation_BN128_fibonacci
+//! WITNESS_BENCH_WASM=$WORKSPACE/0/fibonacci.recursive1_js/fibonacci.recursive1.wasm \
+//! WITNESS_BENCH_INPUT=$WORKSPACE/circuits/0/c12a.zkin.json \
+//!     cargo bench -p circom-witness
+//! ```
+use circom_witness::{flat_array, NativeWitnessCalculator, WitnessCalculator};
+use criterion::{criterion_group, criterion_main, Criterion};
+use num_bigint::BigInt;
+use serde_json::Value;
+use std::collections::HashMap;
+
+const WASM_FILES: [&str; 2] = [
+    concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm"),
+    concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../algebraic/test-vectors/mycircuit.wasm"
+    ),
+];
+
+fn toy_inputs() -> Vec<(String, Vec<BigInt>)> {
+    vec![
+        ("a".to_string(), vec![BigInt::from(3)]),
+        ("b".to_string(), vec![BigInt::from(11)]),
+    ]
+}
+
+fn load_inputs(input_file: &str) -> Vec<(String, Vec<BigInt>)> {
+    let inputs_str = std::fs::read_to_string(input_file).unwrap();
+    let inputs: HashMap<String, Value> = serde_json::from_str(&inputs_str).unwrap();
+    inputs
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.clone(),
+                flat_array(std::slice::from_ref(value)).unwrap(),
+            )
+        })
+        .collect()
+}
+
+// the loading covers the compilation of wasmer and the decoding of the interpreter
+fn bench_circuit(
+    c: &mut Criterion,
+    wasm_file: &str,
+    inputs: &[(String, Vec<BigInt>)],
+    sample_size: usize,
+) {
+    let name = std::path::Path::new(wasm_file)
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    let mut group = c.benchmark_group(format!("witness/{}", name));
+    group.sample_size(sample_size);
+
+    group.bench_function("wasmer/load", |b| {
+        b.iter(|| WitnessCalculator::from_file(wasm_file).unwrap())
+    });
+    group.bench_function("native/load", |b| {
+        b.iter(|| NativeWitnessCalculator::from_file(wasm_file).unwrap())
+    });
+
+    let mut wasmer = WitnessCalculator::from_file(wasm_file).unwrap();
+    group.bench_function("wasmer/calculate", |b| {
+        b.iter(|| wasmer.calculate_witness(inputs.to_vec(), false).unwrap())
+    });
+    let mut native = NativeWitnessCalculator::from_file(wasm_file).unwrap();
+    group.bench_function("native/calculate", |b| {
+        b.iter(|| native.calculate_witness(inputs.to_vec(), false).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_witness(c: &mut Criterion) {
+    for wasm_file in WASM_FILES {
+        bench_circuit(c, wasm_file, &toy_inputs(), 100);
+    }
+
+    // a recursive circuit takes seconds per witness, so fewer samples are taken
+    if let (Ok(wasm_file), Ok(input_file)) = (
+        std::env::var("WITNESS_BENCH_WASM"),
+        std::env::var("WITNESS_BENCH_INPUT"),
+    ) {
+        bench_circuit(c, &wasm_file, &load_inputs(&input_file), 10);
+    }
+}
+
+criterion_group!(benches, bench_witness);
+criterion_main!(benches);
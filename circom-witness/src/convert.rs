@@ -0,0 +1,79 @@
+// the conversions between the circom values and the 32-bit limbs of the witness calculators
+use anyhow::{anyhow, bail, Result};
+use num::ToPrimitive;
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use serde_json::Value;
+use std::str::FromStr;
+
+pub(crate) fn from_array32(arr: Vec<u32>) -> BigInt {
+    let mut res = BigInt::zero();
+    let radix = BigInt::from(0x100000000u64);
+    for &val in arr.iter() {
+        res = res * &radix + BigInt::from(val);
+    }
+    res
+}
+
+pub(crate) fn to_array32(s: &BigInt, size: usize) -> Vec<u32> {
+    let mut res = vec![0; size];
+    let mut rem = s.clone();
+    let radix = BigInt::from(0x100000000u64);
+    let mut c = size;
+    while !rem.is_zero() {
+        c -= 1;
+        res[c] = (&rem % &radix).to_u32().unwrap();
+        rem /= &radix;
+    }
+
+    res
+}
+
+#[allow(dead_code)]
+pub fn value_to_bigint(v: Value) -> BigInt {
+    match v {
+        Value::String(inner) => BigInt::from_str(&inner).unwrap(),
+        Value::Number(inner) => BigInt::from(
+            inner
+                .as_u64()
+                .unwrap_or_else(|| panic!("{} not a u32", inner)),
+        ),
+        _ => panic!("unsupported type {:?}", v),
+    }
+}
+
+pub fn flat_array(v: &[Value]) -> Result<Vec<BigInt>> {
+    let mut result = Vec::new();
+    fn parse_bigint(value: &str) -> Result<BigInt> {
+        BigInt::from_str(value).map_err(|e| anyhow!("invalid input {}, {}", value, e))
+    }
+    fn fill_array(out: &mut Vec<BigInt>, value: &Value) -> Result<()> {
+        match value {
+            Value::Array(inner) => {
+                for v2 in inner.iter() {
+                    fill_array(out, v2)?;
+                }
+            }
+            Value::Bool(inner) => {
+                if *inner {
+                    out.push(BigInt::one());
+                } else {
+                    out.push(BigInt::zero());
+                }
+            }
+            Value::String(inner) => {
+                out.push(parse_bigint(inner)?);
+            }
+            Value::Number(inner) => {
+                out.push(parse_bigint(&inner.to_string())?);
+            }
+            _ => bail!("unsupported input {}", value),
+        }
+        Ok(())
+    }
+
+    for v2 in v.iter() {
+        fill_array(&mut result, v2)?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,1317 @@
+//! A small interpreter of the wasm generated by circom, so that the witness can be calculated
+//! without a wasm runtime. Only the integer instructions of wasm 1.0, the sign extension and the
+//! bulk memory copy/fill are supported, which cover the code circom generates.
+//!
+//! The function bodies are translated once into a flat list of operations, in which the
+//! structured control flow is resolved into jumps with the operand stack height to restore.
+use anyhow::{anyhow, bail, ensure, Result};
+use std::collections::HashMap;
+
+const PAGE_SIZE: usize = 65536;
+const MAX_PAGES: usize = 65536;
+const MAX_CALL_DEPTH: usize = 100_000;
+/// the pages of the imported memory, the same as the memory given by `WitnessCalculator`
+const IMPORTED_MEMORY_PAGES: u32 = 2000;
+
+const I32: u8 = 0x7f;
+const I64: u8 = 0x7e;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FuncType {
+    params: usize,
+    results: usize,
+}
+
+/// a jump to the label, and the operand stack is truncated to `height` except the top `keep` values
+#[derive(Clone, Copy, Debug)]
+struct Branch {
+    target: u32,
+    height: u32,
+    keep: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Unreachable,
+    Br(Branch),
+    BrIf(Branch),
+    /// jump if the top of the stack is zero, the `if` without stack adjustment
+    BrIfZero(u32),
+    Jump(u32),
+    /// the branches are stored in `Function::br_tables[start..start + len]`, the last is the default
+    BrTable {
+        start: u32,
+        len: u32,
+    },
+    Return,
+    Call(u32),
+    CallIndirect(u32),
+    Drop,
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    Load(u8, u32),
+    Store(u8, u32),
+    MemorySize,
+    MemoryGrow,
+    MemoryCopy,
+    MemoryFill,
+    Const(u64),
+    Numeric(u8),
+}
+
+struct Function {
+    params: usize,
+    results: usize,
+    /// the params and the declared locals
+    locals: usize,
+    code: Vec<Op>,
+    br_tables: Vec<Branch>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum HostFunc {
+    Error,
+    ExceptionHandler,
+    /// the logging callbacks, which are ignored
+    Ignore,
+}
+
+struct Import {
+    host: HostFunc,
+    params: usize,
+    results: usize,
+}
+
+struct Module {
+    types: Vec<FuncType>,
+    imports: Vec<Import>,
+    /// the type indices of the defined functions
+    func_types: Vec<u32>,
+    functions: Vec<Function>,
+    memory: Option<(u32, Option<u32>)>,
+    table_size: u32,
+    globals: Vec<u64>,
+    exports: HashMap<String, u32>,
+    elements: Vec<(u32, Vec<u32>)>,
+    data: Vec<(u32, Vec<u8>)>,
+    start: Option<u32>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn eof(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| anyhow!("Unexpected end of the wasm"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        ensure!(
+            self.pos + n <= self.data.len(),
+            "Unexpected end of the wasm"
+        );
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            result |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            ensure!(shift < 35, "Invalid LEB128 integer");
+        }
+        ensure!(result <= u32::MAX as u64, "Invalid LEB128 integer");
+        Ok(result as u32)
+    }
+
+    fn signed(&mut self, bits: u32) -> Result<i64> {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            result |= ((b & 0x7f) as i64) << shift;
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    result |= -1i64 << shift;
+                }
+                break;
+            }
+            ensure!(shift < bits + 7, "Invalid LEB128 integer");
+        }
+        Ok(result)
+    }
+
+    fn name(&mut self) -> Result<String> {
+        let n = self.u32()? as usize;
+        Ok(String::from_utf8(self.bytes(n)?.to_vec())?)
+    }
+
+    fn limits(&mut self) -> Result<(u32, Option<u32>)> {
+        match self.byte()? {
+            0 => Ok((self.u32()?, None)),
+            1 => Ok((self.u32()?, Some(self.u32()?))),
+            flag => bail!(format!("Unsupported limits flag {}", flag)),
+        }
+    }
+
+    fn value_type(&mut self) -> Result<u8> {
+        match self.byte()? {
+            t @ (I32 | I64) => Ok(t),
+            t => bail!(format!("Unsupported value type 0x{:x}", t)),
+        }
+    }
+
+    /// the constant expression of the global, data and element initializers
+    fn const_expr(&mut self, globals: &[u64]) -> Result<u64> {
+        let value = match self.byte()? {
+            0x41 => self.signed(32)? as i32 as u32 as u64,
+            0x42 => self.signed(64)? as u64,
+            0x23 => {
+                let index = self.u32()? as usize;
+                *globals
+                    .get(index)
+                    .ok_or_else(|| anyhow!("Unknown global {}", index))?
+            }
+            op => bail!(format!("Unsupported constant expression 0x{:x}", op)),
+        };
+        ensure!(self.byte()? == 0x0b, "Invalid constant expression");
+        Ok(value)
+    }
+}
+
+impl Module {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        ensure!(reader.bytes(4)? == b"\0asm", "Invalid wasm magic number");
+        ensure!(reader.bytes(4)? == [1, 0, 0, 0], "Unsupported wasm version");
+
+        let mut module = Module {
+            types: vec![],
+            imports: vec![],
+            func_types: vec![],
+            functions: vec![],
+            memory: None,
+            table_size: 0,
+            globals: vec![],
+            exports: HashMap::new(),
+            elements: vec![],
+            data: vec![],
+            start: None,
+        };
+        while !reader.eof() {
+            let id = reader.byte()?;
+            let size = reader.u32()? as usize;
+            let mut section = Reader::new(reader.bytes(size)?);
+            match id {
+                0 | 12 => {}
+                1 => {
+                    for _ in 0..section.u32()? {
+                        ensure!(section.byte()? == 0x60, "Invalid function type");
+                        let params = section.u32()?;
+                        for _ in 0..params {
+                            section.value_type()?;
+                        }
+                        let results = section.u32()?;
+                        for _ in 0..results {
+                            section.value_type()?;
+                        }
+                        module.types.push(FuncType {
+                            params: params as usize,
+                            results: results as usize,
+                        });
+                    }
+                }
+                2 => {
+                    for _ in 0..section.u32()? {
+                        let module_name = section.name()?;
+                        let name = section.name()?;
+                        match section.byte()? {
+                            0 => {
+                                let ty = module.func_type(section.u32()?)?.clone();
+                                let host = match (module_name.as_str(), name.as_str()) {
+                                    ("runtime", "error") => HostFunc::Error,
+                                    ("runtime", "exceptionHandler") => HostFunc::ExceptionHandler,
+                                    ("runtime", _) => HostFunc::Ignore,
+                                    _ => bail!(format!(
+                                        "Unsupported import {}.{}",
+                                        module_name, name
+                                    )),
+                                };
+                                ensure!(ty.results == 0, "Unsupported import {}", name);
+                                module.imports.push(Import {
+                                    host,
+                                    params: ty.params,
+                                    results: ty.results,
+                                });
+                            }
+                            1 => {
+                                section.byte()?;
+                                module.table_size = section.limits()?.0;
+                            }
+                            2 => {
+                                let (min, max) = section.limits()?;
+                                module.memory = Some((min.max(IMPORTED_MEMORY_PAGES), max));
+                            }
+                            _ => bail!(format!("Unsupported import {}.{}", module_name, name)),
+                        }
+                    }
+                }
+                3 => {
+                    for _ in 0..section.u32()? {
+                        let ty = section.u32()?;
+                        module.func_type(ty)?;
+                        module.func_types.push(ty);
+                    }
+                }
+                4 => {
+                    for _ in 0..section.u32()? {
+                        section.byte()?;
+                        module.table_size = section.limits()?.0;
+                    }
+                }
+                5 => {
+                    for _ in 0..section.u32()? {
+                        module.memory = Some(section.limits()?);
+                    }
+                }
+                6 => {
+                    for _ in 0..section.u32()? {
+                        section.value_type()?;
+                        section.byte()?;
+                        let value = section.const_expr(&module.globals)?;
+                        module.globals.push(value);
+                    }
+                }
+                7 => {
+                    for _ in 0..section.u32()? {
+                        let name = section.name()?;
+                        let kind = section.byte()?;
+                        let index = section.u32()?;
+                        if kind == 0 {
+                            module.exports.insert(name, index);
+                        }
+                    }
+                }
+                8 => module.start = Some(section.u32()?),
+                9 => {
+                    for _ in 0..section.u32()? {
+                        ensure!(section.u32()? == 0, "Unsupported element segment");
+                        let offset = section.const_expr(&module.globals)? as u32;
+                        let funcs = (0..section.u32()?)
+                            .map(|_| section.u32())
+                            .collect::<Result<_>>()?;
+                        module.elements.push((offset, funcs));
+                    }
+                }
+                10 => {
+                    let n = section.u32()? as usize;
+                    ensure!(
+                        n == module.func_types.len(),
+                        "The function and code sections mismatch"
+                    );
+                    for i in 0..n {
+                        let size = section.u32()? as usize;
+                        let mut body = Reader::new(section.bytes(size)?);
+                        let function = module.compile(module.func_types[i], &mut body)?;
+                        module.functions.push(function);
+                    }
+                }
+                11 => {
+                    for _ in 0..section.u32()? {
+                        match section.u32()? {
+                            0 => {}
+                            2 => ensure!(section.u32()? == 0, "Unsupported memory index"),
+                            // the passive segments are only used by memory.init
+                            _ => bail!("Unsupported passive data segment"),
+                        }
+                        let offset = section.const_expr(&module.globals)? as u32;
+                        let n = section.u32()? as usize;
+                        module.data.push((offset, section.bytes(n)?.to_vec()));
+                    }
+                }
+                _ => bail!(format!("Unknown section {}", id)),
+            }
+        }
+        ensure!(
+            module.functions.len() == module.func_types.len(),
+            "Missing the code section"
+        );
+        Ok(module)
+    }
+
+    fn func_type(&self, index: u32) -> Result<&FuncType> {
+        self.types
+            .get(index as usize)
+            .ok_or_else(|| anyhow!("Unknown type {}", index))
+    }
+
+    /// the type of the function, the imported functions are indexed first
+    fn func_signature(&self, index: u32) -> Result<FuncType> {
+        let index = index as usize;
+        if index < self.imports.len() {
+            let import = &self.imports[index];
+            return Ok(FuncType {
+                params: import.params,
+                results: import.results,
+            });
+        }
+        let ty = self
+            .func_types
+            .get(index - self.imports.len())
+            .ok_or_else(|| anyhow!("Unknown function {}", index))?;
+        Ok(self.func_type(*ty)?.clone())
+    }
+
+    fn compile(&self, ty: u32, body: &mut Reader) -> Result<Function> {
+        // the signatures are needed before all the functions are compiled
+        let signature = self.func_type(ty)?.clone();
+        let mut locals = signature.params;
+        for _ in 0..body.u32()? {
+            let n = body.u32()? as usize;
+            body.value_type()?;
+            locals += n;
+        }
+        let mut compiler = Compiler {
+            code: vec![],
+            br_tables: vec![],
+            labels: vec![Label {
+                kind: LabelKind::Block,
+                height: 0,
+                params: 0,
+                results: signature.results as u32,
+                start: 0,
+                fixups: vec![],
+                else_fixup: None,
+                outer_unreachable: false,
+            }],
+            height: 0,
+            unreachable: false,
+        };
+        compiler.run(self, body, locals)?;
+        Ok(Function {
+            params: signature.params,
+            results: signature.results,
+            locals,
+            code: compiler.code,
+            br_tables: compiler.br_tables,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LabelKind {
+    Block,
+    Loop,
+    If,
+}
+
+enum Fixup {
+    Op(usize),
+    Table(usize),
+}
+
+struct Label {
+    kind: LabelKind,
+    /// the operand stack height below the block
+    height: u32,
+    params: u32,
+    results: u32,
+    start: u32,
+    fixups: Vec<Fixup>,
+    else_fixup: Option<usize>,
+    outer_unreachable: bool,
+}
+
+struct Compiler {
+    code: Vec<Op>,
+    br_tables: Vec<Branch>,
+    labels: Vec<Label>,
+    height: u32,
+    unreachable: bool,
+}
+
+impl Compiler {
+    fn pop(&mut self, n: u32) -> Result<()> {
+        if !self.unreachable {
+            ensure!(
+                self.height >= n,
+                "Invalid wasm, the operand stack underflows"
+            );
+        }
+        self.height = self.height.saturating_sub(n);
+        Ok(())
+    }
+
+    fn push(&mut self, n: u32) {
+        self.height += n;
+    }
+
+    fn pc(&self) -> u32 {
+        self.code.len() as u32
+    }
+
+    fn branch(&mut self, depth: u32, fixup: Fixup) -> Result<Branch> {
+        ensure!(
+            (depth as usize) < self.labels.len(),
+            "Invalid branch depth {}",
+            depth
+        );
+        let label_index = self.labels.len() - 1 - depth as usize;
+        let label = &mut self.labels[label_index];
+        if label.kind == LabelKind::Loop {
+            return Ok(Branch {
+                target: label.start,
+                height: label.height,
+                keep: label.params,
+            });
+        }
+        label.fixups.push(fixup);
+        Ok(Branch {
+            target: 0,
+            height: label.height,
+            keep: label.results,
+        })
+    }
+
+    fn block_type(&self, module: &Module, body: &mut Reader) -> Result<(u32, u32)> {
+        let b = *body
+            .data
+            .get(body.pos)
+            .ok_or_else(|| anyhow!("Unexpected end of the wasm"))?;
+        match b {
+            0x40 => {
+                body.pos += 1;
+                Ok((0, 0))
+            }
+            I32 | I64 => {
+                body.pos += 1;
+                Ok((0, 1))
+            }
+            _ => {
+                let index = body.signed(33)?;
+                ensure!(index >= 0, "Unsupported block type");
+                let ty = module.func_type(index as u32)?;
+                Ok((ty.params as u32, ty.results as u32))
+            }
+        }
+    }
+
+    fn push_label(&mut self, kind: LabelKind, (params, results): (u32, u32)) -> Result<()> {
+        self.pop(params)?;
+        self.labels.push(Label {
+            kind,
+            height: self.height,
+            params,
+            results,
+            start: self.pc(),
+            fixups: vec![],
+            else_fixup: None,
+            outer_unreachable: self.unreachable,
+        });
+        self.push(params);
+        Ok(())
+    }
+
+    fn patch(&mut self, fixup: Fixup, target: u32) {
+        match fixup {
+            Fixup::Op(i) => match &mut self.code[i] {
+                Op::Br(b) | Op::BrIf(b) => b.target = target,
+                Op::BrIfZero(t) | Op::Jump(t) => *t = target,
+                _ => unreachable!(),
+            },
+            Fixup::Table(i) => self.br_tables[i].target = target,
+        }
+    }
+
+    fn run(&mut self, module: &Module, body: &mut Reader, locals: usize) -> Result<()> {
+        let check_local = |index: u32| -> Result<u32> {
+            ensure!((index as usize) < locals, "Unknown local {}", index);
+            Ok(index)
+        };
+        loop {
+            let op = body.byte()?;
+            match op {
+                0x00 => {
+                    self.code.push(Op::Unreachable);
+                    self.unreachable = true;
+                }
+                0x01 => {}
+                0x02 => {
+                    let bt = self.block_type(module, body)?;
+                    self.push_label(LabelKind::Block, bt)?;
+                }
+                0x03 => {
+                    let bt = self.block_type(module, body)?;
+                    self.push_label(LabelKind::Loop, bt)?;
+                }
+                0x04 => {
+                    let bt = self.block_type(module, body)?;
+                    self.pop(1)?;
+                    let fixup = self.code.len();
+                    self.code.push(Op::BrIfZero(0));
+                    self.push_label(LabelKind::If, bt)?;
+                    self.labels.last_mut().unwrap().else_fixup = Some(fixup);
+                }
+                0x05 => {
+                    let label = self.labels.last().unwrap();
+                    ensure!(
+                        label.kind == LabelKind::If && label.else_fixup.is_some(),
+                        "Invalid else"
+                    );
+                    let jump = self.code.len();
+                    self.code.push(Op::Jump(0));
+                    let pc = self.pc();
+                    let label = self.labels.last_mut().unwrap();
+                    label.fixups.push(Fixup::Op(jump));
+                    let else_fixup = label.else_fixup.take().unwrap();
+                    self.height = label.height + label.params;
+                    self.unreachable = label.outer_unreachable;
+                    self.patch(Fixup::Op(else_fixup), pc);
+                }
+                0x0b => {
+                    if self.labels.len() == 1 {
+                        self.code.push(Op::Return);
+                        let pc = self.pc() - 1;
+                        let label = self.labels.pop().unwrap();
+                        for fixup in label.fixups {
+                            self.patch(fixup, pc);
+                        }
+                        ensure!(body.eof(), "Unexpected instructions after the function end");
+                        return Ok(());
+                    }
+                    let label = self.labels.pop().unwrap();
+                    let pc = self.pc();
+                    if let Some(else_fixup) = label.else_fixup {
+                        ensure!(
+                            label.params == label.results,
+                            "The if without else must not change the stack"
+                        );
+                        self.patch(Fixup::Op(else_fixup), pc);
+                    }
+                    for fixup in label.fixups {
+                        self.patch(fixup, pc);
+                    }
+                    self.height = label.height + label.results;
+                    self.unreachable = label.outer_unreachable;
+                }
+                0x0c => {
+                    let depth = body.u32()?;
+                    let branch = self.branch(depth, Fixup::Op(self.code.len()))?;
+                    self.code.push(Op::Br(branch));
+                    self.unreachable = true;
+                }
+                0x0d => {
+                    let depth = body.u32()?;
+                    self.pop(1)?;
+                    let branch = self.branch(depth, Fixup::Op(self.code.len()))?;
+                    self.code.push(Op::BrIf(branch));
+                }
+                0x0e => {
+                    let n = body.u32()? + 1;
+                    self.pop(1)?;
+                    let start = self.br_tables.len() as u32;
+                    for _ in 0..n {
+                        let depth = body.u32()?;
+                        let branch = self.branch(depth, Fixup::Table(self.br_tables.len()))?;
+                        self.br_tables.push(branch);
+                    }
+                    self.code.push(Op::BrTable { start, len: n });
+                    self.unreachable = true;
+                }
+                0x0f => {
+                    self.code.push(Op::Return);
+                    self.unreachable = true;
+                }
+                0x10 => {
+                    let index = body.u32()?;
+                    let ty = module.func_signature(index)?;
+                    self.pop(ty.params as u32)?;
+                    self.push(ty.results as u32);
+                    self.code.push(Op::Call(index));
+                }
+                0x11 => {
+                    let ty_index = body.u32()?;
+                    ensure!(body.u32()? == 0, "Unsupported table index");
+                    let ty = module.func_type(ty_index)?.clone();
+                    self.pop(1 + ty.params as u32)?;
+                    self.push(ty.results as u32);
+                    self.code.push(Op::CallIndirect(ty_index));
+                }
+                0x1a => {
+                    self.pop(1)?;
+                    self.code.push(Op::Drop);
+                }
+                0x1b | 0x1c => {
+                    if op == 0x1c {
+                        for _ in 0..body.u32()? {
+                            body.value_type()?;
+                        }
+                    }
+                    self.pop(3)?;
+                    self.push(1);
+                    self.code.push(Op::Select);
+                }
+                0x20 => {
+                    let index = check_local(body.u32()?)?;
+                    self.push(1);
+                    self.code.push(Op::LocalGet(index));
+                }
+                0x21 => {
+                    let index = check_local(body.u32()?)?;
+                    self.pop(1)?;
+                    self.code.push(Op::LocalSet(index));
+                }
+                0x22 => {
+                    let index = check_local(body.u32()?)?;
+                    self.pop(1)?;
+                    self.push(1);
+                    self.code.push(Op::LocalTee(index));
+                }
+                0x23 | 0x24 => {
+                    let index = body.u32()?;
+                    ensure!(
+                        (index as usize) < module.globals.len(),
+                        "Unknown global {}",
+                        index
+                    );
+                    if op == 0x23 {
+                        self.push(1);
+                        self.code.push(Op::GlobalGet(index));
+                    } else {
+                        self.pop(1)?;
+                        self.code.push(Op::GlobalSet(index));
+                    }
+                }
+                0x28 | 0x29 | 0x2c..=0x35 => {
+                    body.u32()?;
+                    let offset = body.u32()?;
+                    self.pop(1)?;
+                    self.push(1);
+                    self.code.push(Op::Load(op, offset));
+                }
+                0x36 | 0x37 | 0x3a..=0x3e => {
+                    body.u32()?;
+                    let offset = body.u32()?;
+                    self.pop(2)?;
+                    self.code.push(Op::Store(op, offset));
+                }
+                0x3f => {
+                    body.byte()?;
+                    self.push(1);
+                    self.code.push(Op::MemorySize);
+                }
+                0x40 => {
+                    body.byte()?;
+                    self.pop(1)?;
+                    self.push(1);
+                    self.code.push(Op::MemoryGrow);
+                }
+                0x41 => {
+                    let value = body.signed(32)? as i32 as u32 as u64;
+                    self.push(1);
+                    self.code.push(Op::Const(value));
+                }
+                0x42 => {
+                    let value = body.signed(64)? as u64;
+                    self.push(1);
+                    self.code.push(Op::Const(value));
+                }
+                // eqz, clz, ctz, popcnt, wrap, extend
+                0x45 | 0x50 | 0x67..=0x69 | 0x79..=0x7b | 0xa7 | 0xac | 0xad | 0xc0..=0xc4 => {
+                    self.pop(1)?;
+                    self.push(1);
+                    self.code.push(Op::Numeric(op));
+                }
+                // comparisons and binary operations
+                0x46..=0x4f | 0x51..=0x5a | 0x6a..=0x78 | 0x7c..=0x8a => {
+                    self.pop(2)?;
+                    self.push(1);
+                    self.code.push(Op::Numeric(op));
+                }
+                0xfc => match body.u32()? {
+                    10 => {
+                        body.bytes(2)?;
+                        self.pop(3)?;
+                        self.code.push(Op::MemoryCopy);
+                    }
+                    11 => {
+                        body.byte()?;
+                        self.pop(3)?;
+                        self.code.push(Op::MemoryFill);
+                    }
+                    sub => bail!(format!("Unsupported instruction 0xfc {}", sub)),
+                },
+                _ => bail!(format!("Unsupported instruction 0x{:x}", op)),
+            }
+        }
+    }
+}
+
+/// an instance of the circom wasm, the exported functions can be invoked by name
+pub(crate) struct Instance {
+    module: Module,
+    memory: Vec<u8>,
+    max_pages: usize,
+    globals: Vec<u64>,
+    table: Vec<Option<u32>>,
+}
+
+struct Frame {
+    func: usize,
+    pc: usize,
+    locals: usize,
+    stack: usize,
+}
+
+impl Instance {
+    pub fn new(bytes: &[u8]) -> Result<Self> {
+        let module = Module::parse(bytes)?;
+        let (min_pages, max_pages) = module.memory.unwrap_or((0, Some(0)));
+        let mut memory = vec![0u8; min_pages as usize * PAGE_SIZE];
+        for (offset, data) in module.data.iter() {
+            let offset = *offset as usize;
+            ensure!(
+                offset + data.len() <= memory.len(),
+                "The data segment is out of the memory"
+            );
+            memory[offset..offset + data.len()].copy_from_slice(data);
+        }
+        let mut table = vec![None; module.table_size as usize];
+        for (offset, funcs) in module.elements.iter() {
+            let offset = *offset as usize;
+            ensure!(
+                offset + funcs.len() <= table.len(),
+                "The element segment is out of the table"
+            );
+            for (i, func) in funcs.iter().enumerate() {
+                table[offset + i] = Some(*func);
+            }
+        }
+        let globals = module.globals.clone();
+        let start = module.start;
+        let mut instance = Instance {
+            module,
+            memory,
+            max_pages: max_pages.map_or(MAX_PAGES, |p| p as usize),
+            globals,
+            table,
+        };
+        if let Some(start) = start {
+            instance.call(start, &[])?;
+        }
+        Ok(instance)
+    }
+
+    pub fn has_export(&self, name: &str) -> bool {
+        self.module.exports.contains_key(name)
+    }
+
+    /// invoke the exported function, the i32 values are zero extended
+    pub fn invoke(&mut self, name: &str, args: &[u64]) -> Result<Vec<u64>> {
+        let index = *self
+            .module
+            .exports
+            .get(name)
+            .ok_or_else(|| anyhow!("function {} not found", name))?;
+        self.call(index, args)
+    }
+
+    fn call(&mut self, index: u32, args: &[u64]) -> Result<Vec<u64>> {
+        let Instance {
+            module,
+            memory,
+            max_pages,
+            globals,
+            table,
+        } = self;
+        let module = &*module;
+        let mut stack: Vec<u64> = args.to_vec();
+        let mut locals: Vec<u64> = vec![];
+        let num_imports = module.imports.len();
+        if (index as usize) < num_imports {
+            call_host(&module.imports[index as usize], &mut stack)?;
+            return Ok(stack);
+        }
+
+        let mut frames: Vec<Frame> = vec![];
+        let mut cur = enter(
+            module,
+            index as usize - num_imports,
+            &mut stack,
+            &mut locals,
+        )?;
+        loop {
+            let function = &module.functions[cur.func];
+            let op = function.code[cur.pc];
+            cur.pc += 1;
+            match op {
+                Op::Unreachable => bail!("wasm trap: unreachable"),
+                Op::Br(b) => {
+                    branch(&mut stack, cur.stack, b);
+                    cur.pc = b.target as usize;
+                }
+                Op::BrIf(b) => {
+                    if pop(&mut stack) as u32 != 0 {
+                        branch(&mut stack, cur.stack, b);
+                        cur.pc = b.target as usize;
+                    }
+                }
+                Op::BrIfZero(target) => {
+                    if pop(&mut stack) as u32 == 0 {
+                        cur.pc = target as usize;
+                    }
+                }
+                Op::Jump(target) => cur.pc = target as usize,
+                Op::BrTable { start, len } => {
+                    let i = (pop(&mut stack) as u32).min(len - 1);
+                    let b = function.br_tables[(start + i) as usize];
+                    branch(&mut stack, cur.stack, b);
+                    cur.pc = b.target as usize;
+                }
+                Op::Return => {
+                    let results = stack.len() - function.results;
+                    stack.drain(cur.stack..results);
+                    locals.truncate(cur.locals);
+                    match frames.pop() {
+                        Some(frame) => cur = frame,
+                        None => return Ok(stack),
+                    }
+                }
+                Op::Call(index) => {
+                    let index = index as usize;
+                    if index < num_imports {
+                        call_host(&module.imports[index], &mut stack)?;
+                    } else {
+                        ensure!(
+                            frames.len() < MAX_CALL_DEPTH,
+                            "wasm trap: call stack exhausted"
+                        );
+                        let callee = enter(module, index - num_imports, &mut stack, &mut locals)?;
+                        frames.push(std::mem::replace(&mut cur, callee));
+                    }
+                }
+                Op::CallIndirect(ty) => {
+                    let i = pop(&mut stack) as u32 as usize;
+                    let index = table
+                        .get(i)
+                        .copied()
+                        .flatten()
+                        .ok_or_else(|| anyhow!("wasm trap: undefined element {}", i))?
+                        as usize;
+                    let expected = &module.types[ty as usize];
+                    if index < num_imports {
+                        let import = &module.imports[index];
+                        ensure!(
+                            import.params == expected.params && import.results == expected.results,
+                            "wasm trap: indirect call type mismatch"
+                        );
+                        call_host(import, &mut stack)?;
+                    } else {
+                        let callee = &module.functions[index - num_imports];
+                        ensure!(
+                            callee.params == expected.params && callee.results == expected.results,
+                            "wasm trap: indirect call type mismatch"
+                        );
+                        ensure!(
+                            frames.len() < MAX_CALL_DEPTH,
+                            "wasm trap: call stack exhausted"
+                        );
+                        let callee = enter(module, index - num_imports, &mut stack, &mut locals)?;
+                        frames.push(std::mem::replace(&mut cur, callee));
+                    }
+                }
+                Op::Drop => {
+                    pop(&mut stack);
+                }
+                Op::Select => {
+                    let c = pop(&mut stack) as u32;
+                    let b = pop(&mut stack);
+                    if c == 0 {
+                        *stack.last_mut().unwrap() = b;
+                    }
+                }
+                Op::LocalGet(i) => stack.push(locals[cur.locals + i as usize]),
+                Op::LocalSet(i) => locals[cur.locals + i as usize] = pop(&mut stack),
+                Op::LocalTee(i) => locals[cur.locals + i as usize] = *stack.last().unwrap(),
+                Op::GlobalGet(i) => stack.push(globals[i as usize]),
+                Op::GlobalSet(i) => globals[i as usize] = pop(&mut stack),
+                Op::Load(op, offset) => {
+                    let addr = pop(&mut stack) as u32 as usize + offset as usize;
+                    let value = load(memory, op, addr)?;
+                    stack.push(value);
+                }
+                Op::Store(op, offset) => {
+                    let value = pop(&mut stack);
+                    let addr = pop(&mut stack) as u32 as usize + offset as usize;
+                    store(memory, op, addr, value)?;
+                }
+                Op::MemorySize => stack.push((memory.len() / PAGE_SIZE) as u64),
+                Op::MemoryGrow => {
+                    let delta = pop(&mut stack) as u32 as usize;
+                    let pages = memory.len() / PAGE_SIZE;
+                    if pages + delta > *max_pages {
+                        stack.push(u32::MAX as u64);
+                    } else {
+                        memory.resize((pages + delta) * PAGE_SIZE, 0);
+                        stack.push(pages as u64);
+                    }
+                }
+                Op::MemoryCopy => {
+                    let n = pop(&mut stack) as u32 as usize;
+                    let src = pop(&mut stack) as u32 as usize;
+                    let dst = pop(&mut stack) as u32 as usize;
+                    check_bounds(memory, src, n)?;
+                    check_bounds(memory, dst, n)?;
+                    memory.copy_within(src..src + n, dst);
+                }
+                Op::MemoryFill => {
+                    let n = pop(&mut stack) as u32 as usize;
+                    let value = pop(&mut stack) as u8;
+                    let dst = pop(&mut stack) as u32 as usize;
+                    check_bounds(memory, dst, n)?;
+                    memory[dst..dst + n].fill(value);
+                }
+                Op::Const(value) => stack.push(value),
+                Op::Numeric(op) => numeric(op, &mut stack)?,
+            }
+        }
+    }
+}
+
+/// move the arguments into the locals of the callee, and return its frame
+fn enter(
+    module: &Module,
+    func: usize,
+    stack: &mut Vec<u64>,
+    locals: &mut Vec<u64>,
+) -> Result<Frame> {
+    let function = &module.functions[func];
+    ensure!(
+        stack.len() >= function.params,
+        "Invalid number of arguments"
+    );
+    let base = locals.len();
+    locals.extend(stack.drain(stack.len() - function.params..));
+    locals.resize(base + function.locals, 0);
+    Ok(Frame {
+        func,
+        pc: 0,
+        locals: base,
+        stack: stack.len(),
+    })
+}
+
+fn call_host(import: &Import, stack: &mut Vec<u64>) -> Result<()> {
+    let args = stack.split_off(stack.len() - import.params);
+    match import.host {
+        HostFunc::Error => bail!(format!(
+            "circom runtime error: {:?}",
+            args.iter().map(|a| *a as u32 as i32).collect::<Vec<_>>()
+        )),
+        HostFunc::ExceptionHandler => {
+            let code = args.first().map_or(0, |a| *a as u32);
+            let reason = match code {
+                1 => "Signal not found",
+                2 => "Too many signals set",
+                3 => "Signal already set",
+                4 => "Assert Failed",
+                5 => "Not enough memory",
+                6 => "Input signal array access exceeds the size",
+                _ => "Unknown error",
+            };
+            bail!(format!("circom exception {}: {}", code, reason))
+        }
+        HostFunc::Ignore => Ok(()),
+    }
+}
+
+fn branch(stack: &mut Vec<u64>, base: usize, b: Branch) {
+    let keep = b.keep as usize;
+    let top = stack.len() - keep;
+    let dest = base + b.height as usize;
+    if dest != top {
+        stack.copy_within(top.., dest);
+        stack.truncate(dest + keep);
+    }
+}
+
+#[inline]
+fn pop(stack: &mut Vec<u64>) -> u64 {
+    // the stack heights are checked in the compilation
+    stack.pop().unwrap()
+}
+
+fn check_bounds(memory: &[u8], addr: usize, n: usize) -> Result<()> {
+    ensure!(
+        addr + n <= memory.len(),
+        "wasm trap: out of bounds memory access"
+    );
+    Ok(())
+}
+
+fn load(memory: &[u8], op: u8, addr: usize) -> Result<u64> {
+    let n = match op {
+        0x28 | 0x34 | 0x35 => 4,
+        0x29 => 8,
+        0x2c | 0x2d | 0x30 | 0x31 => 1,
+        _ => 2,
+    };
+    check_bounds(memory, addr, n)?;
+    let mut buf = [0u8; 8];
+    buf[..n].copy_from_slice(&memory[addr..addr + n]);
+    let v = u64::from_le_bytes(buf);
+    Ok(match op {
+        0x28 | 0x29 | 0x2d | 0x2f | 0x31 | 0x33 | 0x35 => v,
+        0x2c => v as u8 as i8 as i32 as u32 as u64,
+        0x2e => v as u16 as i16 as i32 as u32 as u64,
+        0x30 => v as u8 as i8 as i64 as u64,
+        0x32 => v as u16 as i16 as i64 as u64,
+        _ => v as u32 as i32 as i64 as u64,
+    })
+}
+
+fn store(memory: &mut [u8], op: u8, addr: usize, value: u64) -> Result<()> {
+    let n = match op {
+        0x36 | 0x3e => 4,
+        0x37 => 8,
+        0x3a | 0x3c => 1,
+        _ => 2,
+    };
+    check_bounds(memory, addr, n)?;
+    memory[addr..addr + n].copy_from_slice(&value.to_le_bytes()[..n]);
+    Ok(())
+}
+
+fn numeric(op: u8, stack: &mut Vec<u64>) -> Result<()> {
+    let result = match op {
+        // i32 unary
+        0x45 | 0x67..=0x69 | 0xc0 | 0xc1 => {
+            let a = pop(stack) as u32;
+            (match op {
+                0x45 => (a == 0) as u32,
+                0x67 => a.leading_zeros(),
+                0x68 => a.trailing_zeros(),
+                0x69 => a.count_ones(),
+                0xc0 => a as u8 as i8 as i32 as u32,
+                _ => a as u16 as i16 as i32 as u32,
+            }) as u64
+        }
+        // i64 unary and conversions
+        0x50 | 0x79..=0x7b | 0xa7 | 0xac | 0xad | 0xc2..=0xc4 => {
+            let a = pop(stack);
+            match op {
+                0x50 => (a == 0) as u64,
+                0x79 => a.leading_zeros() as u64,
+                0x7a => a.trailing_zeros() as u64,
+                0x7b => a.count_ones() as u64,
+                0xa7 => a as u32 as u64,
+                0xac => a as u32 as i32 as i64 as u64,
+                0xad => a as u32 as u64,
+                0xc2 => a as u8 as i8 as i64 as u64,
+                0xc3 => a as u16 as i16 as i64 as u64,
+                _ => a as u32 as i32 as i64 as u64,
+            }
+        }
+        // i32 binary
+        0x46..=0x4f | 0x6a..=0x78 => {
+            let b = pop(stack) as u32;
+            let a = pop(stack) as u32;
+            let (sa, sb) = (a as i32, b as i32);
+            (match op {
+                0x46 => (a == b) as u32,
+                0x47 => (a != b) as u32,
+                0x48 => (sa < sb) as u32,
+                0x49 => (a < b) as u32,
+                0x4a => (sa > sb) as u32,
+                0x4b => (a > b) as u32,
+                0x4c => (sa <= sb) as u32,
+                0x4d => (a <= b) as u32,
+                0x4e => (sa >= sb) as u32,
+                0x4f => (a >= b) as u32,
+                0x6a => a.wrapping_add(b),
+                0x6b => a.wrapping_sub(b),
+                0x6c => a.wrapping_mul(b),
+                0x6d => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    ensure!(!(sa == i32::MIN && sb == -1), "wasm trap: integer overflow");
+                    (sa / sb) as u32
+                }
+                0x6e => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    a / b
+                }
+                0x6f => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    sa.wrapping_rem(sb) as u32
+                }
+                0x70 => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    a % b
+                }
+                0x71 => a & b,
+                0x72 => a | b,
+                0x73 => a ^ b,
+                0x74 => a.wrapping_shl(b),
+                0x75 => sa.wrapping_shr(b) as u32,
+                0x76 => a.wrapping_shr(b),
+                0x77 => a.rotate_left(b % 32),
+                _ => a.rotate_right(b % 32),
+            }) as u64
+        }
+        // i64 binary
+        _ => {
+            let b = pop(stack);
+            let a = pop(stack);
+            let (sa, sb) = (a as i64, b as i64);
+            match op {
+                0x51 => (a == b) as u64,
+                0x52 => (a != b) as u64,
+                0x53 => (sa < sb) as u64,
+                0x54 => (a < b) as u64,
+                0x55 => (sa > sb) as u64,
+                0x56 => (a > b) as u64,
+                0x57 => (sa <= sb) as u64,
+                0x58 => (a <= b) as u64,
+                0x59 => (sa >= sb) as u64,
+                0x5a => (a >= b) as u64,
+                0x7c => a.wrapping_add(b),
+                0x7d => a.wrapping_sub(b),
+                0x7e => a.wrapping_mul(b),
+                0x7f => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    ensure!(!(sa == i64::MIN && sb == -1), "wasm trap: integer overflow");
+                    (sa / sb) as u64
+                }
+                0x80 => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    a / b
+                }
+                0x81 => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    sa.wrapping_rem(sb) as u64
+                }
+                0x82 => {
+                    ensure!(b != 0, "wasm trap: integer divide by zero");
+                    a % b
+                }
+                0x83 => a & b,
+                0x84 => a | b,
+                0x85 => a ^ b,
+                0x86 => a.wrapping_shl(b as u32),
+                0x87 => sa.wrapping_shr(b as u32) as u64,
+                0x88 => a.wrapping_shr(b as u32),
+                0x89 => a.rotate_left((b % 64) as u32),
+                _ => a.rotate_right((b % 64) as u32),
+            }
+        }
+    };
+    stack.push(result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(id: u8, content: &[u8]) -> Vec<u8> {
+        let mut s = vec![id, content.len() as u8];
+        s.extend_from_slice(content);
+        s
+    }
+
+    fn body(code: &[u8]) -> Vec<u8> {
+        let mut b = vec![code.len() as u8];
+        b.extend_from_slice(code);
+        b
+    }
+
+    fn export(name: &str, index: u8) -> Vec<u8> {
+        let mut e = vec![name.len() as u8];
+        e.extend_from_slice(name.as_bytes());
+        e.extend_from_slice(&[0, index]);
+        e
+    }
+
+    // the functions: fac(i64) -> i64 by recursion, sum(i64) -> i64 by loop, mem(i32) -> i32 to
+    // store and load a byte, table(i32) -> i32 by br_table, indirect(i64) -> i64 calls fac
+    fn test_module() -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend(section(
+            1,
+            &[2, 0x60, 1, 0x7e, 1, 0x7e, 0x60, 1, 0x7f, 1, 0x7f],
+        ));
+        wasm.extend(section(3, &[5, 0, 0, 1, 1, 0]));
+        wasm.extend(section(4, &[1, 0x70, 0, 1]));
+        wasm.extend(section(5, &[1, 0, 1]));
+        let mut exports = vec![5];
+        for (i, name) in ["fac", "sum", "mem", "table", "indirect"]
+            .iter()
+            .enumerate()
+        {
+            exports.extend(export(name, i as u8));
+        }
+        wasm.extend(section(7, &exports));
+        wasm.extend(section(9, &[1, 0, 0x41, 0, 0x0b, 1, 0]));
+        let mut code = vec![5];
+        code.extend(body(&[
+            0, 0x20, 0, 0x50, 0x04, 0x7e, 0x42, 1, 0x05, 0x20, 0, 0x20, 0, 0x42, 1, 0x7d, 0x10, 0,
+            0x7e, 0x0b, 0x0b,
+        ]));
+        code.extend(body(&[
+            1, 1, 0x7e, 0x02, 0x40, 0x03, 0x40, 0x20, 0, 0x50, 0x0d, 1, 0x20, 1, 0x20, 0, 0x7c,
+            0x21, 1, 0x20, 0, 0x42, 1, 0x7d, 0x21, 0, 0x0c, 0, 0x0b, 0x0b, 0x20, 1, 0x0b,
+        ]));
+        code.extend(body(&[
+            0, 0x41, 8, 0x20, 0, 0x36, 2, 0, 0x41, 8, 0x2d, 0, 1, 0x0b,
+        ]));
+        code.extend(body(&[
+            0, 0x02, 0x40, 0x02, 0x40, 0x20, 0, 0x0e, 1, 0, 1, 0x0b, 0x41, 10, 0x0f, 0x0b, 0x41,
+            20, 0x0b,
+        ]));
+        code.extend(body(&[0, 0x20, 0, 0x41, 0, 0x11, 0, 0, 0x0b]));
+        wasm.extend(section(10, &code));
+        wasm.extend(section(11, &[1, 0, 0x41, 16, 0x0b, 2, 42, 43]));
+        wasm
+    }
+
+    #[test]
+    fn test_interpreter_control_flow() {
+        let mut instance = Instance::new(&test_module()).unwrap();
+        assert_eq!(instance.invoke("fac", &[10]).unwrap(), vec![3628800]);
+        assert_eq!(instance.invoke("sum", &[100]).unwrap(), vec![5050]);
+        assert_eq!(instance.invoke("mem", &[0x04030201]).unwrap(), vec![2]);
+        assert_eq!(instance.invoke("table", &[0]).unwrap(), vec![10]);
+        assert_eq!(instance.invoke("table", &[1]).unwrap(), vec![20]);
+        assert_eq!(instance.invoke("table", &[7]).unwrap(), vec![20]);
+        assert_eq!(instance.invoke("indirect", &[5]).unwrap(), vec![120]);
+        assert_eq!(&instance.memory[16..18], &[42, 43]);
+        assert!(instance.invoke("missing", &[]).is_err());
+    }
+}
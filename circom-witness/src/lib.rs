@@ -0,0 +1,90 @@
+//! The witness calculation of the circom wasm, shared by algebraic and algebraic-gpu. The
+//! witness is calculated either by wasmer or by a native interpreter of the wasm.
+#[macro_use]
+extern crate serde;
+
+#[cfg(feature = "wasmer")]
+mod circom;
+mod convert;
+mod interpreter;
+#[cfg(feature = "wasmer")]
+mod memory;
+mod native;
+#[cfg(feature = "wasmer")]
+mod witness_calculator;
+
+use anyhow::{bail, Result};
+pub use convert::flat_array;
+use fnv::FnvHasher;
+pub use native::NativeWitnessCalculator;
+use num_bigint::BigInt;
+use std::hash::Hasher;
+use std::str::FromStr;
+#[cfg(feature = "wasmer")]
+pub use witness_calculator::WitnessCalculator;
+
+/// the way to run the circom wasm for the witness
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WitnessBackend {
+    /// compile and run the wasm by wasmer, which needs the feature `wasmer`
+    #[default]
+    Wasmer,
+    /// interpret the wasm in Rust, no wasm runtime is needed. Experimental: it is not measured
+    /// against wasmer on the recursive circuits yet, see `benches/witness.rs`
+    Native,
+}
+
+impl FromStr for WitnessBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wasmer" => Ok(WitnessBackend::Wasmer),
+            "native" => Ok(WitnessBackend::Native),
+            _ => bail!(format!(
+                "Unknown witness backend: {}, expect wasmer or native",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for WitnessBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WitnessBackend::Wasmer => write!(f, "wasmer"),
+            WitnessBackend::Native => write!(f, "native"),
+        }
+    }
+}
+
+impl WitnessBackend {
+    pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        wasm_file: &str,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        match self {
+            #[cfg(feature = "wasmer")]
+            WitnessBackend::Wasmer => {
+                WitnessCalculator::from_file(wasm_file)?.calculate_witness(inputs, sanity_check)
+            }
+            #[cfg(not(feature = "wasmer"))]
+            WitnessBackend::Wasmer => {
+                bail!("The wasmer witness backend needs the feature wasmer")
+            }
+            WitnessBackend::Native => NativeWitnessCalculator::from_file(wasm_file)?
+                .calculate_witness(inputs, sanity_check),
+        }
+    }
+}
+
+pub(crate) fn fnv(inp: &str) -> (u32, u32) {
+    let mut hasher = FnvHasher::default();
+    hasher.write(inp.as_bytes());
+    let h = hasher.finish();
+
+    ((h >> 32) as u32, h as u32)
+}
@@ -0,0 +1,188 @@
+// the same as `WitnessCalculator`, but the circom wasm is run by the interpreter instead of wasmer
+use crate::convert::{from_array32, to_array32};
+use crate::fnv;
+use crate::interpreter::Instance;
+use anyhow::Result;
+use num_bigint::BigInt;
+
+pub struct NativeWitnessCalculator {
+    instance: Instance,
+    n32: u32,
+    pub prime: BigInt,
+    pub n64: u32,
+    pub circom_version: u32,
+}
+
+impl NativeWitnessCalculator {
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut instance = Instance::new(bytes)?;
+        let circom_version = if instance.has_export("getVersion") {
+            call_u32(&mut instance, "getVersion", &[])?
+        } else {
+            1
+        };
+        let n32 = call_u32(&mut instance, "getFieldNumLen32", &[])?;
+        instance.invoke("getRawPrime", &[])?;
+        let mut arr = vec![0; n32 as usize];
+        for i in 0..n32 {
+            arr[(n32 - i - 1) as usize] =
+                call_u32(&mut instance, "readSharedRWMemory", &[i as u64])?;
+        }
+        let prime = from_array32(arr);
+        let n64 = ((prime.bits() - 1) / 64 + 1) as u32;
+
+        Ok(NativeWitnessCalculator {
+            instance,
+            n32,
+            prime,
+            n64,
+            circom_version,
+        })
+    }
+
+    pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let n32 = self.n32 as usize;
+        let wtns_u32 = self.calculate_witness_bin(inputs, sanity_check)?;
+        Ok(wtns_u32
+            .chunks(n32)
+            .map(|chunk| from_array32(chunk.iter().rev().copied().collect()))
+            .collect())
+    }
+
+    pub fn calculate_witness_bin<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<u32>> {
+        let n32 = self.n32;
+        self.instance.invoke("init", &[sanity_check as u64])?;
+
+        // allocate the inputs
+        for (name, values) in inputs.into_iter() {
+            let (msb, lsb) = fnv(&name);
+            for (i, value) in values.into_iter().enumerate() {
+                let f_arr = to_array32(&value, n32 as usize);
+                for j in 0..n32 {
+                    self.instance.invoke(
+                        "writeSharedRWMemory",
+                        &[j as u64, f_arr[(n32 - 1 - j) as usize] as u64],
+                    )?;
+                }
+                self.instance
+                    .invoke("setInputSignal", &[msb as u64, lsb as u64, i as u64])?;
+            }
+        }
+
+        let mut w = Vec::new();
+        let witness_size = call_u32(&mut self.instance, "getWitnessSize", &[])?;
+        for i in 0..witness_size {
+            self.instance.invoke("getWitness", &[i as u64])?;
+            for j in 0..n32 {
+                w.push(call_u32(
+                    &mut self.instance,
+                    "readSharedRWMemory",
+                    &[j as u64],
+                )?);
+            }
+        }
+        Ok(w)
+    }
+}
+
+fn call_u32(instance: &mut Instance, name: &str, args: &[u64]) -> Result<u32> {
+    let results = instance.invoke(name, args)?;
+    results
+        .first()
+        .map(|r| *r as u32)
+        .ok_or_else(|| anyhow::anyhow!("function {} returns nothing", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn check_same_witness(wasm_file: &str, inputs: Vec<(String, Vec<BigInt>)>, expected: &[&str]) {
+        let mut native = NativeWitnessCalculator::from_file(wasm_file).unwrap();
+        let w = native.calculate_witness(inputs.clone(), true).unwrap();
+        let expected = expected
+            .iter()
+            .map(|e| BigInt::from_str(e).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(w, expected);
+
+        #[cfg(feature = "wasmer")]
+        {
+            let mut wasmer = crate::WitnessCalculator::from_file(wasm_file).unwrap();
+            assert_eq!(native.prime, wasmer.memory.prime);
+            assert_eq!(native.n64, wasmer.n64);
+            assert_eq!(native.circom_version, wasmer.circom_version);
+            assert_eq!(w, wasmer.calculate_witness(inputs.clone(), true).unwrap());
+
+            let bin = native.calculate_witness_bin(inputs.clone(), false).unwrap();
+            assert_eq!(bin, wasmer.calculate_witness_bin(inputs, false).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_native_witness_multiplier() {
+        let inputs = vec![
+            ("a".to_string(), vec![BigInt::from(3)]),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        check_same_witness(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm"),
+            inputs.clone(),
+            &["1", "33", "3", "11"],
+        );
+        check_same_witness(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../algebraic/test-vectors/mycircuit.wasm"
+            ),
+            inputs,
+            &["1", "33", "3", "11"],
+        );
+
+        let inputs = vec![
+            (
+                "a".to_string(),
+                vec![BigInt::from_str(
+                    "21888242871839275222246405745257275088548364400416034343698204186575796149939",
+                )
+                .unwrap()],
+            ),
+            ("b".to_string(), vec![BigInt::from(11)]),
+        ];
+        check_same_witness(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm"),
+            inputs,
+            &[
+                "1",
+                "21888242871839275222246405745257275088548364400416034343698204186575672693159",
+                "21888242871839275222246405745257275088548364400416034343698204186575796149939",
+                "11",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_native_witness_missing_input() {
+        let mut native = NativeWitnessCalculator::from_file(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../test/multiplier.wasm"
+        ))
+        .unwrap();
+        let inputs = vec![("c".to_string(), vec![BigInt::from(3)])];
+        assert!(native.calculate_witness(inputs, false).is_err());
+    }
+}
@@ -1,13 +1,10 @@
 // copied and modified by https://github.com/arkworks-rs/circom-compat/blob/master/src/witness/witness_calculator.rs
-use crate::witness::{circom::Wasm, fnv, memory::SafeMemory};
+use crate::convert::{from_array32, to_array32};
+use crate::{circom::Wasm, fnv, memory::SafeMemory};
 use anyhow::{bail, Result};
-use ff::PrimeField;
-use num::ToPrimitive;
 use num_bigint::BigInt;
 use num_bigint::Sign;
-use num_traits::{One, Zero};
-use serde_json::Value;
-use std::str::FromStr;
+use num_traits::Zero;
 use wasmer::{imports, Function, Instance, Memory, MemoryType, Module, Store};
 
 use std::fs::OpenOptions;
@@ -23,29 +20,6 @@ pub struct WitnessCalculator {
     pub circom_version: u32,
 }
 
-fn from_array32(arr: Vec<u32>) -> BigInt {
-    let mut res = BigInt::zero();
-    let radix = BigInt::from(0x100000000u64);
-    for &val in arr.iter() {
-        res = res * &radix + BigInt::from(val);
-    }
-    res
-}
-
-fn to_array32(s: &BigInt, size: usize) -> Vec<u32> {
-    let mut res = vec![0; size];
-    let mut rem = s.clone();
-    let radix = BigInt::from(0x100000000u64);
-    let mut c = size;
-    while !rem.is_zero() {
-        c -= 1;
-        res[c] = (&rem % &radix).to_u32().unwrap();
-        rem /= &radix;
-    }
-
-    res
-}
-
 impl WitnessCalculator {
     pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
         let mut store = Store::default();
@@ -183,11 +157,7 @@ impl WitnessCalculator {
         Ok(w)
     }
 
-    pub fn save_witness_to_bin_file<E: PrimeField>(
-        &mut self,
-        filename: &str,
-        w: &Vec<u32>,
-    ) -> Result<()> {
+    pub fn save_witness_to_bin_file(&mut self, filename: &str, w: &Vec<u32>) -> Result<()> {
         let writer = OpenOptions::new()
             .write(true)
             .create(true)
@@ -195,10 +165,10 @@ impl WitnessCalculator {
             .open(filename)?;
 
         let writer = BufWriter::new(writer);
-        self.save_witness_from_bin_writer::<E, _>(writer, w)
+        self.save_witness_from_bin_writer(writer, w)
     }
 
-    pub fn save_witness_from_bin_writer<E: PrimeField, W: Write>(
+    pub fn save_witness_from_bin_writer<W: Write>(
         &mut self,
         mut writer: W,
         wtns: &Vec<u32>,
@@ -254,51 +224,6 @@ impl WitnessCalculator {
     }
 }
 
-#[allow(dead_code)]
-pub fn value_to_bigint(v: Value) -> BigInt {
-    match v {
-        Value::String(inner) => BigInt::from_str(&inner).unwrap(),
-        Value::Number(inner) => BigInt::from(
-            inner
-                .as_u64()
-                .unwrap_or_else(|| panic!("{} not a u32", inner)),
-        ),
-        _ => panic!("unsupported type {:?}", v),
-    }
-}
-
-pub fn flat_array(v: &[Value]) -> Vec<BigInt> {
-    let mut result = Vec::new();
-    fn fill_array(out: &mut Vec<BigInt>, value: &Value) {
-        match value {
-            Value::Array(inner) => {
-                for v2 in inner.iter() {
-                    fill_array(out, v2);
-                }
-            }
-            Value::Bool(inner) => {
-                if *inner {
-                    out.push(BigInt::one());
-                } else {
-                    out.push(BigInt::zero());
-                }
-            }
-            Value::String(inner) => {
-                out.push(BigInt::from_str(inner).unwrap());
-            }
-            Value::Number(inner) => {
-                out.push(BigInt::from_str(&inner.to_string()).unwrap());
-            }
-            _ => panic!(),
-        }
-    }
-
-    for v2 in v.iter() {
-        fill_array(&mut result, v2);
-    }
-    result
-}
-
 // callback hooks for debugging
 mod runtime {
     use super::*;
@@ -366,7 +291,9 @@ mod runtime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::convert::value_to_bigint;
     use serde_json::{json, Value};
+    use std::str::FromStr;
     use std::{collections::HashMap, path::PathBuf};
 
     struct TestCase<'a> {
@@ -387,7 +314,7 @@ mod tests {
         let inputs = HashMap::from([("a".to_string(), json!(3)), ("b".to_string(), json!(11))]);
 
         run_test(TestCase {
-            circuit_path: root_path("test-vectors/mycircuit.wasm").as_str(),
+            circuit_path: root_path("../algebraic/test-vectors/mycircuit.wasm").as_str(),
             inputs,
             n64: 4,
             witness: &["1", "33", "3", "11"],
@@ -407,7 +334,7 @@ mod tests {
         ]);
 
         run_test(TestCase {
-            circuit_path: root_path("test-vectors/mycircuit.wasm").as_str(),
+            circuit_path: root_path("../algebraic/test-vectors/mycircuit.wasm").as_str(),
             inputs,
             n64: 4,
             witness: &[
@@ -432,7 +359,7 @@ mod tests {
         ]);
 
         run_test(TestCase {
-            circuit_path: root_path("test-vectors/mycircuit.wasm").as_str(),
+            circuit_path: root_path("../algebraic/test-vectors/mycircuit.wasm").as_str(),
             inputs,
             n64: 4,
             witness: &[
@@ -0,0 +1,205 @@
+//! The circom templates embedded in the binary, so that the circuits generated by pil2circom are
+//! compiled without an `npm i` of their libraries.
+//!
+//! The library of a prime is written into a fresh private directory for each compile, and
+//! searched after the link directories of the user, which therefore override any embedded
+//! template of the same name. The directory is created exclusively and only readable by the
+//! user, so no other user can plant a template into it, and it's removed once compiled.
+use crate::errors::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+macro_rules! circuit {
+    ($name:literal) => {
+        circuit!("circuits", $name)
+    };
+    ($dir:literal, $name:literal) => {
+        (
+            $name,
+            include_str!(concat!("../../stark-circuits/", $dir, "/", $name)),
+        )
+    };
+}
+
+/// `stark-circuits/circuits`, the stark verifier blocks atop the scalar field of BLS12-381
+const STARK_CIRCUITS: [(&str, &str); 12] = [
+    circuit!("bn1togl3.circom"),
+    circuit!("compconstant64.circom"),
+    circuit!("evalpol.circom"),
+    circuit!("fft.circom"),
+    circuit!("gl.circom"),
+    circuit!("linearhash.circom"),
+    circuit!("merkle.circom"),
+    circuit!("merklehash.circom"),
+    circuit!("poseidon.circom"),
+    circuit!("poseidon_constants.circom"),
+    circuit!("treeselector.circom"),
+    circuit!("utils.circom"),
+];
+
+/// `stark-circuits/circuits.bn128`, the blocks atop the scalar field of BN128: the blocks which
+/// don't depend on the big field, and its own poseidon and `BN1toGL3`. circomlib is still
+/// resolved from the link directories.
+const STARK_CIRCUITS_BN128: [(&str, &str); 12] = [
+    circuit!("circuits.bn128", "bn1togl3.circom"),
+    circuit!("compconstant64.circom"),
+    circuit!("evalpol.circom"),
+    circuit!("fft.circom"),
+    circuit!("gl.circom"),
+    circuit!("linearhash.circom"),
+    circuit!("merkle.circom"),
+    circuit!("merklehash.circom"),
+    circuit!("circuits.bn128", "poseidon.circom"),
+    circuit!("circuits.bn128", "poseidon_constants.circom"),
+    circuit!("treeselector.circom"),
+    circuit!("utils.circom"),
+];
+
+/// `stark-circuits/circuits.gl`, the blocks of the goldilocks verifier with the custom gates of
+/// the compressor, and the blocks which don't depend on the field.
+const STARK_CIRCUITS_GL: [(&str, &str); 15] = [
+    circuit!("circuits.gl", "bitify.circom"),
+    circuit!("circuits.gl", "cinv.circom"),
+    circuit!("circuits.gl", "cmuladd.circom"),
+    circuit!("compconstant64.circom"),
+    circuit!("circuits.gl", "evalpol.circom"),
+    circuit!("circuits.gl", "fft.circom"),
+    circuit!("circuits.gl", "iszero.circom"),
+    circuit!("circuits.gl", "linearhash.circom"),
+    circuit!("circuits.gl", "merkle.circom"),
+    circuit!("circuits.gl", "merklehash.circom"),
+    circuit!("circuits.gl", "mux1.circom"),
+    circuit!("circuits.gl", "poseidon.circom"),
+    circuit!("circuits.gl", "poseidon_constants.circom"),
+    circuit!("treeselector.circom"),
+    circuit!("utils.circom"),
+];
+
+/// The embedded templates for the prime, as `(file name, source)`.
+pub fn library(prime: &str) -> &'static [(&'static str, &'static str)] {
+    match prime {
+        "bls12381" => &STARK_CIRCUITS,
+        "bn128" => &STARK_CIRCUITS_BN128,
+        "goldilocks" => &STARK_CIRCUITS_GL,
+        _ => &[],
+    }
+}
+
+/// The directory holding the embedded library during a compile, removed on drop
+#[derive(Debug)]
+pub struct LibraryDir {
+    path: PathBuf,
+}
+
+impl LibraryDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LibraryDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            log::warn!("remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Create a new directory only accessible by the user, failing if the path already exists
+fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(path)
+}
+
+/// Write the library of the prime into a new private directory and return it, or None if no
+/// template is embedded for the prime.
+pub fn library_dir(prime: &str) -> Result<Option<LibraryDir>> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let files = library(prime);
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let dir = loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!(
+            "eigen-circom-lib-{}-{}-{}-{:08x}",
+            prime,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+        match create_private_dir(&path) {
+            Ok(()) => break LibraryDir { path },
+            // taken by another process, or planted, try another name
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    };
+    for (name, source) in files.iter() {
+        std::fs::write(dir.path().join(name), source)?;
+    }
+    Ok(Some(dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circom_lib() {
+        let dir = library_dir("bls12381").unwrap().unwrap();
+        for (name, _) in library("bls12381") {
+            let embedded = std::fs::read(dir.path().join(name)).unwrap();
+            let origin = std::fs::read(format!("../stark-circuits/circuits/{}", name)).unwrap();
+            assert_eq!(embedded, origin, "{}", name);
+        }
+        // every file of stark-circuits is embedded
+        let n = std::fs::read_dir("../stark-circuits/circuits")
+            .unwrap()
+            .count();
+        assert_eq!(library("bls12381").len(), n);
+        assert!(library_dir("vesta").unwrap().is_none());
+
+        // the field dependent blocks of bn128 and goldilocks come from their own directories
+        for (prime, dir) in [("bn128", "circuits.bn128"), ("goldilocks", "circuits.gl")] {
+            let lib = library_dir(prime).unwrap().unwrap();
+            for entry in std::fs::read_dir(format!("../stark-circuits/{}", dir)).unwrap() {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap();
+                let embedded = std::fs::read(lib.path().join(name)).unwrap();
+                assert_eq!(embedded, std::fs::read(&path).unwrap(), "{:?}", path);
+            }
+            let names = library(prime).iter().map(|(n, _)| *n).collect::<Vec<_>>();
+            assert!(names.contains(&"poseidon.circom"));
+            assert!(names.contains(&"treeselector.circom"));
+        }
+    }
+
+    #[test]
+    fn test_circom_lib_private_dir() {
+        let dir = library_dir("bn128").unwrap().unwrap();
+        let path = dir.path().to_path_buf();
+        // a new directory for each compile
+        let other = library_dir("bn128").unwrap().unwrap();
+        assert_ne!(other.path(), dir.path());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        // an existing directory is never reused
+        assert_eq!(
+            create_private_dir(&path).unwrap_err().kind(),
+            std::io::ErrorKind::AlreadyExists
+        );
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -3,8 +3,9 @@
 use crate::errors::Result;
 use std::path::Path;
 
+pub mod circom_lib;
 mod compilation_user;
-mod errors;
+pub mod errors;
 mod execution_user;
 mod input_user;
 mod parser_user;
@@ -13,7 +14,8 @@ mod type_analysis_user;
 /// Align with https://github.com/iden3/circom/blob/master/circom/Cargo.toml#L3
 const CIRCOM_VERSION: &str = "2.1.2";
 
-/// Compile circom circuits to r1cs, and generate witness
+/// Compile circom circuits to r1cs, and generate witness.
+/// The includes not found in `link_directories` are resolved from the embedded `circom_lib`.
 pub fn circom_compiler(
     input: String,
     prime: String,
@@ -35,6 +37,12 @@ pub fn circom_compiler(
     )?;
     let input = Path::new(&input);
     let output = Path::new(&output);
+    let mut link_directories = link_directories;
+    // kept until the end of the compile, which removes the directory
+    let library = circom_lib::library_dir(&prime)?;
+    if let Some(dir) = &library {
+        link_directories.push(dir.path().to_string_lossy().to_string());
+    }
 
     let user_input = input_user::Input::new(input, output, o_style, prime, link_directories)?;
     let mut program_archive = parser_user::parse_project(&user_input)?;
@@ -6,7 +6,22 @@ use crate::bellman_ce::{
         bn256::{Bn256, Fr},
     },
 };
-use crate::{groth16::Groth16, json_utils::*, template::CONTRACT_TEMPLATE};
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+use crate::ceremony::{initial_parameters, rng_from_entropy, MPCParameters};
+use crate::errors::Groth16Error;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+use crate::{
+    binfile::SnarkjsEngine,
+    zkey::{is_zkey, ZkeyParameters},
+};
+use crate::{
+    groth16::Groth16,
+    json_utils::*,
+    template::{CONTRACT_TEMPLATE, CONTRACT_TEMPLATE_BLS12381},
+};
+/// the witness backend of `groth16_prove`
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub use algebraic::witness::WitnessBackend;
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 use algebraic::{
     bellman_ce::Engine,
@@ -16,20 +31,23 @@ use algebraic::{
     Field, PrimeField,
 };
 #[cfg(any(feature = "cuda", feature = "opencl"))]
+pub use algebraic_gpu::witness::WitnessBackend;
+#[cfg(any(feature = "cuda", feature = "opencl"))]
 use algebraic_gpu::{
     circom_circuit::CircomCircuit,
     reader::load_r1cs,
     witness::{load_input_for_witness, WitnessCalculator},
     Field, PrimeField,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Result};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use bellperson::{gpu, groth16::*};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use blstrs::{Bls12, Scalar};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use group::WnafGroup;
-use num_traits::Zero;
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use pairing::{Engine, MultiMillerLoop};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
@@ -49,17 +67,17 @@ pub fn groth16_setup(
     let mut rng = rand::thread_rng();
     match curve_type {
         "BN128" => {
-            let circuit = create_circuit_from_file::<Bn256>(circuit_file, None);
+            let circuit = create_circuit_from_file::<Bn256>(circuit_file, None)?;
             let (pk, vk) = Groth16::circuit_specific_setup(circuit, &mut rng)?;
             write_pk_vk_to_files(curve_type, pk, vk, pk_file, vk_file, to_hex)?
         }
         "BLS12381" => {
-            let circuit = create_circuit_from_file::<Bls12>(circuit_file, None);
+            let circuit = create_circuit_from_file::<Bls12>(circuit_file, None)?;
             let (pk, vk) = Groth16::circuit_specific_setup(circuit, &mut rng)?;
             write_pk_vk_to_files(curve_type, pk, vk, pk_file, vk_file, to_hex)?
         }
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     };
     Ok(())
@@ -77,17 +95,17 @@ pub fn groth16_setup_inplace(curve_type: &str, circuit_file: &str) -> Result<Set
     let mut rng = rand::thread_rng();
     let result = match curve_type {
         "BN128" => {
-            let circuit = create_circuit_from_file::<Bn256>(circuit_file, None);
+            let circuit = create_circuit_from_file::<Bn256>(circuit_file, None)?;
             let (pk, vk) = Groth16::circuit_specific_setup(circuit.clone(), &mut rng)?;
             SetupResult::BN128(circuit, pk, vk)
         }
         "BLS12381" => {
-            let circuit = create_circuit_from_file::<Bls12>(circuit_file, None);
+            let circuit = create_circuit_from_file::<Bls12>(circuit_file, None)?;
             let (pk, vk) = Groth16::circuit_specific_setup(circuit.clone(), &mut rng)?;
             SetupResult::BLS12381(circuit, pk, vk)
         }
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     };
     Ok(result)
@@ -104,13 +122,13 @@ pub fn groth16_setup(
     let mut rng = rand::thread_rng();
     match curve_type {
         "BLS12381" => {
-            let circuit = create_circuit_from_file::<Scalar>(circuit_file, None);
+            let circuit = create_circuit_from_file::<Scalar>(circuit_file, None)?;
             let (pk, vk): (Parameters<Bls12>, VerifyingKey<Bls12>) =
                 Groth16::circuit_specific_setup(circuit, &mut rng)?;
             write_pk_vk_to_files(curve_type, pk, vk, pk_file, vk_file, to_hex)?
         }
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     };
     Ok(())
@@ -131,13 +149,13 @@ pub fn groth16_setup_inplace(curve_type: &str, circuit_file: &str) -> Result<Set
     let mut rng = rand::thread_rng();
     let result = match curve_type {
         "BLS12381" => {
-            let circuit = create_circuit_from_file::<Scalar>(circuit_file, None);
+            let circuit = create_circuit_from_file::<Scalar>(circuit_file, None)?;
             let (pk, vk): (Parameters<Bls12>, VerifyingKey<Bls12>) =
                 Groth16::circuit_specific_setup(circuit.clone(), &mut rng)?;
             SetupResult::BLS12381(circuit, pk, vk)
         }
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     };
     Ok(result)
@@ -154,15 +172,14 @@ pub fn groth16_prove(
     public_input_file: &str,
     proof_file: &str,
     to_hex: bool,
+    witness_backend: WitnessBackend,
 ) -> Result<()> {
     let mut rng = rand::thread_rng();
 
-    let mut wtns = WitnessCalculator::from_file(wtns_file)?;
-    let inputs = load_input_for_witness(input_file);
-    let w = wtns.calculate_witness(inputs, false)?;
+    let inputs = load_input_for_witness(input_file)?;
+    let w = witness_backend.calculate_witness(wtns_file, inputs, false)?;
     match curve_type {
         "BN128" => {
-            let pk: Parameters<Bn256> = read_pk_from_file(pk_file, false)?;
             let w = w
                 .iter()
                 .map(|wi| {
@@ -173,15 +190,14 @@ pub fn groth16_prove(
                     }
                 })
                 .collect::<Vec<_>>();
-            let circuit = create_circuit_from_file::<Bn256>(circuit_file, Some(w));
-            let proof = Groth16::prove(&pk, circuit.clone(), &mut rng)?;
+            let circuit = create_circuit_from_file::<Bn256>(circuit_file, Some(w))?;
+            let proof = prove_with_pk_file(pk_file, circuit.clone(), &mut rng)?;
             let proof_json = serialize_proof(&proof, curve_type, to_hex)?;
             std::fs::write(proof_file, proof_json)?;
             let input_json = circuit.get_public_inputs_json();
             std::fs::write(public_input_file, input_json)?;
         }
         "BLS12381" => {
-            let pk: Parameters<Bls12> = read_pk_from_file(pk_file, false)?;
             let w = w
                 .iter()
                 .map(|wi| {
@@ -192,15 +208,15 @@ pub fn groth16_prove(
                     }
                 })
                 .collect::<Vec<_>>();
-            let circuit = create_circuit_from_file::<Bls12>(circuit_file, Some(w));
-            let proof = Groth16::prove(&pk, circuit.clone(), &mut rng)?;
+            let circuit = create_circuit_from_file::<Bls12>(circuit_file, Some(w))?;
+            let proof = prove_with_pk_file(pk_file, circuit.clone(), &mut rng)?;
             let proof_json = serialize_proof(&proof, curve_type, to_hex)?;
             std::fs::write(proof_file, proof_json)?;
             let input_json = circuit.get_public_inputs_json();
             std::fs::write(public_input_file, input_json)?;
         }
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     };
     Ok(())
@@ -220,7 +236,7 @@ pub fn groth16_prove_inplace<E: Engine + crate::json_utils::Parser>(
 ) -> Result<()> {
     let mut rng = rand::thread_rng();
     let mut wtns = WitnessCalculator::from_file(wtns_file)?;
-    let inputs = load_input_for_witness(input_file);
+    let inputs = load_input_for_witness(input_file)?;
     let w = wtns.calculate_witness(inputs, false)?;
     let circuit1 = create_circuit_add_witness(circuit, w);
     let proof = Groth16::prove(&pk, circuit1.clone(), &mut rng)?;
@@ -242,12 +258,12 @@ pub fn groth16_prove(
     public_input_file: &str,
     proof_file: &str,
     to_hex: bool,
+    witness_backend: WitnessBackend,
 ) -> Result<()> {
     let mut rng = rand::thread_rng();
 
-    let mut wtns = WitnessCalculator::from_file(wtns_file)?;
-    let inputs = load_input_for_witness(input_file);
-    let w = wtns.calculate_witness(inputs, false)?;
+    let inputs = load_input_for_witness(input_file)?;
+    let w = witness_backend.calculate_witness(wtns_file, inputs, false)?;
     match curve_type {
         "BLS12381" => {
             let pk: Parameters<Bls12> = read_pk_from_file(pk_file, false)?;
@@ -262,7 +278,7 @@ pub fn groth16_prove(
                 })
                 .collect::<Vec<_>>();
             let circuit: CircomCircuit<Scalar> =
-                create_circuit_from_file::<Scalar>(circuit_file, Some(w));
+                create_circuit_from_file::<Scalar>(circuit_file, Some(w))?;
             let proof = Groth16::prove(&pk, circuit.clone(), &mut rng)?;
             let proof_json = serialize_proof(&proof, curve_type, to_hex)?;
             std::fs::write(proof_file, proof_json)?;
@@ -270,7 +286,7 @@ pub fn groth16_prove(
             std::fs::write(public_input_file, input_json)?;
         }
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     };
 
@@ -291,7 +307,7 @@ pub fn groth16_prove_inplace(
 ) -> Result<()> {
     let mut rng = rand::thread_rng();
     let mut wtns = WitnessCalculator::from_file(wtns_file)?;
-    let inputs = load_input_for_witness(input_file);
+    let inputs = load_input_for_witness(input_file)?;
     let w = wtns.calculate_witness(inputs, false)?;
     let circuit1 = create_circuit_add_witness(circuit, w);
     let proof = Groth16::prove(&pk, circuit1.clone(), &mut rng)?;
@@ -319,7 +335,7 @@ pub fn groth16_verify(
                 Groth16::<_, CircomCircuit<Bn256>>::verify_with_processed_vk(&vk, &inputs, &proof);
 
             if verification_result.is_err() || !verification_result.unwrap() {
-                bail!("verify failed");
+                bail!(Groth16Error::InvalidProof(proof_file.to_string()));
             }
         }
 
@@ -332,12 +348,12 @@ pub fn groth16_verify(
                 Groth16::<_, CircomCircuit<Bls12>>::verify_with_processed_vk(&vk, &inputs, &proof);
 
             if verification_result.is_err() || !verification_result.unwrap() {
-                bail!("verify failed");
+                bail!(Groth16Error::InvalidProof(proof_file.to_string()));
             }
         }
 
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     }
 
@@ -356,7 +372,7 @@ pub fn groth16_verify_inplace<E: Engine + crate::json_utils::Parser>(
         Groth16::<_, CircomCircuit<E>>::verify_with_processed_vk(&vk, &inputs[..], &proof);
 
     if verification_result.is_err() || !verification_result.unwrap() {
-        bail!("verify failed");
+        bail!(Groth16Error::InvalidProof(proof_file.to_string()));
     }
 
     Ok(())
@@ -378,24 +394,196 @@ pub fn groth16_verify(
                 Groth16::<_, CircomCircuit<Scalar>>::verify_with_processed_vk(&vk, &inputs, &proof);
 
             if verification_result.is_err() || !verification_result.unwrap() {
-                bail!("verify failed");
+                bail!(Groth16Error::InvalidProof(proof_file.to_string()));
             }
         }
 
         _ => {
-            bail!(format!("Unknown curve type: {}", curve_type))
+            bail!(Groth16Error::UnknownCurveType(curve_type.to_string()))
         }
     }
 
     Ok(())
 }
 
-// Acknowledgement: The Solidity verifier template was modified from ZoKrates implementation.
+/// Verify the proofs of the same verification key at once, and pinpoint the invalid proofs
+/// one by one if the batch fails
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_verify_batch(
+    curve_type: &str,
+    vk_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+) -> Result<()> {
+    match curve_type {
+        "BN128" => verify_batch_from_files::<Bn256>(vk_file, public_input_files, proof_files),
+        "BLS12381" => verify_batch_from_files::<Bls12>(vk_file, public_input_files, proof_files),
+        _ => bail!(Groth16Error::UnknownCurveType(curve_type.to_string())),
+    }
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_verify_batch(
+    curve_type: &str,
+    vk_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+) -> Result<()> {
+    if curve_type != "BLS12381" {
+        bail!(Groth16Error::UnknownCurveType(curve_type.to_string()));
+    }
+    check_batch_files(public_input_files, proof_files)?;
+    let vk: VerifyingKey<Bls12> = read_vk_from_file(vk_file)?;
+    let proofs = proof_files
+        .iter()
+        .zip(public_input_files)
+        .map(|(proof_file, public_input_file)| {
+            Ok((
+                read_proof_from_file(proof_file)?,
+                read_public_input_from_file(public_input_file)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    type G16 = Groth16<Bls12, CircomCircuit<Scalar>>;
+    if G16::verify_batch(&vk, &proofs, &mut rand::thread_rng())? {
+        return Ok(());
+    }
+    let invalid: Vec<&str> = proofs
+        .iter()
+        .zip(proof_files)
+        .filter(|((proof, inputs), _)| {
+            !matches!(G16::verify_with_processed_vk(&vk, inputs, proof), Ok(true))
+        })
+        .map(|(_, proof_file)| proof_file.as_str())
+        .collect();
+    if !invalid.is_empty() {
+        bail!(Groth16Error::InvalidProof(invalid.join(", ")));
+    }
+    Ok(())
+}
+
+/// Initialize the phase-2 ceremony of the circuit from a snarkjs powers of tau file
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_ceremony_init(circuit_file: &str, ptau_file: &str, zkey_file: &str) -> Result<()> {
+    let circuit = create_circuit_from_file::<Bn256>(circuit_file, None)?;
+    let ptau = std::io::BufReader::new(std::fs::File::open(ptau_file)?);
+    let mpc = MPCParameters::new(initial_parameters(circuit, ptau)?)?;
+    write_ceremony_file(&mpc, zkey_file)
+}
+
+/// Apply a contribution to delta, and return the contribution hash for the participant to publish
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_ceremony_contribute(zkey_in: &str, zkey_out: &str, entropy: &str) -> Result<String> {
+    let mut mpc = read_ceremony_file(zkey_in)?;
+    let hash = mpc.contribute(&mut rng_from_entropy(entropy));
+    write_ceremony_file(&mpc, zkey_out)?;
+    Ok(hex::encode(hash))
+}
+
+/// Verify the contribution chain against the circuit and the powers of tau,
+/// and return the contribution hashes in order
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_ceremony_verify(
+    circuit_file: &str,
+    ptau_file: &str,
+    zkey_file: &str,
+) -> Result<Vec<String>> {
+    let circuit = create_circuit_from_file::<Bn256>(circuit_file, None)?;
+    let ptau = std::io::BufReader::new(std::fs::File::open(ptau_file)?);
+    let initial = initial_parameters(circuit, ptau)?;
+    let mpc = read_ceremony_file(zkey_file)?;
+    let hashes = mpc.verify(&initial)?;
+    Ok(hashes.iter().map(hex::encode).collect())
+}
+
+/// Export the proving key and verification key of the ceremony for `groth16_prove` and `groth16_verify`
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_ceremony_export(
+    zkey_file: &str,
+    pk_file: &str,
+    vk_file: &str,
+    to_hex: bool,
+) -> Result<()> {
+    let mpc = read_ceremony_file(zkey_file)?;
+    if mpc.contributions.is_empty() {
+        bail!("The ceremony has no contribution, the delta is still public");
+    }
+    let vk = mpc.params.vk.clone();
+    write_pk_vk_to_files("BN128", mpc.params, vk, pk_file, vk_file, to_hex)
+}
+
+/// Export the proving key of `groth16_setup` as a snarkjs zkey, the circuit is needed to
+/// recover the coefficients, and the keys are moved to the evaluation domain of snarkjs
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_export_zkey(
+    curve_type: &str,
+    circuit_file: &str,
+    pk_file: &str,
+    zkey_file: &str,
+) -> Result<()> {
+    match curve_type {
+        "BN128" => export_zkey::<Bn256>(circuit_file, pk_file, zkey_file),
+        "BLS12381" => export_zkey::<Bls12>(circuit_file, pk_file, zkey_file),
+        _ => bail!(Groth16Error::UnknownCurveType(curve_type.to_string())),
+    }
+}
+
+/// Export the verification key of a snarkjs zkey for `groth16_verify` and `generate_verifier`
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_zkey_export_vk(
+    curve_type: &str,
+    zkey_file: &str,
+    vk_file: &str,
+    to_hex: bool,
+) -> Result<()> {
+    let vk_json = match curve_type {
+        "BN128" => serialize_vk(&read_zkey_file::<Bn256>(zkey_file)?.vk, curve_type, to_hex)?,
+        "BLS12381" => serialize_vk(&read_zkey_file::<Bls12>(zkey_file)?.vk, curve_type, to_hex)?,
+        _ => bail!(Groth16Error::UnknownCurveType(curve_type.to_string())),
+    };
+    std::fs::write(vk_file, vk_json)?;
+    Ok(())
+}
+
+/// Export the proof and public inputs as the hex calldata and the JSON arguments of `verifyTx`
+pub fn groth16_export_calldata(
+    proof_file: &str,
+    public_input_file: &str,
+    calldata_file: &str,
+    args_file: &str,
+) -> Result<()> {
+    let proof: ProofFile = serde_json::from_str(&std::fs::read_to_string(proof_file)?)?;
+    let public_inputs: Vec<String> =
+        serde_json::from_str(&std::fs::read_to_string(public_input_file)?)?;
+    let (calldata, args) = to_verifier_calldata(&proof, &public_inputs)?;
+    std::fs::write(calldata_file, calldata)?;
+    std::fs::write(args_file, args)?;
+    Ok(())
+}
+
 pub fn generate_verifier(vk_file_path: &str, sol_file_path: &str) -> Result<()> {
     let json_data = std::fs::read_to_string(vk_file_path)?;
-    let vk_file: VerifyingKeyFile =
-        serde_json::from_str(&json_data).expect("Error during deserialization of the JSON data");
+    let vk_file: VerifyingKeyFile = serde_json::from_str(&json_data).map_err(Groth16Error::from)?;
 
+    let curve = vk_file.curve.clone();
+    let verifier = match curve.as_str() {
+        "BN128" => render_bn128_verifier(vk_file),
+        "BLS12381" => render_bls12381_verifier(vk_file)?,
+        _ => bail!(Groth16Error::UnknownCurveType(curve)),
+    };
+
+    match std::fs::write(sol_file_path, verifier) {
+        Ok(()) => println!("Generate solidity verifier successfully!"),
+        Err(e) => {
+            bail!("write sol file failed, {:?}", e)
+        }
+    }
+    Ok(())
+}
+
+// Acknowledgement: The Solidity verifier template was modified from ZoKrates implementation.
+fn render_bn128_verifier(vk_file: VerifyingKeyFile) -> String {
     let vk_alpha = vk_file.alpha_g1.to_string();
     let vk_beta = vk_file.beta_g2.to_string();
     let vk_gamma = vk_file.gamma_g2.to_string();
@@ -490,29 +678,131 @@ pub fn generate_verifier(vk_file_path: &str, sol_file_path: &str) -> Result<()>
     let re = Regex::new(r"(?P<v>0[xX][0-9a-fA-F]{64})").unwrap();
     template_text = re.replace_all(&template_text, "uint256($v)").to_string();
 
-    match std::fs::write(
-        sol_file_path,
-        format!("{}{}", solidity_pairing_lib_sans_bn256g2, template_text),
-    ) {
-        Ok(()) => println!("Generate solidity verifier successfully!"),
-        Err(e) => {
-            bail!("write sol file failed, {:?}", e)
+    format!("{}{}", solidity_pairing_lib_sans_bn256g2, template_text)
+}
+
+/// The base field modulus of BLS12-381
+const BLS12381_FQ_MODULUS: &str = "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab";
+
+fn render_bls12381_verifier(vk_file: VerifyingKeyFile) -> Result<String> {
+    let modulus = BigUint::from_str_radix(BLS12381_FQ_MODULUS, 16).unwrap();
+    let gamma_abc_count = vk_file.ic.len();
+
+    let mut gamma_abc_repeat_text = String::new();
+    for (i, g1) in vk_file.ic.iter().enumerate() {
+        gamma_abc_repeat_text.push_str(&format!(
+            "vk.gamma_abc[{}] = {};",
+            i,
+            render_bls12381_g1(g1, &modulus)?
+        ));
+        if i < gamma_abc_count - 1 {
+            gamma_abc_repeat_text.push_str("\n        ");
         }
     }
-    Ok(())
+
+    let (input_argument, input_loop) = if gamma_abc_count > 1 {
+        (
+            format!(", uint[{}] memory input", gamma_abc_count - 1),
+            r#"
+        for(uint i = 0; i < input.length; i++){
+            inputValues[i] = input[i];
+        }"#,
+        )
+    } else {
+        (String::new(), "")
+    };
+
+    Ok(CONTRACT_TEMPLATE_BLS12381
+        .replace(
+            "<%vk_alpha%>",
+            &render_bls12381_g1(&vk_file.alpha_g1, &modulus)?,
+        )
+        .replace(
+            "<%vk_beta_neg%>",
+            &render_bls12381_g2(&vk_file.beta_g2, &modulus, true)?,
+        )
+        .replace(
+            "<%vk_gamma_neg%>",
+            &render_bls12381_g2(&vk_file.gamma_g2, &modulus, true)?,
+        )
+        .replace(
+            "<%vk_delta_neg%>",
+            &render_bls12381_g2(&vk_file.delta_g2, &modulus, true)?,
+        )
+        .replace("<%vk_gamma_abc_length%>", &gamma_abc_count.to_string())
+        .replace("<%vk_gamma_abc_pts%>", &gamma_abc_repeat_text)
+        .replace("<%vk_input_length%>", &(gamma_abc_count - 1).to_string())
+        .replace("<%input_argument%>", &input_argument)
+        .replace("<%input_loop%>", input_loop))
+}
+
+/// parse a decimal or 0x-prefixed hex coordinate, which must be a canonical base field element
+fn parse_bls12381_fq(value: &str, modulus: &BigUint) -> Result<BigUint> {
+    let fq = match value.strip_prefix("0x") {
+        Some(hex) => BigUint::from_str_radix(hex, 16),
+        None => BigUint::from_str_radix(value, 10),
+    }
+    .map_err(|e| anyhow!("Invalid BLS12381 coordinate {}, {:?}", value, e))?;
+    if &fq >= modulus {
+        bail!(format!(
+            "BLS12381 coordinate out of the base field: {}",
+            value
+        ));
+    }
+    Ok(fq)
+}
+
+/// split the 48-byte element into the two words of the 64-byte big-endian encoding
+fn render_bls12381_fp(fq: &BigUint) -> String {
+    let low_mask = (BigUint::from(1u32) << 256) - 1u32;
+    format!("Pairing.Fp({}, {})", fq >> 256, fq & &low_mask)
+}
+
+fn render_bls12381_g1(point: &G1, modulus: &BigUint) -> Result<String> {
+    Ok(format!(
+        "Pairing.G1Point({}, {})",
+        render_bls12381_fp(&parse_bls12381_fq(&point.x, modulus)?),
+        render_bls12381_fp(&parse_bls12381_fq(&point.y, modulus)?)
+    ))
+}
+
+fn render_bls12381_g2(point: &G2, modulus: &BigUint, negate: bool) -> Result<String> {
+    let x = [
+        parse_bls12381_fq(&point.x[0], modulus)?,
+        parse_bls12381_fq(&point.x[1], modulus)?,
+    ];
+    let mut y = [
+        parse_bls12381_fq(&point.y[0], modulus)?,
+        parse_bls12381_fq(&point.y[1], modulus)?,
+    ];
+    if negate {
+        // -(x, y) = (x, -y), and -0 is 0 in the canonical form
+        for c in y.iter_mut() {
+            if !c.is_zero() {
+                *c = modulus - &*c;
+            }
+        }
+    }
+    Ok(format!(
+        "Pairing.G2Point(Pairing.Fp2({}, {}), Pairing.Fp2({}, {}))",
+        render_bls12381_fp(&x[0]),
+        render_bls12381_fp(&x[1]),
+        render_bls12381_fp(&y[0]),
+        render_bls12381_fp(&y[1])
+    ))
 }
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 fn create_circuit_from_file<E: Engine>(
     circuit_file: &str,
     witness: Option<Vec<E::Fr>>,
-) -> CircomCircuit<E> {
-    CircomCircuit {
-        r1cs: load_r1cs(circuit_file),
+) -> Result<CircomCircuit<E>> {
+    Ok(CircomCircuit {
+        r1cs: load_r1cs(circuit_file)?,
         witness,
         wire_mapping: None,
         aux_offset: 0,
-    }
+    })
 }
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
@@ -540,13 +830,13 @@ pub fn create_circuit_add_witness<E: Engine>(
 fn create_circuit_from_file<E: PrimeField>(
     circuit_file: &str,
     witness: Option<Vec<E>>,
-) -> CircomCircuit<E> {
-    CircomCircuit {
-        r1cs: load_r1cs(circuit_file),
+) -> Result<CircomCircuit<E>> {
+    Ok(CircomCircuit {
+        r1cs: load_r1cs(circuit_file)?,
         witness,
         wire_mapping: None,
         aux_offset: 0,
-    }
+    })
 }
 
 #[cfg(any(feature = "cuda", feature = "opencl"))]
@@ -594,23 +884,129 @@ where
 
 pub fn read_vk_from_file<P: Parser>(file_path: &str) -> Result<VerifyingKey<P>> {
     let json_data = std::fs::read_to_string(file_path)?;
-    Ok(to_verification_key::<P>(&json_data))
+    to_verification_key::<P>(&json_data)
 }
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 fn read_public_input_from_file<T: PrimeField>(file_path: &str) -> Result<Vec<T>> {
     let json_data = std::fs::read_to_string(file_path)?;
-    Ok(to_public_input::<T>(&json_data))
+    to_public_input::<T>(&json_data)
 }
 
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 fn read_public_input_from_file(file_path: &str) -> Result<Vec<Scalar>> {
     let json_data = std::fs::read_to_string(file_path)?;
-    Ok(to_public_input(&json_data))
+    to_public_input(&json_data)
 }
 
 fn read_proof_from_file<P: Parser>(file_path: &str) -> Result<Proof<P>> {
     let json_data = std::fs::read_to_string(file_path)?;
-    Ok(to_proof::<P>(&json_data))
+    to_proof::<P>(&json_data)
+}
+
+fn check_batch_files(public_input_files: &[String], proof_files: &[String]) -> Result<()> {
+    ensure!(!proof_files.is_empty(), "No proof to verify");
+    ensure!(
+        proof_files.len() == public_input_files.len(),
+        "Got {} proofs but {} public inputs",
+        proof_files.len(),
+        public_input_files.len()
+    );
+    Ok(())
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn verify_batch_from_files<E: Engine + crate::json_utils::Parser>(
+    vk_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+) -> Result<()> {
+    check_batch_files(public_input_files, proof_files)?;
+    let vk: VerifyingKey<E> = read_vk_from_file(vk_file)?;
+    let proofs = proof_files
+        .iter()
+        .zip(public_input_files)
+        .map(|(proof_file, public_input_file)| {
+            Ok((
+                read_proof_from_file(proof_file)?,
+                read_public_input_from_file::<E::Fr>(public_input_file)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if Groth16::<E, CircomCircuit<E>>::verify_batch(&vk, &proofs, &mut rand::thread_rng())? {
+        return Ok(());
+    }
+    let invalid: Vec<&str> = proofs
+        .iter()
+        .zip(proof_files)
+        .filter(|((proof, inputs), _)| {
+            !matches!(
+                Groth16::<E, CircomCircuit<E>>::verify_with_processed_vk(&vk, inputs, proof),
+                Ok(true)
+            )
+        })
+        .map(|(_, proof_file)| proof_file.as_str())
+        .collect();
+    if !invalid.is_empty() {
+        bail!(Groth16Error::InvalidProof(invalid.join(", ")));
+    }
+    Ok(())
+}
+
+/// prove with either a bellman proving key or a snarkjs zkey
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn prove_with_pk_file<E: SnarkjsEngine, R: rand::Rng>(
+    pk_file: &str,
+    circuit: CircomCircuit<E>,
+    rng: &mut R,
+) -> Result<Proof<E>> {
+    use std::io::{Seek, SeekFrom};
+    let file = std::fs::File::open(pk_file).map_err(|e| anyhow!("Open {}, {:?}", pk_file, e))?;
+    let mut reader = std::io::BufReader::new(file);
+    let zkey = is_zkey(&mut reader)?;
+    reader.seek(SeekFrom::Start(0))?;
+    if zkey {
+        let witness = circuit
+            .witness
+            .as_ref()
+            .ok_or_else(|| anyhow!("The witness is required to prove"))?;
+        return ZkeyParameters::<E>::read(reader)?.prove(witness, rng);
+    }
+    let pk = Parameters::<E>::read(&mut reader, false)?;
+    Ok(Groth16::prove(&pk, circuit, rng)?)
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn export_zkey<E: SnarkjsEngine>(circuit_file: &str, pk_file: &str, zkey_file: &str) -> Result<()> {
+    let pk: Parameters<E> = read_pk_from_file(pk_file, false)?;
+    let circuit = create_circuit_from_file::<E>(circuit_file, None)?;
+    let zkey = ZkeyParameters::from_parameters(&pk, circuit)?;
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(zkey_file)?);
+    zkey.write(&mut writer)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn read_zkey_file<E: SnarkjsEngine>(file_path: &str) -> Result<ZkeyParameters<E>> {
+    let file =
+        std::fs::File::open(file_path).map_err(|e| anyhow!("Open {}, {:?}", file_path, e))?;
+    ZkeyParameters::read(std::io::BufReader::new(file))
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn read_ceremony_file(file_path: &str) -> Result<MPCParameters> {
+    let file =
+        std::fs::File::open(file_path).map_err(|e| anyhow!("Open {}, {:?}", file_path, e))?;
+    MPCParameters::read(std::io::BufReader::new(file), true)
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn write_ceremony_file(mpc: &MPCParameters, file_path: &str) -> Result<()> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(file_path)?);
+    mpc.write(&mut writer)?;
+    std::io::Write::flush(&mut writer)?;
+    Ok(())
 }
 
 fn write_pk_vk_to_files<P: Parser>(
@@ -794,3 +1190,176 @@ library Pairing {
         .join("\n")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VK_BIN_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/verification_key_bls12381.bin"
+    );
+
+    fn write_vk_file_bls12381(curve_type: &str, vk_file_path: &str) {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(VK_BIN_BLS12).unwrap());
+        let vk = VerifyingKey::<Bls12>::read(&mut reader).unwrap();
+        std::fs::write(vk_file_path, serialize_vk(&vk, curve_type, false).unwrap()).unwrap();
+    }
+
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[test]
+    fn test_prove_with_exported_zkey_bls12381() {
+        let circuit_file = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/test-vectors/mycircuit_bls12381.r1cs"
+        );
+        let wasm_file = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/test-vectors/mycircuit_bls12381.wasm"
+        );
+        let input_file = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+        let pk_file = "/tmp/test_groth16_zkey_bls12381.pk";
+        let vk_file = "/tmp/test_groth16_zkey_bls12381_vk.json";
+        let zkey_file = "/tmp/test_groth16_bls12381.zkey";
+        let zkey_vk_file = "/tmp/test_groth16_zkey_bls12381_zkey_vk.json";
+        let public_input_file = "/tmp/test_groth16_zkey_bls12381_public.json";
+        let proof_file = "/tmp/test_groth16_zkey_bls12381_proof.json";
+
+        groth16_setup("BLS12381", circuit_file, pk_file, vk_file, false).unwrap();
+        groth16_export_zkey("BLS12381", circuit_file, pk_file, zkey_file).unwrap();
+        groth16_zkey_export_vk("BLS12381", zkey_file, zkey_vk_file, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(vk_file).unwrap(),
+            std::fs::read_to_string(zkey_vk_file).unwrap()
+        );
+        assert!(groth16_zkey_export_vk("BN128", zkey_file, zkey_vk_file, false).is_err());
+
+        groth16_prove(
+            "BLS12381",
+            circuit_file,
+            wasm_file,
+            zkey_file,
+            input_file,
+            public_input_file,
+            proof_file,
+            false,
+            WitnessBackend::Native,
+        )
+        .unwrap();
+        groth16_verify("BLS12381", vk_file, public_input_file, proof_file).unwrap();
+    }
+
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[test]
+    fn test_groth16_verify_batch_pinpoints_invalid_proof() {
+        let circuit_file = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+        let wasm_file = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+        let input_file = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+        let pk_file = "/tmp/test_groth16_batch.pk";
+        let vk_file = "/tmp/test_groth16_batch_vk.json";
+        groth16_setup("BN128", circuit_file, pk_file, vk_file, false).unwrap();
+
+        let mut public_input_files = vec![];
+        let mut proof_files = vec![];
+        for i in 0..3 {
+            let public_input_file = format!("/tmp/test_groth16_batch_public_{}.json", i);
+            let proof_file = format!("/tmp/test_groth16_batch_proof_{}.json", i);
+            groth16_prove(
+                "BN128",
+                circuit_file,
+                wasm_file,
+                pk_file,
+                input_file,
+                &public_input_file,
+                &proof_file,
+                false,
+                WitnessBackend::Wasmer,
+            )
+            .unwrap();
+            public_input_files.push(public_input_file);
+            proof_files.push(proof_file);
+        }
+        groth16_verify_batch("BN128", vk_file, &public_input_files, &proof_files).unwrap();
+
+        let public_input = std::fs::read_to_string(&public_input_files[2]).unwrap();
+        let bad_public_input = public_input.replace("33", "34");
+        assert_ne!(public_input, bad_public_input);
+        std::fs::write(&public_input_files[2], bad_public_input).unwrap();
+        let err =
+            groth16_verify_batch("BN128", vk_file, &public_input_files, &proof_files).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Groth16Error>(),
+            Some(Groth16Error::InvalidProof(_))
+        ));
+        let err = err.to_string();
+        assert!(err.contains(&proof_files[2]));
+        assert!(!err.contains(&proof_files[0]) && !err.contains(&proof_files[1]));
+
+        assert!(
+            groth16_verify_batch("BN128", vk_file, &public_input_files[..2], &proof_files).is_err()
+        );
+    }
+
+    #[test]
+    fn test_generate_verifier_bls12381() {
+        let vk_file_path = "/tmp/test_groth16_verification_key_bls12381.json";
+        let sol_file = "/tmp/test_groth16_verifier_bls12381.sol";
+        write_vk_file_bls12381("BLS12381", vk_file_path);
+        generate_verifier(vk_file_path, sol_file).unwrap();
+        let sol = std::fs::read_to_string(sol_file).unwrap();
+        assert!(!sol.contains("<%"));
+        assert!(sol.contains("address(0x0f)"));
+        assert!(!sol.contains(
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+        ));
+    }
+
+    #[test]
+    fn test_generate_verifier_unsupported_curve() {
+        let vk_file_path = "/tmp/test_groth16_verification_key_bw6761.json";
+        write_vk_file_bls12381("BW6761", vk_file_path);
+        let err =
+            generate_verifier(vk_file_path, "/tmp/test_groth16_verifier_bw6761.sol").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Groth16Error>(),
+            Some(Groth16Error::UnknownCurveType(_))
+        ));
+    }
+
+    #[test]
+    fn test_generate_verifier_malformed_vk() {
+        let vk_file_path = "/tmp/test_groth16_verification_key_malformed.json";
+        std::fs::write(vk_file_path, "{\"protocol\": \"groth16\"").unwrap();
+        let err = generate_verifier(vk_file_path, "/tmp/test_groth16_verifier_malformed.sol")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Groth16Error>(),
+            Some(Groth16Error::MalformedInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_bls12381_point() {
+        let modulus = BigUint::from_str_radix(BLS12381_FQ_MODULUS, 16).unwrap();
+        let point = G2 {
+            x: ["1".to_string(), "0x2".to_string()],
+            y: ["0".to_string(), "1".to_string()],
+        };
+        let p_minus_one = &modulus - 1u32;
+        let low_mask = (BigUint::from(1u32) << 256) - 1u32;
+        assert_eq!(
+            render_bls12381_g2(&point, &modulus, true).unwrap(),
+            format!(
+                "Pairing.G2Point(Pairing.Fp2(Pairing.Fp(0, 1), Pairing.Fp(0, 2)), Pairing.Fp2(Pairing.Fp(0, 0), Pairing.Fp({}, {})))",
+                &p_minus_one >> 256,
+                &p_minus_one & &low_mask
+            )
+        );
+
+        let out_of_field = G1 {
+            x: modulus.to_str_radix(10),
+            y: "1".to_string(),
+        };
+        assert!(render_bls12381_g1(&out_of_field, &modulus).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+// The section based binary files of snarkjs, like ptau and zkey, refer to
+// https://github.com/iden3/binfileutils
+use crate::bellman_ce::pairing::{
+    bls12_381::{Bls12, Fq2 as Fq2_bls12381},
+    bn256::{Bn256, Fq2},
+    CurveAffine, Engine,
+};
+use crate::bellman_ce::{Field, PrimeField, PrimeFieldRepr};
+use anyhow::{bail, ensure, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// section type -> (file offset, section size)
+pub(crate) type Sections = BTreeMap<u32, (u64, u64)>;
+
+/// check the magic, and return the version and the section table
+pub(crate) fn read_sections<R: Read + Seek>(
+    reader: &mut R,
+    magic: &[u8; 4],
+) -> Result<(u32, Sections)> {
+    // the sections must lie in the file, so that their sizes bound the counts read from them
+    let start = reader.stream_position()?;
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    ensure!(
+        &buf == magic,
+        "Invalid magic number, expect {}",
+        String::from_utf8_lossy(magic)
+    );
+    let version = reader.read_u32::<LittleEndian>()?;
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+
+    let mut sections = Sections::new();
+    for _ in 0..num_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let section_size = reader.read_u64::<LittleEndian>()?;
+        let offset = reader.stream_position()?;
+        ensure!(
+            section_size <= len - offset,
+            "The section {} exceeds the file",
+            section_type
+        );
+        sections.insert(section_type, (offset, section_size));
+        reader.seek(SeekFrom::Current(section_size as i64))?;
+    }
+    Ok((version, sections))
+}
+
+/// move to the beginning of the section, and return its size
+pub(crate) fn seek_section<R: Seek>(
+    reader: &mut R,
+    sections: &Sections,
+    section: u32,
+) -> Result<u64> {
+    match sections.get(&section) {
+        Some((offset, size)) => {
+            reader.seek(SeekFrom::Start(*offset))?;
+            Ok(*size)
+        }
+        None => bail!(format!("Missing section {}", section)),
+    }
+}
+
+/// move to the beginning of the section, which must hold exactly n items of the size,
+/// so that the counts read from the file are checked before anything is allocated by them
+pub(crate) fn seek_items<R: Seek>(
+    reader: &mut R,
+    sections: &Sections,
+    section: u32,
+    n: usize,
+    item_size: u64,
+) -> Result<()> {
+    let size = seek_section(reader, sections, section)?;
+    ensure!(
+        size == n as u64 * item_size,
+        "The section {} has {} bytes, expect {} items of {} bytes",
+        section,
+        size,
+        n,
+        item_size
+    );
+    Ok(())
+}
+
+pub(crate) fn write_binfile<W: Write>(
+    mut writer: W,
+    magic: &[u8; 4],
+    version: u32,
+    sections: Vec<(u32, Vec<u8>)>,
+) -> Result<()> {
+    writer.write_all(magic)?;
+    writer.write_u32::<LittleEndian>(version)?;
+    writer.write_u32::<LittleEndian>(sections.len() as u32)?;
+    for (section_type, data) in sections {
+        writer.write_u32::<LittleEndian>(section_type)?;
+        writer.write_u64::<LittleEndian>(data.len() as u64)?;
+        writer.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// the modulus in little-endian, as stored in the headers
+pub(crate) fn modulus_bytes<F: PrimeField>() -> Vec<u8> {
+    let mut buf = vec![];
+    F::char().write_le(&mut buf).unwrap();
+    buf
+}
+
+/// read the modulus of a header, prefixed by its length, and check it is the modulus of the field
+pub(crate) fn read_modulus<F: PrimeField, R: Read>(reader: &mut R) -> Result<bool> {
+    let expect = modulus_bytes::<F>();
+    let n8 = reader.read_u32::<LittleEndian>()? as usize;
+    if n8 != expect.len() {
+        return Ok(false);
+    }
+    let mut q = vec![0u8; n8];
+    reader.read_exact(&mut q)?;
+    Ok(q == expect)
+}
+
+/// the field elements are little-endian in montgomery form
+pub(crate) fn read_montgomery<F: PrimeField, R: Read>(reader: &mut R) -> Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_le(reader)?;
+    Ok(F::from_raw_repr(repr)?)
+}
+
+pub(crate) fn write_montgomery<F: PrimeField, W: Write>(writer: &mut W, f: &F) -> Result<()> {
+    f.into_raw_repr().write_le(writer)?;
+    Ok(())
+}
+
+/// the curves snarkjs supports, the points are affine and the infinity is all zero
+pub trait SnarkjsEngine: Engine {
+    fn fqe_from_parts(c0: Self::Fq, c1: Self::Fq) -> Self::Fqe;
+    fn fqe_to_parts(e: &Self::Fqe) -> (Self::Fq, Self::Fq);
+
+    fn read_g1<R: Read>(reader: &mut R) -> Result<Self::G1Affine> {
+        let x: Self::Fq = read_montgomery(reader)?;
+        let y: Self::Fq = read_montgomery(reader)?;
+        if x.is_zero() && y.is_zero() {
+            return Ok(Self::G1Affine::zero());
+        }
+        Ok(Self::G1Affine::from_xy_checked(x, y)?)
+    }
+
+    fn read_g2<R: Read>(reader: &mut R) -> Result<Self::G2Affine> {
+        let x0 = read_montgomery(reader)?;
+        let x1 = read_montgomery(reader)?;
+        let y0 = read_montgomery(reader)?;
+        let y1 = read_montgomery(reader)?;
+        let x = Self::fqe_from_parts(x0, x1);
+        let y = Self::fqe_from_parts(y0, y1);
+        if x.is_zero() && y.is_zero() {
+            return Ok(Self::G2Affine::zero());
+        }
+        Ok(Self::G2Affine::from_xy_checked(x, y)?)
+    }
+
+    fn write_g1<W: Write>(writer: &mut W, p: &Self::G1Affine) -> Result<()> {
+        let (x, y) = if p.is_zero() {
+            (Self::Fq::zero(), Self::Fq::zero())
+        } else {
+            p.into_xy_unchecked()
+        };
+        write_montgomery(writer, &x)?;
+        write_montgomery(writer, &y)
+    }
+
+    fn write_g2<W: Write>(writer: &mut W, p: &Self::G2Affine) -> Result<()> {
+        let (x, y) = if p.is_zero() {
+            (Self::Fqe::zero(), Self::Fqe::zero())
+        } else {
+            p.into_xy_unchecked()
+        };
+        let (x0, x1) = Self::fqe_to_parts(&x);
+        let (y0, y1) = Self::fqe_to_parts(&y);
+        for fq in [x0, x1, y0, y1] {
+            write_montgomery(writer, &fq)?;
+        }
+        Ok(())
+    }
+}
+
+impl SnarkjsEngine for Bn256 {
+    fn fqe_from_parts(c0: Self::Fq, c1: Self::Fq) -> Self::Fqe {
+        Fq2 { c0, c1 }
+    }
+
+    fn fqe_to_parts(e: &Self::Fqe) -> (Self::Fq, Self::Fq) {
+        (e.c0, e.c1)
+    }
+}
+
+impl SnarkjsEngine for Bls12 {
+    fn fqe_from_parts(c0: Self::Fq, c1: Self::Fq) -> Self::Fqe {
+        Fq2_bls12381 { c0, c1 }
+    }
+
+    fn fqe_to_parts(e: &Self::Fqe) -> (Self::Fq, Self::Fq) {
+        (e.c0, e.c1)
+    }
+}
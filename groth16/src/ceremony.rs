@@ -0,0 +1,752 @@
+// Groth16 phase-2 ceremony on top of a snarkjs powers of tau file, refer to
+// https://github.com/ebfull/phase2 and https://github.com/iden3/snarkjs
+use crate::bellman_ce::{
+    groth16::{Parameters, VerifyingKey},
+    pairing::{
+        bn256::{Bn256, Fq, Fr, G1Affine, G2Affine, G1, G2},
+        CurveAffine, CurveProjective, EncodedPoint, Engine,
+    },
+    Circuit, ConstraintSystem, Field, Index, LinearCombination, PrimeField, SynthesisError,
+    Variable,
+};
+use crate::binfile::{modulus_bytes, read_modulus, read_sections, seek_section, SnarkjsEngine};
+use crate::fft::{bellman_omega, group_ifft};
+use anyhow::{ensure, Result};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand_old::{chacha::ChaChaRng, Rng, SeedableRng};
+use std::io::{Read, Seek, Write};
+use std::sync::Arc;
+use tiny_keccak::{Hasher, Keccak};
+
+const PTAU_MAGIC: &[u8; 4] = b"ptau";
+const PTAU_HEADER: u32 = 1;
+const PTAU_TAU_G1: u32 = 2;
+const PTAU_TAU_G2: u32 = 3;
+const PTAU_ALPHA_TAU_G1: u32 = 4;
+const PTAU_BETA_TAU_G1: u32 = 5;
+const PTAU_BETA_G2: u32 = 6;
+
+const CEREMONY_MAGIC: &[u8; 4] = b"g16c";
+const CEREMONY_VERSION: u32 = 1;
+
+/// the powers of tau needed by a circuit with a domain of size m
+pub struct PowersOfTau {
+    pub power: u32,
+    /// tau^i * G1, i in [0, 2m - 1)
+    pub tau_g1: Vec<G1Affine>,
+    /// tau^i * G2, i in [0, m)
+    pub tau_g2: Vec<G2Affine>,
+    pub alpha_tau_g1: Vec<G1Affine>,
+    pub beta_tau_g1: Vec<G1Affine>,
+    pub beta_g2: G2Affine,
+}
+
+impl PowersOfTau {
+    /// read the prepared or unprepared snarkjs ptau file of bn128, the lagrange sections are ignored
+    pub fn read<R: Read + Seek>(mut reader: R, m: usize) -> Result<Self> {
+        let (_, sections) = read_sections(&mut reader, PTAU_MAGIC)?;
+
+        seek_section(&mut reader, &sections, PTAU_HEADER)?;
+        ensure!(
+            read_modulus::<Fq, _>(&mut reader)?,
+            "The ptau file is not of curve bn128"
+        );
+        let power = reader.read_u32::<LittleEndian>()?;
+        ensure!(
+            m <= 1 << power,
+            "The ptau file supports at most 2^{} constraints, but the circuit needs {}",
+            power,
+            m
+        );
+
+        seek_section(&mut reader, &sections, PTAU_TAU_G1)?;
+        let tau_g1 = (0..2 * m - 1)
+            .map(|_| Bn256::read_g1(&mut reader))
+            .collect::<Result<_>>()?;
+        seek_section(&mut reader, &sections, PTAU_TAU_G2)?;
+        let tau_g2 = (0..m)
+            .map(|_| Bn256::read_g2(&mut reader))
+            .collect::<Result<_>>()?;
+        seek_section(&mut reader, &sections, PTAU_ALPHA_TAU_G1)?;
+        let alpha_tau_g1 = (0..m)
+            .map(|_| Bn256::read_g1(&mut reader))
+            .collect::<Result<_>>()?;
+        seek_section(&mut reader, &sections, PTAU_BETA_TAU_G1)?;
+        let beta_tau_g1 = (0..m)
+            .map(|_| Bn256::read_g1(&mut reader))
+            .collect::<Result<_>>()?;
+        seek_section(&mut reader, &sections, PTAU_BETA_G2)?;
+        let beta_g2 = Bn256::read_g2(&mut reader)?;
+
+        Ok(PowersOfTau {
+            power,
+            tau_g1,
+            tau_g2,
+            alpha_tau_g1,
+            beta_tau_g1,
+            beta_g2,
+        })
+    }
+}
+
+/// collect the QAP of the circuit, the same as the keypair assembly of bellman
+pub(crate) struct KeypairAssembly<E: Engine> {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub num_constraints: usize,
+    pub at_inputs: Vec<Vec<(E::Fr, usize)>>,
+    pub bt_inputs: Vec<Vec<(E::Fr, usize)>>,
+    pub ct_inputs: Vec<Vec<(E::Fr, usize)>>,
+    pub at_aux: Vec<Vec<(E::Fr, usize)>>,
+    pub bt_aux: Vec<Vec<(E::Fr, usize)>>,
+    pub ct_aux: Vec<Vec<(E::Fr, usize)>>,
+}
+
+impl<E: Engine> KeypairAssembly<E> {
+    /// synthesize the circuit in the order of the bellman generator and prover:
+    /// the constant one, the circuit, and the constraints `input_i * 0 = 0` at last
+    pub fn synthesize<C: Circuit<E>>(circuit: C) -> Result<Self> {
+        let mut assembly = KeypairAssembly {
+            num_inputs: 0,
+            num_aux: 0,
+            num_constraints: 0,
+            at_inputs: vec![],
+            bt_inputs: vec![],
+            ct_inputs: vec![],
+            at_aux: vec![],
+            bt_aux: vec![],
+            ct_aux: vec![],
+        };
+        assembly.alloc_input(|| "one", || Ok(E::Fr::one()))?;
+        circuit.synthesize(&mut assembly)?;
+        for i in 0..assembly.num_inputs {
+            assembly.enforce(
+                || "",
+                |lc| lc + Variable::new_unchecked(Index::Input(i)),
+                |lc| lc,
+                |lc| lc,
+            );
+        }
+        Ok(assembly)
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for KeypairAssembly<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _: A, _: F) -> std::result::Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> std::result::Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // the values are not needed in the setup
+        let index = self.num_aux;
+        self.num_aux += 1;
+        self.at_aux.push(vec![]);
+        self.bt_aux.push(vec![]);
+        self.ct_aux.push(vec![]);
+        Ok(Variable::new_unchecked(Index::Aux(index)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _: A, _: F) -> std::result::Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> std::result::Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let index = self.num_inputs;
+        self.num_inputs += 1;
+        self.at_inputs.push(vec![]);
+        self.bt_inputs.push(vec![]);
+        self.ct_inputs.push(vec![]);
+        Ok(Variable::new_unchecked(Index::Input(index)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        fn eval<E: Engine>(
+            l: LinearCombination<E>,
+            inputs: &mut [Vec<(E::Fr, usize)>],
+            aux: &mut [Vec<(E::Fr, usize)>],
+            this_constraint: usize,
+        ) {
+            for &(var, coeff) in l.as_ref() {
+                match var.get_unchecked() {
+                    Index::Input(id) => inputs[id].push((coeff, this_constraint)),
+                    Index::Aux(id) => aux[id].push((coeff, this_constraint)),
+                }
+            }
+        }
+
+        let this_constraint = self.num_constraints;
+        eval(
+            a(LinearCombination::zero()),
+            &mut self.at_inputs,
+            &mut self.at_aux,
+            this_constraint,
+        );
+        eval(
+            b(LinearCombination::zero()),
+            &mut self.bt_inputs,
+            &mut self.bt_aux,
+            this_constraint,
+        );
+        eval(
+            c(LinearCombination::zero()),
+            &mut self.ct_inputs,
+            &mut self.ct_aux,
+            this_constraint,
+        );
+        self.num_constraints += 1;
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// the parameters before any contribution, where delta and gamma are both one.
+/// it is deterministic given the circuit and the powers of tau, so everyone can recompute it
+pub fn initial_parameters<C: Circuit<Bn256>, R: Read + Seek>(
+    circuit: C,
+    ptau: R,
+) -> Result<Parameters<Bn256>> {
+    let assembly = KeypairAssembly::synthesize(circuit)?;
+
+    let m = assembly.num_constraints.next_power_of_two();
+    let ptau = PowersOfTau::read(ptau, m)?;
+    let tau_lagrange_g1 = lagrange_basis(&ptau.tau_g1[..m]);
+    let tau_lagrange_g2 = lagrange_basis(&ptau.tau_g2[..m]);
+    let alpha_lagrange_g1 = lagrange_basis(&ptau.alpha_tau_g1[..m]);
+    let beta_lagrange_g1 = lagrange_basis(&ptau.beta_tau_g1[..m]);
+
+    let mut a = vec![];
+    let mut b_g1 = vec![];
+    let mut b_g2 = vec![];
+    let mut ic = vec![];
+    let mut l = vec![];
+    let variables = assembly
+        .at_inputs
+        .iter()
+        .zip(assembly.bt_inputs.iter())
+        .zip(assembly.ct_inputs.iter())
+        .map(|v| (v, true))
+        .chain(
+            assembly
+                .at_aux
+                .iter()
+                .zip(assembly.bt_aux.iter())
+                .zip(assembly.ct_aux.iter())
+                .map(|v| (v, false)),
+        );
+    for (((at, bt), ct), is_input) in variables {
+        a.push(evaluate(&tau_lagrange_g1, at).into_affine());
+        b_g1.push(evaluate(&tau_lagrange_g1, bt).into_affine());
+        b_g2.push(evaluate(&tau_lagrange_g2, bt).into_affine());
+
+        // beta * A + alpha * B + C
+        let mut abc = evaluate(&beta_lagrange_g1, at);
+        abc.add_assign(&evaluate(&alpha_lagrange_g1, bt));
+        abc.add_assign(&evaluate(&tau_lagrange_g1, ct));
+        if is_input {
+            ic.push(abc.into_affine());
+        } else {
+            l.push(abc.into_affine());
+        }
+    }
+
+    // tau^i * Z(tau), where Z(x) = x^m - 1
+    let h = (0..m - 1)
+        .map(|i| {
+            let mut p = ptau.tau_g1[i].into_projective();
+            p.negate();
+            p.add_assign_mixed(&ptau.tau_g1[i + m]);
+            p.into_affine()
+        })
+        .collect();
+
+    Ok(Parameters {
+        vk: VerifyingKey {
+            alpha_g1: ptau.alpha_tau_g1[0],
+            beta_g1: ptau.beta_tau_g1[0],
+            beta_g2: ptau.beta_g2,
+            gamma_g2: G2Affine::one(),
+            delta_g1: G1Affine::one(),
+            delta_g2: G2Affine::one(),
+            ic,
+        },
+        h: Arc::new(h),
+        l: Arc::new(l),
+        // filter the points at infinity away from the A/B queries as bellman does
+        a: Arc::new(a.into_iter().filter(|e| !e.is_zero()).collect()),
+        b_g1: Arc::new(b_g1.into_iter().filter(|e| !e.is_zero()).collect()),
+        b_g2: Arc::new(b_g2.into_iter().filter(|e| !e.is_zero()).collect()),
+    })
+}
+
+fn evaluate<G: CurveAffine<Scalar = Fr>>(bases: &[G], terms: &[(Fr, usize)]) -> G::Projective {
+    let mut acc = G::Projective::zero();
+    for (coeff, constraint) in terms {
+        acc.add_assign(&bases[*constraint].mul(coeff.into_repr()));
+    }
+    acc
+}
+
+/// convert [tau^i]_{i < m} to [L_i(tau)]_{i < m} by the inverse FFT over the group,
+/// the domain is the same as the `EvaluationDomain` of the bellman prover
+fn lagrange_basis<G: CurveAffine<Scalar = Fr>>(powers: &[G]) -> Vec<G> {
+    let mut a: Vec<G::Projective> = powers.iter().map(|p| p.into_projective()).collect();
+    group_ifft(&mut a, bellman_omega(a.len()));
+    a.into_iter().map(|p| p.into_affine()).collect()
+}
+
+/// the public part of a contribution, proving the knowledge of the delta multiplied in
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PublicKey {
+    /// the delta_g1 after this contribution
+    pub delta_after: G1Affine,
+    pub s: G1Affine,
+    pub s_delta: G1Affine,
+    pub r_delta: G2Affine,
+    /// the hash of the ceremony before this contribution
+    pub transcript: [u8; 32],
+}
+
+impl PublicKey {
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(self.delta_after.into_uncompressed().as_ref())?;
+        writer.write_all(self.s.into_uncompressed().as_ref())?;
+        writer.write_all(self.s_delta.into_uncompressed().as_ref())?;
+        writer.write_all(self.r_delta.into_uncompressed().as_ref())?;
+        writer.write_all(&self.transcript)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let read_uncompressed_g1 = |reader: &mut R| -> Result<G1Affine> {
+            let mut repr = <G1Affine as CurveAffine>::Uncompressed::empty();
+            reader.read_exact(repr.as_mut())?;
+            Ok(repr.into_affine()?)
+        };
+        let delta_after = read_uncompressed_g1(&mut reader)?;
+        let s = read_uncompressed_g1(&mut reader)?;
+        let s_delta = read_uncompressed_g1(&mut reader)?;
+        let mut repr = <G2Affine as CurveAffine>::Uncompressed::empty();
+        reader.read_exact(repr.as_mut())?;
+        let r_delta = repr.into_affine()?;
+        let mut transcript = [0u8; 32];
+        reader.read_exact(&mut transcript)?;
+        Ok(PublicKey {
+            delta_after,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        })
+    }
+
+    /// the contribution hash published by the participant
+    pub fn hash(&self) -> [u8; 32] {
+        let mut bytes = vec![];
+        self.write(&mut bytes).unwrap();
+        keccak256(&[&bytes])
+    }
+}
+
+/// the state of the ceremony: the current parameters and all the contributions applied so far
+#[derive(Clone, PartialEq)]
+pub struct MPCParameters {
+    pub params: Parameters<Bn256>,
+    /// the hash of the initial parameters, binding the ceremony to the circuit and the ptau
+    pub cs_hash: [u8; 32],
+    pub contributions: Vec<PublicKey>,
+}
+
+impl MPCParameters {
+    pub fn new(params: Parameters<Bn256>) -> Result<Self> {
+        let mut bytes = vec![];
+        params.write(&mut bytes)?;
+        Ok(MPCParameters {
+            params,
+            cs_hash: keccak256(&[&bytes]),
+            contributions: vec![],
+        })
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(CEREMONY_MAGIC)?;
+        writer.write_u32::<BigEndian>(CEREMONY_VERSION)?;
+        self.params.write(&mut writer)?;
+        writer.write_all(&self.cs_hash)?;
+        writer.write_u32::<BigEndian>(self.contributions.len() as u32)?;
+        for pubkey in self.contributions.iter() {
+            pubkey.write(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R, checked: bool) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        ensure!(
+            &magic == CEREMONY_MAGIC,
+            "Invalid ceremony file magic number"
+        );
+        let version = reader.read_u32::<BigEndian>()?;
+        ensure!(
+            version == CEREMONY_VERSION,
+            "Unsupported ceremony file version: {}",
+            version
+        );
+        let params = Parameters::read(&mut reader, checked)?;
+        let mut cs_hash = [0u8; 32];
+        reader.read_exact(&mut cs_hash)?;
+        let num_contributions = reader.read_u32::<BigEndian>()?;
+        let contributions = (0..num_contributions)
+            .map(|_| PublicKey::read(&mut reader))
+            .collect::<Result<_>>()?;
+        Ok(MPCParameters {
+            params,
+            cs_hash,
+            contributions,
+        })
+    }
+
+    /// the hash chain over the initial parameters and the contributions so far
+    pub fn transcript(&self) -> [u8; 32] {
+        transcript_of(&self.cs_hash, &self.contributions)
+    }
+
+    /// multiply a random secret delta into the parameters, and return the contribution hash.
+    /// the secret is dropped once this returns
+    pub fn contribute<R: Rng>(&mut self, rng: &mut R) -> [u8; 32] {
+        let delta: Fr = rng.gen();
+        let delta_inv = delta.inverse().expect("delta must not be zero");
+
+        let s = rng.gen::<G1>().into_affine();
+        let s_delta = s.mul(delta.into_repr()).into_affine();
+        let transcript = self.transcript();
+        let r = hash_to_g2(&s, &s_delta, &transcript);
+        let r_delta = r.mul(delta.into_repr()).into_affine();
+
+        let scale = |points: &[G1Affine]| -> Vec<G1Affine> {
+            points
+                .iter()
+                .map(|p| p.mul(delta_inv.into_repr()).into_affine())
+                .collect()
+        };
+        self.params.h = Arc::new(scale(&self.params.h));
+        self.params.l = Arc::new(scale(&self.params.l));
+        self.params.vk.delta_g1 = self.params.vk.delta_g1.mul(delta.into_repr()).into_affine();
+        self.params.vk.delta_g2 = self.params.vk.delta_g2.mul(delta.into_repr()).into_affine();
+
+        let pubkey = PublicKey {
+            delta_after: self.params.vk.delta_g1,
+            s,
+            s_delta,
+            r_delta,
+            transcript,
+        };
+        let hash = pubkey.hash();
+        self.contributions.push(pubkey);
+        hash
+    }
+
+    /// check the parameters are derived from the initial ones by the recorded contributions,
+    /// return the contribution hashes in order
+    pub fn verify(&self, initial: &Parameters<Bn256>) -> Result<Vec<[u8; 32]>> {
+        let mut bytes = vec![];
+        initial.write(&mut bytes)?;
+        ensure!(
+            self.cs_hash == keccak256(&[&bytes]),
+            "The ceremony was not initialized from this circuit and ptau"
+        );
+
+        let (params, vk) = (&self.params, &self.params.vk);
+        ensure!(
+            vk.alpha_g1 == initial.vk.alpha_g1
+                && vk.beta_g1 == initial.vk.beta_g1
+                && vk.beta_g2 == initial.vk.beta_g2
+                && vk.gamma_g2 == initial.vk.gamma_g2
+                && vk.ic == initial.vk.ic
+                && params.a == initial.a
+                && params.b_g1 == initial.b_g1
+                && params.b_g2 == initial.b_g2,
+            "The parameters independent of delta are modified"
+        );
+        ensure!(
+            params.h.len() == initial.h.len() && params.l.len() == initial.l.len(),
+            "The size of the H or L query is modified"
+        );
+
+        let mut hashes = vec![];
+        let mut delta_before = G1Affine::one();
+        for (i, pubkey) in self.contributions.iter().enumerate() {
+            ensure!(
+                pubkey.transcript == transcript_of(&self.cs_hash, &self.contributions[..i]),
+                "Contribution {} is not applied on the previous ones",
+                i
+            );
+            let r = hash_to_g2(&pubkey.s, &pubkey.s_delta, &pubkey.transcript);
+            ensure!(
+                !pubkey.s.is_zero() && same_ratio((pubkey.s, pubkey.s_delta), (r, pubkey.r_delta)),
+                "Contribution {} has an invalid proof of knowledge",
+                i
+            );
+            ensure!(
+                same_ratio((delta_before, pubkey.delta_after), (r, pubkey.r_delta)),
+                "Contribution {} is not a multiplication of delta",
+                i
+            );
+            delta_before = pubkey.delta_after;
+            hashes.push(pubkey.hash());
+        }
+
+        ensure!(
+            vk.delta_g1 == delta_before,
+            "The delta does not match the last contribution"
+        );
+        ensure!(
+            same_ratio(
+                (G1Affine::one(), vk.delta_g1),
+                (G2Affine::one(), vk.delta_g2)
+            ),
+            "The delta of G1 and G2 are inconsistent"
+        );
+        ensure!(
+            same_ratio(
+                merge_pairs(&params.h, &initial.h),
+                (G2Affine::one(), vk.delta_g2)
+            ),
+            "The H query is not divided by delta"
+        );
+        ensure!(
+            same_ratio(
+                merge_pairs(&params.l, &initial.l),
+                (G2Affine::one(), vk.delta_g2)
+            ),
+            "The L query is not divided by delta"
+        );
+        Ok(hashes)
+    }
+}
+
+fn keccak256(data: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    for d in data {
+        hasher.update(d);
+    }
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+    output
+}
+
+fn transcript_of(cs_hash: &[u8; 32], contributions: &[PublicKey]) -> [u8; 32] {
+    let mut bytes = cs_hash.to_vec();
+    for pubkey in contributions {
+        pubkey.write(&mut bytes).unwrap();
+    }
+    keccak256(&[&bytes])
+}
+
+/// a G2 point nobody knows the discrete log of, bound to s, s_delta and the transcript
+fn hash_to_g2(s: &G1Affine, s_delta: &G1Affine, transcript: &[u8; 32]) -> G2Affine {
+    let digest = keccak256(&[
+        s.into_uncompressed().as_ref(),
+        s_delta.into_uncompressed().as_ref(),
+        transcript,
+    ]);
+    let seed: Vec<u32> = digest
+        .chunks(4)
+        .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    let mut rng = ChaChaRng::from_seed(&seed);
+    rng.gen::<G2>().into_affine()
+}
+
+/// seed the contribution randomness by the system randomness and the participant's entropy
+pub fn rng_from_entropy(entropy: &str) -> ChaChaRng {
+    let mut system = [0u8; 32];
+    rand_old::thread_rng().fill_bytes(&mut system);
+    let digest = keccak256(&[&system, entropy.as_bytes()]);
+    let seed: Vec<u32> = digest
+        .chunks(4)
+        .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    ChaChaRng::from_seed(&seed)
+}
+
+/// e(g1.0, g2.1) == e(g1.1, g2.0)
+fn same_ratio(g1: (G1Affine, G1Affine), g2: (G2Affine, G2Affine)) -> bool {
+    Bn256::pairing(g1.0, g2.1) == Bn256::pairing(g1.1, g2.0)
+}
+
+/// compress the pairs (v1[i], v2[i]) into one by a random linear combination
+fn merge_pairs(v1: &[G1Affine], v2: &[G1Affine]) -> (G1Affine, G1Affine) {
+    let mut rng = rand_old::thread_rng();
+    let mut s = G1::zero();
+    let mut sx = G1::zero();
+    for (a, b) in v1.iter().zip(v2.iter()) {
+        let rho: Fr = rng.gen();
+        s.add_assign(&a.mul(rho.into_repr()));
+        sx.add_assign(&b.mul(rho.into_repr()));
+    }
+    (s.into_affine(), sx.into_affine())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::groth16::{
+        create_random_proof, generate_parameters, prepare_verifying_key, verify_proof,
+    };
+    use crate::binfile::write_binfile;
+    use algebraic::{circom_circuit::CircomCircuit, reader::load_r1cs};
+    use std::io::Cursor;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+
+    // a ptau file of known secrets in the snarkjs layout, for test only
+    fn gen_ptau(power: u32, tau: Fr, alpha: Fr, beta: Fr) -> Vec<u8> {
+        let powers = |n: usize, k: Fr| -> Vec<Fr> {
+            let mut acc = k;
+            (0..n)
+                .map(|_| {
+                    let cur = acc;
+                    acc.mul_assign(&tau);
+                    cur
+                })
+                .collect()
+        };
+        let g1_section = |scalars: Vec<Fr>| -> Vec<u8> {
+            let mut buf = vec![];
+            for e in scalars {
+                let p = G1Affine::one().mul(e.into_repr()).into_affine();
+                Bn256::write_g1(&mut buf, &p).unwrap();
+            }
+            buf
+        };
+        let g2_section = |scalars: Vec<Fr>| -> Vec<u8> {
+            let mut buf = vec![];
+            for e in scalars {
+                let p = G2Affine::one().mul(e.into_repr()).into_affine();
+                Bn256::write_g2(&mut buf, &p).unwrap();
+            }
+            buf
+        };
+        let n = 1usize << power;
+
+        let mut header = vec![];
+        header.write_u32::<LittleEndian>(32).unwrap();
+        header.extend(modulus_bytes::<Fq>());
+        header.write_u32::<LittleEndian>(power).unwrap();
+        header.write_u32::<LittleEndian>(power).unwrap();
+
+        let mut ptau = vec![];
+        write_binfile(
+            &mut ptau,
+            PTAU_MAGIC,
+            1,
+            vec![
+                (PTAU_HEADER, header),
+                (PTAU_TAU_G1, g1_section(powers(2 * n - 1, Fr::one()))),
+                (PTAU_TAU_G2, g2_section(powers(n, Fr::one()))),
+                (PTAU_ALPHA_TAU_G1, g1_section(powers(n, alpha))),
+                (PTAU_BETA_TAU_G1, g1_section(powers(n, beta))),
+                (PTAU_BETA_G2, g2_section(vec![beta])),
+            ],
+        )
+        .unwrap();
+        ptau
+    }
+
+    fn circuit() -> CircomCircuit<Bn256> {
+        CircomCircuit {
+            r1cs: load_r1cs(CIRCUIT_FILE).unwrap(),
+            witness: None,
+            wire_mapping: None,
+            aux_offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_initial_parameters_match_bellman() {
+        let mut rng = rand_old::thread_rng();
+        let (tau, alpha, beta): (Fr, Fr, Fr) = (rng.gen(), rng.gen(), rng.gen());
+        let ptau = gen_ptau(4, tau, alpha, beta);
+        let params = initial_parameters(circuit(), Cursor::new(ptau)).unwrap();
+
+        let expected = generate_parameters::<Bn256, _>(
+            circuit(),
+            G1::one(),
+            G2::one(),
+            alpha,
+            beta,
+            Fr::one(),
+            Fr::one(),
+            tau,
+        )
+        .unwrap();
+        assert!(params == expected);
+    }
+
+    #[test]
+    fn test_ceremony_contribute_and_verify() {
+        let mut rng = rand_old::thread_rng();
+        let ptau = gen_ptau(4, rng.gen(), rng.gen(), rng.gen());
+        let initial = initial_parameters(circuit(), Cursor::new(ptau.clone())).unwrap();
+
+        let mut mpc = MPCParameters::new(initial.clone()).unwrap();
+        let h1 = mpc.contribute(&mut rng_from_entropy("alice"));
+        let h2 = mpc.contribute(&mut rng_from_entropy("bob"));
+        assert_eq!(mpc.verify(&initial).unwrap(), vec![h1, h2]);
+
+        let mut bytes = vec![];
+        mpc.write(&mut bytes).unwrap();
+        let mpc = MPCParameters::read(Cursor::new(bytes), true).unwrap();
+        assert_eq!(mpc.verify(&initial).unwrap(), vec![h1, h2]);
+
+        // drop the first contribution from the chain
+        let mut forged = mpc.clone();
+        forged.contributions.remove(0);
+        assert!(forged.verify(&initial).is_err());
+
+        // the delta of G1 is replaced without updating the queries
+        let mut forged = mpc.clone();
+        forged.params.vk.delta_g1 = G1Affine::one()
+            .mul(rng.gen::<Fr>().into_repr())
+            .into_affine();
+        assert!(forged.verify(&initial).is_err());
+
+        // a proof over the ceremony parameters verifies
+        let circuit = CircomCircuit {
+            witness: Some(vec![
+                Fr::one(),
+                Fr::from_str("33").unwrap(),
+                Fr::from_str("3").unwrap(),
+                Fr::from_str("11").unwrap(),
+            ]),
+            ..circuit()
+        };
+        let inputs = circuit.get_public_inputs().unwrap();
+        let proof = create_random_proof(circuit, &mpc.params, &mut rng).unwrap();
+        let pvk = prepare_verifying_key(&mpc.params.vk);
+        assert!(verify_proof(&pvk, &proof, &inputs).unwrap());
+    }
+}
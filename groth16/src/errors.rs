@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+pub use anyhow::{bail, Result};
+
+#[derive(Error, Debug)]
+pub enum Groth16Error {
+    #[error("Unknown curve type: {0}")]
+    UnknownCurveType(String),
+
+    #[error("Malformed input, {0}")]
+    MalformedInput(String),
+
+    #[error("verify failed, invalid proofs: {0}")]
+    InvalidProof(String),
+
+    #[error("Unknown error, `{0}`")]
+    Unknown(String),
+}
+
+impl From<String> for Groth16Error {
+    fn from(e: String) -> Self {
+        Groth16Error::Unknown(e)
+    }
+}
+
+impl From<serde_json::Error> for Groth16Error {
+    fn from(e: serde_json::Error) -> Self {
+        Groth16Error::MalformedInput(e.to_string())
+    }
+}
@@ -0,0 +1,157 @@
+// Radix-2 FFT over the scalar field and the curve groups
+use crate::bellman_ce::pairing::CurveProjective;
+use crate::bellman_ce::{Field, PrimeField, PrimeFieldRepr};
+
+/// the m-th root of unity of bellman's `EvaluationDomain`, derived from the multiplicative generator
+pub(crate) fn bellman_omega<F: PrimeField>(m: usize) -> F {
+    let mut omega = F::root_of_unity();
+    for _ in m.trailing_zeros()..F::S {
+        omega.square();
+    }
+    omega
+}
+
+/// the m-th root of unity of snarkjs, derived from the smallest quadratic non-residue
+pub(crate) fn snarkjs_omega<F: PrimeField>(m: usize) -> F {
+    let mut minus_one = F::one();
+    minus_one.negate();
+    let mut r_minus_one = F::char();
+    r_minus_one.sub_noborrow(&F::Repr::from(1));
+    let mut half = r_minus_one;
+    half.shr(1);
+
+    let mut nqr = F::one();
+    nqr.double();
+    while nqr.pow(half) != minus_one {
+        nqr.add_assign(&F::one());
+    }
+
+    let mut t = r_minus_one;
+    t.shr(F::S);
+    let mut omega = nqr.pow(t);
+    for _ in m.trailing_zeros()..F::S {
+        omega.square();
+    }
+    omega
+}
+
+fn bitreverse_permute<T>(a: &mut [T]) {
+    let log_n = a.len().trailing_zeros();
+    for k in 0..a.len() {
+        let mut rk = 0;
+        let mut n = k;
+        for _ in 0..log_n {
+            rk = (rk << 1) | (n & 1);
+            n >>= 1;
+        }
+        if k < rk {
+            a.swap(k, rk);
+        }
+    }
+}
+
+/// a[j] <- sum_i a[i] * omega^(ij), the size must be a power of two
+pub(crate) fn fft<F: PrimeField>(a: &mut [F], omega: F) {
+    let n = a.len();
+    bitreverse_permute(a);
+    let mut half = 1;
+    while half < n {
+        let w_m = omega.pow([(n / (2 * half)) as u64]);
+        for start in (0..n).step_by(2 * half) {
+            let mut w = F::one();
+            for j in start..start + half {
+                let mut t = a[j + half];
+                t.mul_assign(&w);
+                a[j + half] = a[j];
+                a[j + half].sub_assign(&t);
+                a[j].add_assign(&t);
+                w.mul_assign(&w_m);
+            }
+        }
+        half *= 2;
+    }
+}
+
+pub(crate) fn ifft<F: PrimeField>(a: &mut [F], omega: F) {
+    fft(a, omega.inverse().unwrap());
+    let n_inv = F::from_str(&a.len().to_string())
+        .unwrap()
+        .inverse()
+        .unwrap();
+    for e in a.iter_mut() {
+        e.mul_assign(&n_inv);
+    }
+}
+
+/// the same as `fft`, but over the group points
+pub(crate) fn group_fft<G: CurveProjective>(a: &mut [G], omega: G::Scalar) {
+    let n = a.len();
+    bitreverse_permute(a);
+    let mut half = 1;
+    while half < n {
+        let w_m = omega.pow([(n / (2 * half)) as u64]);
+        for start in (0..n).step_by(2 * half) {
+            let mut w = G::Scalar::one();
+            for j in start..start + half {
+                let mut t = a[j + half];
+                t.mul_assign(w.into_repr());
+                let mut u = a[j];
+                a[j].add_assign(&t);
+                t.negate();
+                u.add_assign(&t);
+                a[j + half] = u;
+                w.mul_assign(&w_m);
+            }
+        }
+        half *= 2;
+    }
+}
+
+pub(crate) fn group_ifft<G: CurveProjective>(a: &mut [G], omega: G::Scalar) {
+    group_fft(a, omega.inverse().unwrap());
+    let n_inv = G::Scalar::from_str(&a.len().to_string())
+        .unwrap()
+        .inverse()
+        .unwrap();
+    for p in a.iter_mut() {
+        p.mul_assign(n_inv.into_repr());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::pairing::bn256::Fr;
+
+    #[test]
+    fn test_fft_roundtrip_and_roots() {
+        let m = 16;
+        for omega in [bellman_omega::<Fr>(m), snarkjs_omega::<Fr>(m)] {
+            assert_eq!(omega.pow([m as u64]), Fr::one());
+            assert_ne!(omega.pow([(m / 2) as u64]), Fr::one());
+
+            let coeffs: Vec<Fr> = (0..m)
+                .map(|i| Fr::from_str(&(i * i + 1).to_string()).unwrap())
+                .collect();
+            let mut a = coeffs.clone();
+            fft(&mut a, omega);
+            // a[1] is the polynomial evaluated at omega
+            let mut x = Fr::one();
+            let mut eval = Fr::zero();
+            for c in coeffs.iter() {
+                let mut t = *c;
+                t.mul_assign(&x);
+                eval.add_assign(&t);
+                x.mul_assign(&omega);
+            }
+            assert_eq!(a[1], eval);
+            ifft(&mut a, omega);
+            assert_eq!(a, coeffs);
+        }
+
+        let mut shift = snarkjs_omega::<Fr>(2 * m);
+        shift.square();
+        assert_eq!(shift, snarkjs_omega::<Fr>(m));
+        assert_ne!(bellman_omega::<Fr>(m), snarkjs_omega::<Fr>(m));
+    }
+}
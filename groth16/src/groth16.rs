@@ -1,8 +1,8 @@
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
-use crate::bellman_ce::{groth16::*, Circuit};
-use anyhow::Result;
+use crate::bellman_ce::{groth16::*, Circuit, Field, PrimeField};
+use anyhow::{ensure, Result};
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
-use franklin_crypto::bellman::pairing::Engine;
+use franklin_crypto::bellman::pairing::{CurveAffine, CurveProjective, Engine};
 #[allow(unused_imports)]
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 use rand_old::{self as rand, Rng};
@@ -66,6 +66,33 @@ where
 
         Ok(result)
     }
+
+    /// verify the proofs of the same circuit at once by a random linear combination
+    pub fn verify_batch<R: RngCore>(
+        circuit_vk: &VerifyingKey<E>,
+        proofs: &[(Proof<E>, Vec<E::Fr>)],
+        rng: &mut R,
+    ) -> Result<bool>
+    where
+        <E::Fr as ff::PrimeField>::Repr: Sync + Copy,
+    {
+        for (_, public_input) in proofs {
+            ensure!(
+                public_input.len() + 1 == circuit_vk.ic.len(),
+                "The public input has {} elements, but the verification key expects {}",
+                public_input.len(),
+                circuit_vk.ic.len() - 1
+            );
+        }
+        let circuit_pvk = prepare_verifying_key(circuit_vk);
+        let (proofs, public_inputs): (Vec<_>, Vec<_>) = proofs
+            .iter()
+            .map(|(proof, input)| (proof, input.clone()))
+            .unzip();
+        let result = verify_proofs_batch(&circuit_pvk, rng, &proofs, &public_inputs)?;
+
+        Ok(result)
+    }
 }
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
@@ -105,6 +132,56 @@ impl<E: Engine, C: Circuit<E>> Groth16<E, C> {
 
         Ok(result)
     }
+
+    /// verify the proofs of the same circuit at once, the equations of the proofs are combined
+    /// by random scalars, so one multi-Miller loop and one final exponentiation are needed:
+    /// prod e(r_i * A_i, B_i) * e(-sum(r_i) * alpha, beta) * e(sum(r_i * IC_i), -gamma)
+    ///     * e(sum(r_i * C_i), -delta) == 1
+    pub fn verify_batch<R: Rng>(
+        circuit_vk: &VerifyingKey<E>,
+        proofs: &[(Proof<E>, Vec<E::Fr>)],
+        rng: &mut R,
+    ) -> Result<bool> {
+        let mut sum_r = E::Fr::zero();
+        let mut acc_ic = E::G1::zero();
+        let mut acc_c = E::G1::zero();
+        let mut terms = Vec::with_capacity(proofs.len() + 3);
+        for (proof, public_input) in proofs {
+            ensure!(
+                public_input.len() + 1 == circuit_vk.ic.len(),
+                "The public input has {} elements, but the verification key expects {}",
+                public_input.len(),
+                circuit_vk.ic.len() - 1
+            );
+            let r: E::Fr = rng.gen();
+            let mut ic = circuit_vk.ic[0].into_projective();
+            for (input, base) in public_input.iter().zip(circuit_vk.ic.iter().skip(1)) {
+                ic.add_assign(&base.mul(input.into_repr()));
+            }
+            ic.mul_assign(r.into_repr());
+            acc_ic.add_assign(&ic);
+            acc_c.add_assign(&proof.c.mul(r.into_repr()));
+            sum_r.add_assign(&r);
+            terms.push((
+                proof.a.mul(r.into_repr()).into_affine().prepare(),
+                proof.b.prepare(),
+            ));
+        }
+
+        let mut alpha = circuit_vk.alpha_g1.mul(sum_r.into_repr());
+        alpha.negate();
+        let mut neg_gamma = circuit_vk.gamma_g2;
+        neg_gamma.negate();
+        let mut neg_delta = circuit_vk.delta_g2;
+        neg_delta.negate();
+        terms.push((alpha.into_affine().prepare(), circuit_vk.beta_g2.prepare()));
+        terms.push((acc_ic.into_affine().prepare(), neg_gamma.prepare()));
+        terms.push((acc_c.into_affine().prepare(), neg_delta.prepare()));
+
+        let terms: Vec<_> = terms.iter().map(|(p, q)| (p, q)).collect();
+        let result = E::final_exponentiation(&E::miller_loop(terms.iter()));
+        Ok(result == Some(E::Fqk::one()))
+    }
 }
 
 #[cfg(test)]
@@ -140,7 +217,7 @@ mod tests {
         //1. SRS
         let t = std::time::Instant::now();
         let circuit: CircomCircuit<Bn256> = CircomCircuit {
-            r1cs: reader::load_r1cs(CIRCUIT_FILE),
+            r1cs: reader::load_r1cs(CIRCUIT_FILE)?,
             witness: None,
             wire_mapping: None,
             aux_offset: 0,
@@ -153,7 +230,7 @@ mod tests {
         //2. Prove
         let t1 = std::time::Instant::now();
         let mut wtns = WitnessCalculator::from_file(WASM_FILE)?;
-        let inputs = load_input_for_witness(INPUT_FILE);
+        let inputs = load_input_for_witness(INPUT_FILE)?;
         let w = wtns.calculate_witness(inputs, false).unwrap();
         let w = w
             .iter()
@@ -167,7 +244,7 @@ mod tests {
             })
             .collect::<Vec<_>>();
         let circuit1: CircomCircuit<Bn256> = CircomCircuit {
-            r1cs: reader::load_r1cs(CIRCUIT_FILE),
+            r1cs: reader::load_r1cs(CIRCUIT_FILE)?,
             witness: Some(w),
             wire_mapping: None,
             aux_offset: 0,
@@ -190,6 +267,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn groth16_verify_batch() -> Result<()> {
+        let circuit: CircomCircuit<Bn256> = CircomCircuit {
+            r1cs: reader::load_r1cs(CIRCUIT_FILE)?,
+            witness: None,
+            wire_mapping: None,
+            aux_offset: 0,
+        };
+        let mut rng = rand::thread_rng();
+        let (pk, vk) = Groth16::circuit_specific_setup(circuit.clone(), &mut rng)?;
+
+        let mut wtns = WitnessCalculator::from_file(WASM_FILE)?;
+        let w = wtns.calculate_witness(load_input_for_witness(INPUT_FILE)?, false)?;
+        let circuit = create_circuit_add_witness::<Bn256>(circuit, w);
+        let inputs = circuit.get_public_inputs().unwrap();
+        let mut proofs = vec![];
+        for _ in 0..3 {
+            let proof = Groth16::prove(&pk, circuit.clone(), &mut rng)?;
+            proofs.push((proof, inputs.clone()));
+        }
+        assert!(Groth16::<_, CircomCircuit<Bn256>>::verify_batch(
+            &vk, &proofs, &mut rng
+        )?);
+
+        proofs[1].1[0].add_assign(&Fr::one());
+        assert!(!Groth16::<_, CircomCircuit<Bn256>>::verify_batch(
+            &vk, &proofs, &mut rng
+        )?);
+
+        proofs[1].1.push(Fr::one());
+        assert!(Groth16::<_, CircomCircuit<Bn256>>::verify_batch(&vk, &proofs, &mut rng).is_err());
+        Ok(())
+    }
+
     #[test]
     fn groth16_proof_bls12381_inpace() -> Result<()> {
         //1. SRS
@@ -206,7 +317,7 @@ mod tests {
         let t1 = std::time::Instant::now();
         let mut rng = rand::thread_rng();
         let mut wtns = WitnessCalculator::from_file(WASM_FILE_BLS12)?;
-        let inputs = load_input_for_witness(INPUT_FILE);
+        let inputs = load_input_for_witness(INPUT_FILE)?;
         let w = wtns.calculate_witness(inputs, false).unwrap();
         let circuit1: CircomCircuit<Bls12> = create_circuit_add_witness::<Bls12>(circuit, w);
         let proof = Groth16::prove(&pk, circuit1.clone(), &mut rng)?;
@@ -305,7 +416,7 @@ mod tests {
         //2. Prove
         let t1 = std::time::Instant::now();
         let mut wtns = WitnessCalculator::from_file(WASM_FILE_BLS12)?;
-        let inputs = load_input_for_witness(INPUT_FILE);
+        let inputs = load_input_for_witness(INPUT_FILE)?;
         let w = wtns.calculate_witness(inputs, false).unwrap();
         let circuit1: CircomCircuit<Scalar> = create_circuit_add_witness(circuit, w);
         let proof = Groth16::prove(&pk, circuit1.clone(), &mut OsRng)?;
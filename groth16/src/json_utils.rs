@@ -1,12 +1,13 @@
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 use crate::bellman_ce::pairing::{bls12_381::Bls12, bn256::Bn256};
+use crate::errors::Groth16Error;
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 use algebraic::utils::repr_to_big;
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 use algebraic::PrimeField;
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use algebraic_gpu::circom_circuit::repr_to_big;
-use anyhow::Result;
+use anyhow::{bail, Result};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use bellperson::groth16::*;
 #[cfg(any(feature = "cuda", feature = "opencl"))]
@@ -25,8 +26,9 @@ use num_traits::Num;
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use pairing::MultiMillerLoop;
 use serde::{Deserialize, Serialize};
-use serde_json::to_string;
+use serde_json::{json, to_string};
 use std::fmt;
+use tiny_keccak::{Hasher, Keccak};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct G1 {
     pub x: String,
@@ -109,8 +111,8 @@ pub trait Parser: franklin_crypto::bellman::pairing::Engine {
             y: (parsed.2, parsed.3).into(),
         }
     }
-    fn to_g1(x: &str, y: &str) -> Self::G1Affine;
-    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Self::G2Affine;
+    fn to_g1(x: &str, y: &str) -> Result<Self::G1Affine>;
+    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Result<Self::G2Affine>;
 }
 
 #[cfg(any(feature = "cuda", feature = "opencl"))]
@@ -131,8 +133,8 @@ pub trait Parser: MultiMillerLoop {
             y: (parsed.2, parsed.3).into(),
         }
     }
-    fn to_g1(x: &str, y: &str) -> Self::G1Affine;
-    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Self::G2Affine;
+    fn to_g1(x: &str, y: &str) -> Result<Self::G1Affine>;
+    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Result<Self::G2Affine>;
 }
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
@@ -146,19 +148,16 @@ pub fn render_scalar_to_str<F: PrimeField>(el: &F, to_hex: bool) -> String {
 }
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
-pub fn render_str_to_scalar<F: PrimeField>(value: &str) -> F {
-    let value = match value.starts_with("0x") {
-        true => BigUint::from_str_radix(&value[2..], 16)
-            .unwrap()
-            .to_str_radix(10),
-        _ => value.to_string(),
-    };
-    F::from_str(&value).unwrap()
+pub fn render_str_to_scalar<F: PrimeField>(value: &str) -> Result<F> {
+    let big = parse_uint(value)?.to_str_radix(10);
+    F::from_str(&big).ok_or_else(|| {
+        Groth16Error::MalformedInput(format!("Invalid field element {}", value)).into()
+    })
 }
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
-pub fn to_public_input<T: PrimeField>(s: &str) -> Vec<T> {
-    let input: Vec<String> = serde_json::from_str(s).unwrap();
+pub fn to_public_input<T: PrimeField>(s: &str) -> Result<Vec<T>> {
+    let input: Vec<String> = serde_json::from_str(s).map_err(Groth16Error::from)?;
     input
         .iter()
         .map(|hex_str| render_str_to_scalar::<T>(hex_str))
@@ -184,20 +183,23 @@ impl Parser for Bn256 {
         )
     }
 
-    fn to_g1(x: &str, y: &str) -> Self::G1Affine {
-        G1Affine::from_xy_unchecked(render_str_to_scalar(x), render_str_to_scalar(y))
+    fn to_g1(x: &str, y: &str) -> Result<Self::G1Affine> {
+        Ok(G1Affine::from_xy_unchecked(
+            render_str_to_scalar(x)?,
+            render_str_to_scalar(y)?,
+        ))
     }
 
-    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Self::G2Affine {
+    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Result<Self::G2Affine> {
         let x = Fq2 {
-            c0: render_str_to_scalar(x0),
-            c1: render_str_to_scalar(x1),
+            c0: render_str_to_scalar(x0)?,
+            c1: render_str_to_scalar(x1)?,
         };
         let y = Fq2 {
-            c0: render_str_to_scalar(y0),
-            c1: render_str_to_scalar(y1),
+            c0: render_str_to_scalar(y0)?,
+            c1: render_str_to_scalar(y1)?,
         };
-        G2Affine::from_xy_unchecked(x, y)
+        Ok(G2Affine::from_xy_unchecked(x, y))
     }
 }
 
@@ -221,20 +223,23 @@ impl Parser for Bls12 {
         )
     }
 
-    fn to_g1(x: &str, y: &str) -> Self::G1Affine {
-        G1Affine_bls12381::from_xy_unchecked(render_str_to_scalar(x), render_str_to_scalar(y))
+    fn to_g1(x: &str, y: &str) -> Result<Self::G1Affine> {
+        Ok(G1Affine_bls12381::from_xy_unchecked(
+            render_str_to_scalar(x)?,
+            render_str_to_scalar(y)?,
+        ))
     }
 
-    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Self::G2Affine {
+    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Result<Self::G2Affine> {
         let x = Fq2_bls12381 {
-            c0: render_str_to_scalar(x0),
-            c1: render_str_to_scalar(x1),
+            c0: render_str_to_scalar(x0)?,
+            c1: render_str_to_scalar(x1)?,
         };
         let y = Fq2_bls12381 {
-            c0: render_str_to_scalar(y0),
-            c1: render_str_to_scalar(y1),
+            c0: render_str_to_scalar(y0)?,
+            c1: render_str_to_scalar(y1)?,
         };
-        G2Affine_bls12381::from_xy_unchecked(x, y)
+        Ok(G2Affine_bls12381::from_xy_unchecked(x, y))
     }
 }
 
@@ -253,51 +258,38 @@ pub fn render_fp_to_str(fp: &Fp, to_hex: bool) -> String {
     }
 }
 
+/// the big endian bytes of a decimal or 0x-prefixed hex value, left padded to `N` bytes
 #[cfg(any(feature = "cuda", feature = "opencl"))]
-pub fn render_str_to_fp(value: &str) -> Fp {
-    let mut be_bytes = [0u8; 48];
-    let hex_str;
-    if value.starts_with("0x") {
-        hex_str = value[2..].to_string();
-    } else {
-        let big_uint = BigUint::from_str_radix(value, 10).unwrap();
-        hex_str = big_uint.to_str_radix(16);
+fn render_str_to_be_bytes<const N: usize>(value: &str) -> Result<[u8; N]> {
+    let bytes = parse_uint(value)?.to_bytes_be();
+    if bytes.len() > N {
+        bail!(Groth16Error::MalformedInput(format!(
+            "The value {} overflows {} bytes",
+            value, N
+        )));
     }
-    let final_hex_str = if hex_str.len() % 2 != 0 {
-        format!("0{}", hex_str)
-    } else {
-        hex_str
-    };
-    let bytes = hex::decode(final_hex_str).expect("Invalid hex string");
-    let start = 48 - bytes.len();
-    be_bytes[start..].copy_from_slice(&bytes);
-    Fp::from_bytes_be(&be_bytes).unwrap()
+    let mut be_bytes = [0u8; N];
+    be_bytes[N - bytes.len()..].copy_from_slice(&bytes);
+    Ok(be_bytes)
 }
 
 #[cfg(any(feature = "cuda", feature = "opencl"))]
-pub fn render_str_to_scalar(value: &str) -> Scalar {
-    let mut be_bytes = [0u8; 32];
-    let hex_str;
-    if value.starts_with("0x") {
-        hex_str = value[2..].to_string();
-    } else {
-        let big_uint = BigUint::from_str_radix(value, 10).unwrap();
-        hex_str = big_uint.to_str_radix(16);
-    }
-    let final_hex_str = if hex_str.len() % 2 != 0 {
-        format!("0{}", hex_str)
-    } else {
-        hex_str
-    };
-    let bytes = hex::decode(final_hex_str).expect("Invalid hex string");
-    let start = 32 - bytes.len();
-    be_bytes[start..].copy_from_slice(&bytes);
-    Scalar::from_bytes_be(&be_bytes).unwrap()
+pub fn render_str_to_fp(value: &str) -> Result<Fp> {
+    Option::from(Fp::from_bytes_be(&render_str_to_be_bytes(value)?)).ok_or_else(|| {
+        Groth16Error::MalformedInput(format!("Invalid field element {}", value)).into()
+    })
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn render_str_to_scalar(value: &str) -> Result<Scalar> {
+    Option::from(Scalar::from_bytes_be(&render_str_to_be_bytes(value)?)).ok_or_else(|| {
+        Groth16Error::MalformedInput(format!("Invalid field element {}", value)).into()
+    })
 }
 
 #[cfg(any(feature = "cuda", feature = "opencl"))]
-pub fn to_public_input(s: &str) -> Vec<Scalar> {
-    let input: Vec<String> = serde_json::from_str(s).unwrap();
+pub fn to_public_input(s: &str) -> Result<Vec<Scalar>> {
+    let input: Vec<String> = serde_json::from_str(s).map_err(Groth16Error::from)?;
     input
         .iter()
         .map(|hex_str| render_str_to_scalar(hex_str))
@@ -321,14 +313,18 @@ impl Parser for Bls12 {
         )
     }
 
-    fn to_g1(x: &str, y: &str) -> Self::G1Affine {
-        G1Affine::from_raw_unchecked(render_str_to_fp(x), render_str_to_fp(y), false)
+    fn to_g1(x: &str, y: &str) -> Result<Self::G1Affine> {
+        Ok(G1Affine::from_raw_unchecked(
+            render_str_to_fp(x)?,
+            render_str_to_fp(y)?,
+            false,
+        ))
     }
 
-    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Self::G2Affine {
-        let x = Fp2::new(render_str_to_fp(x0), render_str_to_fp(x1));
-        let y = Fp2::new(render_str_to_fp(y0), render_str_to_fp(y1));
-        G2Affine::from_raw_unchecked(x, y, false)
+    fn to_g2(x0: &str, x1: &str, y0: &str, y1: &str) -> Result<Self::G2Affine> {
+        let x = Fp2::new(render_str_to_fp(x0)?, render_str_to_fp(x1)?);
+        let y = Fp2::new(render_str_to_fp(y0)?, render_str_to_fp(y1)?);
+        Ok(G2Affine::from_raw_unchecked(x, y, false))
     }
 }
 
@@ -368,36 +364,138 @@ pub fn serialize_proof<P: Parser>(p: &Proof<P>, curve_type: &str, to_hex: bool)
     Ok(to_string(&proof_file)?)
 }
 
-pub fn to_verification_key<P: Parser>(s: &str) -> VerifyingKey<P> {
-    let vk_file: VerifyingKeyFile =
-        serde_json::from_str(s).expect("Error during deserialization of the JSON data");
+/// Render the proof and public inputs as the arguments of `verifyTx` of the verifier generated by
+/// `generate_verifier`, returns the hex ABI-encoded calldata and the JSON argument array.
+/// The G2 coordinates stay in the (c0, c1) order, the verifier swaps them for the precompile.
+pub fn to_verifier_calldata(
+    proof: &ProofFile,
+    public_inputs: &[String],
+) -> Result<(String, String)> {
+    let (proof_type, proof_arg, mut words) = match proof.curve.as_str() {
+        "BN128" => {
+            let g1 = |p: &G1| -> Result<(Vec<BigUint>, serde_json::Value)> {
+                let (x, y) = (parse_uint(&p.x)?, parse_uint(&p.y)?);
+                let arg = json!([x.to_string(), y.to_string()]);
+                Ok((vec![x, y], arg))
+            };
+            let (a, a_arg) = g1(&proof.a)?;
+            let (c, c_arg) = g1(&proof.c)?;
+            let b = [&proof.b.x[0], &proof.b.x[1], &proof.b.y[0], &proof.b.y[1]]
+                .into_iter()
+                .map(|v| parse_uint(v))
+                .collect::<Result<Vec<_>>>()?;
+            let b_arg = json!([
+                [b[0].to_string(), b[1].to_string()],
+                [b[2].to_string(), b[3].to_string()]
+            ]);
+            (
+                "((uint256,uint256),(uint256[2],uint256[2]),(uint256,uint256))".to_string(),
+                json!([a_arg, b_arg, c_arg]),
+                [a, b, c].concat(),
+            )
+        }
+        "BLS12381" => {
+            // the 48-byte base field element is split into two words, a holds the top 16 bytes
+            let fp = |v: &str| -> Result<(Vec<BigUint>, serde_json::Value)> {
+                let v = parse_uint(v)?;
+                let a = &v >> 256;
+                let b = v & ((BigUint::from(1u32) << 256) - 1u32);
+                let arg = json!([a.to_string(), b.to_string()]);
+                Ok((vec![a, b], arg))
+            };
+            let g1 = |p: &G1| -> Result<(Vec<BigUint>, serde_json::Value)> {
+                let (x, x_arg) = fp(&p.x)?;
+                let (y, y_arg) = fp(&p.y)?;
+                Ok(([x, y].concat(), json!([x_arg, y_arg])))
+            };
+            let (a, a_arg) = g1(&proof.a)?;
+            let (c, c_arg) = g1(&proof.c)?;
+            let (x0, x0_arg) = fp(&proof.b.x[0])?;
+            let (x1, x1_arg) = fp(&proof.b.x[1])?;
+            let (y0, y0_arg) = fp(&proof.b.y[0])?;
+            let (y1, y1_arg) = fp(&proof.b.y[1])?;
+            let b_arg = json!([[x0_arg, x1_arg], [y0_arg, y1_arg]]);
+            let fp_type = "(uint256,uint256)";
+            let g1_type = format!("({},{})", fp_type, fp_type);
+            let g2_type = format!("(({},{}),({},{}))", fp_type, fp_type, fp_type, fp_type);
+            (
+                format!("({},{},{})", g1_type, g2_type, g1_type),
+                json!([a_arg, b_arg, c_arg]),
+                [a, x0, x1, y0, y1, c].concat(),
+            )
+        }
+        _ => bail!(format!("Unsupported curve type: {}", proof.curve)),
+    };
+
+    let inputs = public_inputs
+        .iter()
+        .map(|v| parse_uint(v))
+        .collect::<Result<Vec<_>>>()?;
+    let (signature, args) = if inputs.is_empty() {
+        (format!("verifyTx({})", proof_type), json!([proof_arg]))
+    } else {
+        let input_arg: Vec<String> = inputs.iter().map(|v| v.to_string()).collect();
+        (
+            format!("verifyTx({},uint256[{}])", proof_type, inputs.len()),
+            json!([proof_arg, input_arg]),
+        )
+    };
+    words.extend(inputs);
+
+    let mut selector = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(signature.as_bytes());
+    hasher.finalize(&mut selector);
+
+    let mut calldata = format!("0x{}", hex::encode(&selector[..4]));
+    for word in words.iter() {
+        let bytes = word.to_bytes_be();
+        if bytes.len() > 32 {
+            bail!(format!("The value {} overflows uint256", word));
+        }
+        calldata.push_str(&"00".repeat(32 - bytes.len()));
+        calldata.push_str(&hex::encode(bytes));
+    }
+    Ok((calldata, to_string(&args)?))
+}
+
+/// parse a decimal or 0x-prefixed hex value
+fn parse_uint(value: &str) -> Result<BigUint> {
+    match value.strip_prefix("0x") {
+        Some(hex) => BigUint::from_str_radix(hex, 16),
+        None => BigUint::from_str_radix(value, 10),
+    }
+    .map_err(|e| Groth16Error::MalformedInput(format!("Invalid integer {}, {:?}", value, e)).into())
+}
+
+pub fn to_verification_key<P: Parser>(s: &str) -> Result<VerifyingKey<P>> {
+    let vk_file: VerifyingKeyFile = serde_json::from_str(s).map_err(Groth16Error::from)?;
 
     let convert_g1 = |point: &G1| P::to_g1(&point.x, &point.y);
     let convert_g2 = |point: &G2| P::to_g2(&point.x[0], &point.x[1], &point.y[0], &point.y[1]);
 
-    VerifyingKey {
-        alpha_g1: convert_g1(&vk_file.alpha_g1),
-        beta_g1: convert_g1(&vk_file.beta_g1),
-        beta_g2: convert_g2(&vk_file.beta_g2),
-        gamma_g2: convert_g2(&vk_file.gamma_g2),
-        delta_g1: convert_g1(&vk_file.delta_g1),
-        delta_g2: convert_g2(&vk_file.delta_g2),
-        ic: vk_file.ic.iter().map(convert_g1).collect(),
-    }
+    Ok(VerifyingKey {
+        alpha_g1: convert_g1(&vk_file.alpha_g1)?,
+        beta_g1: convert_g1(&vk_file.beta_g1)?,
+        beta_g2: convert_g2(&vk_file.beta_g2)?,
+        gamma_g2: convert_g2(&vk_file.gamma_g2)?,
+        delta_g1: convert_g1(&vk_file.delta_g1)?,
+        delta_g2: convert_g2(&vk_file.delta_g2)?,
+        ic: vk_file.ic.iter().map(convert_g1).collect::<Result<_>>()?,
+    })
 }
 
-pub fn to_proof<P: Parser>(s: &str) -> Proof<P> {
-    let proof: ProofFile =
-        serde_json::from_str(s).expect("Error during deserialization of the JSON data");
+pub fn to_proof<P: Parser>(s: &str) -> Result<Proof<P>> {
+    let proof: ProofFile = serde_json::from_str(s).map_err(Groth16Error::from)?;
 
     let convert_g1 = |point: &G1| P::to_g1(&point.x, &point.y);
     let convert_g2 = |point: &G2| P::to_g2(&point.x[0], &point.x[1], &point.y[0], &point.y[1]);
 
-    Proof {
-        a: convert_g1(&proof.a),
-        b: convert_g2(&proof.b),
-        c: convert_g1(&proof.c),
-    }
+    Ok(Proof {
+        a: convert_g1(&proof.a)?,
+        b: convert_g2(&proof.b)?,
+        c: convert_g1(&proof.c)?,
+    })
 }
 
 #[cfg(test)]
@@ -418,7 +516,7 @@ mod tests {
 
         let json_data = std::fs::read_to_string("./test-vectors/verification_key.json")
             .expect("Unable to read the JSON file");
-        let verifying_key_from_json = to_verification_key::<Bn256>(&json_data);
+        let verifying_key_from_json = to_verification_key::<Bn256>(&json_data).unwrap();
         assert_eq!(
             vk_from_bin.alpha_g1, verifying_key_from_json.alpha_g1,
             "VerificationKey are not equal"
@@ -437,7 +535,7 @@ mod tests {
             .expect("Unable to write data to file");
         let json_data = std::fs::read_to_string("./test-vectors/verification_key_bls12381.json")
             .expect("Unable to read the JSON file");
-        let verifying_key_from_json = to_verification_key::<Bls12>(&json_data);
+        let verifying_key_from_json = to_verification_key::<Bls12>(&json_data).unwrap();
         assert_eq!(
             vk_from_bin.alpha_g1, verifying_key_from_json.alpha_g1,
             "VerificationKey are not equal"
@@ -456,9 +554,78 @@ mod tests {
 
         let json_data = std::fs::read_to_string("./test-vectors/proof.json")
             .expect("Unable to read the JSON file");
-        let proof_from_json = to_proof::<Bn256>(&json_data);
+        let proof_from_json = to_proof::<Bn256>(&json_data).unwrap();
         assert_eq!(proof_from_bin.a, proof_from_json.a, "Proofs are not equal");
     }
+
+    #[test]
+    fn test_to_verifier_calldata() {
+        let g1 = |x: &str, y: &str| G1 {
+            x: x.to_string(),
+            y: y.to_string(),
+        };
+        let mut proof = ProofFile {
+            a: g1("1", "2"),
+            b: G2 {
+                x: ["3".to_string(), "4".to_string()],
+                y: ["5".to_string(), "0x6".to_string()],
+            },
+            c: g1("7", "8"),
+            protocol: "groth16".to_string(),
+            curve: "BN128".to_string(),
+        };
+        let (calldata, args) = to_verifier_calldata(&proof, &["9".to_string()]).unwrap();
+        // keccak256("verifyTx(((uint256,uint256),(uint256[2],uint256[2]),(uint256,uint256)),uint256[1])")
+        let expected = (1..=9).fold("0xf48d7729".to_string(), |acc, i| {
+            format!("{}{:064x}", acc, i)
+        });
+        assert_eq!(calldata, expected);
+        assert_eq!(
+            args,
+            r#"[[["1","2"],[["3","4"],["5","6"]],["7","8"]],["9"]]"#
+        );
+
+        // the 48-byte coordinates take two words each
+        proof.curve = "BLS12381".to_string();
+        proof.a.x = format!("0x{:032x}{:064x}", 1, 2);
+        let (calldata, args) = to_verifier_calldata(&proof, &[]).unwrap();
+        assert_eq!(calldata.len(), 2 + 8 + 16 * 64);
+        assert_eq!(&calldata[10..138], format!("{:064x}{:064x}", 1, 2));
+        assert!(args.starts_with(r#"[[[["1","2"],["0","2"]],"#));
+
+        proof.curve = "BW6761".to_string();
+        assert!(to_verifier_calldata(&proof, &[]).is_err());
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        use crate::bellman_ce::pairing::bn256::Fr;
+        let input = to_public_input::<Fr>(r#"["1", "0x2"]"#).unwrap();
+        assert_eq!(
+            input,
+            vec![Fr::from_str("1").unwrap(), Fr::from_str("2").unwrap()]
+        );
+
+        let is_malformed = |err: anyhow::Error| {
+            matches!(
+                err.downcast_ref::<Groth16Error>(),
+                Some(Groth16Error::MalformedInput(_))
+            )
+        };
+        assert!(is_malformed(
+            to_public_input::<Fr>(r#"["1", "x"]"#).unwrap_err()
+        ));
+        assert!(is_malformed(
+            to_public_input::<Fr>(r#"["0xzz"]"#).unwrap_err()
+        ));
+        // the modulus of the scalar field is not a field element
+        let modulus =
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+        assert!(is_malformed(
+            to_public_input::<Fr>(&format!(r#"["{}"]"#, modulus)).unwrap_err()
+        ));
+        assert!(is_malformed(Bn256::to_g1("1", "y").unwrap_err()));
+    }
 }
 
 #[cfg(test)]
@@ -478,7 +645,7 @@ mod tests {
             .expect("Unable to write data to file");
         let json_data = std::fs::read_to_string("./test-vectors/verification_key_bls12381.json")
             .expect("Unable to read the JSON file");
-        let verifying_key_from_json = to_verification_key::<Bls12>(&json_data);
+        let verifying_key_from_json = to_verification_key::<Bls12>(&json_data).unwrap();
         assert_eq!(
             vk_from_bin.alpha_g1, verifying_key_from_json.alpha_g1,
             "VerificationKey are not equal"
@@ -1,7 +1,16 @@
 pub mod api;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+mod binfile;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub mod ceremony;
+pub mod errors;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+mod fft;
 pub mod groth16;
 pub mod json_utils;
 mod template;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub mod zkey;
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 mod non_gpu_specific {
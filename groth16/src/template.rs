@@ -52,3 +52,137 @@ contract Verifier {
     }
 }
 "#;
+
+// The BLS12-381 verifier runs on the EIP-2537 precompiles, G1MSM at 0x0c and PAIRING_CHECK at
+// 0x0f. A 48-byte base field element is encoded as 64 big-endian bytes, i.e. two words.
+pub const CONTRACT_TEMPLATE_BLS12381: &str = r#"// This file is MIT Licensed.
+pragma solidity ^0.8.16;
+library Pairing {
+    address constant G1MSM = address(0x0c);
+    address constant PAIRING_CHECK = address(0x0f);
+    // Encoding of a base field element is: a * 2^256 + b, a holds the top 16 bytes
+    struct Fp {
+        uint a;
+        uint b;
+    }
+    // Encoding of an extension field element is: c0 + c1 * u
+    struct Fp2 {
+        Fp c0;
+        Fp c1;
+    }
+    struct G1Point {
+        Fp X;
+        Fp Y;
+    }
+    struct G2Point {
+        Fp2 X;
+        Fp2 Y;
+    }
+    function encode(G1Point memory p) pure internal returns (bytes memory) {
+        return abi.encode(p.X.a, p.X.b, p.Y.a, p.Y.b);
+    }
+    function encode(G2Point memory p) pure internal returns (bytes memory) {
+        return abi.encode(
+            p.X.c0.a, p.X.c0.b, p.X.c1.a, p.X.c1.b,
+            p.Y.c0.a, p.Y.c0.b, p.Y.c1.a, p.Y.c1.b
+        );
+    }
+    /// @return r the multi-scalar multiplication p[0] * s[0] + ... + p[n] * s[n]
+    function msm(G1Point[] memory p, uint[] memory s) internal view returns (G1Point memory r) {
+        require(p.length == s.length && p.length > 0);
+        bytes memory input;
+        for (uint i = 0; i < p.length; i++) {
+            input = bytes.concat(input, encode(p[i]), abi.encode(s[i]));
+        }
+        (bool success, bytes memory out) = G1MSM.staticcall(input);
+        require(success && out.length == 128);
+        (r.X.a, r.X.b, r.Y.a, r.Y.b) = abi.decode(out, (uint, uint, uint, uint));
+    }
+    /// @return the result of computing the pairing check
+    /// e(p1[0], p2[0]) *  .... * e(p1[n], p2[n]) == 1
+    function pairing(G1Point[] memory p1, G2Point[] memory p2) internal view returns (bool) {
+        require(p1.length == p2.length);
+        bytes memory input;
+        for (uint i = 0; i < p1.length; i++) {
+            input = bytes.concat(input, encode(p1[i]), encode(p2[i]));
+        }
+        (bool success, bytes memory out) = PAIRING_CHECK.staticcall(input);
+        require(success && out.length == 32);
+        return abi.decode(out, (uint)) == 1;
+    }
+    /// Convenience method for a pairing check for four pairs.
+    function pairingProd4(
+            G1Point memory a1, G2Point memory a2,
+            G1Point memory b1, G2Point memory b2,
+            G1Point memory c1, G2Point memory c2,
+            G1Point memory d1, G2Point memory d2
+    ) internal view returns (bool) {
+        G1Point[] memory p1 = new G1Point[](4);
+        G2Point[] memory p2 = new G2Point[](4);
+        p1[0] = a1;
+        p1[1] = b1;
+        p1[2] = c1;
+        p1[3] = d1;
+        p2[0] = a2;
+        p2[1] = b2;
+        p2[2] = c2;
+        p2[3] = d2;
+        return pairing(p1, p2);
+    }
+}
+
+contract Verifier {
+    using Pairing for *;
+    // beta, gamma and delta are negated, so that no base field arithmetic is needed on chain
+    struct VerifyingKey {
+        Pairing.G1Point alpha;
+        Pairing.G2Point beta_neg;
+        Pairing.G2Point gamma_neg;
+        Pairing.G2Point delta_neg;
+        Pairing.G1Point[] gamma_abc;
+    }
+    struct Proof {
+        Pairing.G1Point a;
+        Pairing.G2Point b;
+        Pairing.G1Point c;
+    }
+    function verifyingKey() pure internal returns (VerifyingKey memory vk) {
+        vk.alpha = <%vk_alpha%>;
+        vk.beta_neg = <%vk_beta_neg%>;
+        vk.gamma_neg = <%vk_gamma_neg%>;
+        vk.delta_neg = <%vk_delta_neg%>;
+        vk.gamma_abc = new Pairing.G1Point[](<%vk_gamma_abc_length%>);
+        <%vk_gamma_abc_pts%>
+    }
+    function verify(uint[] memory input, Proof memory proof) internal view returns (uint) {
+        uint256 snark_scalar_field = 52435875175126190479447740508185965837690552500527637822603658699938581184513;
+        VerifyingKey memory vk = verifyingKey();
+        require(input.length + 1 == vk.gamma_abc.length);
+        // Compute the linear combination vk_x
+        uint[] memory scalars = new uint[](vk.gamma_abc.length);
+        scalars[0] = 1;
+        for (uint i = 0; i < input.length; i++) {
+            require(input[i] < snark_scalar_field);
+            scalars[i + 1] = input[i];
+        }
+        Pairing.G1Point memory vk_x = Pairing.msm(vk.gamma_abc, scalars);
+        if(!Pairing.pairingProd4(
+             proof.a, proof.b,
+             vk_x, vk.gamma_neg,
+             proof.c, vk.delta_neg,
+             vk.alpha, vk.beta_neg)) return 1;
+        return 0;
+    }
+    function verifyTx(
+            Proof memory proof<%input_argument%>
+        ) public view returns (bool r) {
+        uint[] memory inputValues = new uint[](<%vk_input_length%>);
+        <%input_loop%>
+        if (verify(inputValues, proof) == 0) {
+            return true;
+        } else {
+            return false;
+        }
+    }
+}
+"#;
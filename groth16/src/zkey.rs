@@ -0,0 +1,611 @@
+// Read and write the snarkjs groth16 zkey, refer to
+// https://github.com/iden3/snarkjs/blob/master/src/zkey_utils.js
+//
+// bellman and snarkjs evaluate the QAP over different roots of unity, and snarkjs computes
+// the quotient on the odd coset of the doubled domain, so a snarkjs key can not be turned
+// into bellman's `Parameters`. It is proven by `ZkeyParameters::prove` instead, which
+// follows the snarkjs prover, and the exported keys are adjusted to the snarkjs domain.
+use crate::bellman_ce::{
+    groth16::{Parameters, Proof, VerifyingKey},
+    multiexp::dense_multiexp,
+    pairing::{CurveAffine, CurveProjective},
+    worker::Worker,
+    Circuit, Field, PrimeField, PrimeFieldRepr,
+};
+pub use crate::binfile::SnarkjsEngine;
+use crate::binfile::{
+    modulus_bytes, read_modulus, read_sections, seek_items, seek_section, write_binfile,
+};
+use crate::ceremony::KeypairAssembly;
+use crate::fft::{bellman_omega, fft, group_ifft, ifft, snarkjs_omega};
+use anyhow::{bail, ensure, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand_old::Rng;
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+
+const ZKEY_MAGIC: &[u8; 4] = b"zkey";
+const ZKEY_VERSION: u32 = 1;
+const GROTH16_PROTOCOL_ID: u32 = 1;
+
+const ZKEY_HEADER: u32 = 1;
+const ZKEY_GROTH16_HEADER: u32 = 2;
+const ZKEY_IC: u32 = 3;
+const ZKEY_COEFS: u32 = 4;
+const ZKEY_POINTS_A: u32 = 5;
+const ZKEY_POINTS_B1: u32 = 6;
+const ZKEY_POINTS_B2: u32 = 7;
+const ZKEY_POINTS_C: u32 = 8;
+const ZKEY_POINTS_H: u32 = 9;
+const ZKEY_CONTRIBUTIONS: u32 = 10;
+
+const MATRIX_A: u32 = 0;
+const MATRIX_B: u32 = 1;
+
+/// a non-zero entry of the A or B matrix, the matrix C is A * B on the domain
+#[derive(Clone, Debug)]
+pub struct Coef<F: PrimeField> {
+    pub matrix: u32,
+    pub constraint: u32,
+    pub signal: u32,
+    pub value: F,
+}
+
+pub struct ZkeyParameters<E: SnarkjsEngine> {
+    pub vk: VerifyingKey<E>,
+    pub n_vars: usize,
+    pub n_public: usize,
+    pub domain_size: usize,
+    pub coefs: Vec<Coef<E::Fr>>,
+    /// the A/B queries of all the signals, not filtered as bellman does
+    pub a: Vec<E::G1Affine>,
+    pub b_g1: Vec<E::G1Affine>,
+    pub b_g2: Vec<E::G2Affine>,
+    /// the private signals, the same as the L query of bellman
+    pub c: Vec<E::G1Affine>,
+    /// L_{2i+1}(tau) / delta over the doubled domain, i in [0, domain_size)
+    pub h: Vec<E::G1Affine>,
+}
+
+/// the coefficients are stored as value * R^2 in the standard form, R is the montgomery factor
+fn coef_factor<F: PrimeField>() -> F {
+    // the raw 1 in montgomery form is 1/R
+    let r_inv = F::from_raw_repr(F::Repr::from(1)).unwrap();
+    let mut factor = r_inv;
+    factor.square();
+    factor
+}
+
+impl<E: SnarkjsEngine> ZkeyParameters<E> {
+    pub fn read<R: Read + Seek>(mut reader: R) -> Result<Self> {
+        let (_, sections) = read_sections(&mut reader, ZKEY_MAGIC)?;
+
+        seek_section(&mut reader, &sections, ZKEY_HEADER)?;
+        let protocol = reader.read_u32::<LittleEndian>()?;
+        ensure!(
+            protocol == GROTH16_PROTOCOL_ID,
+            "Only the groth16 zkey is supported, got protocol {}",
+            protocol
+        );
+
+        seek_section(&mut reader, &sections, ZKEY_GROTH16_HEADER)?;
+        ensure!(
+            read_modulus::<E::Fq, _>(&mut reader)?,
+            "The base field of the zkey does not match the curve"
+        );
+        ensure!(
+            read_modulus::<E::Fr, _>(&mut reader)?,
+            "The scalar field of the zkey does not match the curve"
+        );
+        let n_vars = reader.read_u32::<LittleEndian>()? as usize;
+        let n_public = reader.read_u32::<LittleEndian>()? as usize;
+        let domain_size = reader.read_u32::<LittleEndian>()? as usize;
+        let alpha_g1 = E::read_g1(&mut reader)?;
+        let beta_g1 = E::read_g1(&mut reader)?;
+        let beta_g2 = E::read_g2(&mut reader)?;
+        let gamma_g2 = E::read_g2(&mut reader)?;
+        let delta_g1 = E::read_g1(&mut reader)?;
+        let delta_g2 = E::read_g2(&mut reader)?;
+        ensure!(
+            n_public < n_vars,
+            "The zkey has {} public signals of {} signals",
+            n_public,
+            n_vars
+        );
+
+        let n8q = modulus_bytes::<E::Fq>().len() as u64;
+        let n8r = modulus_bytes::<E::Fr>().len() as u64;
+        seek_items(&mut reader, &sections, ZKEY_IC, n_public + 1, 2 * n8q)?;
+        let ic = (0..n_public + 1)
+            .map(|_| E::read_g1(&mut reader))
+            .collect::<Result<_>>()?;
+
+        let mut read_g1_section = |section: u32, n: usize| -> Result<Vec<E::G1Affine>> {
+            seek_items(&mut reader, &sections, section, n, 2 * n8q)?;
+            (0..n).map(|_| E::read_g1(&mut reader)).collect()
+        };
+        let a = read_g1_section(ZKEY_POINTS_A, n_vars)?;
+        let b_g1 = read_g1_section(ZKEY_POINTS_B1, n_vars)?;
+        let c = read_g1_section(ZKEY_POINTS_C, n_vars - n_public - 1)?;
+        let h = read_g1_section(ZKEY_POINTS_H, domain_size)?;
+        seek_items(&mut reader, &sections, ZKEY_POINTS_B2, n_vars, 4 * n8q)?;
+        let b_g2 = (0..n_vars)
+            .map(|_| E::read_g2(&mut reader))
+            .collect::<Result<_>>()?;
+
+        // the number of coefficients is checked against the section size as well
+        let size = seek_section(&mut reader, &sections, ZKEY_COEFS)?;
+        let factor = coef_factor::<E::Fr>();
+        let n_coefs = reader.read_u32::<LittleEndian>()?;
+        ensure!(
+            size == 4 + n_coefs as u64 * (12 + n8r),
+            "The coefficient section of the zkey has {} bytes, expect {} coefficients",
+            size,
+            n_coefs
+        );
+        let mut coefs = Vec::with_capacity(n_coefs as usize);
+        for _ in 0..n_coefs {
+            let matrix = reader.read_u32::<LittleEndian>()?;
+            let constraint = reader.read_u32::<LittleEndian>()?;
+            let signal = reader.read_u32::<LittleEndian>()?;
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.read_le(&mut reader)?;
+            let mut value = E::Fr::from_repr(repr)?;
+            value.mul_assign(&factor);
+            ensure!(
+                matrix == MATRIX_A || matrix == MATRIX_B,
+                "Invalid coefficient matrix {}",
+                matrix
+            );
+            ensure!(
+                (constraint as usize) < domain_size && (signal as usize) < n_vars,
+                "The coefficient ({}, {}) is out of range",
+                constraint,
+                signal
+            );
+            coefs.push(Coef {
+                matrix,
+                constraint,
+                signal,
+                value,
+            });
+        }
+
+        Ok(ZkeyParameters {
+            vk: VerifyingKey {
+                alpha_g1,
+                beta_g1,
+                beta_g2,
+                gamma_g2,
+                delta_g1,
+                delta_g2,
+                ic,
+            },
+            n_vars,
+            n_public,
+            domain_size,
+            coefs,
+            a,
+            b_g1,
+            b_g2,
+            c,
+            h,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let mut header = vec![];
+        header.write_u32::<LittleEndian>(GROTH16_PROTOCOL_ID)?;
+
+        let mut groth16_header = vec![];
+        for modulus in [modulus_bytes::<E::Fq>(), modulus_bytes::<E::Fr>()] {
+            groth16_header.write_u32::<LittleEndian>(modulus.len() as u32)?;
+            groth16_header.extend(modulus);
+        }
+        groth16_header.write_u32::<LittleEndian>(self.n_vars as u32)?;
+        groth16_header.write_u32::<LittleEndian>(self.n_public as u32)?;
+        groth16_header.write_u32::<LittleEndian>(self.domain_size as u32)?;
+        E::write_g1(&mut groth16_header, &self.vk.alpha_g1)?;
+        E::write_g1(&mut groth16_header, &self.vk.beta_g1)?;
+        E::write_g2(&mut groth16_header, &self.vk.beta_g2)?;
+        E::write_g2(&mut groth16_header, &self.vk.gamma_g2)?;
+        E::write_g1(&mut groth16_header, &self.vk.delta_g1)?;
+        E::write_g2(&mut groth16_header, &self.vk.delta_g2)?;
+
+        let mut coefs = vec![];
+        let factor = coef_factor::<E::Fr>().inverse().unwrap();
+        coefs.write_u32::<LittleEndian>(self.coefs.len() as u32)?;
+        for coef in self.coefs.iter() {
+            coefs.write_u32::<LittleEndian>(coef.matrix)?;
+            coefs.write_u32::<LittleEndian>(coef.constraint)?;
+            coefs.write_u32::<LittleEndian>(coef.signal)?;
+            let mut value = coef.value;
+            value.mul_assign(&factor);
+            value.into_repr().write_le(&mut coefs)?;
+        }
+
+        let g1_section = |points: &[E::G1Affine]| -> Result<Vec<u8>> {
+            let mut buf = vec![];
+            for p in points {
+                E::write_g1(&mut buf, p)?;
+            }
+            Ok(buf)
+        };
+        let mut b_g2 = vec![];
+        for p in self.b_g2.iter() {
+            E::write_g2(&mut b_g2, p)?;
+        }
+
+        // no contribution is recorded, the circuit hash is left empty
+        let mut contributions = vec![0u8; 64];
+        contributions.write_u32::<LittleEndian>(0)?;
+
+        write_binfile(
+            writer,
+            ZKEY_MAGIC,
+            ZKEY_VERSION,
+            vec![
+                (ZKEY_HEADER, header),
+                (ZKEY_GROTH16_HEADER, groth16_header),
+                (ZKEY_IC, g1_section(&self.vk.ic)?),
+                (ZKEY_COEFS, coefs),
+                (ZKEY_POINTS_A, g1_section(&self.a)?),
+                (ZKEY_POINTS_B1, g1_section(&self.b_g1)?),
+                (ZKEY_POINTS_B2, b_g2),
+                (ZKEY_POINTS_C, g1_section(&self.c)?),
+                (ZKEY_POINTS_H, g1_section(&self.h)?),
+                (ZKEY_CONTRIBUTIONS, contributions),
+            ],
+        )
+    }
+
+    /// convert the bellman proving key of the circuit to a zkey,
+    /// the constraints are placed where snarkjs evaluates the same root of unity as bellman
+    pub fn from_parameters<C: Circuit<E>>(params: &Parameters<E>, circuit: C) -> Result<Self> {
+        let assembly = KeypairAssembly::synthesize(circuit)?;
+        let m = assembly.num_constraints.next_power_of_two();
+        ensure!(
+            params.h.len() == m - 1
+                && params.vk.ic.len() == assembly.num_inputs
+                && params.l.len() == assembly.num_aux,
+            "The proving key does not match the circuit"
+        );
+
+        let snarkjs = snarkjs_omega::<E::Fr>(m);
+        let mut positions = HashMap::new();
+        let mut x = E::Fr::one();
+        for j in 0..m {
+            positions.insert(x.into_repr().as_ref().to_vec(), j as u32);
+            x.mul_assign(&snarkjs);
+        }
+        let bellman = bellman_omega::<E::Fr>(m);
+        let mut x = E::Fr::one();
+        let mut perm = Vec::with_capacity(m);
+        for _ in 0..m {
+            perm.push(positions[x.into_repr().as_ref()]);
+            x.mul_assign(&bellman);
+        }
+
+        let at: Vec<_> = assembly.at_inputs.iter().chain(&assembly.at_aux).collect();
+        let bt: Vec<_> = assembly.bt_inputs.iter().chain(&assembly.bt_aux).collect();
+        let mut coefs = vec![];
+        for (matrix, signals) in [(MATRIX_A, &at), (MATRIX_B, &bt)] {
+            for (signal, terms) in signals.iter().enumerate() {
+                for (value, constraint) in terms.iter() {
+                    coefs.push(Coef {
+                        matrix,
+                        constraint: perm[*constraint],
+                        signal: signal as u32,
+                        value: *value,
+                    });
+                }
+            }
+        }
+
+        // bellman drops the points at infinity, which are the signals not in the matrix
+        fn unfilter<G: CurveAffine>(
+            points: &[G],
+            used: impl Iterator<Item = bool>,
+        ) -> Result<Vec<G>> {
+            let mut points = points.iter();
+            let mut result = vec![];
+            for used in used {
+                result.push(match used {
+                    true => match points.next() {
+                        Some(p) => *p,
+                        None => bail!("The proving key does not match the circuit"),
+                    },
+                    false => G::zero(),
+                });
+            }
+            ensure!(
+                points.next().is_none(),
+                "The proving key does not match the circuit"
+            );
+            Ok(result)
+        }
+        let a = unfilter(&params.a, at.iter().map(|t| !t.is_empty()))?;
+        let b_g1 = unfilter(&params.b_g1, bt.iter().map(|t| !t.is_empty()))?;
+        let b_g2 = unfilter(&params.b_g2, bt.iter().map(|t| !t.is_empty()))?;
+
+        // bellman's h_i = tau^i * Z(tau) / delta, snarkjs sums (AB - C)(w'^(2k+1)) * H_k where
+        // w'^2 = w, so H is the inverse FFT of -h_i / (2 * w'^i), as Z(w'^(2k+1)) = -2
+        let shift_inv = snarkjs_omega::<E::Fr>(2 * m).inverse().unwrap();
+        let mut factor = E::Fr::one();
+        factor.double();
+        factor.negate();
+        let mut factor = factor.inverse().unwrap();
+        let mut h = vec![E::G1::zero(); m];
+        for (hi, p) in h.iter_mut().zip(params.h.iter()) {
+            *hi = p.mul(factor.into_repr());
+            factor.mul_assign(&shift_inv);
+        }
+        group_ifft(&mut h, snarkjs);
+
+        Ok(ZkeyParameters {
+            vk: params.vk.clone(),
+            n_vars: assembly.num_inputs + assembly.num_aux,
+            n_public: assembly.num_inputs - 1,
+            domain_size: m,
+            coefs,
+            a,
+            b_g1,
+            b_g2,
+            c: params.l.to_vec(),
+            h: h.into_iter().map(|p| p.into_affine()).collect(),
+        })
+    }
+
+    /// prove with the full witness, the same as the snarkjs prover
+    pub fn prove<R: Rng>(&self, witness: &[E::Fr], rng: &mut R) -> Result<Proof<E>> {
+        ensure!(
+            witness.len() == self.n_vars,
+            "The witness has {} signals, but the zkey needs {}",
+            witness.len(),
+            self.n_vars
+        );
+        let m = self.domain_size;
+        let mut a = vec![E::Fr::zero(); m];
+        let mut b = vec![E::Fr::zero(); m];
+        for coef in self.coefs.iter() {
+            let mut v = witness[coef.signal as usize];
+            v.mul_assign(&coef.value);
+            match coef.matrix {
+                MATRIX_A => a[coef.constraint as usize].add_assign(&v),
+                _ => b[coef.constraint as usize].add_assign(&v),
+            }
+        }
+        let mut c: Vec<E::Fr> = a
+            .iter()
+            .zip(b.iter())
+            .map(|(x, y)| {
+                let mut x = *x;
+                x.mul_assign(y);
+                x
+            })
+            .collect();
+
+        // move A, B and C to the odd coset of the doubled domain
+        let omega = snarkjs_omega::<E::Fr>(m);
+        let shift = snarkjs_omega::<E::Fr>(2 * m);
+        for poly in [&mut a, &mut b, &mut c] {
+            ifft(poly, omega);
+            let mut x = E::Fr::one();
+            for e in poly.iter_mut() {
+                e.mul_assign(&x);
+                x.mul_assign(&shift);
+            }
+            fft(poly, omega);
+        }
+        let abc: Vec<E::Fr> = (0..m)
+            .map(|i| {
+                let mut e = a[i];
+                e.mul_assign(&b[i]);
+                e.sub_assign(&c[i]);
+                e
+            })
+            .collect();
+
+        let r: E::Fr = rng.gen();
+        let s: E::Fr = rng.gen();
+        let vk = &self.vk;
+
+        let worker = Worker::new();
+        let witness: Vec<_> = witness.iter().map(|w| w.into_repr()).collect();
+        let abc: Vec<_> = abc.iter().map(|e| e.into_repr()).collect();
+
+        let mut pi_a = dense_multiexp(&worker, &self.a, &witness)?;
+        pi_a.add_assign_mixed(&vk.alpha_g1);
+        pi_a.add_assign(&vk.delta_g1.mul(r.into_repr()));
+
+        let mut pi_b = dense_multiexp(&worker, &self.b_g2, &witness)?;
+        pi_b.add_assign_mixed(&vk.beta_g2);
+        pi_b.add_assign(&vk.delta_g2.mul(s.into_repr()));
+
+        let mut pi_b1 = dense_multiexp(&worker, &self.b_g1, &witness)?;
+        pi_b1.add_assign_mixed(&vk.beta_g1);
+        pi_b1.add_assign(&vk.delta_g1.mul(s.into_repr()));
+
+        let mut pi_c = dense_multiexp(&worker, &self.c, &witness[self.n_public + 1..])?;
+        pi_c.add_assign(&dense_multiexp(&worker, &self.h, &abc)?);
+        let mut t = pi_a;
+        t.mul_assign(s.into_repr());
+        pi_c.add_assign(&t);
+        let mut t = pi_b1;
+        t.mul_assign(r.into_repr());
+        pi_c.add_assign(&t);
+        let mut rs = r;
+        rs.mul_assign(&s);
+        let mut t = vk.delta_g1.mul(rs.into_repr());
+        t.negate();
+        pi_c.add_assign(&t);
+
+        Ok(Proof {
+            a: pi_a.into_affine(),
+            b: pi_b.into_affine(),
+            c: pi_c.into_affine(),
+        })
+    }
+}
+
+/// the zkey starts with the magic "zkey", which never begins bellman's `Parameters`
+pub fn is_zkey<R: Read>(mut reader: R) -> Result<bool> {
+    let mut magic = [0u8; 4];
+    match reader.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == ZKEY_MAGIC),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::groth16::{
+        generate_random_parameters, prepare_verifying_key, verify_proof,
+    };
+    use crate::bellman_ce::pairing::bn256::{Bn256, Fr, G1Affine, G2Affine};
+    use crate::json_utils::Parser;
+    use algebraic::{circom_circuit::CircomCircuit, reader::load_r1cs};
+    use serde_json::Value;
+    use std::io::Cursor;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    /// generated by `test-vectors/snarkjs/gen.sh`
+    const SNARKJS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/snarkjs");
+
+    fn circuit() -> CircomCircuit<Bn256> {
+        CircomCircuit {
+            r1cs: load_r1cs(CIRCUIT_FILE).unwrap(),
+            witness: None,
+            wire_mapping: None,
+            aux_offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_zkey_export_and_prove() {
+        let mut rng = rand_old::thread_rng();
+        let params = generate_random_parameters::<Bn256, _, _>(circuit(), &mut rng).unwrap();
+        let zkey = ZkeyParameters::from_parameters(&params, circuit()).unwrap();
+
+        let mut bytes = vec![];
+        zkey.write(&mut bytes).unwrap();
+        assert!(is_zkey(Cursor::new(&bytes)).unwrap());
+        let zkey = ZkeyParameters::<Bn256>::read(Cursor::new(&bytes)).unwrap();
+        let mut rewritten = vec![];
+        zkey.write(&mut rewritten).unwrap();
+        assert_eq!(bytes, rewritten);
+
+        let mut pk = vec![];
+        params.write(&mut pk).unwrap();
+        assert!(!is_zkey(Cursor::new(&pk)).unwrap());
+
+        let witness: Vec<Fr> = ["1", "33", "3", "11"]
+            .iter()
+            .map(|w| Fr::from_str(w).unwrap())
+            .collect();
+        let pvk = prepare_verifying_key(&zkey.vk);
+        let proof = zkey.prove(&witness, &mut rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &witness[1..2]).unwrap());
+
+        let mut bad_witness = witness.clone();
+        bad_witness[1] = Fr::from_str("34").unwrap();
+        let proof = zkey.prove(&bad_witness, &mut rng).unwrap();
+        assert!(!verify_proof(&pvk, &proof, &bad_witness[1..2]).unwrap());
+    }
+
+    fn read_snarkjs_json(name: &str) -> Value {
+        let json = std::fs::read_to_string(format!("{}/{}", SNARKJS_DIR, name)).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    // snarkjs keeps the points in the projective coordinates, [x, y, "1"]
+    fn snarkjs_g1(v: &Value) -> G1Affine {
+        Bn256::to_g1(v[0].as_str().unwrap(), v[1].as_str().unwrap()).unwrap()
+    }
+
+    fn snarkjs_g2(v: &Value) -> G2Affine {
+        Bn256::to_g2(
+            v[0][0].as_str().unwrap(),
+            v[0][1].as_str().unwrap(),
+            v[1][0].as_str().unwrap(),
+            v[1][1].as_str().unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_snarkjs_zkey() {
+        let mut rng = rand_old::thread_rng();
+        let file = std::fs::File::open(format!("{}/multiplier.zkey", SNARKJS_DIR)).unwrap();
+        let zkey = ZkeyParameters::<Bn256>::read(std::io::BufReader::new(file)).unwrap();
+
+        let vk = read_snarkjs_json("verification_key.json");
+        let snarkjs_vk = VerifyingKey::<Bn256> {
+            alpha_g1: snarkjs_g1(&vk["vk_alpha_1"]),
+            beta_g1: zkey.vk.beta_g1,
+            beta_g2: snarkjs_g2(&vk["vk_beta_2"]),
+            gamma_g2: snarkjs_g2(&vk["vk_gamma_2"]),
+            delta_g1: zkey.vk.delta_g1,
+            delta_g2: snarkjs_g2(&vk["vk_delta_2"]),
+            ic: vk["IC"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(snarkjs_g1)
+                .collect(),
+        };
+        assert!(snarkjs_vk == zkey.vk);
+        let pvk = prepare_verifying_key(&snarkjs_vk);
+
+        // proven here, verified by the key of snarkjs
+        let witness: Vec<Fr> = ["1", "33", "3", "11"]
+            .iter()
+            .map(|w| Fr::from_str(w).unwrap())
+            .collect();
+        let proof = zkey.prove(&witness, &mut rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &witness[1..2]).unwrap());
+
+        // proven by snarkjs, verified by the key read here
+        let proof = read_snarkjs_json("proof.json");
+        let proof = Proof::<Bn256> {
+            a: snarkjs_g1(&proof["pi_a"]),
+            b: snarkjs_g2(&proof["pi_b"]),
+            c: snarkjs_g1(&proof["pi_c"]),
+        };
+        let public: Vec<Fr> = read_snarkjs_json("public.json")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| Fr::from_str(v.as_str().unwrap()).unwrap())
+            .collect();
+        assert_eq!(public, witness[1..2]);
+        let pvk = prepare_verifying_key(&zkey.vk);
+        assert!(verify_proof(&pvk, &proof, &public).unwrap());
+        let other = [Fr::from_str("34").unwrap()];
+        assert!(!verify_proof(&pvk, &proof, &other).unwrap());
+    }
+
+    #[test]
+    fn test_zkey_with_corrupt_counts() {
+        let mut rng = rand_old::thread_rng();
+        let params = generate_random_parameters::<Bn256, _, _>(circuit(), &mut rng).unwrap();
+        let zkey = ZkeyParameters::from_parameters(&params, circuit()).unwrap();
+        let mut bytes = vec![];
+        zkey.write(&mut bytes).unwrap();
+
+        // the number of coefficients, after the header and the groth16 header
+        let (_, sections) = read_sections(&mut Cursor::new(&bytes), ZKEY_MAGIC).unwrap();
+        let offset = sections[&ZKEY_COEFS].0 as usize;
+        let mut corrupt = bytes.clone();
+        corrupt[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ZkeyParameters::<Bn256>::read(Cursor::new(&corrupt)).is_err());
+
+        // the domain size, after the moduli and the numbers of signals
+        let offset = sections[&ZKEY_GROTH16_HEADER].0 as usize + 4 + 32 + 4 + 32 + 8;
+        let mut corrupt = bytes;
+        corrupt[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ZkeyParameters::<Bn256>::read(Cursor::new(&corrupt)).is_err());
+    }
+}
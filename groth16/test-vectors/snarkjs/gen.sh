@@ -0,0 +1,21 @@
+#!/bin/bash
+# Generate the snarkjs groth16 fixture of test/multiplier.r1cs for the zkey tests, the output
+# is committed, so this is only needed once the fixture changes. Requires snarkjs.
+set -ex
+
+CUR_DIR=$(cd $(dirname $0);pwd)
+TEST_DIR=${CUR_DIR}/../../../test
+cd $CUR_DIR
+
+snarkjs powersoftau new bn128 4 pot_0000.ptau
+snarkjs powersoftau contribute pot_0000.ptau pot_0001.ptau --name="first" -e="eigen-zkit"
+snarkjs powersoftau prepare phase2 pot_0001.ptau pot_final.ptau
+snarkjs groth16 setup ${TEST_DIR}/multiplier.r1cs pot_final.ptau multiplier_0000.zkey
+snarkjs zkey contribute multiplier_0000.zkey multiplier.zkey --name="second" -e="eigen-zkit"
+snarkjs zkey export verificationkey multiplier.zkey verification_key.json
+
+snarkjs wtns calculate ${TEST_DIR}/multiplier.wasm ${TEST_DIR}/multiplier.input.json witness.wtns
+snarkjs groth16 prove multiplier.zkey witness.wtns proof.json public.json
+snarkjs groth16 verify verification_key.json public.json proof.json
+
+rm pot_*.ptau multiplier_0000.zkey witness.wtns
@@ -0,0 +1,205 @@
+// Aggregate the plonk proofs of the same circuit into one, refer to
+// https://github.com/matter-labs/recursive_aggregation_circuit
+use crate::bellman_ce::{
+    kate_commitment::{Crs, CrsForMonomialForm},
+    pairing::bn256::{Bn256, Fr},
+    plonk::{
+        better_better_cs::{
+            cs::Circuit, proof::Proof as NewProof, setup::VerificationKey as NewVerificationKey,
+            verifier::verify as core_verify,
+        },
+        better_cs::{
+            cs::PlonkCsWidth4WithNextStepParams,
+            keys::{Proof as OldProof, VerificationKey as OldVerificationKey},
+        },
+        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
+    },
+    worker::Worker,
+};
+use crate::plonk::{fe_to_string, get_default_rescue_transcript_params, point_to_strings};
+use anyhow::{ensure, Result};
+use recursive_aggregation_circuit::circuit::{
+    create_recursive_circuit_vk_and_setup, create_vks_tree, make_aggregate,
+    make_public_input_and_limbed_aggregate, proof_recursive_aggregate_for_zksync,
+    RecursiveAggregationCircuitBn256,
+};
+
+/// all the old proofs are of the same circuit, so the vk tree holds only one key
+pub const VK_TREE_DEPTH: usize = 1;
+const VK_INDEX: usize = 0;
+
+pub type AggregatedProof = NewProof<Bn256, RecursiveAggregationCircuitBn256<'static>>;
+pub type AggregatedVerificationKey =
+    NewVerificationKey<Bn256, RecursiveAggregationCircuitBn256<'static>>;
+
+pub fn prove(
+    big_crs: Crs<Bn256, CrsForMonomialForm>,
+    old_proofs: Vec<OldProof<Bn256, PlonkCsWidth4WithNextStepParams>>,
+    old_vk: OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
+) -> Result<AggregatedProof> {
+    let num_inputs = check_old_proofs(&old_proofs)?;
+    let num_proofs_to_check = old_proofs.len();
+
+    let worker = Worker::new();
+    let (recursive_circuit_vk, recursive_circuit_setup) = create_recursive_circuit_vk_and_setup(
+        num_proofs_to_check,
+        num_inputs,
+        VK_TREE_DEPTH,
+        &big_crs,
+    )?;
+
+    let vk_indexes = vec![VK_INDEX; num_proofs_to_check];
+    let proof = proof_recursive_aggregate_for_zksync(
+        VK_TREE_DEPTH,
+        vec![old_vk],
+        old_proofs,
+        vk_indexes,
+        &recursive_circuit_vk,
+        &recursive_circuit_setup,
+        &big_crs,
+        true,
+        &worker,
+    )?;
+    Ok(proof)
+}
+
+pub fn verify(vk: AggregatedVerificationKey, proof: AggregatedProof) -> Result<bool> {
+    Ok(core_verify::<_, _, RollingKeccakTranscript<Fr>>(
+        &vk, &proof, None,
+    )?)
+}
+
+/// the verification key only depends on the number and the public input size of the old proofs
+pub fn export_vk(
+    num_proofs_to_check: usize,
+    num_inputs: usize,
+    big_crs: &Crs<Bn256, CrsForMonomialForm>,
+) -> Result<AggregatedVerificationKey> {
+    let (recursive_circuit_vk, _) = create_recursive_circuit_vk_and_setup(
+        num_proofs_to_check,
+        num_inputs,
+        VK_TREE_DEPTH,
+        big_crs,
+    )?;
+    Ok(recursive_circuit_vk)
+}
+
+/// recompute the public input of the aggregated proof from the old proofs and the old vk
+pub fn get_aggregated_input(
+    old_proofs: Vec<OldProof<Bn256, PlonkCsWidth4WithNextStepParams>>,
+    old_vk: OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
+) -> Result<Fr> {
+    check_old_proofs(&old_proofs)?;
+    let num_proofs_to_check = old_proofs.len();
+
+    let (rescue_params, rns_params) = get_default_rescue_transcript_params();
+    let (_, (vks_tree, _)) = create_vks_tree(&[old_vk.clone()], VK_TREE_DEPTH)?;
+    let vks_tree_root = vks_tree.get_commitment();
+
+    let vk_indexes = vec![VK_INDEX; num_proofs_to_check];
+    let old_vks = vec![old_vk; num_proofs_to_check];
+    let aggregate = make_aggregate(&old_proofs, &old_vks, &rescue_params, &rns_params)?;
+    let (expected_input, _) = make_public_input_and_limbed_aggregate(
+        vks_tree_root,
+        &vk_indexes,
+        &old_proofs,
+        &aggregate,
+        &rns_params,
+    );
+    Ok(expected_input)
+}
+
+/// the arguments of `verifyAggregatedProof` of the solidity verifier, in order:
+/// recursive inputs, serialized proof, vk indexes, old proofs' inputs and the limbed aggregate
+pub fn serialize_aggregated_proof(
+    proof: &AggregatedProof,
+    old_proofs: &[OldProof<Bn256, PlonkCsWidth4WithNextStepParams>],
+    old_vk: &OldVerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
+) -> Result<serde_json::Value> {
+    let num_proofs_to_check = old_proofs.len();
+    let (rescue_params, rns_params) = get_default_rescue_transcript_params();
+    let (_, (vks_tree, _)) = create_vks_tree(&[old_vk.clone()], VK_TREE_DEPTH)?;
+    let vk_indexes = vec![VK_INDEX; num_proofs_to_check];
+    let old_vks = vec![old_vk.clone(); num_proofs_to_check];
+    let aggregate = make_aggregate(old_proofs, &old_vks, &rescue_params, &rns_params)?;
+    let (_, limbed_aggregate) = make_public_input_and_limbed_aggregate(
+        vks_tree.get_commitment(),
+        &vk_indexes,
+        old_proofs,
+        &aggregate,
+        &rns_params,
+    );
+
+    let (inputs, serialized_proof) = serialize_new_proof(proof);
+    let individual_inputs: Vec<String> = old_proofs
+        .iter()
+        .flat_map(|p| p.input_values.iter().map(fe_to_string))
+        .collect();
+    let limbs: Vec<String> = limbed_aggregate.iter().map(fe_to_string).collect();
+
+    Ok(serde_json::json!([
+        inputs,
+        serialized_proof,
+        vk_indexes,
+        individual_inputs,
+        limbs
+    ]))
+}
+
+fn serialize_new_proof<C: Circuit<Bn256>>(
+    proof: &NewProof<Bn256, C>,
+) -> (Vec<String>, Vec<String>) {
+    let inputs = proof.inputs.iter().map(fe_to_string).collect();
+
+    let mut serialized_proof = vec![];
+    for p in proof.state_polys_commitments.iter() {
+        serialized_proof.extend(point_to_strings(p));
+    }
+    serialized_proof.extend(point_to_strings(
+        &proof.copy_permutation_grand_product_commitment,
+    ));
+    for p in proof.quotient_poly_parts_commitments.iter() {
+        serialized_proof.extend(point_to_strings(p));
+    }
+    serialized_proof.extend(proof.state_polys_openings_at_z.iter().map(fe_to_string));
+    serialized_proof.extend(
+        proof
+            .state_polys_openings_at_dilations
+            .iter()
+            .map(|(_, _, fe)| fe_to_string(fe)),
+    );
+    serialized_proof.extend(
+        proof
+            .gate_selectors_openings_at_z
+            .iter()
+            .map(|(_, fe)| fe_to_string(fe)),
+    );
+    serialized_proof.extend(
+        proof
+            .copy_permutation_polys_openings_at_z
+            .iter()
+            .map(fe_to_string),
+    );
+    serialized_proof.push(fe_to_string(
+        &proof.copy_permutation_grand_product_opening_at_z_omega,
+    ));
+    serialized_proof.push(fe_to_string(&proof.quotient_poly_opening_at_z));
+    serialized_proof.push(fe_to_string(&proof.linearization_poly_opening_at_z));
+    serialized_proof.extend(point_to_strings(&proof.opening_proof_at_z));
+    serialized_proof.extend(point_to_strings(&proof.opening_proof_at_z_omega));
+
+    (inputs, serialized_proof)
+}
+
+/// the old proofs must be non-empty and of the same public input size, which is returned
+fn check_old_proofs(
+    old_proofs: &[OldProof<Bn256, PlonkCsWidth4WithNextStepParams>],
+) -> Result<usize> {
+    ensure!(!old_proofs.is_empty(), "No proof to aggregate");
+    let num_inputs = old_proofs[0].num_inputs;
+    ensure!(
+        old_proofs.iter().all(|p| p.num_inputs == num_inputs),
+        "The old proofs must have the same number of public inputs"
+    );
+    Ok(num_inputs)
+}
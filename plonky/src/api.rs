@@ -0,0 +1,262 @@
+use crate::aggregation::{self, AggregatedProof, AggregatedVerificationKey, VK_TREE_DEPTH};
+use crate::bellman_ce::{
+    pairing::bn256::{Bn256, Fr},
+    plonk::better_cs::{
+        cs::PlonkCsWidth4WithNextStepParams,
+        keys::{Proof, VerificationKey},
+    },
+};
+use crate::plonk::{self, SetupForProver, AUX_OFFSET};
+use algebraic::{
+    circom_circuit::CircomCircuit,
+    reader::{load_key_monomial_form, load_r1cs, load_witness_from_file},
+    witness::{load_input_for_witness, WitnessCalculator},
+};
+use anyhow::{bail, Result};
+use recursive_aggregation_circuit::circuit::create_vks_tree;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+
+/// universal setup of size 2^power, saved in monomial form
+pub fn setup(power: u32, srs_monomial_form: &str) -> Result<()> {
+    let srs = plonk::gen_key_monomial_form(power)?;
+    let writer = BufWriter::new(File::create(srs_monomial_form)?);
+    srs.write(writer)?;
+    Ok(())
+}
+
+/// calculate the witness by the wasm circuit and save it in the wtns format
+pub fn calculate_witness(wasm_file: &str, input_json: &str, output: &str) -> Result<()> {
+    let mut wtns = WitnessCalculator::from_file(wasm_file)?;
+    let inputs = load_input_for_witness(input_json)?;
+    let w = wtns.calculate_witness_bin(inputs, false)?;
+    wtns.save_witness_to_bin_file(output, &w)
+}
+
+pub fn export_verification_key(
+    srs_monomial_form: &str,
+    circuit_file: &str,
+    output_vk: &str,
+) -> Result<()> {
+    let circuit = create_circuit_from_file(circuit_file, None)?;
+    let setup = SetupForProver::prepare_setup_for_prover(
+        circuit,
+        load_key_monomial_form(srs_monomial_form)?,
+    )?;
+    let vk = setup.make_verification_key()?;
+    let writer = BufWriter::new(File::create(output_vk)?);
+    vk.write(writer)?;
+    Ok(())
+}
+
+pub fn prove(
+    circuit_file: &str,
+    witness: &str,
+    srs_monomial_form: &str,
+    transcript: &str,
+    proof_bin: &str,
+    proof_json: &str,
+    public_json: Option<&str>,
+) -> Result<()> {
+    let circuit = create_circuit_from_file(
+        circuit_file,
+        Some(load_witness_from_file::<Bn256>(witness)?),
+    )?;
+    let setup = SetupForProver::prepare_setup_for_prover(
+        circuit.clone(),
+        load_key_monomial_form(srs_monomial_form)?,
+    )?;
+    let proof = setup.prove(circuit, transcript)?;
+
+    let writer = BufWriter::new(File::create(proof_bin)?);
+    proof.write(writer)?;
+
+    let (inputs, serialized_proof) = plonk::serialize_proof(&proof);
+    std::fs::write(proof_json, serde_json::to_string_pretty(&serialized_proof)?)?;
+    if let Some(public_json) = public_json {
+        std::fs::write(public_json, serde_json::to_string_pretty(&inputs)?)?;
+    }
+    Ok(())
+}
+
+pub fn verify(vk_file: &str, proof_bin: &str, transcript: &str) -> Result<()> {
+    let vk = load_verification_key(vk_file)?;
+    let proof = load_proof(proof_bin)?;
+    if !plonk::verify(&vk, &proof, transcript)? {
+        bail!("Invalid plonk proof");
+    }
+    Ok(())
+}
+
+/// render the solidity verifier of the plonk verification key
+pub fn generate_verifier(vk_file: &str, sol: &str) -> Result<()> {
+    let vk = load_verification_key(vk_file)?;
+    bellman_vk_codegen::render_verification_key_from_default_template(&vk, sol, true);
+    Ok(())
+}
+
+pub fn export_aggregation_verification_key(
+    num_proofs_to_check: usize,
+    num_inputs: usize,
+    srs_monomial_form: &str,
+    output_vk: &str,
+) -> Result<()> {
+    let big_crs = load_key_monomial_form(srs_monomial_form)?;
+    let vk = aggregation::export_vk(num_proofs_to_check, num_inputs, &big_crs)?;
+    let writer = BufWriter::new(File::create(output_vk)?);
+    vk.write(writer)?;
+    Ok(())
+}
+
+pub fn aggregation_prove(
+    srs_monomial_form: &str,
+    old_proof_list: &str,
+    old_vk: &str,
+    new_proof: &str,
+    proof_json: &str,
+) -> Result<()> {
+    let big_crs = load_key_monomial_form(srs_monomial_form)?;
+    let old_proofs = load_proofs_from_list(old_proof_list)?;
+    let old_vk = load_verification_key(old_vk)?;
+
+    let proof = aggregation::prove(big_crs, old_proofs.clone(), old_vk.clone())?;
+    let writer = BufWriter::new(File::create(new_proof)?);
+    proof.write(writer)?;
+
+    let json = aggregation::serialize_aggregated_proof(&proof, &old_proofs, &old_vk)?;
+    std::fs::write(proof_json, serde_json::to_string_pretty(&json)?)?;
+    Ok(())
+}
+
+pub fn aggregation_verify(proof: &str, vk: &str) -> Result<()> {
+    let proof = load_aggregated_proof(proof)?;
+    let vk = load_aggregation_verification_key(vk)?;
+    if !aggregation::verify(vk, proof)? {
+        bail!("Invalid aggregation proof");
+    }
+    Ok(())
+}
+
+/// check the public input of the aggregated proof commits to the old proofs and the old vk
+pub fn aggregation_check(old_proof_list: &str, old_vk: &str, new_proof: &str) -> Result<()> {
+    let old_proofs = load_proofs_from_list(old_proof_list)?;
+    let old_vk = load_verification_key(old_vk)?;
+    let new_proof = load_aggregated_proof(new_proof)?;
+
+    let expected = aggregation::get_aggregated_input(old_proofs, old_vk)?;
+    if new_proof.inputs != vec![expected] {
+        bail!(format!(
+            "Aggregation proof input mismatch, expected: {}, actual: {:?}",
+            expected, new_proof.inputs
+        ));
+    }
+    Ok(())
+}
+
+/// render the solidity verifier of the aggregated proof
+pub fn generate_aggregation_verifier(
+    old_vk: &str,
+    new_vk: &str,
+    num_inputs: usize,
+    sol: &str,
+) -> Result<()> {
+    let old_vk = load_verification_key(old_vk)?;
+    let new_vk = load_aggregation_verification_key(new_vk)?;
+    let (_, (vks_tree, _)) = create_vks_tree(&[old_vk], VK_TREE_DEPTH)?;
+    bellman_vk_codegen::render_aggregated_verification_key_from_default_template(
+        &new_vk,
+        vks_tree.get_commitment(),
+        num_inputs,
+        sol,
+        true,
+    );
+    Ok(())
+}
+
+fn create_circuit_from_file(
+    circuit_file: &str,
+    witness: Option<Vec<Fr>>,
+) -> Result<CircomCircuit<Bn256>> {
+    Ok(CircomCircuit {
+        r1cs: load_r1cs(circuit_file)?,
+        witness,
+        wire_mapping: None,
+        aux_offset: AUX_OFFSET,
+    })
+}
+
+fn load_verification_key(
+    filename: &str,
+) -> Result<VerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>> {
+    let reader = BufReader::new(File::open(filename)?);
+    Ok(VerificationKey::read(reader)?)
+}
+
+fn load_proof(filename: &str) -> Result<Proof<Bn256, PlonkCsWidth4WithNextStepParams>> {
+    let reader = BufReader::new(File::open(filename)?);
+    Ok(Proof::read(reader)?)
+}
+
+/// the proof list is a text file with one proof path per line
+fn load_proofs_from_list(
+    list_file: &str,
+) -> Result<Vec<Proof<Bn256, PlonkCsWidth4WithNextStepParams>>> {
+    let reader = BufReader::new(File::open(list_file)?);
+    let mut proofs = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        let path = line.trim();
+        if !path.is_empty() {
+            proofs.push(load_proof(path)?);
+        }
+    }
+    Ok(proofs)
+}
+
+fn load_aggregation_verification_key(filename: &str) -> Result<AggregatedVerificationKey> {
+    let reader = BufReader::new(File::open(filename)?);
+    Ok(AggregatedVerificationKey::read(reader)?)
+}
+
+fn load_aggregated_proof(filename: &str) -> Result<AggregatedProof> {
+    let reader = BufReader::new(File::open(filename)?);
+    Ok(AggregatedProof::read(reader)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const SRS_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../keys/setup_2^10.key");
+
+    #[test]
+    fn test_plonk_api() {
+        let witness = "/tmp/test_plonk_api.wtns";
+        let vk = "/tmp/test_plonk_api.vk.bin";
+        let proof_bin = "/tmp/test_plonk_api.proof.bin";
+        let proof_json = "/tmp/test_plonk_api.proof.json";
+        let public_json = "/tmp/test_plonk_api.public.json";
+
+        calculate_witness(WASM_FILE, INPUT_FILE, witness).unwrap();
+        export_verification_key(SRS_FILE, CIRCUIT_FILE, vk).unwrap();
+        prove(
+            CIRCUIT_FILE,
+            witness,
+            SRS_FILE,
+            "rescue",
+            proof_bin,
+            proof_json,
+            Some(public_json),
+        )
+        .unwrap();
+        verify(vk, proof_bin, "rescue").unwrap();
+        assert!(verify(vk, proof_bin, "keccak").is_err());
+
+        let inputs: Vec<String> =
+            serde_json::from_str(&std::fs::read_to_string(public_json).unwrap()).unwrap();
+        assert_eq!(inputs, vec!["33".to_string()]);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod aggregation;
+pub mod api;
+pub mod plonk;
+
+pub use bellman_ce::pairing::ff;
+pub use ff::*;
+pub use franklin_crypto::bellman as bellman_ce;
@@ -0,0 +1,247 @@
+// Plonk prover over the circom R1CS, refer to https://github.com/fluidex/plonkit
+use crate::bellman_ce::{
+    kate_commitment::{Crs, CrsForMonomialForm},
+    pairing::{
+        bn256::{Bn256, Fr},
+        ff::PrimeField,
+        CurveAffine, Engine,
+    },
+    plonk::{
+        better_cs::{
+            adaptor::TranspilationVariant,
+            cs::PlonkCsWidth4WithNextStepParams,
+            keys::{Proof, SetupPolynomials, VerificationKey},
+        },
+        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
+        is_satisfied_using_one_shot_check, make_verification_key, prove_by_steps, setup, transpile,
+    },
+    worker::Worker,
+    Circuit,
+};
+use algebraic::utils::repr_to_big;
+use anyhow::{bail, ensure, Result};
+use franklin_crypto::plonk::circuit::bigint::field::RnsParameters;
+use franklin_crypto::rescue::{
+    bn256::Bn256RescueParams, rescue_transcript::RescueTranscriptForRNS,
+};
+
+/// the circom variables are shifted by one as the plonk cs allocates the constant one as aux 0
+pub const AUX_OFFSET: usize = 1;
+
+const SETUP_MIN_POW2: u32 = 10;
+const SETUP_MAX_POW2: u32 = 26;
+
+/// generate the universal setup of size 2^power, for test only
+pub fn gen_key_monomial_form(power: u32) -> Result<Crs<Bn256, CrsForMonomialForm>> {
+    ensure!(
+        (SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&power),
+        "setup power of two {} is not in the range [{}, {}]",
+        power,
+        SETUP_MIN_POW2,
+        SETUP_MAX_POW2
+    );
+    Ok(Crs::<Bn256, CrsForMonomialForm>::crs_42(
+        1 << power,
+        &Worker::new(),
+    ))
+}
+
+pub struct SetupForProver<E: Engine> {
+    setup_polynomials: SetupPolynomials<E, PlonkCsWidth4WithNextStepParams>,
+    hints: Vec<(usize, TranspilationVariant)>,
+    key_monomial_form: Crs<E, CrsForMonomialForm>,
+}
+
+impl SetupForProver<Bn256> {
+    pub fn prepare_setup_for_prover<C: Circuit<Bn256> + Clone>(
+        circuit: C,
+        key_monomial_form: Crs<Bn256, CrsForMonomialForm>,
+    ) -> Result<Self> {
+        let hints = transpile(circuit.clone())?;
+        let setup_polynomials = setup(circuit, &hints)?;
+        let size = setup_polynomials.n.next_power_of_two().trailing_zeros();
+        let setup_power_of_two = std::cmp::max(size, SETUP_MIN_POW2);
+        ensure!(
+            (SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&setup_power_of_two),
+            "setup power of two {} is not in the range [{}, {}]",
+            setup_power_of_two,
+            SETUP_MIN_POW2,
+            SETUP_MAX_POW2
+        );
+        ensure!(
+            key_monomial_form.g1_bases.len() >= setup_polynomials.n.next_power_of_two(),
+            "the universal setup supports at most 2^{} gates, but the circuit needs 2^{}",
+            key_monomial_form.g1_bases.len().trailing_zeros(),
+            size
+        );
+
+        Ok(SetupForProver {
+            setup_polynomials,
+            hints,
+            key_monomial_form,
+        })
+    }
+
+    pub fn validate_witness<C: Circuit<Bn256> + Clone>(&self, circuit: C) -> Result<()> {
+        Ok(is_satisfied_using_one_shot_check(circuit, &self.hints)?)
+    }
+
+    pub fn make_verification_key(
+        &self,
+    ) -> Result<VerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>> {
+        Ok(make_verification_key(
+            &self.setup_polynomials,
+            &self.key_monomial_form,
+        )?)
+    }
+
+    pub fn prove<C: Circuit<Bn256> + Clone>(
+        &self,
+        circuit: C,
+        transcript: &str,
+    ) -> Result<Proof<Bn256, PlonkCsWidth4WithNextStepParams>> {
+        self.validate_witness(circuit.clone())?;
+
+        let proof = match transcript {
+            "keccak" => prove_by_steps::<_, _, RollingKeccakTranscript<Fr>>(
+                circuit,
+                &self.hints,
+                &self.setup_polynomials,
+                None,
+                &self.key_monomial_form,
+                None,
+            )?,
+            "rescue" => {
+                let (rescue_params, rns_params) = get_default_rescue_transcript_params();
+                prove_by_steps::<_, _, RescueTranscriptForRNS<Bn256>>(
+                    circuit,
+                    &self.hints,
+                    &self.setup_polynomials,
+                    None,
+                    &self.key_monomial_form,
+                    Some((&rescue_params, &rns_params)),
+                )?
+            }
+            _ => bail!(format!("Unknown transcript: {}", transcript)),
+        };
+        Ok(proof)
+    }
+}
+
+pub fn verify(
+    vk: &VerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>,
+    proof: &Proof<Bn256, PlonkCsWidth4WithNextStepParams>,
+    transcript: &str,
+) -> Result<bool> {
+    let result = match transcript {
+        "keccak" => {
+            crate::bellman_ce::plonk::verify::<_, _, RollingKeccakTranscript<Fr>>(proof, vk, None)?
+        }
+        "rescue" => {
+            let (rescue_params, rns_params) = get_default_rescue_transcript_params();
+            crate::bellman_ce::plonk::verify::<_, _, RescueTranscriptForRNS<Bn256>>(
+                proof,
+                vk,
+                Some((&rescue_params, &rns_params)),
+            )?
+        }
+        _ => bail!(format!("Unknown transcript: {}", transcript)),
+    };
+    Ok(result)
+}
+
+/// the rescue transcript parameters used by the recursive aggregation circuit
+pub fn get_default_rescue_transcript_params() -> (
+    Bn256RescueParams,
+    RnsParameters<Bn256, <Bn256 as Engine>::Fq>,
+) {
+    let rns_params = RnsParameters::<Bn256, <Bn256 as Engine>::Fq>::new_for_field(68, 110, 4);
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    (rescue_params, rns_params)
+}
+
+/// serialize the public inputs and proof in the layout of the solidity verifier
+pub fn serialize_proof(
+    proof: &Proof<Bn256, PlonkCsWidth4WithNextStepParams>,
+) -> (Vec<String>, Vec<String>) {
+    let inputs = proof.input_values.iter().map(fe_to_string).collect();
+
+    let mut serialized_proof = vec![];
+    for p in proof.wire_commitments.iter() {
+        serialized_proof.extend(point_to_strings(p));
+    }
+    serialized_proof.extend(point_to_strings(&proof.grand_product_commitment));
+    for p in proof.quotient_poly_commitments.iter() {
+        serialized_proof.extend(point_to_strings(p));
+    }
+    serialized_proof.extend(proof.wire_values_at_z.iter().map(fe_to_string));
+    serialized_proof.extend(proof.wire_values_at_z_omega.iter().map(fe_to_string));
+    serialized_proof.push(fe_to_string(&proof.grand_product_at_z_omega));
+    serialized_proof.push(fe_to_string(&proof.quotient_polynomial_at_z));
+    serialized_proof.push(fe_to_string(&proof.linearization_polynomial_at_z));
+    serialized_proof.extend(proof.permutation_polynomials_at_z.iter().map(fe_to_string));
+    serialized_proof.extend(point_to_strings(&proof.opening_at_z_proof));
+    serialized_proof.extend(point_to_strings(&proof.opening_at_z_omega_proof));
+
+    (inputs, serialized_proof)
+}
+
+pub(crate) fn fe_to_string<F: PrimeField>(fe: &F) -> String {
+    repr_to_big(fe.into_repr())
+}
+
+pub(crate) fn point_to_strings<G: CurveAffine>(p: &G) -> Vec<String> {
+    let (x, y) = p.into_xy_unchecked();
+    vec![fe_to_string(&x), fe_to_string(&y)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::{ConstraintSystem, Field, SynthesisError};
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        a: Option<Fr>,
+    }
+
+    // a * a = b, b is public
+    impl Circuit<Bn256> for TestCircuit {
+        fn synthesize<CS: ConstraintSystem<Bn256>>(
+            self,
+            cs: &mut CS,
+        ) -> std::result::Result<(), SynthesisError> {
+            let b_value = self.a.map(|a| {
+                let mut b = a;
+                b.square();
+                b
+            });
+            let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+            let b = cs.alloc_input(|| "b", || b_value.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "a * a = b", |lc| lc + a, |lc| lc + a, |lc| lc + b);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_plonk_prove_and_verify() {
+        let crs = gen_key_monomial_form(10).unwrap();
+        let circuit = TestCircuit {
+            a: Some(Fr::from_str("3").unwrap()),
+        };
+        let setup = SetupForProver::prepare_setup_for_prover(circuit.clone(), crs).unwrap();
+        let vk = setup.make_verification_key().unwrap();
+        for transcript in ["keccak", "rescue"] {
+            let proof = setup.prove(circuit.clone(), transcript).unwrap();
+            assert_eq!(proof.input_values, vec![Fr::from_str("9").unwrap()]);
+            assert!(verify(&vk, &proof, transcript).unwrap());
+        }
+        assert!(setup.prove(circuit, "poseidon").is_err());
+    }
+
+    #[test]
+    fn test_gen_key_monomial_form_out_of_range() {
+        assert!(gen_key_monomial_form(SETUP_MIN_POW2 - 1).is_err());
+        assert!(gen_key_monomial_form(SETUP_MAX_POW2 + 1).is_err());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::compressor12_pil::CompressorNameSpace::*;
 use crate::compressor12_pil::CompressorPolName::a;
+use crate::errors::RecursionError;
 use crate::io_utils::read_vec_from_file;
 use crate::pilcom::compile_pil_from_path;
-use algebraic::witness::{load_input_for_witness, WitnessCalculator};
-use anyhow::{anyhow, Result};
+use algebraic::witness::{load_input_for_witness, WitnessBackend};
+use anyhow::{anyhow, bail, Result};
 use fields::ff::PrimeField;
 use fields::field_gl::Fr as FGL;
 use num_traits::Zero;
@@ -21,6 +22,7 @@ pub fn exec(
     pil_file: &str,
     exec_file: &str,
     commit_file: &str,
+    witness_backend: WitnessBackend,
 ) -> Result<()> {
     // 0. load exec_file,
 
@@ -31,8 +33,7 @@ pub fn exec(
     // 1. Compiles a .pil file to its json form , and save it.
     // TODO: the pil_str has been compiled in plonk_setup#3
     let pil_json = compile_pil_from_path(pil_file);
-    let mut file = File::create(Path::new(&format!("{pil_file}.json")))
-        .unwrap_or_else(|_| panic!("{:?}", pil_file));
+    let mut file = File::create(Path::new(&format!("{pil_file}.json")))?;
     let input = serde_json::to_string(&pil_json)?;
     write!(file, "{}", input)?;
 
@@ -40,20 +41,25 @@ pub fn exec(
     let mut cm_pols = PolsArray::new(&pil_json, PolKind::Commit);
 
     // 3. calculate witness. wasm+input->witness
-    let mut wtns = WitnessCalculator::from_file(wasm_file)?;
-    let inputs = load_input_for_witness(input_file);
-    let w = wtns.calculate_witness(inputs, false)?;
+    let inputs = load_input_for_witness(input_file)?;
+    let w = witness_backend.calculate_witness(wasm_file, inputs, false)?;
     let mut w = w
         .iter()
         .map(|wi| {
             if wi.is_zero() {
-                FGL::ZERO
+                Ok(FGL::ZERO)
             } else {
-                assert!(wi.to_u64_digits().1.len() < 2);
-                FGL::from(wi.to_u64_digits().1[0])
+                let digits = wi.to_u64_digits().1;
+                if digits.len() > 1 {
+                    bail!(RecursionError::InvalidWitness(format!(
+                        "{} is out of the Goldilocks field",
+                        wi
+                    )));
+                }
+                Ok(FGL::from(digits[0]))
             }
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>>>()?;
 
     for i in 0..adds_len {
         let w2 = FGL::from_raw_repr(<FGL as PrimeField>::Repr::from(adds[i * 4 + 2]))?;
@@ -104,13 +110,24 @@ pub fn exec(
 
 fn read_exec_file<R: Read>(reader: R) -> Result<(usize, usize, Vec<u64>, Vec<u64>)> {
     let mut buff = read_vec_from_file(reader)?;
+    if buff.len() < 2 {
+        bail!(RecursionError::MalformedExecFile(
+            "missing the length header".to_string()
+        ));
+    }
 
     let mut new_buff = buff.split_off(2);
     let adds_len = buff[0] as usize;
     let s_map_column_len = buff[1] as usize;
 
     let size = adds_len * 4 + s_map_column_len * 12;
-    assert_eq!(new_buff.len(), size);
+    if new_buff.len() != size {
+        bail!(RecursionError::MalformedExecFile(format!(
+            "expect {} elements, but got {}",
+            size,
+            new_buff.len()
+        )));
+    }
 
     let s_map = new_buff.split_off(adds_len * 4);
     let adds = new_buff;
@@ -154,5 +171,12 @@ mod test {
         let (adds_len, _s_map_column_len, _adds, _s_map) = read_exec_file(reader).unwrap();
 
         assert_eq!(adds_len, target_adds.len());
+
+        let reader = std::io::Cursor::new(b"[0, 1, 2]");
+        let err = read_exec_file(reader).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RecursionError>(),
+            Some(RecursionError::MalformedExecFile(_))
+        ));
     }
 }
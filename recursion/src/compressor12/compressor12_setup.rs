@@ -24,7 +24,7 @@ pub fn setup(
 ) -> Result<()> {
     // 0. readR1cs
     let r1cs_reader = File::open(r1cs_file)?;
-    let (r1cs, _) = load_r1cs_from_bin::<_, GL>(r1cs_reader);
+    let (r1cs, _) = load_r1cs_from_bin::<_, GL>(r1cs_reader)?;
     let opts = Options {
         force_bits: force_n_bits,
     };
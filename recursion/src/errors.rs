@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+pub use anyhow::{bail, Result};
+
+#[derive(Error, Debug)]
+pub enum RecursionError {
+    #[error("Malformed exec file, {0}")]
+    MalformedExecFile(String),
+
+    #[error("Invalid witness, {0}")]
+    InvalidWitness(String),
+
+    #[error("Unknown error, `{0}`")]
+    Unknown(String),
+}
+
+impl From<String> for RecursionError {
+    fn from(e: String) -> Self {
+        RecursionError::Unknown(e)
+    }
+}
@@ -1,5 +1,6 @@
 pub mod compressor12;
 pub use compressor12::*;
+pub mod errors;
 mod io_utils;
 pub mod pilcom;
 pub mod r1cs2plonk;
@@ -0,0 +1,24 @@
+pragma circom 2.0.6;
+
+include "bitify.circom";
+
+// The 3 Goldilocks elements of the low 192 bits of a BN128 scalar, the decomposition is strict
+// so the bits are the ones of the canonical value.
+template BN1toGL3() {
+    signal input in;
+    signal output out[3];
+
+    component n2b = Num2Bits_strict();
+
+    n2b.in <== in;
+
+    component b2n[3];
+
+    for (var i=0; i<3; i++) {
+        b2n[i] = Bits2Num(64);
+        for (var j=0; j<64; j++) {
+            b2n[i].in[j] <== n2b.out[64*i+j];
+        }
+        out[i] <== b2n[i].out;
+    }
+}
@@ -0,0 +1,50 @@
+pragma circom 2.1.0;
+
+include "compconstant64.circom";
+
+template Num2Bits(n) {
+    signal input in;
+    signal output out[n];
+    var lc1=0;
+
+    var e2=1;
+    for (var i = 0; i<n; i++) {
+        out[i] <-- (in >> i) & 1;
+        out[i] * (out[i] -1 ) === 0;
+        lc1 += out[i] * e2;
+        e2 = e2+e2;
+    }
+
+    lc1 === in;
+}
+
+template Bits2Num(n) {
+    signal input in[n];
+    signal output out;
+    var lc1=0;
+
+    var e2 = 1;
+    for (var i = 0; i<n; i++) {
+        lc1 += in[i] * e2;
+        e2 = e2 + e2;
+    }
+
+    lc1 ==> out;
+}
+
+// The 64 bits of a goldilocks element, rejecting the encodings of in + p.
+template Num2Bits_strict() {
+    signal input in;
+    signal output out[64];
+
+    component aliasCheck = CompConstant64(0xFFFFFFFF00000000);
+    component n2b = Num2Bits(64);
+    in ==> n2b.in;
+
+    for (var i=0; i<64; i++) {
+        n2b.out[i] ==> out[i];
+        n2b.out[i] ==> aliasCheck.in[i];
+    }
+
+    aliasCheck.out === 0;
+}
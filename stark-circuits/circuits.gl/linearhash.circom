@@ -0,0 +1,91 @@
+pragma circom 2.1.0;
+
+include "poseidon.circom";
+
+// The sponge of starky's `LinearHash::_hash`: up to 4 elements are returned zero padded, more are
+// absorbed by blocks of 8, the last one zero padded, with the previous output as capacity.
+template LinearHashSponge(n) {
+    signal input in[n];
+    signal output out[4];
+
+    var nBlocks = n<=4 ? 0 : (n - 1)\8 + 1;
+
+    component hash[nBlocks];
+
+    if (nBlocks == 0) {
+        for (var i=0; i<4; i++) {
+            if (i<n) {
+                out[i] <== in[i];
+            } else {
+                out[i] <== 0;
+            }
+        }
+    } else {
+        for (var b=0; b<nBlocks; b++) {
+            hash[b] = Poseidon(4);
+            for (var i=0; i<8; i++) {
+                if (b*8+i < n) {
+                    hash[b].in[i] <== in[b*8+i];
+                } else {
+                    hash[b].in[i] <== 0;
+                }
+            }
+            for (var i=0; i<4; i++) {
+                if (b==0) {
+                    hash[b].capacity[i] <== 0;
+                } else {
+                    hash[b].capacity[i] <== hash[b-1].out[i];
+                }
+            }
+        }
+        out <== hash[nBlocks-1].out;
+    }
+}
+
+// starky's `LinearHash::hash` with the default batch size: the flattened inputs are split in
+// batches of max(8, ceil(n/4)) elements and the hashes of the batches are hashed again.
+template LinearHash(nInputs, eSize) {
+    signal input in[nInputs][eSize];
+    signal output out[4];
+
+    var n = nInputs*eSize;
+    var bs = (n+3)\4 > 8 ? (n+3)\4 : 8;
+    var nBatches = n<=4 ? 1 : (n - 1)\bs + 1;
+    var lastBs = n<=4 ? n : n - (nBatches-1)*bs;
+
+    signal flat[n];
+    for (var i=0; i<nInputs; i++) {
+        for (var e=0; e<eSize; e++) {
+            flat[i*eSize + e] <== in[i][e];
+        }
+    }
+
+    component batch[nBatches-1];
+    component lastBatch = LinearHashSponge(lastBs);
+    component top;
+
+    for (var b=0; b<nBatches-1; b++) {
+        batch[b] = LinearHashSponge(bs);
+        for (var i=0; i<bs; i++) {
+            batch[b].in[i] <== flat[b*bs + i];
+        }
+    }
+    for (var i=0; i<lastBs; i++) {
+        lastBatch.in[i] <== flat[(nBatches-1)*bs + i];
+    }
+
+    if (nBatches == 1) {
+        out <== lastBatch.out;
+    } else {
+        top = LinearHashSponge(nBatches*4);
+        for (var b=0; b<nBatches-1; b++) {
+            for (var i=0; i<4; i++) {
+                top.in[b*4 + i] <== batch[b].out[i];
+            }
+        }
+        for (var i=0; i<4; i++) {
+            top.in[(nBatches-1)*4 + i] <== lastBatch.out[i];
+        }
+        out <== top.out;
+    }
+}
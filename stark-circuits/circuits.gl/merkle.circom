@@ -0,0 +1,36 @@
+pragma circom 2.1.0;
+
+include "poseidon.circom";
+
+// The root of the binary Merkle tree of starky's `MerkleTree`: at every level the node is the
+// left child when its key bit is 0.
+template Merkle(nLevels) {
+    signal input value[4];
+    signal input siblings[nLevels][4];
+    signal input key[nLevels];
+    signal output root[4];
+
+    component hash[nLevels];
+
+    signal acc[nLevels+1][4];
+    signal left[nLevels][4];
+    signal right[nLevels][4];
+
+    acc[0] <== value;
+
+    for (var i=0; i<nLevels; i++) {
+        key[i] * (key[i] - 1) === 0;
+
+        hash[i] = Poseidon(4);
+        for (var j=0; j<4; j++) {
+            left[i][j] <== key[i]*(siblings[i][j] - acc[i][j]) + acc[i][j];
+            right[i][j] <== acc[i][j] + siblings[i][j] - left[i][j];
+            hash[i].in[j] <== left[i][j];
+            hash[i].in[4+j] <== right[i][j];
+            hash[i].capacity[j] <== 0;
+        }
+        acc[i+1] <== hash[i].out;
+    }
+
+    root <== acc[nLevels];
+}
@@ -0,0 +1,34 @@
+pragma circom 2.1.0;
+
+include "linearhash.circom";
+include "merkle.circom";
+include "utils.circom";
+
+template parallel MerkleHash(eSize, elementsInLinear, nLinears) {
+    var nBits = log2(nLinears);
+    assert(1 << nBits == nLinears);
+    signal input values[elementsInLinear][eSize];
+    signal input siblings[nBits][4];
+    signal input key[nBits];
+    signal output root[4];
+
+    component linearHash = LinearHash(elementsInLinear, eSize);
+
+    for (var i=0; i<elementsInLinear; i++) {
+        for (var e=0; e<eSize; e++) {
+            linearHash.in[i][e] <== values[i][e];
+        }
+    }
+
+    component merkle = Merkle(nBits);
+
+    merkle.value <== linearHash.out;
+    for (var i=0; i<nBits; i++) {
+        merkle.key[i] <== key[i];
+        for (var j=0; j<4; j++) {
+            merkle.siblings[i][j] <== siblings[i][j];
+        }
+    }
+
+    root <== merkle.root;
+}
@@ -0,0 +1,68 @@
+pragma circom 2.1.0;
+pragma custom_templates;
+
+include "poseidon_constants.circom";
+
+// The Poseidon permutation of width 12, proved by the `Poseidon12` gate of the compressor: one
+// row per round state, from the input to the output, so the states between the rounds are
+// outputs too.
+template custom Poseidon12() {
+    signal input in[12];
+    signal output im[29][12];
+    signal output out[12];
+
+    var C[360] = POSEIDON_C();
+    var M[12][12] = POSEIDON_M();
+
+    var st[12];
+    for (var i=0; i<12; i++) {
+        st[i] = in[i];
+    }
+
+    for (var r=0; r<30; r++) {
+        for (var i=0; i<12; i++) {
+            st[i] = st[i] + C[r*12 + i];
+        }
+        // 4 full rounds, 22 partial rounds and 4 full rounds
+        if ((r<4) || (r>=26)) {
+            for (var i=0; i<12; i++) {
+                st[i] = st[i]**7;
+            }
+        } else {
+            st[0] = st[0]**7;
+        }
+        var mix[12];
+        for (var i=0; i<12; i++) {
+            mix[i] = 0;
+            for (var j=0; j<12; j++) {
+                mix[i] = mix[i] + M[i][j]*st[j];
+            }
+        }
+        for (var i=0; i<12; i++) {
+            st[i] = mix[i];
+            if (r<29) {
+                im[r][i] <-- st[i];
+            } else {
+                out[i] <-- st[i];
+            }
+        }
+    }
+}
+
+// The sponge of starky's linear hash and transcript: 8 inputs and a capacity of 4.
+template Poseidon(nOuts) {
+    signal input in[8];
+    signal input capacity[4];
+    signal output out[nOuts];
+
+    component p = Poseidon12();
+    for (var i=0; i<8; i++) {
+        p.in[i] <== in[i];
+    }
+    for (var i=0; i<4; i++) {
+        p.in[8+i] <== capacity[i];
+    }
+    for (var i=0; i<nOuts; i++) {
+        out[i] <== p.out[i];
+    }
+}
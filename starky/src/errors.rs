@@ -0,0 +1,36 @@
+use thiserror::Error;
+
+pub use anyhow::{bail, Result};
+
+#[derive(Error, Debug)]
+pub enum StarkyError {
+    #[error("Invalid hashtype {0}")]
+    InvalidHashType(String),
+
+    #[error("Malformed input, {0}")]
+    MalformedInput(String),
+
+    #[error("Malformed PIL, {0}")]
+    MalformedPil(String),
+
+    #[error("Unsatisfied PIL identities, {0}")]
+    UnsatisfiedIdentities(String),
+
+    #[error("Invalid stark proof: {0}")]
+    InvalidProof(String),
+
+    #[error("Memory limit exceeded, {0}")]
+    MemoryLimit(String),
+
+    #[error("Insufficient security, {0}")]
+    InsufficientSecurity(String),
+
+    #[error("Unknown error, `{0}`")]
+    Unknown(String),
+}
+
+impl From<String> for StarkyError {
+    fn from(e: String) -> Self {
+        StarkyError::Unknown(e)
+    }
+}
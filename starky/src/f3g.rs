@@ -51,6 +51,7 @@ const ELEMENT_BYTES: usize = core::mem::size_of::<u64>();
 impl FieldExtension for F3G {
     const ELEMENT_BYTES: usize = ELEMENT_BYTES;
     const IS_CANONICAL: bool = false;
+    const DIM: usize = 3;
 
     const ZERO: Self = Self {
         cube: [Fr::ZERO, Fr::ZERO, Fr::ZERO],
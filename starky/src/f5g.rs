@@ -39,6 +39,7 @@ const ELEMENT_BYTES: usize = core::mem::size_of::<u64>();
 impl FieldExtension for F5G {
     const ELEMENT_BYTES: usize = ELEMENT_BYTES;
     const IS_CANONICAL: bool = false;
+    const DIM: usize = 5;
 
     const ZERO: Self = Self {
         cube: [Fr::ZERO, Fr::ZERO, Fr::ZERO, Fr::ZERO, Fr::ZERO],
@@ -128,11 +129,7 @@ impl FieldExtension for F5G {
         let res = rhs.as_elements();
         match self.dim {
             5 => {
-                (les[0] > res[0]
-                    && (les[1] == res[1])
-                    && (les[2] == res[2])
-                    && (les[3] == res[3])
-                    && (les[4] == res[4]))
+                (les[0] > res[0])
                     || ((les[0] == res[0]) && (les[1] > res[1]))
                     || ((les[0] == res[0]) && (les[1] == res[1]) && (les[2] > res[2]))
                     || ((les[0] == res[0])
@@ -159,7 +156,7 @@ impl FieldExtension for F5G {
 
     #[inline]
     fn lt(&self, rhs: &Self) -> bool {
-        !self.gt(rhs) || self.lt(rhs)
+        !self.geq(rhs)
     }
 
     #[inline]
@@ -521,7 +518,7 @@ impl Neg for F5G {
     #[inline]
     fn neg(self) -> Self::Output {
         match self.dim {
-            3 => Self {
+            5 => Self {
                 cube: [
                     -self.cube[0],
                     -self.cube[1],
@@ -529,7 +526,7 @@ impl Neg for F5G {
                     -self.cube[3],
                     -self.cube[4],
                 ],
-                dim: 3,
+                dim: 5,
             },
             1 => Self::from(-self.to_be()),
             _ => {
@@ -916,6 +913,19 @@ pub mod tests {
         assert!(e1.lt(&e12));
         assert!(e12.gt(&e1));
         assert!(e12.geq(&e1));
+        assert!(!e12.lt(&e1));
+        assert!(!e1.gt(&e12));
+
+        let e13 = F5G::new(
+            Fr::from(2u64),
+            Fr::ONE,
+            Fr::from(3u64),
+            Fr::from(4u64),
+            Fr::from(5u64),
+        );
+        assert!(e13.gt(&e1));
+        assert!(e12.gt(&e13));
+        assert_eq!(-(-e13), e13);
     }
 
     #[test]
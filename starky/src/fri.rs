@@ -1,6 +1,7 @@
 #![allow(clippy::map_all_any_identity)]
 
 use crate::constant::{MG, SHIFT, SHIFT_INV};
+use crate::errors::StarkyError;
 use crate::fft::FFT;
 use crate::helper::log2_any;
 use crate::polutils::{eval_pol, pol_mul_axi};
@@ -129,14 +130,13 @@ impl FRI {
                 let group_size = (1 << stepi.nBits) / n_groups;
                 let pol2_etb = get_transposed_buffer(&pol2_e, self.steps[si + 1].nBits);
                 let mut tmptree = M::new();
-                tmptree.merkelize(pol2_etb, 3 * group_size, n_groups)?;
+                tmptree.merkelize(pol2_etb, F::DIM * group_size, n_groups)?;
                 tree.push(tmptree);
                 proof.queries[si + 1].root = tree[si].root();
                 transcript.put(&[tree[si].root().as_elements().to_vec()])?;
             } else {
                 for e in pol2_e.iter() {
-                    let elems = e.as_elements();
-                    let v = [vec![elems[0]], vec![elems[1]], vec![elems[2]]];
+                    let v: Vec<Vec<FGL>> = e.as_elements().into_iter().map(|e| vec![e]).collect();
                     transcript.put(&v)?;
                 }
             }
@@ -192,7 +192,7 @@ impl FRI {
     ) -> Result<bool> {
         let tree = M::new();
         let mut standard_fft = FFT::new();
-        assert_eq!(proof.queries.len(), self.steps.len()); // the last +1 is omitted
+        let group_bits = self.check_proof_shape(proof)?;
         let mut special_x: Vec<F> = vec![];
         for si in 0..self.steps.len() {
             special_x.push(transcript.get_field());
@@ -203,10 +203,7 @@ impl FRI {
             } else {
                 let mut pp: Vec<Vec<FGL>> = vec![];
                 for e in proof.last.iter() {
-                    let elems = e.as_elements();
-                    pp.push(vec![elems[0]]);
-                    pp.push(vec![elems[1]]);
-                    pp.push(vec![elems[2]]);
+                    pp.extend(e.as_elements().into_iter().map(|e| vec![e]));
                 }
                 transcript.put(&pp[..])?;
             }
@@ -227,16 +224,16 @@ impl FRI {
                 log::error!("check_query_fn failed si:{},idx:{}", si, idx);
                 bail!("FRIVerifierFailed");
             }
-            Ok(split3(&query[0].0))
+            Ok(split(&query[0].0))
         };
         for (si, stepi) in self.steps.iter().enumerate() {
             let proof_item = &proof.queries[si];
-            let reduction_bits = pol_bits - stepi.nBits;
+            let reduction_bits = group_bits[si];
             for i in 0..n_queries {
                 let pgroup_e: Vec<F> = match si {
                     0 => {
                         let pgroup_e = check_query(&proof_item.pol_queries[i], ys[i])?;
-                        if pgroup_e.is_empty() {
+                        if pgroup_e.len() != (1 << reduction_bits) {
                             log::error!("check_query failed si:{}", si);
                             return Ok(false);
                         }
@@ -252,7 +249,7 @@ impl FRI {
                 if si < self.steps.len() - 1 {
                     let next_n_groups = 1 << self.steps[si + 1].nBits;
                     let group_idx = ys[i] / next_n_groups;
-                    if !ev._eq(&get3(&proof.queries[si + 1].pol_queries[i][0].0, group_idx)) {
+                    if !ev._eq(&get(&proof.queries[si + 1].pol_queries[i][0].0, group_idx)) {
                         log::error!("eq query failed si:{}", si + 1);
                         return Ok(false);
                     }
@@ -294,40 +291,92 @@ impl FRI {
         }
         Ok(true)
     }
+
+    /// Check the number of steps, queries and values of an untrusted proof against the stark
+    /// struct, and return the bits of the group size folded by each step.
+    fn check_proof_shape<F: FieldExtension, M: MerkleTree<ExtendField = F>>(
+        &self,
+        proof: &FRIProof<F, M>,
+    ) -> Result<Vec<usize>> {
+        // the last +1 is omitted
+        if proof.queries.len() != self.steps.len() {
+            bail!(StarkyError::InvalidProof(format!(
+                "{} FRI steps, expected {}",
+                proof.queries.len(),
+                self.steps.len()
+            )));
+        }
+        let mut group_bits = Vec::with_capacity(self.steps.len());
+        let mut pol_bits = self.in_nbits;
+        for (si, stepi) in self.steps.iter().enumerate() {
+            let bits = match pol_bits.checked_sub(stepi.nBits) {
+                Some(bits) => bits,
+                None => bail!(StarkyError::MalformedInput(format!(
+                    "the FRI step {} of {} bits follows one of {} bits",
+                    si, stepi.nBits, pol_bits
+                ))),
+            };
+            let pol_queries = &proof.queries[si].pol_queries;
+            if pol_queries.len() != self.n_queries {
+                bail!(StarkyError::InvalidProof(format!(
+                    "{} queries in the FRI step {}, expected {}",
+                    pol_queries.len(),
+                    si,
+                    self.n_queries
+                )));
+            }
+            // the queries of the first step are the ones of the stark trees, checked by the caller
+            if si > 0 {
+                for query in pol_queries.iter() {
+                    if query.is_empty() || query[0].0.len() != (1 << bits) * F::DIM {
+                        bail!(StarkyError::InvalidProof(format!(
+                            "a query of the FRI step {} doesn't hold {} values",
+                            si,
+                            1 << bits
+                        )));
+                    }
+                }
+            }
+            group_bits.push(bits);
+            pol_bits = stepi.nBits;
+        }
+        if proof.last.len() != 1 << pol_bits {
+            bail!(StarkyError::InvalidProof(format!(
+                "the last FRI polynomial has {} values, expected {}",
+                proof.last.len(),
+                1 << pol_bits
+            )));
+        }
+        Ok(group_bits)
+    }
 }
 
 fn get_transposed_buffer<F: FieldExtension>(pol: &[F], transpose_bits: usize) -> Vec<FGL> {
     let n = pol.len();
     let w = 1 << transpose_bits;
     let h = n / w;
-    let mut res: Vec<FGL> = vec![FGL::ZERO; n * 3];
+    let dim = F::DIM;
+    let mut res: Vec<FGL> = vec![FGL::ZERO; n * dim];
     for i in 0..w {
         for j in 0..h {
-            let di = i * h * 3 + j * 3;
+            let di = i * h * dim + j * dim;
             let fi = j * w + i;
-            let pb = pol[fi].as_elements();
-            // TODO: Support F5G
-            assert_eq!(pol[fi].dim(), 3);
-            res[di] = pb[0];
-            res[di + 1] = pb[1];
-            res[di + 2] = pb[2];
+            assert_eq!(pol[fi].dim(), dim);
+            res[di..(di + dim)].copy_from_slice(&pol[fi].as_elements());
         }
     }
     res
 }
 
-// TODO: Support F5G
-fn get3<F: FieldExtension>(arr: &[FGL], idx: usize) -> F {
-    F::from_vec(vec![arr[idx * 3], arr[idx * 3 + 1], arr[idx * 3 + 2]])
+/// The `idx`-th element of the extension field in the flattened `arr`.
+fn get<F: FieldExtension>(arr: &[FGL], idx: usize) -> F {
+    F::from_vec(arr[(idx * F::DIM)..((idx + 1) * F::DIM)].to_vec())
 }
 
-// TODO: Support F5G
-fn split3<F: FieldExtension>(arr: &[FGL]) -> Vec<F> {
-    let mut res: Vec<F> = Vec::new();
-    for i in (0..arr.len()).step_by(3) {
-        res.push(F::from_vec(vec![arr[i], arr[i + 1], arr[i + 2]]));
-    }
-    res
+fn split<F: FieldExtension>(arr: &[FGL]) -> Vec<F> {
+    arr.chunks(F::DIM)
+        .map(|e| F::from_vec(e.to_vec()))
+        .collect()
 }
 
 /*
@@ -1,10 +1,12 @@
 #![allow(non_snake_case, dead_code)]
+use crate::errors::StarkyError;
 use crate::stark_gen::StarkContext;
 use crate::starkinfo::StarkInfo;
 use crate::starkinfo_codegen::Node;
 use crate::starkinfo_codegen::Section;
 use crate::traits::FieldExtension;
 use crate::types::parse_pil_number;
+use anyhow::{bail, Result};
 use std::fmt;
 
 #[derive(Clone, Debug)]
@@ -52,7 +54,11 @@ impl<T: FieldExtension> fmt::Display for Expr<T> {
                     self.addr[1],
                     self.addr[2],
                     self.addr[3],
-                    if self.syms.len() == 2 { 3 } else { 1 }
+                    if self.syms.len() == 2 {
+                        self.syms[1].as_str()
+                    } else {
+                        "1"
+                    }
                 )
             }
             Ops::Vari(x) => {
@@ -93,7 +99,7 @@ impl<T: FieldExtension> Block<T> {
     /// example:
     /// let block = compile_code();
     /// block.eval(&mut ctx, i);
-    pub fn eval(&self, ctx: &mut StarkContext<T>, arg_i: usize) -> T {
+    pub fn eval(&self, ctx: &mut StarkContext<T>, arg_i: usize) -> Result<T> {
         let mut val_stack: Vec<T> = Vec::new();
         let length = self.exprs.len();
 
@@ -104,7 +110,7 @@ impl<T: FieldExtension> Block<T> {
             i += 1;
             match expr.op {
                 Ops::Ret => {
-                    return val_stack.pop().unwrap();
+                    return Ok(val_stack.pop().unwrap());
                 }
                 Ops::Vari(x) => {
                     val_stack.push(x);
@@ -112,33 +118,33 @@ impl<T: FieldExtension> Block<T> {
                 Ops::Add => {
                     let lhs = match expr.defs[0].op {
                         Ops::Vari(x) => x,
-                        _ => get_value(ctx, &expr.defs[0], arg_i),
+                        _ => get_value(ctx, &expr.defs[0], arg_i)?,
                     };
                     let rhs = match expr.defs[1].op {
                         Ops::Vari(x) => x,
-                        _ => get_value(ctx, &expr.defs[1], arg_i),
+                        _ => get_value(ctx, &expr.defs[1], arg_i)?,
                     };
                     val_stack.push(lhs + rhs);
                 }
                 Ops::Mul => {
                     let lhs = match expr.defs[0].op {
                         Ops::Vari(x) => x,
-                        _ => get_value(ctx, &expr.defs[0], arg_i),
+                        _ => get_value(ctx, &expr.defs[0], arg_i)?,
                     };
                     let rhs = match expr.defs[1].op {
                         Ops::Vari(x) => x,
-                        _ => get_value(ctx, &expr.defs[1], arg_i),
+                        _ => get_value(ctx, &expr.defs[1], arg_i)?,
                     };
                     val_stack.push(lhs * rhs);
                 }
                 Ops::Sub => {
                     let lhs = match expr.defs[0].op {
                         Ops::Vari(x) => x,
-                        _ => get_value(ctx, &expr.defs[0], arg_i),
+                        _ => get_value(ctx, &expr.defs[0], arg_i)?,
                     };
                     let rhs = match expr.defs[1].op {
                         Ops::Vari(x) => x,
-                        _ => get_value(ctx, &expr.defs[1], arg_i),
+                        _ => get_value(ctx, &expr.defs[1], arg_i)?,
                     };
                     val_stack.push(lhs - rhs);
                 }
@@ -147,7 +153,7 @@ impl<T: FieldExtension> Block<T> {
                         x
                     } else {
                         // get value from address
-                        get_value(ctx, &expr.defs[0], arg_i)
+                        get_value(ctx, &expr.defs[0], arg_i)?
                     };
                     val_stack.push(x);
                 }
@@ -157,26 +163,25 @@ impl<T: FieldExtension> Block<T> {
                     let addr = &next_expr.syms[0];
                     let val = val_stack.pop().unwrap(); // get the value from stack
 
-                    let val_addr = ctx.get_mut(addr.as_str());
+                    let val_addr = ctx.get_mut(addr.as_str())?;
                     if val.dim() == 1 || addr.as_str() == "tmp" {
                         // TODO: need double confirm the condition
                         val_addr[id] = val;
                     } else {
-                        // here we again unfold elements of GF(2^3) to 3-tuple(triple)
-                        let vals = val.as_elements();
-                        val_addr[id] = T::from(vals[0]);
-                        val_addr[id + 1] = T::from(vals[1]);
-                        val_addr[id + 2] = T::from(vals[2]);
+                        // here we again unfold elements of GF(p^dim) to dim-tuple
+                        for (k, v) in val.as_elements().into_iter().enumerate() {
+                            val_addr[id + k] = T::from(v);
+                        }
                     }
                 }
                 Ops::Refer => {
                     // push value into stack
-                    let x = get_value(ctx, expr, arg_i);
+                    let x = get_value(ctx, expr, arg_i)?;
                     val_stack.push(x);
                 }
             }
         }
-        T::ZERO
+        Ok(T::ZERO)
     }
 }
 
@@ -195,7 +200,7 @@ pub fn compile_code<T: FieldExtension>(
     code: &[Section],
     dom: &str,
     ret: bool,
-) -> Block<T> {
+) -> Result<Block<T>> {
     let next = if dom == "n" {
         1
     } else {
@@ -217,28 +222,36 @@ pub fn compile_code<T: FieldExtension>(
     for cj in code.iter() {
         let mut src: Vec<Expr<T>> = Vec::new();
         for k in 0..cj.src.len() {
-            src.push(get_ref(ctx, starkinfo, &cj.src[k], dom, next, modulas));
+            src.push(get_ref(ctx, starkinfo, &cj.src[k], dom, next, modulas)?);
             //log::trace!("get_ref_src: {}", src[src.len() - 1]);
         }
 
-        let exp = match cj.op.as_str() {
-            "add" => Expr::new(Ops::Add, Vec::new(), src[0..2].to_vec(), vec![]),
-            "sub" => Expr::new(Ops::Sub, Vec::new(), src[0..2].to_vec(), vec![]),
-            "mul" => Expr::new(Ops::Mul, Vec::new(), src[0..2].to_vec(), vec![]),
-            "copy" => Expr::new(Ops::Copy_, Vec::new(), src[0..1].to_vec(), vec![]),
-            _ => {
-                panic!("Invalid op {:?}", cj)
-            }
+        let (op, arity) = match cj.op.as_str() {
+            "add" => (Ops::Add, 2),
+            "sub" => (Ops::Sub, 2),
+            "mul" => (Ops::Mul, 2),
+            "copy" => (Ops::Copy_, 1),
+            _ => bail!(StarkyError::MalformedPil(format!("invalid op {}", cj.op))),
         };
-        set_ref(ctx, starkinfo, &cj.dest, exp, dom, next, modulas, &mut body);
+        if src.len() < arity {
+            bail!(StarkyError::MalformedPil(format!(
+                "{} with {} operands",
+                cj.op,
+                src.len()
+            )));
+        }
+        let exp = Expr::new(op, Vec::new(), src[0..arity].to_vec(), vec![]);
+        set_ref(ctx, starkinfo, &cj.dest, exp, dom, next, modulas, &mut body)?;
     }
     if ret {
-        let sz = code.len() - 1;
+        let last = code
+            .last()
+            .ok_or_else(|| StarkyError::MalformedPil("empty code to return".to_string()))?;
         body.exprs
-            .push(get_ref(ctx, starkinfo, &code[sz].dest, dom, next, modulas));
+            .push(get_ref(ctx, starkinfo, &last.dest, dom, next, modulas)?);
         body.exprs.push(Expr::new(Ops::Ret, vec![], vec![], vec![]));
     }
-    body
+    Ok(body)
 }
 
 #[inline(always)]
@@ -250,16 +263,20 @@ fn get_i<T: FieldExtension>(expr: &Expr<T>, arg_i: usize) -> usize {
     offset + ((arg_i + next) % modulas) * size
 }
 
-fn get_value<T: FieldExtension>(ctx: &mut StarkContext<T>, expr: &Expr<T>, arg_i: usize) -> T {
+fn get_value<T: FieldExtension>(
+    ctx: &mut StarkContext<T>,
+    expr: &Expr<T>,
+    arg_i: usize,
+) -> Result<T> {
     let addr = &expr.syms[0];
-    match addr.as_str() {
+    let val = match addr.as_str() {
         "tmp" | "cm1_n" | "cm1_2ns" | "cm2_n" | "cm2_2ns" | "cm3_n" | "cm3_2ns" | "cm4_n"
         | "cm4_2ns" | "q_2ns" | "f_2ns" | "publics" | "challenge" | "exps_n" | "exps_2ns"
         | "const_n" | "const_2ns" | "evals" | "x_n" | "x_2ns" | "tmpexp_n" => {
             let id = get_i(expr, arg_i);
-            let ctx_section = ctx.get_mut(addr.as_str()); // OPT: readonly ctx
+            let ctx_section = ctx.get_mut(addr.as_str())?; // OPT: readonly ctx
             let dim = match expr.syms.len() {
-                2 => expr.syms[1].parse::<usize>().unwrap(),
+                2 => expr.syms[1].parse::<usize>()?,
                 _ => 1,
             };
 
@@ -279,32 +296,27 @@ fn get_value<T: FieldExtension>(ctx: &mut StarkContext<T>, expr: &Expr<T>, arg_i
                 ]),
 
                 1 => ctx_section[id],
-                _ => panic!("Invalid dim"),
+                _ => bail!(StarkyError::MalformedPil(format!(
+                    "invalid dim {} of {}",
+                    dim, addr
+                ))),
             }
         }
         "xDivXSubXi" => {
             let id = get_i(expr, arg_i);
-            // TODO: We need to Support F5G , FG
-            T::from_vec(vec![
-                ctx.xDivXSubXi[id],
-                ctx.xDivXSubXi[id + 1],
-                ctx.xDivXSubXi[id + 2],
-            ])
+            T::from_vec(ctx.xDivXSubXi[id..(id + T::DIM)].to_vec())
         }
         "xDivXSubWXi" => {
             let id = get_i(expr, arg_i);
-            // TODO: We need to Support F5G , FG
-            T::from_vec(vec![
-                ctx.xDivXSubWXi[id],
-                ctx.xDivXSubWXi[id + 1],
-                ctx.xDivXSubWXi[id + 2],
-            ])
+            T::from_vec(ctx.xDivXSubWXi[id..(id + T::DIM)].to_vec())
         }
         "Zi" => (ctx.Zi)(arg_i),
-        _ => {
-            panic!("invalid symbol {:?}", addr);
-        }
-    }
+        _ => bail!(StarkyError::MalformedPil(format!(
+            "invalid symbol {}",
+            addr
+        ))),
+    };
+    Ok(val)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -317,7 +329,7 @@ fn set_ref<T: FieldExtension>(
     next: usize,
     modulas: usize,
     body: &mut Block<T>,
-) {
+) -> Result<()> {
     //log::trace!("set_ref: r {:?}  dom {} val {}", r, dom, val);
     let e_dst = match r.type_.as_str() {
         "tmp" => Expr::new(
@@ -328,14 +340,17 @@ fn set_ref<T: FieldExtension>(
         ),
         "q" => {
             if dom == "n" {
-                panic!("Accesssing q in domain n");
+                bail!(StarkyError::MalformedPil(format!(
+                    "accessing {} in domain n",
+                    r.type_
+                )));
             } else if dom == "2ns" {
-                if starkinfo.q_dim == 3 {
+                if starkinfo.q_dim == starkinfo.ext_dim {
                     Expr::new(
                         Ops::Refer,
-                        vec!["q_2ns".to_string(), "3".to_string()],
+                        vec!["q_2ns".to_string(), starkinfo.q_dim.to_string()],
                         vec![],
-                        vec![r.id, 0, modulas, 3],
+                        vec![r.id, 0, modulas, starkinfo.q_dim],
                     )
                 } else if starkinfo.q_dim == 1 {
                     Expr::new(
@@ -345,52 +360,57 @@ fn set_ref<T: FieldExtension>(
                         vec![r.id, 0, modulas, 1],
                     )
                 } else {
-                    panic!("Invalid dom");
+                    bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
                 }
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "f" => {
             if dom == "n" {
-                panic!("Accesssing q in domain n");
+                bail!(StarkyError::MalformedPil(format!(
+                    "accessing {} in domain n",
+                    r.type_
+                )));
             } else if dom == "2ns" {
                 Expr::new(
                     Ops::Refer,
-                    vec!["f_2ns".to_string(), "3".to_string()],
+                    vec!["f_2ns".to_string(), starkinfo.ext_dim.to_string()],
                     vec![],
-                    vec![r.id, 0, modulas, 3],
+                    vec![r.id, 0, modulas, starkinfo.ext_dim],
                 )
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "cm" => {
             if dom == "n" {
                 let pol_id = starkinfo.cm_n[r.id];
-                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)
+                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)?
             } else if dom == "2ns" {
                 let pol_id = starkinfo.cm_2ns[r.id];
-                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)
+                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)?
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "tmpExp" => {
             if dom == "n" {
                 let pol_id = starkinfo.tmpexp_n[r.id];
-                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)
+                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)?
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
-        _ => {
-            panic!("Invalid reference type set {}", r.type_)
-        }
+        _ => bail!(StarkyError::MalformedPil(format!(
+            "invalid reference type set {}",
+            r.type_
+        ))),
     };
     body.exprs.push(val);
     body.exprs
         .push(Expr::new(Ops::Write, vec![], vec![e_dst], vec![]));
+    Ok(())
 }
 
 fn get_ref<F: FieldExtension>(
@@ -400,9 +420,9 @@ fn get_ref<F: FieldExtension>(
     dom: &str,
     next: usize,
     modulas: usize,
-) -> Expr<F> {
+) -> Result<Expr<F>> {
     //log::trace!("get_ref: r {:?}  dom {} ", r, dom);
-    match r.type_.as_str() {
+    let expr = match r.type_.as_str() {
         "tmp" => Expr::new(
             Ops::Refer,
             vec!["tmp".to_string()],
@@ -443,30 +463,34 @@ fn get_ref<F: FieldExtension>(
                     )
                 }
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "cm" => {
             if dom == "n" {
                 let pol_id = starkinfo.cm_n[r.id];
-                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)
+                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)?
             } else if dom == "2ns" {
                 let pol_id = starkinfo.cm_2ns[r.id];
-                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)
+                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)?
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "tmpExp" => {
             if dom == "n" {
                 let pol_id = starkinfo.tmpexp_n[r.id];
-                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)
+                eval_map(ctx, starkinfo, pol_id, r.prime, next, modulas)?
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "number" => {
-            let n_val = parse_pil_number(r.value.as_ref().unwrap());
+            let value = r
+                .value
+                .as_ref()
+                .ok_or_else(|| StarkyError::MalformedPil("number without value".to_string()))?;
+            let n_val = parse_pil_number(value)?;
             Expr::new(Ops::Vari(F::from(n_val)), vec![], vec![], vec![])
         }
         "public" => Expr::new(
@@ -489,15 +513,15 @@ fn get_ref<F: FieldExtension>(
         ),
         "xDivXSubXi" => Expr::new(
             Ops::Refer,
-            vec!["xDivXSubXi".to_string(), "3".to_string()],
+            vec!["xDivXSubXi".to_string(), starkinfo.ext_dim.to_string()],
             vec![],
-            vec![0, 0, modulas, 3],
+            vec![0, 0, modulas, starkinfo.ext_dim],
         ),
         "xDivXSubWXi" => Expr::new(
             Ops::Refer,
-            vec!["xDivXSubWXi".to_string(), "3".to_string()],
+            vec!["xDivXSubWXi".to_string(), starkinfo.ext_dim.to_string()],
             vec![],
-            vec![0, 0, modulas, 3],
+            vec![0, 0, modulas, starkinfo.ext_dim],
         ),
         "x" => {
             if dom == "n" {
@@ -515,7 +539,7 @@ fn get_ref<F: FieldExtension>(
                     vec![0, 0, modulas, 1], //i
                 )
             } else {
-                panic!("Invalid dom");
+                bail!(StarkyError::MalformedPil(format!("invalid domain {}", dom)));
             }
         }
         "Zi" => Expr::new(
@@ -524,8 +548,12 @@ fn get_ref<F: FieldExtension>(
             vec![],
             vec![0, 0, modulas, 1],
         ),
-        _ => panic!("Invalid reference type get, {}", r.type_),
-    }
+        _ => bail!(StarkyError::MalformedPil(format!(
+            "invalid reference type get {}",
+            r.type_
+        ))),
+    };
+    Ok(expr)
 }
 
 fn eval_map<F: FieldExtension>(
@@ -535,13 +563,13 @@ fn eval_map<F: FieldExtension>(
     prime: bool,
     next: usize,
     modulas: usize,
-) -> Expr<F> {
+) -> Result<Expr<F>> {
     let p = &starkinfo.var_pol_map[pol_id];
     //log::trace!("eval_map: {:?}", p);
     let offset = p.section_pos;
     let size = starkinfo.map_sectionsN.get(&p.section);
     let zero = 0;
-    if p.dim == 1 {
+    let expr = if p.dim == 1 {
         if prime {
             Expr::new(
                 Ops::Refer,
@@ -557,23 +585,27 @@ fn eval_map<F: FieldExtension>(
                 vec![offset, zero, modulas, size],
             )
         }
-    } else if p.dim == 3 {
+    } else if p.dim == starkinfo.ext_dim {
         if prime {
             Expr::new(
                 Ops::Refer,
-                vec![p.section.clone(), "3".to_string()],
+                vec![p.section.clone(), p.dim.to_string()],
                 vec![],
                 vec![offset, next, modulas, size],
             )
         } else {
             Expr::new(
                 Ops::Refer,
-                vec![p.section.clone(), "3".to_string()],
+                vec![p.section.clone(), p.dim.to_string()],
                 vec![],
                 vec![offset, zero, modulas, size],
             )
         }
     } else {
-        panic!("Invalid dim {}", p.dim);
-    }
+        bail!(StarkyError::MalformedPil(format!(
+            "invalid dim {} of {}",
+            p.dim, p.section
+        )));
+    };
+    Ok(expr)
 }
@@ -37,6 +37,8 @@ pub mod merklehash;
 pub mod merklehash_bls12381;
 
 pub mod merklehash_bn128;
+pub mod merklehash_ext;
+pub mod merklehash_keccak;
 
 mod digest;
 pub use digest::ElementDigest;
@@ -51,8 +53,10 @@ mod fft_worker;
 mod fri;
 pub mod helper;
 mod interpreter;
+pub mod memory_plan;
 pub mod stark_gen;
 pub mod stark_setup;
+pub mod stark_verifier_solidity;
 pub mod stark_verify;
 pub mod starkinfo;
 mod starkinfo_Z;
@@ -65,13 +69,20 @@ mod starkinfo_map;
 pub mod transcript;
 pub mod transcript_bls12381;
 pub mod transcript_bn128;
+pub mod transcript_keccak;
 
 pub mod pil2circom;
+pub mod pil_verify;
 pub mod prove;
+pub mod security;
 pub mod serializer;
+pub mod serializer_bin;
+pub mod trace;
+pub mod trace_examples;
 pub mod zkin_join;
 
 pub mod dev;
+pub mod errors;
 
 #[macro_use]
 extern crate serde_json;
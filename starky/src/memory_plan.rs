@@ -0,0 +1,326 @@
+#![allow(non_snake_case)]
+//! Estimate the memory `stark_gen` needs before allocating, fit it under a memory limit, and
+//! report the peak usage per stage.
+//!
+//! The prover frees each buffer after its last use whether a limit is given or not. Besides, the
+//! expression evaluation of each step copies its input sections into chunks of rows, one per
+//! thread, and by default the chunks of the whole domain are copied at once. When that is above
+//! the limit, the expressions are evaluated in batches of rows instead, so that only a few rows
+//! of the sections are copied at a time. A proof which does not fit the limit even so fails with
+//! `StarkyError::MemoryLimit` before allocating anything.
+use crate::constant::{get_max_workers, MIN_OPS_PER_THREAD};
+use crate::errors::StarkyError;
+use crate::starkinfo::StarkInfo;
+use crate::traits::{FieldExtension, MerkleTree};
+use crate::types::StarkStruct;
+use anyhow::{bail, Result};
+use fields::field_gl::Fr as FGL;
+use std::mem::size_of;
+
+/// The stages of `stark_gen`, each one ends with a new merkle root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// extend and commit the witness
+    Commit1,
+    /// plookup h1 and h2
+    Commit2,
+    /// grand product polynomials
+    Commit3,
+    /// quotient polynomial
+    Quotient,
+    /// evaluations and FRI
+    Fri,
+}
+
+pub const STAGES: [Stage; 5] = [
+    Stage::Commit1,
+    Stage::Commit2,
+    Stage::Commit3,
+    Stage::Quotient,
+    Stage::Fri,
+];
+
+/// A buffer alive from stage `first` to stage `last`, both included.
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: &'static str,
+    pub bytes: usize,
+    pub first: Stage,
+    pub last: Stage,
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryPlan {
+    pub sections: Vec<Section>,
+    /// The rows the expression evaluation copies at once, the whole domain if `None`.
+    pub exec_rows: Option<usize>,
+}
+
+impl MemoryPlan {
+    /// The buffers are sized by `map_sectionsN`, the n-domain sections are freed after the
+    /// third commitment and the quotient buffers after the fourth one. The copies of the
+    /// expression evaluation take `exec_rows` rows of their sections.
+    pub fn new<M: MerkleTree>(
+        starkinfo: &StarkInfo,
+        stark_struct: &StarkStruct,
+        const_elements: usize,
+        exec_rows: Option<usize>,
+    ) -> Self {
+        let N = 1usize << stark_struct.nBits;
+        let Next = 1usize << stark_struct.nBitsExt;
+        let (exec_n, exec_ext) = match exec_rows {
+            Some(rows) => (rows.min(N), rows.min(Next)),
+            None => (N, Next),
+        };
+        let ef = size_of::<M::ExtendField>();
+        let bf = size_of::<FGL>();
+        let sections = &starkinfo.map_sectionsN;
+        let tree = |w: usize| w * Next * bf + 2 * Next * size_of::<M::MTNode>();
+
+        let (cm1, cm2, cm3, cm4) = (
+            sections.cm1_n,
+            sections.cm2_n,
+            sections.cm3_n,
+            sections.cm4_n,
+        );
+        let tmpexp = sections.tmpexp_n;
+        let n_const = starkinfo.n_constants;
+        let q_dim = starkinfo.q_dim;
+        let dim = M::ExtendField::DIM;
+
+        use Stage::*;
+        let mut plan = MemoryPlan {
+            sections: vec![],
+            exec_rows,
+        };
+        let mut add = |name: &'static str, bytes: usize, first: Stage, last: Stage| {
+            plan.sections.push(Section {
+                name,
+                bytes,
+                first,
+                last,
+            });
+        };
+
+        add("const_2ns", const_elements * ef, Commit1, Fri);
+        add("cm1_n", cm1 * N * ef, Commit1, Commit3);
+        add("const_n", n_const * N * ef, Commit1, Commit3);
+        add("x_n", N * ef, Commit1, Commit3);
+        add(
+            "extend_buffer",
+            *[8, cm1, cm2, cm3].iter().max().unwrap() * Next * ef,
+            Commit1,
+            Commit3,
+        );
+        add("cm1_2ns", cm1 * Next * ef, Commit1, Fri);
+        add("tree1", tree(cm1), Commit1, Fri);
+
+        add("cm2_n", cm2 * N * ef, Commit2, Commit3);
+        add("cm3_n", cm3 * N * ef, Commit2, Commit3);
+        add("tmpexp_n", tmpexp * N * ef, Commit2, Commit3);
+        add(
+            "step2prev",
+            (cm1 + n_const + cm2 + cm3 + tmpexp) * exec_n * ef,
+            Commit2,
+            Commit2,
+        );
+        add("cm2_2ns", cm2 * Next * ef, Commit2, Fri);
+        add("tree2", tree(cm2), Commit2, Fri);
+
+        add(
+            "step3",
+            (cm1 + cm2 + 2 * cm3 + n_const + 1 + tmpexp) * exec_n * ef,
+            Commit3,
+            Commit3,
+        );
+        add("cm3_2ns", cm3 * Next * ef, Commit3, Fri);
+        add("tree3", tree(cm3), Commit3, Fri);
+
+        add("x_2ns", Next * ef, Quotient, Quotient);
+        add("q_2ns", q_dim * Next * ef, Quotient, Quotient);
+        add(
+            "step4",
+            (cm1 + cm2 + cm3 + n_const + 1 + q_dim) * exec_ext * ef,
+            Quotient,
+            Quotient,
+        );
+        add(
+            "qq",
+            q_dim * Next * (starkinfo.q_deg + 1) * ef,
+            Quotient,
+            Quotient,
+        );
+        add("cm4_2ns", cm4 * Next * ef, Quotient, Fri);
+        add("tree4", tree(cm4), Quotient, Fri);
+
+        add("xDivXSub", 2 * dim * Next * bf, Fri, Fri);
+        add("f_2ns", dim * Next * ef, Fri, Fri);
+        add(
+            "step5",
+            (cm1 + cm2 + cm3 + cm4 + n_const + dim) * exec_ext * ef + 2 * dim * exec_ext * bf,
+            Fri,
+            Fri,
+        );
+        // the fri polynomial and its first folded layer
+        add("fri_pol", 2 * Next * ef, Fri, Fri);
+        plan
+    }
+
+    /// The plan within `limit` bytes, evaluating the expressions over the whole domain at once
+    /// if that fits, or in batches of the fewest rows that keep every thread busy otherwise.
+    pub fn fit<M: MerkleTree>(
+        starkinfo: &StarkInfo,
+        stark_struct: &StarkStruct,
+        const_elements: usize,
+        limit: Option<usize>,
+    ) -> Result<Self> {
+        let plan = Self::new::<M>(starkinfo, stark_struct, const_elements, None);
+        match limit {
+            Some(limit) if plan.peak().1 > limit => {
+                let rows = get_max_workers().max(1) * MIN_OPS_PER_THREAD;
+                let batched = Self::new::<M>(starkinfo, stark_struct, const_elements, Some(rows));
+                batched.check(Some(limit))?;
+                log::info!(
+                    "evaluating the expressions in batches of {} rows to fit the memory limit",
+                    rows
+                );
+                Ok(batched)
+            }
+            _ => Ok(plan),
+        }
+    }
+
+    /// The bytes alive during `stage`.
+    pub fn stage_bytes(&self, stage: Stage) -> usize {
+        self.sections
+            .iter()
+            .filter(|s| s.first <= stage && stage <= s.last)
+            .map(|s| s.bytes)
+            .sum()
+    }
+
+    /// The largest stage.
+    pub fn peak(&self) -> (Stage, usize) {
+        STAGES
+            .iter()
+            .map(|s| (*s, self.stage_bytes(*s)))
+            .max_by_key(|(_, bytes)| *bytes)
+            .unwrap()
+    }
+
+    /// Fail if the peak estimate is above `limit` bytes.
+    pub fn check(&self, limit: Option<usize>) -> Result<()> {
+        let (stage, peak) = self.peak();
+        match limit {
+            Some(limit) if peak > limit => bail!(StarkyError::MemoryLimit(format!(
+                "stage {:?} needs about {}, above the limit of {}",
+                stage,
+                to_mib(peak),
+                to_mib(limit)
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn report(&self) {
+        let (stage, peak) = self.peak();
+        log::info!(
+            "estimated prover memory {}, at stage {:?}",
+            to_mib(peak),
+            stage
+        );
+        for s in STAGES {
+            log::debug!("stage {:?}: {}", s, to_mib(self.stage_bytes(s)));
+        }
+    }
+
+    /// Log the estimate of `stage` and the peak rss of the process so far.
+    pub fn stage_done(&self, stage: Stage) {
+        let planned = to_mib(self.stage_bytes(stage));
+        match peak_rss() {
+            Some(peak) => log::info!(
+                "stage {:?}: estimated {}, peak rss so far {}",
+                stage,
+                planned,
+                to_mib(peak)
+            ),
+            None => log::info!("stage {:?}: estimated {}", stage, planned),
+        }
+    }
+}
+
+fn to_mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+}
+
+/// The peak resident set size of the process in bytes, only available on Linux.
+pub fn peak_rss() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merklehash::MerkleTreeGL;
+    use crate::polsarray::{PolKind, PolsArray};
+    use crate::stark_setup::StarkSetup;
+    use crate::types::{load_json, PIL};
+
+    #[test]
+    fn test_memory_plan() {
+        let mut pil = load_json::<PIL>("data/fib.pil.json").unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load("data/fib.const").unwrap();
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json").unwrap();
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+
+        let plan = MemoryPlan::new::<MerkleTreeGL>(
+            &setup.starkinfo,
+            &stark_struct,
+            setup.const_tree.element_size(),
+            None,
+        );
+        let (_, peak) = plan.peak();
+        assert!(peak > 0);
+        // the committed trace and its extension are alive from the first stage to the last
+        let cm1_n = setup.starkinfo.map_sectionsN.cm1_n;
+        assert!(plan.stage_bytes(Stage::Fri) >= cm1_n * (1 << stark_struct.nBitsExt) * 24);
+
+        plan.check(None).unwrap();
+        plan.check(Some(peak)).unwrap();
+        let err = plan.check(Some(peak - 1)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MemoryLimit(_))
+        ));
+
+        // the copies of the expression evaluation shrink with the rows evaluated at once
+        let batched = MemoryPlan::new::<MerkleTreeGL>(
+            &setup.starkinfo,
+            &stark_struct,
+            setup.const_tree.element_size(),
+            Some(1),
+        );
+        assert!(batched.peak().1 < peak);
+
+        let fit = |limit| {
+            MemoryPlan::fit::<MerkleTreeGL>(
+                &setup.starkinfo,
+                &stark_struct,
+                setup.const_tree.element_size(),
+                limit,
+            )
+        };
+        assert_eq!(fit(None).unwrap().exec_rows, None);
+        assert_eq!(fit(Some(peak)).unwrap().exec_rows, None);
+        let err = fit(Some(batched.peak().1 - 1)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MemoryLimit(_))
+        ));
+    }
+}
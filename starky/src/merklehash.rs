@@ -193,6 +193,9 @@ impl MerkleTreeGL {
         if mp.len() == offset {
             return Ok(*value);
         }
+        if mp[offset].len() < 4 {
+            bail!("MerkleTreeError: invalid sibling");
+        }
         let cur_idx = idx & 1;
         let next_idx = idx / 2;
         let init = [FGL::ZERO; 4];
@@ -254,6 +257,7 @@ impl MerkleTree for MerkleTreeGL {
     type BaseField = FGL;
     type MTNode = ElementDigest<4, FGL>;
     type ExtendField = F3G;
+    const HASH_TYPE: &'static str = "GL";
     fn new() -> Self {
         Self {
             nodes: Vec::new(),
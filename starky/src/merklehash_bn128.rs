@@ -120,12 +120,14 @@ impl MerkleTreeBN128 {
         if mp.len() == offset {
             return Ok(*value);
         }
-        //let cur_idx = idx & 0xF;
-        let next_idx = idx >> 4;
-        let mut vals: Vec<Fr> = vec![];
-        for i in 0..16 {
-            vals.push(mp[offset][i]);
+        if mp[offset].len() != 16 {
+            bail!("MerkleTreeError: invalid sibling");
         }
+        let cur_idx = idx & 0xF;
+        let next_idx = idx >> 4;
+        // the siblings hold the node itself, which is the value computed from the proof
+        let mut vals: Vec<Fr> = mp[offset].clone();
+        vals[cur_idx] = Fr(value.as_scalar::<Fr>());
         let init = Fr::zero();
         let next_value = self.poseidon.hash(&vals, &init)?;
         let next_value = <Self as MerkleTree>::MTNode::from_scalar(&next_value);
@@ -148,6 +150,7 @@ impl MerkleTree for MerkleTreeBN128 {
     type MTNode = ElementDigest<4, Fr>;
     type ExtendField = F3G;
 
+    const HASH_TYPE: &'static str = "BN128";
     fn new() -> Self {
         Self {
             nodes: Vec::new(),
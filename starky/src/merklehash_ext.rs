@@ -0,0 +1,119 @@
+//! Lift a merkle tree to another extension field of the Goldilocks field.
+//!
+//! The trees commit to the base field elements only, the extension field is the one the prover
+//! evaluates the polynomials in, so `MerkleTreeExt<MerkleTreeGL, F5G>` is the GL tree of the
+//! prover over the quintic extension.
+use crate::f3g::F3G;
+use crate::traits::{FieldExtension, MerkleTree};
+use anyhow::Result;
+use fields::field_gl::Fr as FGL;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MerkleTreeExt<M, F> {
+    pub tree: M,
+    #[serde(skip)]
+    _extension: PhantomData<F>,
+}
+
+impl<M: MerkleTree<ExtendField = F3G>, F: FieldExtension> MerkleTree for MerkleTreeExt<M, F> {
+    type BaseField = M::BaseField;
+    type MTNode = M::MTNode;
+    type ExtendField = F;
+    const HASH_TYPE: &'static str = M::HASH_TYPE;
+
+    fn new() -> Self {
+        Self {
+            tree: M::new(),
+            _extension: PhantomData,
+        }
+    }
+
+    fn to_extend(&self, p_be: &mut Vec<F>) {
+        let mut elements = vec![F3G::ZERO; p_be.len()];
+        self.tree.to_extend(&mut elements);
+        p_be.par_iter_mut().zip(elements).for_each(|(be_out, e)| {
+            *be_out = F::from(e.to_be());
+        });
+    }
+
+    fn to_basefield(node: &Self::MTNode) -> Vec<Self::BaseField> {
+        M::to_basefield(node)
+    }
+
+    fn from_basefield(node: &Self::BaseField) -> Self::MTNode {
+        M::from_basefield(node)
+    }
+
+    fn merkelize(&mut self, buff: Vec<FGL>, width: usize, height: usize) -> Result<()> {
+        self.tree.merkelize(buff, width, height)
+    }
+
+    fn get_element(&self, idx: usize, sub_idx: usize) -> FGL {
+        self.tree.get_element(idx, sub_idx)
+    }
+
+    fn get_group_proof(&self, idx: usize) -> Result<(Vec<FGL>, Vec<Vec<Self::BaseField>>)> {
+        self.tree.get_group_proof(idx)
+    }
+
+    fn verify_group_proof(
+        &self,
+        root: &Self::MTNode,
+        mp: &[Vec<Self::BaseField>],
+        idx: usize,
+        group_elements: &[FGL],
+    ) -> Result<bool> {
+        self.tree.verify_group_proof(root, mp, idx, group_elements)
+    }
+
+    fn root(&self) -> Self::MTNode {
+        self.tree.root()
+    }
+
+    fn eq_root(&self, r1: &Self::MTNode, r2: &Self::MTNode) -> bool {
+        self.tree.eq_root(r1, r2)
+    }
+
+    fn element_size(&self) -> usize {
+        self.tree.element_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::f5g::F5G;
+    use crate::merklehash::MerkleTreeGL;
+
+    #[test]
+    fn test_merklehash_ext() {
+        let (width, height) = (3, 16);
+        let buff = (0..(width * height) as u64)
+            .map(FGL::from)
+            .collect::<Vec<FGL>>();
+        let mut tree = MerkleTreeGL::new();
+        tree.merkelize(buff.clone(), width, height).unwrap();
+        let mut ext_tree = MerkleTreeExt::<MerkleTreeGL, F5G>::new();
+        ext_tree.merkelize(buff.clone(), width, height).unwrap();
+        assert_eq!(ext_tree.root(), tree.root());
+
+        let mut p_be = vec![F5G::ZERO; ext_tree.element_size()];
+        ext_tree.to_extend(&mut p_be);
+        assert_eq!(p_be, buff.into_iter().map(F5G::from).collect::<Vec<_>>());
+
+        let (vals, mp) = ext_tree.get_group_proof(5).unwrap();
+        assert!(ext_tree
+            .verify_group_proof(&tree.root(), &mp, 5, &vals)
+            .unwrap());
+
+        // the same json as the wrapped tree
+        let json = serde_json::to_string(&ext_tree).unwrap();
+        assert_eq!(json, serde_json::to_string(&tree).unwrap());
+        let de: MerkleTreeExt<MerkleTreeGL, F5G> = serde_json::from_str(&json).unwrap();
+        assert_eq!(de, ext_tree);
+    }
+}
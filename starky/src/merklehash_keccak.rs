@@ -0,0 +1,277 @@
+#![allow(dead_code)]
+//! Binary merkle tree over Keccak-256 for the proofs verified by the EVM directly.
+//!
+//! A leaf is `keccak256` of the big-endian `uint64` row values, a node is `keccak256` of its
+//! two children. The 32-byte digest is kept as 4 big-endian `uint64` words, each reduced by
+//! the Goldilocks modulus, so it can be absorbed by the transcript as field elements.
+use crate::digest::ElementDigest;
+use crate::f3g::F3G;
+use crate::traits::MTNodeType;
+use crate::traits::MerkleTree;
+use anyhow::{bail, Result};
+use fields::field_gl::Fr as FGL;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tiny_keccak::{Hasher, Keccak};
+
+/// The Goldilocks modulus
+const GL_MODULUS: u64 = 0xFFFF_FFFF_0000_0001;
+
+#[derive(Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MerkleTreeKeccak {
+    pub elements: Vec<FGL>,
+    pub width: usize,
+    pub height: usize,
+    pub nodes: Vec<ElementDigest<4, FGL>>,
+}
+
+fn get_n_nodes(n_: usize) -> usize {
+    let mut n = n_;
+    let mut next_n = (n - 1) / 2 + 1;
+    let mut acc = next_n * 2;
+    while n > 1 {
+        n = next_n;
+        next_n = (n - 1) / 2 + 1;
+        if n > 1 {
+            acc += next_n * 2;
+        } else {
+            acc += 1;
+        }
+    }
+    acc
+}
+
+/// Split the digest into 4 big-endian words reduced to Goldilocks elements.
+pub fn digest_to_node(digest: &[u8; 32]) -> ElementDigest<4, FGL> {
+    let mut elems = [FGL::ZERO; 4];
+    for (i, e) in elems.iter_mut().enumerate() {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&digest[i * 8..(i + 1) * 8]);
+        let w = u64::from_be_bytes(word);
+        *e = FGL::from(if w >= GL_MODULUS { w - GL_MODULUS } else { w });
+    }
+    ElementDigest::new(&elems)
+}
+
+pub fn node_to_bytes(node: &ElementDigest<4, FGL>) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, e) in node.as_elements().iter().enumerate() {
+        bytes[i * 8..(i + 1) * 8].copy_from_slice(&e.as_int().to_be_bytes());
+    }
+    bytes
+}
+
+pub fn hash_leaf(values: &[FGL]) -> ElementDigest<4, FGL> {
+    let mut keccak = Keccak::v256();
+    for v in values {
+        keccak.update(&v.as_int().to_be_bytes());
+    }
+    let mut digest = [0u8; 32];
+    keccak.finalize(&mut digest);
+    digest_to_node(&digest)
+}
+
+pub fn hash_node(
+    left: &ElementDigest<4, FGL>,
+    right: &ElementDigest<4, FGL>,
+) -> ElementDigest<4, FGL> {
+    let mut keccak = Keccak::v256();
+    keccak.update(&node_to_bytes(left));
+    keccak.update(&node_to_bytes(right));
+    let mut digest = [0u8; 32];
+    keccak.finalize(&mut digest);
+    digest_to_node(&digest)
+}
+
+impl MerkleTreeKeccak {
+    fn merkle_gen_merkle_proof(&self, idx: usize, offset: usize, n: usize) -> Vec<Vec<FGL>> {
+        if n <= 1 {
+            return vec![];
+        }
+        let next_idx = idx >> 1;
+        let si = idx ^ 1;
+        let sib = self.nodes[offset + si].as_elements().to_vec();
+
+        let next_n = (n - 1) / 2 + 1;
+        let mut result = vec![sib];
+        result.append(&mut self.merkle_gen_merkle_proof(next_idx, offset + next_n * 2, next_n));
+        result
+    }
+
+    fn merklize_level(&mut self, p_in: usize, n_ops: usize, p_out: usize) {
+        let (low, high) = self.nodes.split_at_mut(p_out);
+        let buff = &low[p_in..(p_in + n_ops * 2)];
+        high[..n_ops]
+            .par_iter_mut()
+            .zip(buff.par_chunks(2))
+            .for_each(|(out, pair)| *out = hash_node(&pair[0], &pair[1]));
+    }
+
+    fn calculate_root_from_group_proof(
+        &self,
+        mp: &[Vec<FGL>],
+        idx: usize,
+        vals: &[FGL],
+    ) -> Result<ElementDigest<4, FGL>> {
+        let mut value = hash_leaf(vals);
+        let mut idx = idx;
+        for sibling in mp.iter() {
+            if sibling.len() < 4 {
+                bail!("MerkleTreeError: invalid sibling");
+            }
+            let sibling = ElementDigest::<4, FGL>::new(sibling);
+            value = if idx & 1 == 0 {
+                hash_node(&value, &sibling)
+            } else {
+                hash_node(&sibling, &value)
+            };
+            idx >>= 1;
+        }
+        Ok(value)
+    }
+}
+
+impl MerkleTree for MerkleTreeKeccak {
+    type BaseField = FGL;
+    type MTNode = ElementDigest<4, FGL>;
+    type ExtendField = F3G;
+    const HASH_TYPE: &'static str = "KECCAK";
+    fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            elements: Vec::new(),
+            width: 0,
+            height: 0,
+        }
+    }
+
+    fn element_size(&self) -> usize {
+        self.elements.len()
+    }
+
+    fn to_extend(&self, p_be: &mut Vec<F3G>) {
+        assert_eq!(p_be.len(), self.elements.len());
+        p_be.par_iter_mut()
+            .zip(&self.elements)
+            .for_each(|(be_out, f3g_in)| {
+                *be_out = F3G::from(*f3g_in);
+            });
+    }
+
+    // the same layout as the GL tree, so the proofs are serialized in the same way
+    fn to_basefield(node: &Self::MTNode) -> Vec<Self::BaseField> {
+        vec![node.as_elements().to_vec()[0]]
+    }
+
+    fn from_basefield(node: &FGL) -> Self::MTNode {
+        Self::MTNode::new(&[*node, FGL::ZERO, FGL::ZERO, FGL::ZERO])
+    }
+
+    fn merkelize(&mut self, buff: Vec<FGL>, width: usize, height: usize) -> Result<()> {
+        let mut nodes = vec![Self::MTNode::default(); get_n_nodes(height)];
+        if !buff.is_empty() {
+            nodes[..height]
+                .par_iter_mut()
+                .zip(buff.par_chunks(width))
+                .for_each(|(out, row)| *out = hash_leaf(row));
+        }
+
+        self.nodes = nodes;
+        self.elements = buff;
+        self.width = width;
+        self.height = height;
+
+        let mut n64: usize = height;
+        let mut next_n64: usize = (n64 - 1) / 2 + 1;
+        let mut p_in: usize = 0;
+        let mut p_out: usize = p_in + next_n64 * 2;
+        while n64 > 1 {
+            self.merklize_level(p_in, next_n64, p_out);
+            n64 = next_n64;
+            next_n64 = (n64 - 1) / 2 + 1;
+            p_in = p_out;
+            p_out = p_in + next_n64 * 2;
+        }
+        Ok(())
+    }
+
+    fn get_element(&self, idx: usize, sub_idx: usize) -> FGL {
+        self.elements[self.width * idx + sub_idx]
+    }
+
+    fn get_group_proof(&self, idx: usize) -> Result<(Vec<FGL>, Vec<Vec<FGL>>)> {
+        if idx >= self.height {
+            bail!("MerkleTreeError: access invalid node");
+        }
+
+        let v = (0..self.width)
+            .map(|i| self.get_element(idx, i))
+            .collect::<Vec<_>>();
+        let mp = self.merkle_gen_merkle_proof(idx, 0, self.height);
+        Ok((v, mp))
+    }
+
+    fn eq_root(&self, r1: &Self::MTNode, r2: &Self::MTNode) -> bool {
+        r1 == r2
+    }
+
+    fn verify_group_proof(
+        &self,
+        root: &Self::MTNode,
+        mp: &[Vec<FGL>],
+        idx: usize,
+        group_elements: &[FGL],
+    ) -> Result<bool> {
+        let c_root = self.calculate_root_from_group_proof(mp, idx, group_elements)?;
+        Ok(self.eq_root(root, &c_root))
+    }
+
+    fn root(&self) -> Self::MTNode {
+        self.nodes[self.nodes.len() - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(n: usize, n_pols: usize) -> MerkleTreeKeccak {
+        let mut pols: Vec<FGL> = vec![FGL::ZERO; n_pols * n];
+        for i in 0..n {
+            for j in 0..n_pols {
+                pols[i * n_pols + j] = FGL::from((i + j * 1000) as u64);
+            }
+        }
+        let mut tree = MerkleTreeKeccak::new();
+        tree.merkelize(pols, n_pols, n).unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_merklehash_keccak() {
+        // keccak256 of the empty string
+        let node = hash_leaf(&[]);
+        assert_eq!(
+            node_to_bytes(&node)[..8],
+            [0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c]
+        );
+
+        for (n, idx) in [(256, 3), (33, 32)] {
+            let tree = build(n, 9);
+            let (group_elements, mp) = tree.get_group_proof(idx).unwrap();
+            let root = tree.root();
+            assert!(tree
+                .verify_group_proof(&root, &mp, idx, &group_elements)
+                .unwrap());
+
+            let mut tampered = group_elements.clone();
+            tampered[0] = tampered[0] + FGL::ONE;
+            assert!(!tree.verify_group_proof(&root, &mp, idx, &tampered).unwrap());
+        }
+
+        // the root of two leaves is the hash of both
+        let tree = build(2, 3);
+        let expected = hash_node(&tree.nodes[0], &tree.nodes[1]);
+        assert_eq!(tree.root(), expected);
+    }
+}
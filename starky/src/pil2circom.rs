@@ -1,8 +1,9 @@
 use crate::digest::ElementDigest;
+use crate::errors::StarkyError;
 use crate::starkinfo::Program;
 use crate::starkinfo::StarkInfo;
 use crate::types::{StarkStruct, PIL};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use profiler_macro::time_profiler;
 
 /// The option to control the generation of recursive verifier
@@ -11,8 +12,8 @@ pub struct StarkOption {
     pub enable_input: bool,
     // normalize the proof
     pub verkey_input: bool,
-    // aggragte the proof
-    pub agg_stage: bool,
+    // aggragte the proofs, the number of inputs or 0
+    pub agg_inputs: usize,
     // generate the main component in Circom
     pub skip_main: bool,
 }
@@ -26,6 +27,27 @@ pub fn pil2circom<F: ff::PrimeField + Default>(
     program: &mut Program,
     options: &StarkOption,
 ) -> Result<String> {
+    // the circom templates are written for the cubic extension only
+    if stark_struct.extensionDegree != 3 {
+        bail!(StarkyError::MalformedInput(format!(
+            "the circom verifier only supports the extension degree 3, got {}",
+            stark_struct.extensionDegree
+        )));
+    }
+    // the inputs are prefixed by `zkin_prefix`, a_ to z_
+    if options.agg_inputs == 1 || options.agg_inputs > 26 {
+        bail!(StarkyError::MalformedInput(format!(
+            "the aggregation stage verifies 2 to 26 proofs, got {}",
+            options.agg_inputs
+        )));
+    }
+    // the aggregated proofs publish the rootC they verify last
+    if options.agg_inputs > 0 && pil.publics.len() < 4 {
+        bail!(StarkyError::MalformedInput(format!(
+            "the aggregated proofs publish their rootC, got {} publics",
+            pil.publics.len()
+        )));
+    }
     starkinfo.set_code_dimensions_first(&mut program.verifier_code)?;
     starkinfo.set_code_dimensions_first(&mut program.verifier_query_code)?;
     let res = match stark_struct.verificationHashType.as_str() {
@@ -45,7 +67,9 @@ pub fn pil2circom<F: ff::PrimeField + Default>(
             const_root,
             options,
         ),
-        _ => panic!("Invalid hash type: {}", stark_struct.verificationHashType),
+        _ => bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType.clone()
+        )),
     };
     Ok(res)
 }
@@ -0,0 +1,532 @@
+//! Check the identities of the PIL directly over the traces, like `pil_verifier.js` of pil-stark,
+//! so that a wrong trace is reported by identity and row instead of an invalid proof.
+use crate::constant::MG;
+use crate::errors::StarkyError;
+use crate::helper::get_ks;
+use crate::polsarray::{PolKind, PolsArray};
+use crate::types::{load_json, parse_pil_number, Expression, PIL};
+use anyhow::{anyhow, bail, ensure, Result};
+use fields::field_gl::Fr as FGL;
+use std::collections::HashMap;
+use std::fmt;
+
+/// the failing rows reported for each identity
+const MAX_REPORTED_ROWS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityKind {
+    Polynomial,
+    Plookup,
+    Permutation,
+    Connection,
+}
+
+impl fmt::Display for IdentityKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            IdentityKind::Polynomial => "polIdentities",
+            IdentityKind::Plookup => "plookupIdentities",
+            IdentityKind::Permutation => "permutationIdentities",
+            IdentityKind::Connection => "connectionIdentities",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// an identity of the PIL which does not hold over the traces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityFailure {
+    pub kind: IdentityKind,
+    /// the index in the identities of its kind
+    pub index: usize,
+    pub file_name: String,
+    pub line: usize,
+    /// the first failing rows, at most `MAX_REPORTED_ROWS`
+    pub rows: Vec<usize>,
+    pub n_rows: usize,
+    /// what is wrong on the first failing row
+    pub detail: String,
+}
+
+impl fmt::Display for IdentityFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}] fails on {} rows {:?}{}, {}",
+            self.file_name,
+            self.line,
+            self.kind,
+            self.index,
+            self.n_rows,
+            self.rows,
+            if self.n_rows > self.rows.len() {
+                " ..."
+            } else {
+                ""
+            },
+            self.detail
+        )
+    }
+}
+
+impl IdentityFailure {
+    fn new(kind: IdentityKind, index: usize, file_name: &str, line: usize, detail: String) -> Self {
+        IdentityFailure {
+            kind,
+            index,
+            file_name: file_name.to_string(),
+            line,
+            rows: vec![],
+            n_rows: 0,
+            detail,
+        }
+    }
+
+    fn add_row(&mut self, row: usize) {
+        if self.rows.len() < MAX_REPORTED_ROWS {
+            self.rows.push(row);
+        }
+        self.n_rows += 1;
+    }
+}
+
+/// evaluate the expressions over all the rows, the referenced expressions are cached
+struct Evaluator<'a> {
+    pil: &'a PIL,
+    cm_pols: &'a PolsArray,
+    const_pols: &'a PolsArray,
+    publics: Vec<FGL>,
+    n: usize,
+    cache: HashMap<usize, Vec<FGL>>,
+}
+
+impl<'a> Evaluator<'a> {
+    fn exp(&mut self, id: usize) -> Result<Vec<FGL>> {
+        if let Some(values) = self.cache.get(&id) {
+            return Ok(values.clone());
+        }
+        let pil = self.pil;
+        let exp = pil
+            .expressions
+            .get(id)
+            .ok_or_else(|| anyhow!("Unknown expression {}", id))?;
+        let values = self.eval(exp)?;
+        self.cache.insert(id, values.clone());
+        Ok(values)
+    }
+
+    fn operand(&mut self, exp: &Expression, i: usize) -> Result<Vec<FGL>> {
+        match exp.values.as_ref().and_then(|v| v.get(i)) {
+            Some(e) => self.eval(e),
+            None => bail!("Missing the operand of {}", exp.op),
+        }
+    }
+
+    fn eval(&mut self, exp: &Expression) -> Result<Vec<FGL>> {
+        let values = match exp.op.as_str() {
+            "add" | "sub" | "mul" => {
+                let a = self.operand(exp, 0)?;
+                let b = self.operand(exp, 1)?;
+                a.iter()
+                    .zip(b.iter())
+                    .map(|(a, b)| match exp.op.as_str() {
+                        "add" => *a + *b,
+                        "sub" => *a - *b,
+                        _ => *a * *b,
+                    })
+                    .collect()
+            }
+            "addc" | "mulc" => {
+                let c = exp
+                    .const_
+                    .ok_or_else(|| anyhow!("Missing the constant of {}", exp.op))?;
+                let c = FGL::from(parse_pil_number(&c.to_string())?);
+                let a = self.operand(exp, 0)?;
+                a.iter()
+                    .map(|a| if exp.op == "addc" { *a + c } else { *a * c })
+                    .collect()
+            }
+            "neg" => self
+                .operand(exp, 0)?
+                .iter()
+                .map(|a| FGL::ZERO - *a)
+                .collect(),
+            "cm" | "const" | "exp" => {
+                let id = exp
+                    .id
+                    .ok_or_else(|| anyhow!("Missing the id of {}", exp.op))?;
+                let mut values = match exp.op.as_str() {
+                    "cm" => self.pol(self.cm_pols, id)?,
+                    "const" => self.pol(self.const_pols, id)?,
+                    _ => self.exp(id)?,
+                };
+                if exp.next() {
+                    values.rotate_left(1);
+                }
+                values
+            }
+            "number" => {
+                let value = exp
+                    .value
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Missing the value of number"))?;
+                vec![FGL::from(parse_pil_number(value)?); self.n]
+            }
+            "public" => {
+                let id = exp.id.ok_or_else(|| anyhow!("Missing the id of public"))?;
+                let value = *self
+                    .publics
+                    .get(id)
+                    .ok_or_else(|| anyhow!("Unknown public {}", id))?;
+                vec![value; self.n]
+            }
+            op => bail!(StarkyError::MalformedPil(format!(
+                "unsupported expression op {}",
+                op
+            ))),
+        };
+        Ok(values)
+    }
+
+    fn pol(&self, pols: &PolsArray, id: usize) -> Result<Vec<FGL>> {
+        let values = pols
+            .array
+            .get(id)
+            .ok_or_else(|| anyhow!("Unknown polynomial {}", id))?;
+        ensure!(
+            values.len() == self.n,
+            "The polynomial {} has {} rows, expect {}",
+            pols.defArray[id].name,
+            values.len(),
+            self.n
+        );
+        Ok(values.clone())
+    }
+
+    fn exps(&mut self, ids: &Option<Vec<usize>>) -> Result<Vec<Vec<FGL>>> {
+        ids.iter().flatten().map(|id| self.exp(*id)).collect()
+    }
+
+    /// the selector of each row, all the rows are selected if absent
+    fn selector(&mut self, id: Option<usize>) -> Result<Vec<bool>> {
+        Ok(match id {
+            Some(id) => self.exp(id)?.iter().map(|v| *v != FGL::ZERO).collect(),
+            None => vec![true; self.n],
+        })
+    }
+}
+
+fn row_values(columns: &[Vec<FGL>], row: usize) -> Vec<u64> {
+    columns.iter().map(|c| c[row].as_int()).collect()
+}
+
+/// check all the identities of the PIL over the constant and committed polynomials, and return
+/// the identities which fail
+pub fn verify_pil(
+    pil: &PIL,
+    const_pols: &PolsArray,
+    cm_pols: &PolsArray,
+) -> Result<Vec<IdentityFailure>> {
+    let n = cm_pols.n;
+    ensure!(
+        n.is_power_of_two(),
+        "The number of rows must be a power of 2, but got {}",
+        n
+    );
+    let mut ev = Evaluator {
+        pil,
+        cm_pols,
+        const_pols,
+        publics: vec![],
+        n,
+        cache: HashMap::new(),
+    };
+    for public in pil.publics.iter() {
+        ensure!(
+            public.idx < n,
+            "The row of public {} is out of range",
+            public.name
+        );
+        let value = match public.polType.as_str() {
+            "cmP" => ev.pol(cm_pols, public.polId)?[public.idx],
+            "imP" => ev.exp(public.polId)?[public.idx],
+            t => bail!(StarkyError::MalformedPil(format!(
+                "unsupported public type {}",
+                t
+            ))),
+        };
+        ev.publics.push(value);
+    }
+
+    let mut failures = vec![];
+    for (i, pi) in pil.polIdentities.iter().enumerate() {
+        let values = ev.exp(pi.e)?;
+        let mut failure = None;
+        for (row, v) in values.iter().enumerate() {
+            if *v != FGL::ZERO {
+                failure
+                    .get_or_insert_with(|| {
+                        IdentityFailure::new(
+                            IdentityKind::Polynomial,
+                            i,
+                            &pi.fileName,
+                            pi.line,
+                            format!("the value is {} on row {}", v.as_int(), row),
+                        )
+                    })
+                    .add_row(row);
+            }
+        }
+        failures.extend(failure);
+    }
+
+    for (i, pi) in pil.plookupIdentities.iter().enumerate() {
+        let f = ev.exps(&pi.f)?;
+        let t = ev.exps(&pi.t)?;
+        ensure!(f.len() == t.len(), "The plookup {} has different widths", i);
+        let sel_f = ev.selector(pi.selF)?;
+        let sel_t = ev.selector(pi.selT)?;
+        let table: std::collections::HashSet<Vec<u64>> = (0..n)
+            .filter(|row| sel_t[*row])
+            .map(|row| row_values(&t, row))
+            .collect();
+        let mut failure = None;
+        for row in (0..n).filter(|row| sel_f[*row]) {
+            let values = row_values(&f, row);
+            if !table.contains(&values) {
+                failure
+                    .get_or_insert_with(|| {
+                        IdentityFailure::new(
+                            IdentityKind::Plookup,
+                            i,
+                            &pi.fileName,
+                            pi.line,
+                            format!("{:?} on row {} is not found", values, row),
+                        )
+                    })
+                    .add_row(row);
+            }
+        }
+        failures.extend(failure);
+    }
+
+    for (i, pi) in pil.permutationIdentities.iter().flatten().enumerate() {
+        let f = ev.exps(&pi.f)?;
+        let t = ev.exps(&pi.t)?;
+        ensure!(
+            f.len() == t.len(),
+            "The permutation {} has different widths",
+            i
+        );
+        let sel_f = ev.selector(pi.selF)?;
+        let sel_t = ev.selector(pi.selT)?;
+        let mut table: HashMap<Vec<u64>, Vec<usize>> = HashMap::new();
+        for row in (0..n).filter(|row| sel_t[*row]) {
+            table.entry(row_values(&t, row)).or_default().push(row);
+        }
+        let mut failure = None;
+        for row in (0..n).filter(|row| sel_f[*row]) {
+            let values = row_values(&f, row);
+            if table.get_mut(&values).and_then(|rows| rows.pop()).is_none() {
+                failure
+                    .get_or_insert_with(|| {
+                        IdentityFailure::new(
+                            IdentityKind::Permutation,
+                            i,
+                            &pi.fileName,
+                            pi.line,
+                            format!("{:?} on row {} is not found", values, row),
+                        )
+                    })
+                    .add_row(row);
+            }
+        }
+        failures.extend(failure);
+
+        // the rows of t which are not matched by f
+        let mut remaining: Vec<usize> = table.into_values().flatten().collect();
+        remaining.sort_unstable();
+        if let Some(first) = remaining.first() {
+            let mut failure = IdentityFailure::new(
+                IdentityKind::Permutation,
+                i,
+                &pi.fileName,
+                pi.line,
+                format!(
+                    "{:?} on row {} of t is not matched",
+                    row_values(&t, *first),
+                    first
+                ),
+            );
+            for row in remaining {
+                failure.add_row(row);
+            }
+            failures.push(failure);
+        }
+    }
+
+    let n_bits = n.trailing_zeros() as usize;
+    for (i, ci) in pil.connectionIdentities.iter().flatten().enumerate() {
+        let pols = ev.exps(&ci.pols)?;
+        let connections = ev.exps(&ci.connections)?;
+        ensure!(
+            pols.len() == connections.len(),
+            "The connection {} has different widths",
+            i
+        );
+        // k_j * w^row -> (j, row)
+        let ks = if pols.len() > 1 {
+            get_ks(pols.len() - 1)
+        } else {
+            vec![]
+        };
+        let w = MG.0[n_bits];
+        let mut positions: HashMap<u64, (usize, usize)> = HashMap::new();
+        let mut x = FGL::ONE;
+        for row in 0..n {
+            positions.insert(x.as_int(), (0, row));
+            for (j, k) in ks.iter().enumerate() {
+                positions.insert((x * *k).as_int(), (j + 1, row));
+            }
+            x = x * w;
+        }
+
+        let mut failure = None;
+        for row in 0..n {
+            for j in 0..pols.len() {
+                let detail = match positions.get(&connections[j][row].as_int()) {
+                    None => Some(format!(
+                        "the connection of pols[{}] on row {} is invalid",
+                        j, row
+                    )),
+                    Some((cp, cw)) if pols[j][row] != pols[*cp][*cw] => Some(format!(
+                        "pols[{}] on row {} is {}, but pols[{}] on row {} is {}",
+                        j,
+                        row,
+                        pols[j][row].as_int(),
+                        cp,
+                        cw,
+                        pols[*cp][*cw].as_int()
+                    )),
+                    _ => None,
+                };
+                if let Some(detail) = detail {
+                    failure
+                        .get_or_insert_with(|| {
+                            IdentityFailure::new(
+                                IdentityKind::Connection,
+                                i,
+                                &ci.fileName,
+                                ci.line,
+                                detail,
+                            )
+                        })
+                        .add_row(row);
+                    break;
+                }
+            }
+        }
+        failures.extend(failure);
+    }
+
+    Ok(failures)
+}
+
+/// Check the committed polynomials against the PIL before proving, and report every failing
+/// identity with its source line and rows.
+pub fn pil_verify(pil_file: &str, const_pol_file: &str, cm_pol_file: &str) -> Result<()> {
+    let pil = load_json::<PIL>(pil_file)?;
+    let mut const_pols = PolsArray::new(&pil, PolKind::Constant);
+    const_pols.load(const_pol_file)?;
+    let mut cm_pols = PolsArray::new(&pil, PolKind::Commit);
+    cm_pols.load(cm_pol_file)?;
+
+    let failures = verify_pil(&pil, &const_pols, &cm_pols)?;
+    if !failures.is_empty() {
+        let report = failures
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        bail!(StarkyError::UnsatisfiedIdentities(format!(
+            "{} identities fail:\n{}",
+            failures.len(),
+            report
+        )));
+    }
+    log::info!("pil_verify passed, {} rows", cm_pols.n);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str) -> (PIL, PolsArray, PolsArray) {
+        let pil = load_json::<PIL>(&format!("data/{}.pil.json", name)).unwrap();
+        let mut const_pols = PolsArray::new(&pil, PolKind::Constant);
+        const_pols.load(&format!("data/{}.const", name)).unwrap();
+        let mut cm_pols = PolsArray::new(&pil, PolKind::Commit);
+        cm_pols.load(&format!("data/{}.cm", name)).unwrap();
+        (pil, const_pols, cm_pols)
+    }
+
+    #[test]
+    fn test_pil_verify_valid_traces() {
+        for name in ["fib", "pe", "plookup", "connection"] {
+            let (pil, const_pols, cm_pols) = load(name);
+            let failures = verify_pil(&pil, &const_pols, &cm_pols).unwrap();
+            assert!(failures.is_empty(), "{}: {:?}", name, failures);
+        }
+        pil_verify("data/fib.pil.json", "data/fib.const", "data/fib.cm").unwrap();
+    }
+
+    #[test]
+    fn test_pil_verify_reports_rows() {
+        let (pil, const_pols, mut cm_pols) = load("fib");
+        cm_pols.array[0][5] = cm_pols.array[0][5] + FGL::ONE;
+        let failures = verify_pil(&pil, &const_pols, &cm_pols).unwrap();
+        assert!(!failures.is_empty());
+        for f in failures.iter() {
+            assert_eq!(f.kind, IdentityKind::Polynomial);
+            assert_eq!(f.file_name, pil.polIdentities[f.index].fileName);
+            assert_eq!(f.line, pil.polIdentities[f.index].line);
+            // the row itself, or the previous row through the next reference
+            assert!(f.rows.iter().all(|r| *r == 4 || *r == 5), "{}", f);
+        }
+
+        let (pil, const_pols, mut cm_pols) = load("plookup");
+        let f = pil.plookupIdentities[0].f.as_ref().unwrap()[0];
+        let id = pil.expressions[f].id.unwrap();
+        cm_pols.array[id][7] = FGL::from(0xdeadbeefu64);
+        let failures = verify_pil(&pil, &const_pols, &cm_pols).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].kind, IdentityKind::Plookup);
+
+        let (pil, const_pols, mut cm_pols) = load("pe");
+        let f = pil.permutationIdentities.as_ref().unwrap()[0]
+            .f
+            .as_ref()
+            .unwrap()[0];
+        let id = pil.expressions[f].id.unwrap();
+        cm_pols.array[id][4] = FGL::from(0xdeadbeefu64);
+        let failures = verify_pil(&pil, &const_pols, &cm_pols).unwrap();
+        assert_eq!(failures.len(), 2);
+        assert!(failures
+            .iter()
+            .all(|f| f.kind == IdentityKind::Permutation && f.n_rows == 1));
+        assert_eq!(failures[0].rows, vec![4]);
+
+        let (pil, const_pols, mut cm_pols) = load("connection");
+        let p = pil.connectionIdentities.as_ref().unwrap()[0]
+            .pols
+            .as_ref()
+            .unwrap()[0];
+        let id = pil.expressions[p].id.unwrap();
+        cm_pols.array[id][2] = cm_pols.array[id][2] + FGL::ONE;
+        let failures = verify_pil(&pil, &const_pols, &cm_pols).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].kind, IdentityKind::Connection);
+        assert!(failures[0].rows.contains(&2));
+    }
+}
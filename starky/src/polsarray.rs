@@ -1,7 +1,9 @@
 #![allow(non_snake_case)]
-use crate::{traits::FieldExtension, types::PIL};
-use anyhow::Result;
-use fields::field_gl::Fr as FGL;
+use crate::{errors::StarkyError, traits::FieldExtension, types::PIL};
+use anyhow::{bail, Result};
+use fields::field_gl::{Fr as FGL, FrRepr};
+use fields::PrimeField;
+use memmap2::Mmap;
 use profiler_macro::time_profiler;
 use rayon::prelude::*;
 use std::collections::HashMap;
@@ -16,6 +18,8 @@ pub struct PolsArray {
     pub defArray: Vec<Pol>,
     pub array: Vec<Vec<FGL>>,
     pub n: usize,
+    // the row-major file mapped by `load_mmap`, `array` is left empty then
+    mmap: Option<Mmap>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -36,6 +40,28 @@ pub enum PolKind {
 impl PolsArray {
     #[time_profiler("new_pols_array")]
     pub fn new(pil: &PIL, kind: PolKind) -> Self {
+        let mut pols = Self::layout(pil, kind);
+        pols.array = pols
+            .defArray
+            .iter()
+            .map(|pol| vec![FGL::default(); pol.polDeg])
+            .collect();
+        pols
+    }
+
+    /// Map the row-major polynomial file instead of copying it into `array`, so the trace is
+    /// only read once by `write_buff`. The file must not be modified while it's mapped.
+    #[time_profiler("load_mmap_pols_array")]
+    pub fn load_mmap(pil: &PIL, kind: PolKind, fileName: &str) -> Result<Self> {
+        let mut pols = Self::layout(pil, kind);
+        let f = File::open(fileName)?;
+        pols.check_file_size(fileName, f.metadata()?.len())?;
+        let mmap = unsafe { Mmap::map(&f)? };
+        pols.mmap = Some(mmap);
+        Ok(pols)
+    }
+
+    fn layout(pil: &PIL, kind: PolKind) -> Self {
         let nPols = match kind {
             PolKind::Commit => pil.nCommitments,
             PolKind::Constant => pil.nConstants,
@@ -43,7 +69,6 @@ impl PolsArray {
 
         let mut def: HashMap<String, HashMap<String, Vec<usize>>> = HashMap::new();
         let mut defArray: Vec<Pol> = vec![Pol::default(); nPols];
-        let mut array: Vec<Vec<FGL>> = (0..nPols).map(|_| vec![FGL::default(); nPols]).collect();
 
         for (refName, ref_) in pil.references.iter() {
             if (ref_.type_ == "cmP" && kind == PolKind::Commit)
@@ -72,7 +97,6 @@ impl PolsArray {
                             polDeg: ref_.polDeg,
                         };
                         arrayPols[i] = ref_.id + i;
-                        array[ref_.id + i] = vec![FGL::default(); ref_.polDeg];
                     }
                     ns.insert(namePols, arrayPols);
                     def.insert(nameSpace, ns);
@@ -88,7 +112,6 @@ impl PolsArray {
                     let mut ns: HashMap<String, Vec<usize>> = HashMap::new();
                     ns.insert(namePols, arrayPols);
                     def.insert(nameSpace, ns);
-                    array[ref_.id] = vec![FGL::default(); ref_.polDeg];
                 }
             }
         }
@@ -103,11 +126,61 @@ impl PolsArray {
             nPols: defArray.len(),
             n: defArray[0].polDeg,
             defArray,
-            array,
             def,
+            ..Default::default()
+        }
+    }
+
+    /// Check that every polynomial has the 2^nBits rows of the stark struct, before proving
+    pub fn check_shape(&self, nBits: usize) -> Result<()> {
+        for pol in self.defArray.iter() {
+            if pol.polDeg != 1 << nBits {
+                bail!(StarkyError::MalformedPil(format!(
+                    "{} has {} rows, but the stark struct expects 2^{}",
+                    pol.name, pol.polDeg, nBits
+                )));
+            }
         }
+        Ok(())
+    }
+
+    fn check_file_size(&self, fileName: &str, size: u64) -> Result<()> {
+        let expect = (self.nPols * self.n * 8) as u64;
+        if size < expect {
+            bail!(StarkyError::MalformedInput(format!(
+                "{} has {} bytes, but {} polynomials of {} rows need {}",
+                fileName, size, self.nPols, self.n, expect
+            )));
+        }
+        if size > expect {
+            log::warn!(
+                "{} has {} bytes, only the first {} are used",
+                fileName,
+                size,
+                expect
+            );
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn mmap_values(&self) -> Option<&[u64]> {
+        self.mmap.as_ref().map(|m| unsafe {
+            std::slice::from_raw_parts(m.as_ptr() as *const u64, self.nPols * self.n)
+        })
     }
 
+    /// The j-th row of the polynomial `id`, from either `array` or the mapped file, in which a
+    /// non canonical value is malformed
+    #[inline(always)]
+    pub fn value(&self, id: usize, j: usize) -> Result<FGL> {
+        match self.mmap_values() {
+            Some(values) => decode(values[j * self.nPols + id]),
+            None => Ok(self.array[id][j]),
+        }
+    }
+
+    /// Like `set_matrix`, this polsarray must not be mapped by `load_mmap`
     #[inline(always)]
     pub fn get(&self, pil: &PIL, ns: &String, np: &String, i: usize, j: usize) -> FGL {
         let ref_id = self.get_pol_id(pil, ns, np, i);
@@ -119,7 +192,8 @@ impl PolsArray {
     ///
     /// e.g. For JS statement, constPols.Compressor.C[7][pr.row] = c[5], i is 7 and j is pr.row.
     ///
-    /// Before calling this function, you must ensure that this polsarray has been initialized
+    /// Before calling this function, you must ensure that this polsarray has been initialized,
+    /// and not mapped by `load_mmap`, which is read-only
     #[inline(always)]
     pub fn set_matrix(
         &mut self,
@@ -142,6 +216,7 @@ impl PolsArray {
     #[time_profiler("load_cm_pols_array")]
     pub fn load(&mut self, fileName: &str) -> Result<()> {
         let mut f = File::open(fileName)?;
+        self.check_file_size(fileName, f.metadata()?.len())?;
         let maxBufferSize = 1024 * 1024 * 32;
         let totalSize = self.nPols * self.n;
         let mut buff8: Vec<u8> = vec![0u8; std::cmp::min(totalSize, maxBufferSize) * 8];
@@ -174,7 +249,7 @@ impl PolsArray {
             n = rs / 8;
 
             for l in 0..n {
-                self.array[i][j] = FGL::from(buff[l]);
+                self.array[i][j] = decode(buff[l])?;
                 i += 1;
                 if i == self.nPols {
                     i = 0;
@@ -195,7 +270,7 @@ impl PolsArray {
         let mut p = 0usize;
         for i in 0..self.n {
             for j in 0..self.nPols {
-                buff[p] = self.array[j][i].as_int() % 0xFFFFFFFF00000001; //u128
+                buff[p] = self.value(j, i)?.as_int() % 0xFFFFFFFF00000001; //u128
                 p += 1;
                 if p == buff.capacity() {
                     // copy to [u8]
@@ -214,7 +289,7 @@ impl PolsArray {
             let buff8: &[u8] = unsafe {
                 std::slice::from_raw_parts(
                     buff.as_ptr() as *const u8,
-                    buff.len() * std::mem::size_of::<u64>(),
+                    p * std::mem::size_of::<u64>(),
                 )
             };
             writer.write_all(buff8)?;
@@ -222,16 +297,41 @@ impl PolsArray {
         Ok(())
     }
 
-    pub fn write_buff<F: FieldExtension>(&self) -> Vec<F> {
+    pub fn write_buff<F: FieldExtension>(&self) -> Result<Vec<F>> {
         let mut buff: Vec<F> = vec![F::ZERO; self.n * self.nPols];
-        buff.par_chunks_mut(self.nPols)
-            .enumerate()
-            .for_each(|(i, chunk)| {
-                for j in 0..self.nPols {
-                    chunk[j] = F::from(self.array[j][i]);
-                }
-            });
-        buff
+        match self.mmap_values() {
+            // the file is row-major already
+            Some(values) => buff
+                .par_chunks_mut(self.nPols)
+                .zip(values.par_chunks(self.nPols))
+                .try_for_each(|(chunk, row)| -> Result<()> {
+                    for j in 0..self.nPols {
+                        chunk[j] = F::from(decode(row[j])?);
+                    }
+                    Ok(())
+                })?,
+            None => buff
+                .par_chunks_mut(self.nPols)
+                .enumerate()
+                .for_each(|(i, chunk)| {
+                    for j in 0..self.nPols {
+                        chunk[j] = F::from(self.array[j][i]);
+                    }
+                }),
+        }
+        Ok(buff)
+    }
+}
+
+/// Decode a value of the pols file, which must be a canonical goldilocks element
+#[inline(always)]
+fn decode(v: u64) -> Result<FGL> {
+    match FGL::from_repr(FrRepr::from(v)) {
+        Ok(e) => Ok(e),
+        Err(_) => bail!(StarkyError::MalformedInput(format!(
+            "non canonical goldilocks element {}",
+            v
+        ))),
     }
 }
 
@@ -253,4 +353,65 @@ pub mod tests {
         let file = File::create("data/fib.exec.cp").unwrap();
         cmp.save(file).unwrap();
     }
+
+    #[test]
+    fn test_load_mmap_polsarray() {
+        let pil = types::load_json::<PIL>("data/plookup.pil.json").unwrap();
+        let mut cm = PolsArray::new(&pil, PolKind::Commit);
+        cm.load("data/plookup.cm").unwrap();
+        let cm_mmap = PolsArray::load_mmap(&pil, PolKind::Commit, "data/plookup.cm").unwrap();
+        assert!(cm_mmap.array.is_empty());
+        assert_eq!(cm.value(1, 7).unwrap(), cm_mmap.value(1, 7).unwrap());
+        assert_eq!(
+            cm.write_buff::<FGL>().unwrap(),
+            cm_mmap.write_buff::<FGL>().unwrap()
+        );
+        cm_mmap.check_shape(10).unwrap();
+        assert!(cm_mmap.check_shape(11).is_err());
+
+        let file = File::create("/tmp/test_load_mmap_polsarray.cm").unwrap();
+        cm_mmap.save(file).unwrap();
+        assert_eq!(
+            std::fs::read("/tmp/test_load_mmap_polsarray.cm").unwrap(),
+            std::fs::read("data/plookup.cm").unwrap()
+        );
+
+        // a truncated trace is rejected before proving
+        let data = std::fs::read("data/plookup.cm").unwrap();
+        std::fs::write(
+            "/tmp/test_load_mmap_polsarray_short.cm",
+            &data[..data.len() - 8],
+        )
+        .unwrap();
+        let err = PolsArray::load_mmap(
+            &pil,
+            PolKind::Commit,
+            "/tmp/test_load_mmap_polsarray_short.cm",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+        let mut cm = PolsArray::new(&pil, PolKind::Commit);
+        assert!(cm.load("/tmp/test_load_mmap_polsarray_short.cm").is_err());
+
+        // so is a value out of the goldilocks field
+        let mut data = data;
+        data[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        let non_canonical = "/tmp/test_load_mmap_polsarray_non_canonical.cm";
+        std::fs::write(non_canonical, &data).unwrap();
+        let malformed = |err: anyhow::Error| {
+            matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::MalformedInput(_))
+            )
+        };
+        let mut cm = PolsArray::new(&pil, PolKind::Commit);
+        assert!(malformed(cm.load(non_canonical).unwrap_err()));
+        let cm_mmap = PolsArray::load_mmap(&pil, PolKind::Commit, non_canonical).unwrap();
+        assert!(malformed(cm_mmap.value(1, 0).unwrap_err()));
+        assert!(malformed(cm_mmap.write_buff::<FGL>().unwrap_err()));
+        assert!(malformed(cm_mmap.save(std::io::sink()).unwrap_err()));
+    }
 }
@@ -1,16 +1,24 @@
 use crate::{
+    errors::StarkyError,
+    f3g::F3G,
+    f5g::F5G,
     merklehash::MerkleTreeGL,
     merklehash_bls12381::MerkleTreeBLS12381,
     merklehash_bn128::MerkleTreeBN128,
+    merklehash_ext::MerkleTreeExt,
+    merklehash_keccak::MerkleTreeKeccak,
     pil2circom,
     polsarray::{PolKind, PolsArray},
+    security::validate_stark_struct,
     stark_gen::StarkProof,
     stark_setup::StarkSetup,
-    stark_verify::stark_verify,
+    stark_verifier_solidity::{self, SolidityHash},
+    stark_verify,
     traits::{MerkleTree, Transcript},
     transcript::TranscriptGL,
     transcript_bls12381::TranscriptBLS128,
     transcript_bn128::TranscriptBN128,
+    transcript_keccak::TranscriptKeccak,
     types::*,
     ElementDigest,
 };
@@ -19,12 +27,73 @@ use crate::field_bls12381::Fr as Fr_BLS12381;
 use crate::field_bn128::Fr as Fr_BN128;
 use ff::PrimeField;
 use fields::field_gl::Fr as FGL;
+use num_bigint::BigUint;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use profiler_macro::time_profiler;
+use serde::{de::DeserializeOwned, Serialize};
 use std::fs::File;
 use std::io::Write;
+use std::path::Path;
 
+/// Build the stark setup once and save it to `stark_setup_file`, which can be reused by
+/// `stark_prove` and `stark_verify` for the same PIL and stark struct.
+///
+/// The stark struct must reach `min_security_bits`, `DEFAULT_SECURITY_BITS` if not given, see
+/// `validate_stark_struct`.
+#[time_profiler()]
+pub fn stark_setup(
+    stark_struct: &str,
+    pil_file: &str,
+    const_pol_file: &str,
+    stark_setup_file: &str,
+    min_security_bits: Option<usize>,
+) -> Result<()> {
+    let mut pil = load_json::<PIL>(pil_file)?;
+    let const_pol = PolsArray::load_mmap(&pil, PolKind::Constant, const_pol_file)?;
+
+    let stark_struct = load_json::<StarkStruct>(stark_struct)?;
+    validate_stark_struct(&stark_struct, min_security_bits)?;
+    match stark_struct.verificationHashType.as_str() {
+        "BN128" => setup::<MerkleTreeBN128>(&const_pol, &mut pil, &stark_struct, stark_setup_file),
+        "BLS12381" => {
+            setup::<MerkleTreeBLS12381>(&const_pol, &mut pil, &stark_struct, stark_setup_file)
+        }
+        "GL" => setup::<MerkleTreeGL>(&const_pol, &mut pil, &stark_struct, stark_setup_file),
+        "KECCAK" => {
+            setup::<MerkleTreeKeccak>(&const_pol, &mut pil, &stark_struct, stark_setup_file)
+        }
+        _ => bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType
+        )),
+    }
+}
+
+fn setup<M: MerkleTree<ExtendField = F3G> + Serialize>(
+    const_pol: &PolsArray,
+    pil: &mut PIL,
+    stark_struct: &StarkStruct,
+    stark_setup_file: &str,
+) -> Result<()> {
+    match stark_struct.extensionDegree {
+        5 => StarkSetup::<MerkleTreeExt<M, F5G>>::new(const_pol, pil, stark_struct, None)?
+            .save(stark_struct, stark_setup_file),
+        _ => StarkSetup::<M>::new(const_pol, pil, stark_struct, None)?
+            .save(stark_struct, stark_setup_file),
+    }
+}
+
+/// Prove the committed polynomials and write the zkin, and the circom verifier if `circom_file`
+/// is given.
+///
+/// The circom verifier of the aggregation stage verifies `agg_inputs` proofs, 0 for no
+/// aggregation stage. The circom templates are written for the cubic extension of the GL, BN128
+/// and BLS12381 proofs, so requesting the verifier of a quintic extension or KECCAK proof fails
+/// before proving.
+///
+/// The expressions are evaluated in batches of rows if the memory estimate is above
+/// `memory_limit` bytes, and the proof fails before allocating if it is still above, or if the
+/// stark struct does not reach `min_security_bits`, `DEFAULT_SECURITY_BITS` if not given.
 #[allow(clippy::too_many_arguments)]
 #[time_profiler()]
 pub fn stark_prove(
@@ -32,84 +101,379 @@ pub fn stark_prove(
     pil_file: &str,
     norm_stage: bool,
     skip_main: bool,
-    agg_stage: bool,
+    agg_inputs: usize,
     const_pol_file: &str,
     cm_pol_file: &str,
-    circom_file: &str,
+    stark_setup_file: Option<&str>,
+    circom_file: Option<&str>,
     zkin: &str,
     prover_addr: &str,
+    memory_limit: Option<usize>,
+    min_security_bits: Option<usize>,
 ) -> Result<()> {
     let mut pil = load_json::<PIL>(pil_file)?;
-    let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
-    const_pol.load(const_pol_file)?;
-
-    let mut cm_pol = PolsArray::new(&pil, PolKind::Commit);
-    cm_pol.load(cm_pol_file)?;
+    let const_pol = PolsArray::load_mmap(&pil, PolKind::Constant, const_pol_file)?;
+    let cm_pol = PolsArray::load_mmap(&pil, PolKind::Commit, cm_pol_file)?;
 
     let stark_struct = load_json::<StarkStruct>(stark_struct)?;
-    let circom_file_writer = File::create(circom_file)?;
+    validate_stark_struct(&stark_struct, min_security_bits)?;
+    const_pol.check_shape(stark_struct.nBits)?;
+    cm_pol.check_shape(stark_struct.nBits)?;
+    if circom_file.is_some() {
+        // a keccak proof is verified by the EVM directly, there is no circom verifier to wrap it
+        if stark_struct.verificationHashType == "KECCAK" {
+            bail!(StarkyError::MalformedInput(
+                "no circom verifier of the KECCAK proof, see stark_solidity_verifier".to_string()
+            ));
+        }
+        if stark_struct.extensionDegree != 3 {
+            bail!(StarkyError::MalformedInput(format!(
+                "the circom verifier only supports the extension degree 3, got {}",
+                stark_struct.extensionDegree
+            )));
+        }
+    }
+    let circom_file_writer = circom_file.map(File::create).transpose()?;
     let zkin_writer = File::create(zkin)?;
     match stark_struct.verificationHashType.as_str() {
         "BN128" => prove::<Fr_BN128, MerkleTreeBN128, TranscriptBN128, _>(
             &mut pil,
             const_pol,
             cm_pol,
+            stark_setup_file,
             &stark_struct,
-            false,
+            0,
             norm_stage,
             skip_main,
             circom_file_writer,
             zkin_writer,
             prover_addr,
+            memory_limit,
         ),
         "BLS12381" => prove::<Fr_BLS12381, MerkleTreeBLS12381, TranscriptBLS128, _>(
             &mut pil,
             const_pol,
             cm_pol,
+            stark_setup_file,
             &stark_struct,
-            false,
+            0,
             norm_stage,
             skip_main,
             circom_file_writer,
             zkin_writer,
             prover_addr,
+            memory_limit,
         ),
         "GL" => prove::<FGL, MerkleTreeGL, TranscriptGL, _>(
             &mut pil,
             const_pol,
             cm_pol,
+            stark_setup_file,
+            &stark_struct,
+            agg_inputs,
+            norm_stage,
+            skip_main,
+            circom_file_writer,
+            zkin_writer,
+            prover_addr,
+            memory_limit,
+        ),
+        "KECCAK" => prove::<FGL, MerkleTreeKeccak, TranscriptKeccak, _>(
+            &mut pil,
+            const_pol,
+            cm_pol,
+            stark_setup_file,
             &stark_struct,
-            agg_stage,
+            0,
             norm_stage,
             skip_main,
             circom_file_writer,
             zkin_writer,
             prover_addr,
+            memory_limit,
         ),
-        _ => panic!("Invalid hashtype {}", stark_struct.verificationHashType),
+        _ => bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType
+        )),
     }
 }
 
+/// Verify the zkin proof generated by `stark_prove`.
+///
+/// The setup is loaded from `stark_setup_file` if given, otherwise it is rebuilt from the
+/// constant polynomials.
+#[time_profiler()]
+pub fn stark_verify(
+    stark_struct: &str,
+    pil_file: &str,
+    const_pol_file: &str,
+    stark_setup_file: Option<&str>,
+    zkin: &str,
+) -> Result<()> {
+    let mut pil = load_json::<PIL>(pil_file)?;
+    let stark_struct = load_json::<StarkStruct>(stark_struct)?;
+    match stark_struct.verificationHashType.as_str() {
+        "BN128" => verify::<MerkleTreeBN128, TranscriptBN128>(
+            &mut pil,
+            const_pol_file,
+            stark_setup_file,
+            &stark_struct,
+            zkin,
+        ),
+        "BLS12381" => verify::<MerkleTreeBLS12381, TranscriptBLS128>(
+            &mut pil,
+            const_pol_file,
+            stark_setup_file,
+            &stark_struct,
+            zkin,
+        ),
+        "GL" => verify::<MerkleTreeGL, TranscriptGL>(
+            &mut pil,
+            const_pol_file,
+            stark_setup_file,
+            &stark_struct,
+            zkin,
+        ),
+        "KECCAK" => verify::<MerkleTreeKeccak, TranscriptKeccak>(
+            &mut pil,
+            const_pol_file,
+            stark_setup_file,
+            &stark_struct,
+            zkin,
+        ),
+        _ => bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType
+        )),
+    }
+}
+
+fn verify<M: MerkleTree<ExtendField = F3G> + Default + DeserializeOwned, T: Transcript>(
+    pil: &mut PIL,
+    const_pol_file: &str,
+    stark_setup_file: Option<&str>,
+    stark_struct: &StarkStruct,
+    zkin: &str,
+) -> Result<()> {
+    match stark_struct.extensionDegree {
+        5 => verify_with::<MerkleTreeExt<M, F5G>, T>(
+            pil,
+            const_pol_file,
+            stark_setup_file,
+            stark_struct,
+            zkin,
+        ),
+        _ => verify_with::<M, T>(pil, const_pol_file, stark_setup_file, stark_struct, zkin),
+    }
+}
+
+fn verify_with<M: MerkleTree + Default + DeserializeOwned, T: Transcript>(
+    pil: &mut PIL,
+    const_pol_file: &str,
+    stark_setup_file: Option<&str>,
+    stark_struct: &StarkStruct,
+    zkin: &str,
+) -> Result<()> {
+    let setup = load_setup::<M>(pil, const_pol_file, stark_setup_file, stark_struct)?;
+
+    let starkproof = load_json::<StarkProof<M>>(zkin)?;
+    if let Some(root_c) = starkproof.rootC {
+        if root_c != setup.const_root {
+            bail!(StarkyError::InvalidProof(format!(
+                "rootC mismatch, {} is generated by {}, but the setup is {}",
+                zkin, root_c, setup.const_root
+            )));
+        }
+    }
+
+    let result = stark_verify::stark_verify::<M, T>(
+        &starkproof,
+        &setup.const_root,
+        &setup.starkinfo,
+        stark_struct,
+        &setup.program,
+    )?;
+    if !result {
+        bail!(StarkyError::InvalidProof(zkin.to_string()));
+    }
+    Ok(())
+}
+
+fn load_setup<M: MerkleTree + Default + DeserializeOwned>(
+    pil: &mut PIL,
+    const_pol_file: &str,
+    stark_setup_file: Option<&str>,
+    stark_struct: &StarkStruct,
+) -> Result<StarkSetup<M>> {
+    let const_pol = PolsArray::load_mmap(pil, PolKind::Constant, const_pol_file)?;
+    match stark_setup_file {
+        Some(stark_setup_file) => {
+            StarkSetup::<M>::load(stark_struct, pil, &const_pol, stark_setup_file)
+        }
+        None => StarkSetup::<M>::new(&const_pol, pil, stark_struct, None),
+    }
+}
+
+/// Generate the solidity verifier of the BN128 or KECCAK stark proofs, the setup is loaded
+/// from `stark_setup_file` if given, otherwise it is rebuilt from the constant polynomials.
+/// The BN128 verifier calls a `PoseidonEx` contract, whose round constants are written next
+/// to `sol_file` with the `poseidon.json` extension, one hex chunk per `load` transaction.
+#[time_profiler()]
+pub fn stark_solidity_verifier(
+    stark_struct: &str,
+    pil_file: &str,
+    const_pol_file: &str,
+    stark_setup_file: Option<&str>,
+    sol_file: &str,
+) -> Result<()> {
+    let mut pil = load_json::<PIL>(pil_file)?;
+    let stark_struct = load_json::<StarkStruct>(stark_struct)?;
+    stark_verifier_solidity::check_extension_degree(&stark_struct)?;
+    let contract = match stark_struct.verificationHashType.as_str() {
+        "BN128" => {
+            let chunks = stark_verifier_solidity::poseidon_chunks();
+            let file = File::create(Path::new(sol_file).with_extension("poseidon.json"))?;
+            serde_json::to_writer(file, &chunks)?;
+            render_solidity::<MerkleTreeBN128>(
+                &mut pil,
+                const_pol_file,
+                stark_setup_file,
+                &stark_struct,
+            )?
+        }
+        "KECCAK" => render_solidity::<MerkleTreeKeccak>(
+            &mut pil,
+            const_pol_file,
+            stark_setup_file,
+            &stark_struct,
+        )?,
+        _ => bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType
+        )),
+    };
+    let mut file = File::create(sol_file)?;
+    write!(file, "{}", contract)?;
+    Ok(())
+}
+
+fn render_solidity<M: SolidityHash + Default + DeserializeOwned>(
+    pil: &mut PIL,
+    const_pol_file: &str,
+    stark_setup_file: Option<&str>,
+    stark_struct: &StarkStruct,
+) -> Result<String> {
+    let setup = load_setup::<M>(pil, const_pol_file, stark_setup_file, stark_struct)?;
+    stark_verifier_solidity::render::<M>(
+        &setup.starkinfo,
+        &setup.program,
+        stark_struct,
+        &setup.const_root,
+    )
+}
+
+/// Flatten the stark proof `zkin` into the json `[proof, publics]` taken by `verifyProof` of
+/// the solidity verifier.
+pub fn stark_solidity_calldata(stark_struct: &str, zkin: &str, output: &str) -> Result<()> {
+    let stark_struct = load_json::<StarkStruct>(stark_struct)?;
+    stark_verifier_solidity::check_extension_degree(&stark_struct)?;
+    let (proof, publics) = match stark_struct.verificationHashType.as_str() {
+        "BN128" => {
+            stark_verifier_solidity::proof_words(&load_json::<StarkProof<MerkleTreeBN128>>(zkin)?)
+        }
+        "KECCAK" => {
+            stark_verifier_solidity::proof_words(&load_json::<StarkProof<MerkleTreeKeccak>>(zkin)?)
+        }
+        _ => bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType
+        )),
+    };
+    let to_strings =
+        |words: Vec<BigUint>| -> Vec<String> { words.iter().map(|w| w.to_string()).collect() };
+    let calldata = (to_strings(proof), to_strings(publics));
+    let file = File::create(output)?;
+    serde_json::to_writer(file, &calldata)?;
+    Ok(())
+}
+
 // Adopt with different curve, eg: BN128, BLS12381, Goldilocks
 #[allow(clippy::too_many_arguments)]
 fn prove<
     F: PrimeField + Default,
-    M: MerkleTree<MTNode = ElementDigest<4, F>> + Default,
+    M: MerkleTree<MTNode = ElementDigest<4, F>, ExtendField = F3G> + Default + DeserializeOwned,
+    T: Transcript,
+    W: Write,
+>(
+    pil: &mut PIL,
+    const_pol: PolsArray,
+    cm_pol: PolsArray,
+    stark_setup_file: Option<&str>,
+    stark_struct: &StarkStruct,
+    agg_inputs: usize,
+    norm_stage: bool,
+    skip_main: bool,
+    circom_file_writer: Option<W>,
+    zkin_writer: W,
+    prover_addr: &str,
+    memory_limit: Option<usize>,
+) -> Result<()> {
+    match stark_struct.extensionDegree {
+        5 => prove_with::<F, MerkleTreeExt<M, F5G>, T, W>(
+            pil,
+            const_pol,
+            cm_pol,
+            stark_setup_file,
+            stark_struct,
+            agg_inputs,
+            norm_stage,
+            skip_main,
+            circom_file_writer,
+            zkin_writer,
+            prover_addr,
+            memory_limit,
+        ),
+        _ => prove_with::<F, M, T, W>(
+            pil,
+            const_pol,
+            cm_pol,
+            stark_setup_file,
+            stark_struct,
+            agg_inputs,
+            norm_stage,
+            skip_main,
+            circom_file_writer,
+            zkin_writer,
+            prover_addr,
+            memory_limit,
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prove_with<
+    F: PrimeField + Default,
+    M: MerkleTree<MTNode = ElementDigest<4, F>> + Default + DeserializeOwned,
     T: Transcript,
     W: Write,
 >(
     pil: &mut PIL,
     const_pol: PolsArray,
     cm_pol: PolsArray,
+    stark_setup_file: Option<&str>,
     stark_struct: &StarkStruct,
-    agg_stage: bool,
+    agg_inputs: usize,
     norm_stage: bool,
     skip_main: bool,
-    mut circom_file_writer: W,
+    circom_file_writer: Option<W>,
     mut zkin_writer: W,
     prover_addr: &str,
+    memory_limit: Option<usize>,
 ) -> Result<()> {
-    let mut setup = StarkSetup::<M>::new(&const_pol, pil, stark_struct, None)?;
+    // only the commitment-dependent work is left if the setup is reused
+    let mut setup = match stark_setup_file {
+        Some(stark_setup_file) => {
+            StarkSetup::<M>::load(stark_struct, pil, &const_pol, stark_setup_file)?
+        }
+        None => StarkSetup::<M>::new(&const_pol, pil, stark_struct, None)?,
+    };
     let starkproof = StarkProof::<M>::stark_gen::<T>(
         cm_pol,
         const_pol,
@@ -119,9 +483,10 @@ fn prove<
         pil,
         stark_struct,
         prover_addr,
+        memory_limit,
     )?;
 
-    let result = stark_verify::<M, T>(
+    let result = stark_verify::stark_verify::<M, T>(
         &starkproof,
         &setup.const_root,
         &setup.starkinfo,
@@ -129,24 +494,31 @@ fn prove<
         &setup.program,
     )?;
 
-    assert!(result);
+    if !result {
+        // a trace that breaks the PIL only shows up here, `pil_verify` tells which identity
+        bail!(StarkyError::InvalidProof(
+            "the generated proof does not verify, check the trace by pil_verify".to_string()
+        ));
+    }
 
-    let opt = pil2circom::StarkOption {
-        enable_input: false,
-        verkey_input: norm_stage,
-        skip_main,
-        agg_stage,
-    };
+    if let Some(mut circom_file_writer) = circom_file_writer {
+        let opt = pil2circom::StarkOption {
+            enable_input: false,
+            verkey_input: norm_stage,
+            skip_main,
+            agg_inputs,
+        };
 
-    let str_ver = pil2circom::pil2circom::<F>(
-        pil,
-        &setup.const_root,
-        stark_struct,
-        &mut setup.starkinfo,
-        &mut setup.program,
-        &opt,
-    )?;
-    write!(circom_file_writer, "{}", str_ver)?;
+        let str_ver = pil2circom::pil2circom::<F>(
+            pil,
+            &setup.const_root,
+            stark_struct,
+            &mut setup.starkinfo,
+            &mut setup.program,
+            &opt,
+        )?;
+        write!(circom_file_writer, "{}", str_ver)?;
+    }
 
     // if agg_stage {
     //     starkproof.rootC = None;
@@ -158,3 +530,409 @@ fn prove<
     drop(starkproof);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the stark structs of data/ are toy ones, so the tests require no security with `Some(0)`
+
+    #[test]
+    fn test_stark_prove_and_verify_gl() {
+        stark_prove(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            Some("/tmp/test_stark_verify.gl.circom"),
+            "/tmp/test_stark_verify.gl.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        stark_verify(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            None,
+            "/tmp/test_stark_verify.gl.zkin.json",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_stark_verify_truncated_proof() {
+        stark_prove(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            None,
+            "/tmp/test_stark_verify_truncated.gl.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+        let zkin: serde_json::Value =
+            load_json("/tmp/test_stark_verify_truncated.gl.zkin.json").unwrap();
+
+        let truncations: [fn(&mut serde_json::Value); 4] = [
+            |z| {
+                z["finalPol"].as_array_mut().unwrap().pop();
+            },
+            |z| {
+                z["evals"].as_array_mut().unwrap().pop();
+            },
+            |z| {
+                z["s0_vals1"][0].as_array_mut().unwrap().pop();
+            },
+            |z| {
+                // one query less
+                for k in ["1", "2", "3", "4", "C"] {
+                    for key in [format!("s0_vals{}", k), format!("s0_siblings{}", k)] {
+                        if let Some(queries) = z.get_mut(&key).and_then(|v| v.as_array_mut()) {
+                            queries.pop();
+                        }
+                    }
+                }
+            },
+        ];
+        for truncate in truncations {
+            let mut truncated = zkin.clone();
+            truncate(&mut truncated);
+            std::fs::write(
+                "/tmp/test_stark_verify_truncated.gl.bad.zkin.json",
+                truncated.to_string(),
+            )
+            .unwrap();
+            let err = stark_verify(
+                "data/starkStruct.json.gl",
+                "data/fib.pil.json.gl",
+                "data/fib.const.gl",
+                None,
+                "/tmp/test_stark_verify_truncated.gl.bad.zkin.json",
+            )
+            .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::InvalidProof(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stark_prove_and_verify_bn128() {
+        stark_prove(
+            "data/starkStruct.json",
+            "data/fib.pil.json",
+            false,
+            false,
+            0,
+            "data/fib.const",
+            "data/fib.cm",
+            None,
+            Some("/tmp/test_stark_verify.bn128.circom"),
+            "/tmp/test_stark_verify.bn128.zkin.json",
+            "273030697313060285579891744179749754319274977764",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        stark_verify(
+            "data/starkStruct.json",
+            "data/fib.pil.json",
+            "data/fib.const",
+            None,
+            "/tmp/test_stark_verify.bn128.zkin.json",
+        )
+        .unwrap();
+
+        // the const root of another PIL must be rejected
+        assert!(stark_verify(
+            "data/starkStruct.json",
+            "data/plookup.pil.json",
+            "data/plookup.const",
+            None,
+            "/tmp/test_stark_verify.bn128.zkin.json",
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stark_setup_and_prove_gl() {
+        stark_setup(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            "/tmp/test_stark_prove_setup.gl.json",
+            Some(0),
+        )
+        .unwrap();
+
+        stark_prove(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            Some("/tmp/test_stark_prove_setup.gl.json"),
+            Some("/tmp/test_stark_prove_setup.gl.circom"),
+            "/tmp/test_stark_prove_setup.gl.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        stark_verify(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            Some("/tmp/test_stark_prove_setup.gl.json"),
+            "/tmp/test_stark_prove_setup.gl.zkin.json",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_stark_prove_and_verify_keccak() {
+        let mut stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        stark_struct.verificationHashType = "KECCAK".to_string();
+        let stark_struct_file = "/tmp/test_stark_verify.keccak.starkStruct.json";
+        std::fs::write(
+            stark_struct_file,
+            serde_json::to_string(&stark_struct).unwrap(),
+        )
+        .unwrap();
+
+        stark_prove(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            None,
+            "/tmp/test_stark_verify.keccak.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        stark_verify(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            None,
+            "/tmp/test_stark_verify.keccak.zkin.json",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_stark_prove_and_verify_gl_f5g() {
+        let mut stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        stark_struct.extensionDegree = 5;
+        let stark_struct_file = "/tmp/test_stark_verify.gl_f5g.starkStruct.json";
+        std::fs::write(
+            stark_struct_file,
+            serde_json::to_string(&stark_struct).unwrap(),
+        )
+        .unwrap();
+
+        stark_setup(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            "/tmp/test_stark_verify.gl_f5g.setup.json",
+            Some(0),
+        )
+        .unwrap();
+
+        stark_prove(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            Some("/tmp/test_stark_verify.gl_f5g.setup.json"),
+            None,
+            "/tmp/test_stark_verify.gl_f5g.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        stark_verify(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            None,
+            "/tmp/test_stark_verify.gl_f5g.zkin.json",
+        )
+        .unwrap();
+
+        // the circom templates are written for the cubic extension only
+        let err = stark_prove(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            Some("/tmp/test_stark_verify.gl_f5g.setup.json"),
+            Some("/tmp/test_stark_verify.gl_f5g.circom"),
+            "/tmp/test_stark_verify.gl_f5g.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_stark_prove_memory_limit() {
+        let err = stark_prove(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            Some("/tmp/test_stark_prove_memory_limit.circom"),
+            "/tmp/test_stark_prove_memory_limit.zkin.json",
+            "",
+            Some(1 << 10),
+            Some(0),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MemoryLimit(_))
+        ));
+    }
+
+    #[test]
+    fn test_stark_prove_agg_inputs() {
+        for agg_inputs in [1, 2, 27] {
+            let err = stark_prove(
+                "data/starkStruct.json.gl",
+                "data/fib.pil.json.gl",
+                true,
+                false,
+                agg_inputs,
+                "data/fib.const.gl",
+                "data/fib.cm.gl",
+                None,
+                Some("/tmp/test_stark_prove_agg_inputs.circom"),
+                "/tmp/test_stark_prove_agg_inputs.zkin.json",
+                "",
+                None,
+                Some(0),
+            )
+            .unwrap_err();
+            // fib publishes no rootC to aggregate
+            assert!(matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::MalformedInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stark_prove_min_security_bits() {
+        let err = stark_setup(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            "/tmp/test_stark_prove_min_security_bits.setup.json",
+            Some(100),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InsufficientSecurity(_))
+        ));
+
+        let err = stark_prove(
+            "data/starkStruct.json.gl",
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            Some("/tmp/test_stark_prove_min_security_bits.circom"),
+            "/tmp/test_stark_prove_min_security_bits.zkin.json",
+            "",
+            None,
+            Some(100),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InsufficientSecurity(_))
+        ));
+    }
+
+    #[test]
+    fn test_stark_prove_invalid_hash_type() {
+        let mut stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        stark_struct.verificationHashType = "MD5".to_string();
+        let stark_struct_file = "/tmp/test_stark_prove_invalid_hash_type.json";
+        std::fs::write(
+            stark_struct_file,
+            serde_json::to_string(&stark_struct).unwrap(),
+        )
+        .unwrap();
+
+        let err = stark_prove(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            Some("/tmp/test_stark_prove_invalid_hash_type.circom"),
+            "/tmp/test_stark_prove_invalid_hash_type.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InvalidHashType(_))
+        ));
+    }
+}
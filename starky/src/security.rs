@@ -0,0 +1,250 @@
+//! Derive a `StarkStruct` from a security target, and check the security of a given one.
+//!
+//! The estimate is the conjectured FRI soundness, `nQueries * log2(blowup)` bits, capped by
+//! the extension field less the LDE size and by the collision resistance of the hash.
+use crate::errors::StarkyError;
+use crate::types::{StarkStruct, Step};
+use anyhow::{bail, Result};
+
+/// The security a stark struct must reach unless the caller requires another level.
+pub const DEFAULT_SECURITY_BITS: usize = 128;
+
+/// The number of bits folded by each FRI step.
+pub const FRI_FOLDING_BITS: usize = 4;
+
+/// The lower bound of the last FRI step, whose polynomial is sent in full.
+const FRI_LAST_STEP_MIN_BITS: usize = 2;
+
+/// Half of the digest size.
+fn hash_security_bits(hash_type: &str) -> Result<usize> {
+    match hash_type {
+        "GL" | "KECCAK" => Ok(128),
+        "BN128" | "BLS12381" => Ok(127),
+        _ => bail!(StarkyError::InvalidHashType(hash_type.to_string())),
+    }
+}
+
+/// The conjectured security of the stark struct in bits.
+pub fn security_bits(stark_struct: &StarkStruct) -> Result<usize> {
+    check_shape(stark_struct)?;
+    let hash_bits = hash_security_bits(&stark_struct.verificationHashType)?;
+    let blowup_bits = stark_struct.nBitsExt - stark_struct.nBits;
+    let field_bits = (stark_struct.extensionDegree * 64).saturating_sub(stark_struct.nBitsExt);
+    Ok((stark_struct.nQueries * blowup_bits)
+        .min(field_bits)
+        .min(hash_bits))
+}
+
+fn check_shape(stark_struct: &StarkStruct) -> Result<()> {
+    let malformed = |msg: String| -> Result<()> { bail!(StarkyError::MalformedInput(msg)) };
+    if stark_struct.nBitsExt <= stark_struct.nBits {
+        return malformed(format!(
+            "nBitsExt {} must be larger than nBits {}",
+            stark_struct.nBitsExt, stark_struct.nBits
+        ));
+    }
+    if stark_struct.nQueries == 0 {
+        return malformed("nQueries must not be 0".to_string());
+    }
+    if ![3, 5].contains(&stark_struct.extensionDegree) {
+        return malformed(format!(
+            "the extension degree must be 3 or 5, got {}",
+            stark_struct.extensionDegree
+        ));
+    }
+    match stark_struct.steps.first() {
+        Some(step) if step.nBits == stark_struct.nBitsExt => {}
+        _ => {
+            return malformed(format!(
+                "the first FRI step must have nBitsExt {} bits",
+                stark_struct.nBitsExt
+            ))
+        }
+    }
+    if stark_struct
+        .steps
+        .windows(2)
+        .any(|w| w[1].nBits >= w[0].nBits)
+    {
+        return malformed("the FRI steps must be decreasing".to_string());
+    }
+    Ok(())
+}
+
+/// Check the stark struct is well formed, and that it reaches `min_bits` of security,
+/// `DEFAULT_SECURITY_BITS` if not given.
+pub fn validate_stark_struct(stark_struct: &StarkStruct, min_bits: Option<usize>) -> Result<()> {
+    let bits = security_bits(stark_struct)?;
+    let min_bits = min_bits.unwrap_or(DEFAULT_SECURITY_BITS);
+    if bits < min_bits {
+        bail!(StarkyError::InsufficientSecurity(format!(
+            "the stark struct reaches {} bits, below the required {} bits, use more queries or a larger blowup",
+            bits, min_bits
+        )));
+    }
+    Ok(())
+}
+
+/// Derive the stark struct of a `2^n_bits` trace extended by `2^blowup_bits`, with enough
+/// queries to reach `security_bits` over the extension field of `extension_degree`.
+pub fn derive_stark_struct(
+    n_bits: usize,
+    blowup_bits: usize,
+    security_bits: usize,
+    hash_type: &str,
+    extension_degree: usize,
+) -> Result<StarkStruct> {
+    if blowup_bits == 0 {
+        bail!(StarkyError::MalformedInput(
+            "the blowup factor must be at least 2".to_string()
+        ));
+    }
+    if ![3, 5].contains(&extension_degree) {
+        bail!(StarkyError::MalformedInput(format!(
+            "the extension degree must be 3 or 5, got {}",
+            extension_degree
+        )));
+    }
+    let n_bits_ext = n_bits + blowup_bits;
+    let field_bits = (extension_degree * 64).saturating_sub(n_bits_ext);
+    let max_bits = hash_security_bits(hash_type)?.min(field_bits);
+    if security_bits > max_bits {
+        bail!(StarkyError::InsufficientSecurity(format!(
+            "at most {} bits can be reached with the {} hash, {} LDE bits and the extension degree {}",
+            max_bits, hash_type, n_bits_ext, extension_degree
+        )));
+    }
+
+    let steps = (FRI_LAST_STEP_MIN_BITS.min(n_bits_ext)..=n_bits_ext)
+        .rev()
+        .step_by(FRI_FOLDING_BITS)
+        .map(|b| Step { nBits: b })
+        .collect();
+
+    Ok(StarkStruct {
+        nBits: n_bits,
+        nBitsExt: n_bits_ext,
+        nQueries: (security_bits + blowup_bits - 1) / blowup_bits,
+        verificationHashType: hash_type.to_string(),
+        steps,
+        extensionDegree: extension_degree,
+    })
+}
+
+/// Derive the stark struct and save it to `output`.
+pub fn generate_stark_struct(
+    n_bits: usize,
+    blowup_bits: usize,
+    security_bits: usize,
+    hash_type: &str,
+    extension_degree: usize,
+    output: &str,
+) -> Result<()> {
+    let stark_struct = derive_stark_struct(
+        n_bits,
+        blowup_bits,
+        security_bits,
+        hash_type,
+        extension_degree,
+    )?;
+    log::info!(
+        "nQueries {}, FRI steps {:?}",
+        stark_struct.nQueries,
+        stark_struct
+            .steps
+            .iter()
+            .map(|s| s.nBits)
+            .collect::<Vec<_>>()
+    );
+    std::fs::write(output, serde_json::to_string_pretty(&stark_struct)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::load_json;
+
+    #[test]
+    fn test_derive_stark_struct() {
+        let stark_struct = derive_stark_struct(10, 1, 128, "GL", 3).unwrap();
+        assert_eq!(stark_struct.nBitsExt, 11);
+        assert_eq!(stark_struct.nQueries, 128);
+        let steps = stark_struct
+            .steps
+            .iter()
+            .map(|s| s.nBits)
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec![11, 7, 3]);
+        assert_eq!(security_bits(&stark_struct).unwrap(), 128);
+        validate_stark_struct(&stark_struct, Some(128)).unwrap();
+
+        let stark_struct = derive_stark_struct(20, 3, 100, "BN128", 3).unwrap();
+        assert_eq!(stark_struct.nQueries, 34);
+        assert!(security_bits(&stark_struct).unwrap() >= 100);
+
+        let err = derive_stark_struct(10, 1, 160, "GL", 3).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InsufficientSecurity(_))
+        ));
+        let err = derive_stark_struct(10, 1, 128, "MD5", 3).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InvalidHashType(_))
+        ));
+        let err = derive_stark_struct(10, 1, 128, "GL", 4).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_stark_struct() {
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json").unwrap();
+        assert_eq!(security_bits(&stark_struct).unwrap(), 8);
+        validate_stark_struct(&stark_struct, Some(8)).unwrap();
+        // the default level is required without a given one
+        for min_bits in [None, Some(100)] {
+            let err = validate_stark_struct(&stark_struct, min_bits).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::InsufficientSecurity(_))
+            ));
+        }
+
+        generate_stark_struct(10, 2, 100, "GL", 3, "/tmp/test_stark_struct.json").unwrap();
+        let generated = load_json::<StarkStruct>("/tmp/test_stark_struct.json").unwrap();
+        assert_eq!(generated, derive_stark_struct(10, 2, 100, "GL", 3).unwrap());
+        validate_stark_struct(&generated, Some(100)).unwrap();
+
+        let mut malformed = stark_struct.clone();
+        malformed.steps[1].nBits = 12;
+        let err = validate_stark_struct(&malformed, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+
+        let mut malformed = stark_struct.clone();
+        malformed.extensionDegree = 4;
+        let err = validate_stark_struct(&malformed, None).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+
+        // the quintic extension lifts the cap of the field size
+        let err = derive_stark_struct(70, 4, 128, "GL", 3).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InsufficientSecurity(_))
+        ));
+        let quintic = derive_stark_struct(70, 4, 128, "GL", 5).unwrap();
+        assert_eq!(quintic.extensionDegree, 5);
+        assert_eq!(quintic.nQueries, 32);
+        assert_eq!(security_bits(&quintic).unwrap(), 128);
+        validate_stark_struct(&quintic, None).unwrap();
+    }
+}
@@ -10,7 +10,7 @@ use crate::traits::{MTNodeType, MerkleTree};
 use fields::field_gl::Fr as FGL;
 use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 use serde::{
-    de::{self, MapAccess, SeqAccess, Visitor},
+    de::{self, DeserializeOwned, MapAccess, SeqAccess, Visitor},
     Deserialize, Deserializer,
 };
 use std::any::TypeId;
@@ -58,7 +58,7 @@ impl<'de> Deserialize<'de> for F3G {
             {
                 let mut entries = Vec::new();
                 while let Some(entry) = seq.next_element::<String>()? {
-                    let entry: u64 = entry.parse().unwrap();
+                    let entry: u64 = entry.parse().map_err(de::Error::custom)?;
                     entries.push(FGL::from(entry));
                 }
                 Ok(F3G::from_vec(entries))
@@ -68,7 +68,7 @@ impl<'de> Deserialize<'de> for F3G {
             where
                 E: de::Error,
             {
-                let ien: u64 = s.parse().unwrap();
+                let ien: u64 = s.parse().map_err(de::Error::custom)?;
                 Ok(F3G::from(ien))
             }
         }
@@ -116,7 +116,7 @@ impl<'de> Deserialize<'de> for F5G {
             {
                 let mut entries = Vec::new();
                 while let Some(entry) = seq.next_element::<String>()? {
-                    let entry: u64 = entry.parse().unwrap();
+                    let entry: u64 = entry.parse().map_err(de::Error::custom)?;
                     entries.push(FGL::from(entry));
                 }
                 Ok(F5G::from_vec(entries))
@@ -126,7 +126,7 @@ impl<'de> Deserialize<'de> for F5G {
             where
                 E: de::Error,
             {
-                let ien: u64 = s.parse().unwrap();
+                let ien: u64 = s.parse().map_err(de::Error::custom)?;
                 Ok(F5G::from(ien))
             }
         }
@@ -286,6 +286,19 @@ impl<M: MerkleTree> Serialize for StarkProof<M> {
     }
 }
 
+/// Fetch and decode the `key` entry of a zkin map, reporting a missing or malformed entry as a
+/// deserialization error instead of panicking.
+fn get_entry<T: DeserializeOwned, E: de::Error>(
+    map: &HashMap<String, serde_json::Value>,
+    key: &str,
+) -> Result<T, E> {
+    let value = map
+        .get(key)
+        .ok_or_else(|| E::custom(format!("missing field `{}`", key)))?;
+    serde_json::from_value(value.clone())
+        .map_err(|e| E::custom(format!("invalid field `{}`: {}", key, e)))
+}
+
 impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -310,35 +323,21 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                     map.insert(key, value);
                 }
                 let mut sp: StarkProof<MT> = Default::default();
-                let root: MT::MTNode =
-                    serde_json::from_value(map.get("root1").unwrap().clone()).unwrap();
-                sp.root1 = root;
-
-                let root: MT::MTNode =
-                    serde_json::from_value(map.get("root2").unwrap().clone()).unwrap();
-                sp.root2 = root;
-
-                let root: MT::MTNode =
-                    serde_json::from_value(map.get("root3").unwrap().clone()).unwrap();
-                sp.root3 = root;
-
-                let root: MT::MTNode =
-                    serde_json::from_value(map.get("root4").unwrap().clone()).unwrap();
-                sp.root4 = root;
-
-                let root = map.get("rootC");
-                if root.is_some() {
-                    let root: MT::MTNode = serde_json::from_value(root.unwrap().clone()).unwrap();
-                    sp.rootC = Some(root);
+                sp.root1 = get_entry::<_, M::Error>(&map, "root1")?;
+                sp.root2 = get_entry::<_, M::Error>(&map, "root2")?;
+                sp.root3 = get_entry::<_, M::Error>(&map, "root3")?;
+                sp.root4 = get_entry::<_, M::Error>(&map, "root4")?;
+
+                if map.contains_key("rootC") {
+                    sp.rootC = Some(get_entry::<_, M::Error>(&map, "rootC")?);
                 }
 
-                let prover_addr = map.get("proverAddr");
-                if prover_addr.is_some() {
-                    sp.prover_addr = serde_json::from_value(prover_addr.unwrap().clone()).unwrap();
+                if map.contains_key("proverAddr") {
+                    sp.prover_addr = get_entry::<_, M::Error>(&map, "proverAddr")?;
                 }
-                sp.evals = serde_json::from_value(map.get("evals").unwrap().clone()).unwrap();
+                sp.evals = get_entry::<_, M::Error>(&map, "evals")?;
 
-                sp.publics = serde_json::from_value(map.get("publics").unwrap().clone()).unwrap();
+                sp.publics = get_entry::<_, M::Error>(&map, "publics")?;
 
                 let mut fri_proof: FRIProof<MT::ExtendField, MT> = FRIProof::default();
 
@@ -363,15 +362,14 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                 let mut s0_siblings_all: Vec<Vec<Vec<Vec<MT::MTNode>>>> = vec![];
                 // handle queries[0]
                 for j in ["1", "2", "3", "4", "C"] {
-                    let key = map.get(&format!("s0_vals{}", j));
-                    if key.is_none() {
+                    let key = format!("s0_vals{}", j);
+                    if !map.contains_key(&key) {
                         log::info!("skip s0_vals{}", j);
                         s0_vals_all.push(vec![]);
                         s0_siblings_all.push(vec![]);
                         continue;
                     }
-                    let s0_vals: Vec<Vec<F3G>> =
-                        serde_json::from_value(key.unwrap().clone()).unwrap();
+                    let s0_vals: Vec<Vec<F3G>> = get_entry::<_, M::Error>(&map, &key)?;
                     let s0_vals: Vec<Vec<FGL>> = s0_vals
                         .iter()
                         .map(|e| {
@@ -386,9 +384,8 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                         })
                         .collect();
 
-                    let key = map.get(&format!("s0_siblings{}", j));
                     let s0_siblings: Vec<Vec<Vec<MT::MTNode>>> =
-                        serde_json::from_value(key.unwrap().clone()).unwrap();
+                        get_entry::<_, M::Error>(&map, &format!("s0_siblings{}", j))?;
                     s0_vals_all.push(s0_vals);
                     s0_siblings_all.push(s0_siblings);
                 }
@@ -430,12 +427,11 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
 
                 // handle query 1 to num_query
                 for i in 1..=num_query {
-                    let key = map.get(&format!("s{}_root", i));
-                    let root: MT::MTNode = serde_json::from_value(key.unwrap().clone()).unwrap();
-                    fri_proof.queries[i].root = root;
+                    fri_proof.queries[i].root =
+                        get_entry::<_, M::Error>(&map, &format!("s{}_root", i))?;
 
-                    let key = map.get(&format!("s{}_vals", i));
-                    let val: Vec<Vec<F3G>> = serde_json::from_value(key.unwrap().clone()).unwrap();
+                    let val: Vec<Vec<F3G>> =
+                        get_entry::<_, M::Error>(&map, &format!("s{}_vals", i))?;
                     let vals: Vec<Vec<FGL>> = val
                         .iter()
                         .map(|e| {
@@ -450,9 +446,14 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                         })
                         .collect();
 
-                    let key = map.get(&format!("s{}_siblings", i));
                     let sibs: Vec<Vec<Vec<MT::MTNode>>> =
-                        serde_json::from_value(key.unwrap().clone()).unwrap();
+                        get_entry::<_, M::Error>(&map, &format!("s{}_siblings", i))?;
+                    if sibs.len() < num_pol_queries || vals.len() < num_pol_queries {
+                        return Err(de::Error::custom(format!(
+                            "s{}_vals or s{}_siblings has less than {} queries",
+                            i, i, num_pol_queries
+                        )));
+                    }
                     fri_proof.queries[i].pol_queries = vec![vec![]; num_pol_queries];
                     for q in 0..num_pol_queries {
                         let node_to_bf = crate::traits::mt_node_to_basefield::<MT>(&sibs[q]);
@@ -461,8 +462,7 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                 }
 
                 // handle finalPol
-                let key = map.get("finalPol");
-                fri_proof.last = serde_json::from_value(key.unwrap().clone()).unwrap();
+                fri_proof.last = get_entry::<_, M::Error>(&map, "finalPol")?;
                 sp.fri_proof = fri_proof;
                 Ok(sp)
             }
@@ -599,6 +599,7 @@ mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
 
@@ -648,6 +649,7 @@ mod tests {
             &pil,
             &stark_struct,
             "",
+            None,
         )
         .unwrap();
 
@@ -699,6 +701,7 @@ mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
 
@@ -0,0 +1,493 @@
+// compact binary form of the stark proof, alongside the zkin json
+#![allow(non_snake_case)]
+use crate::errors::StarkyError;
+use crate::field_bls12381::Fr as Fr_BLS12381;
+use crate::field_bn128::Fr as Fr_BN128;
+use crate::fri::{FRIProof, Query};
+use crate::merklehash::MerkleTreeGL;
+use crate::merklehash_bls12381::MerkleTreeBLS12381;
+use crate::merklehash_bn128::MerkleTreeBN128;
+use crate::merklehash_keccak::MerkleTreeKeccak;
+use crate::stark_gen::StarkProof;
+use crate::traits::{FieldExtension, MTNodeType, MerkleTree};
+use crate::types::load_json;
+use anyhow::{anyhow, bail, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use fields::field_gl::{Fr as FGL, FrRepr};
+use fields::PrimeField;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+/// The leading bytes of a binary stark proof.
+pub const PROOF_BIN_MAGIC: &[u8; 4] = b"ESTK";
+/// The version of the binary layout, bump it once the layout changes.
+pub const PROOF_BIN_VERSION: u32 = 1;
+
+// the lengths are read from an untrusted input, the vectors grow beyond this by the elements read
+const MAX_PREALLOC: usize = 1 << 16;
+
+const HASH_TYPE_GL: u8 = 0;
+const HASH_TYPE_BN128: u8 = 1;
+const HASH_TYPE_BLS12381: u8 = 2;
+const HASH_TYPE_KECCAK: u8 = 3;
+
+/// Layout, all the integers are little-endian and every sequence is prefixed by its u64 length:
+///
+/// ```text
+/// magic: "ESTK", version: u32, hash type: u8 (0: GL, 1: BN128, 2: BLS12381, 3: KECCAK)
+/// root1, root2, root3, root4: node, has rootC: u8, [rootC: node]
+/// evals: [ext], publics: [ext], proverAddr: [u8]
+/// fri queries: [root: node, pol_queries: [[(vals: [u64], siblings: [[base]])]]]
+/// finalPol: [ext]
+/// ```
+///
+/// where a node is 4 u64, an ext is its dim as u8 followed by the elements, and a base is 1
+/// u64 for GL and KECCAK or 4 u64 for BN128 and BLS12381. Every u64 of a goldilocks element
+/// must be canonical.
+impl<M: MerkleTree> StarkProof<M> {
+    pub fn write_bin<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(PROOF_BIN_MAGIC)?;
+        writer.write_u32::<LittleEndian>(PROOF_BIN_VERSION)?;
+        writer.write_u8(hash_type::<M>()?)?;
+
+        write_node::<M, _>(&mut writer, &self.root1)?;
+        write_node::<M, _>(&mut writer, &self.root2)?;
+        write_node::<M, _>(&mut writer, &self.root3)?;
+        write_node::<M, _>(&mut writer, &self.root4)?;
+        match &self.rootC {
+            Some(root) => {
+                writer.write_u8(1)?;
+                write_node::<M, _>(&mut writer, root)?;
+            }
+            None => writer.write_u8(0)?,
+        }
+
+        write_exts(&mut writer, &self.evals)?;
+        write_exts(&mut writer, &self.publics)?;
+        write_len(&mut writer, self.prover_addr.len())?;
+        writer.write_all(self.prover_addr.as_bytes())?;
+
+        self.fri_proof.write_bin(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read the proof written by `write_bin`, a corrupt or truncated input is `MalformedInput`.
+    pub fn read_bin<R: Read>(reader: R) -> Result<Self>
+    where
+        M: Default,
+    {
+        Self::read_bin_unchecked(reader)
+            .map_err(|e| StarkyError::MalformedInput(format!("binary stark proof, {:#}", e)).into())
+    }
+
+    fn read_bin_unchecked<R: Read>(mut reader: R) -> Result<Self>
+    where
+        M: Default,
+    {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != PROOF_BIN_MAGIC {
+            bail!("Invalid binary stark proof, magic: {:?}", magic);
+        }
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != PROOF_BIN_VERSION {
+            bail!(
+                "Invalid binary stark proof version {}, expect {}",
+                version,
+                PROOF_BIN_VERSION
+            );
+        }
+        let expect = hash_type::<M>()?;
+        let actual = reader.read_u8()?;
+        if actual != expect {
+            bail!(
+                "Invalid hash type {} of binary stark proof, expect {}",
+                actual,
+                expect
+            );
+        }
+
+        let mut sp: StarkProof<M> = Default::default();
+        sp.root1 = read_node::<M, _>(&mut reader)?;
+        sp.root2 = read_node::<M, _>(&mut reader)?;
+        sp.root3 = read_node::<M, _>(&mut reader)?;
+        sp.root4 = read_node::<M, _>(&mut reader)?;
+        if reader.read_u8()? == 1 {
+            sp.rootC = Some(read_node::<M, _>(&mut reader)?);
+        }
+
+        sp.evals = read_exts(&mut reader)?;
+        sp.publics = read_exts(&mut reader)?;
+        let n = read_len(&mut reader)?;
+        let mut prover_addr = Vec::with_capacity(n.min(MAX_PREALLOC));
+        (&mut reader).take(n as u64).read_to_end(&mut prover_addr)?;
+        if prover_addr.len() != n {
+            bail!("truncated prover address");
+        }
+        sp.prover_addr = String::from_utf8(prover_addr)?;
+
+        sp.fri_proof = FRIProof::read_bin(&mut reader)?;
+        Ok(sp)
+    }
+}
+
+impl<F: FieldExtension, M: MerkleTree<ExtendField = F>> FRIProof<F, M> {
+    fn write_bin<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_len(writer, self.queries.len())?;
+        for query in self.queries.iter() {
+            write_node::<M, _>(writer, &query.root)?;
+            write_len(writer, query.pol_queries.len())?;
+            for pol_query in query.pol_queries.iter() {
+                write_len(writer, pol_query.len())?;
+                for (vals, siblings) in pol_query.iter() {
+                    write_len(writer, vals.len())?;
+                    for v in vals.iter() {
+                        writer.write_u64::<LittleEndian>(v.as_int())?;
+                    }
+                    write_len(writer, siblings.len())?;
+                    for sib in siblings.iter() {
+                        write_len(writer, sib.len())?;
+                        for s in sib.iter() {
+                            write_base::<M, _>(writer, s)?;
+                        }
+                    }
+                }
+            }
+        }
+        write_exts(writer, &self.last)
+    }
+
+    fn read_bin<R: Read>(reader: &mut R) -> Result<Self> {
+        let n_queries = read_len(reader)?;
+        let mut queries = Vec::with_capacity(n_queries.min(MAX_PREALLOC));
+        for _ in 0..n_queries {
+            let mut query = Query::<M::BaseField, M::MTNode> {
+                root: read_node::<M, _>(reader)?,
+                ..Default::default()
+            };
+            let n_pol_queries = read_len(reader)?;
+            for _ in 0..n_pol_queries {
+                let n_trees = read_len(reader)?;
+                let mut pol_query = Vec::with_capacity(n_trees.min(MAX_PREALLOC));
+                for _ in 0..n_trees {
+                    let n_vals = read_len(reader)?;
+                    let mut vals = Vec::with_capacity(n_vals.min(MAX_PREALLOC));
+                    for _ in 0..n_vals {
+                        vals.push(read_fgl(reader)?);
+                    }
+                    let n_siblings = read_len(reader)?;
+                    let mut siblings = Vec::with_capacity(n_siblings.min(MAX_PREALLOC));
+                    for _ in 0..n_siblings {
+                        let n_sib = read_len(reader)?;
+                        let mut sib = Vec::with_capacity(n_sib.min(MAX_PREALLOC));
+                        for _ in 0..n_sib {
+                            sib.push(read_base::<M, _>(reader)?);
+                        }
+                        siblings.push(sib);
+                    }
+                    pol_query.push((vals, siblings));
+                }
+                query.pol_queries.push(pol_query);
+            }
+            queries.push(query);
+        }
+        Ok(FRIProof {
+            queries,
+            last: read_exts(reader)?,
+        })
+    }
+}
+
+// tagged by the tree, since the KECCAK nodes are goldilocks elements like the GL ones
+fn hash_type<M: MerkleTree>() -> Result<u8> {
+    match M::HASH_TYPE {
+        "GL" => Ok(HASH_TYPE_GL),
+        "BN128" => Ok(HASH_TYPE_BN128),
+        "BLS12381" => Ok(HASH_TYPE_BLS12381),
+        "KECCAK" => Ok(HASH_TYPE_KECCAK),
+        _ => bail!("Invalid merkle tree {} to serialize", M::HASH_TYPE),
+    }
+}
+
+// the goldilocks base field is a single element, the others are packed into a node
+fn base_width<M: MerkleTree>() -> Result<usize> {
+    Ok(match hash_type::<M>()? {
+        HASH_TYPE_GL | HASH_TYPE_KECCAK => 1,
+        _ => 4,
+    })
+}
+
+fn write_len<W: Write>(writer: &mut W, len: usize) -> Result<()> {
+    writer.write_u64::<LittleEndian>(len as u64)?;
+    Ok(())
+}
+
+fn read_len<R: Read>(reader: &mut R) -> Result<usize> {
+    Ok(reader.read_u64::<LittleEndian>()? as usize)
+}
+
+// a goldilocks element, rejecting the u64 beyond the modulus instead of panicking
+fn read_fgl<R: Read>(reader: &mut R) -> Result<FGL> {
+    let v = reader.read_u64::<LittleEndian>()?;
+    FGL::from_repr(FrRepr::from(v)).map_err(|_| anyhow!("non canonical goldilocks element {}", v))
+}
+
+fn write_node<M: MerkleTree, W: Write>(writer: &mut W, node: &M::MTNode) -> Result<()> {
+    for e in node.as_elements().iter() {
+        writer.write_u64::<LittleEndian>(e.as_int())?;
+    }
+    Ok(())
+}
+
+fn read_node<M: MerkleTree, R: Read>(reader: &mut R) -> Result<M::MTNode> {
+    let mut elems = [FGL::ZERO; 4];
+    for e in elems.iter_mut() {
+        *e = read_fgl(reader)?;
+    }
+    Ok(M::MTNode::new(&elems))
+}
+
+fn write_base<M: MerkleTree, W: Write>(writer: &mut W, base: &M::BaseField) -> Result<()> {
+    let node = M::from_basefield(base);
+    for e in node.as_elements().iter().take(base_width::<M>()?) {
+        writer.write_u64::<LittleEndian>(e.as_int())?;
+    }
+    Ok(())
+}
+
+fn read_base<M: MerkleTree, R: Read>(reader: &mut R) -> Result<M::BaseField> {
+    let mut elems = [FGL::ZERO; 4];
+    for e in elems.iter_mut().take(base_width::<M>()?) {
+        *e = read_fgl(reader)?;
+    }
+    M::to_basefield(&M::MTNode::new(&elems))
+        .pop()
+        .ok_or_else(|| anyhow!("Invalid base field element"))
+}
+
+fn write_exts<F: FieldExtension, W: Write>(writer: &mut W, exts: &[F]) -> Result<()> {
+    write_len(writer, exts.len())?;
+    for ext in exts.iter() {
+        let elems = ext.as_elements();
+        writer.write_u8(elems.len() as u8)?;
+        for e in elems.iter() {
+            writer.write_u64::<LittleEndian>(e.as_int())?;
+        }
+    }
+    Ok(())
+}
+
+fn read_exts<F: FieldExtension, R: Read>(reader: &mut R) -> Result<Vec<F>> {
+    let n = read_len(reader)?;
+    let mut exts = Vec::with_capacity(n.min(MAX_PREALLOC));
+    for _ in 0..n {
+        let dim = reader.read_u8()? as usize;
+        if dim != 1 && dim != F::ZEROS.dim() {
+            bail!("Invalid dimension {} of field extension", dim);
+        }
+        let mut elems = Vec::with_capacity(dim);
+        for _ in 0..dim {
+            elems.push(read_fgl(reader)?);
+        }
+        exts.push(if dim == 1 {
+            F::from(elems[0])
+        } else {
+            F::from_vec(elems)
+        });
+    }
+    Ok(exts)
+}
+
+/// Convert the stark proof between the zkin json and the binary form.
+///
+/// The direction is detected from the input, a binary proof is converted to zkin json and
+/// vice versa.
+pub fn convert_proof(hash_type: &str, input: &str, output: &str) -> Result<()> {
+    match hash_type {
+        "GL" => convert::<MerkleTreeGL>(input, output),
+        "BN128" => convert::<MerkleTreeBN128>(input, output),
+        "BLS12381" => convert::<MerkleTreeBLS12381>(input, output),
+        "KECCAK" => convert::<MerkleTreeKeccak>(input, output),
+        _ => bail!(StarkyError::InvalidHashType(hash_type.to_string())),
+    }
+}
+
+fn convert<M: MerkleTree + Default>(input: &str, output: &str) -> Result<()> {
+    let mut magic = [0u8; 4];
+    let is_bin = {
+        let mut file = File::open(input).map_err(|e| anyhow!("open {} error: {:?}", input, e))?;
+        file.read_exact(&mut magic).is_ok() && &magic == PROOF_BIN_MAGIC
+    };
+
+    let writer = BufWriter::new(File::create(output)?);
+    if is_bin {
+        let proof = StarkProof::<M>::read_bin(BufReader::new(File::open(input)?))?;
+        serde_json::to_writer(writer, &proof)?;
+    } else {
+        let proof = load_json::<StarkProof<M>>(input)?;
+        proof.write_bin(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::StarkyError;
+    use crate::merklehash::MerkleTreeGL;
+    use crate::merklehash_bls12381::MerkleTreeBLS12381;
+    use crate::merklehash_bn128::MerkleTreeBN128;
+    use crate::merklehash_keccak::MerkleTreeKeccak;
+    use crate::polsarray::{PolKind, PolsArray};
+    use crate::stark_gen::StarkProof;
+    use crate::stark_setup::StarkSetup;
+    use crate::traits::{MerkleTree, Transcript};
+    use crate::transcript::TranscriptGL;
+    use crate::transcript_bls12381::TranscriptBLS128;
+    use crate::transcript_bn128::TranscriptBN128;
+    use crate::transcript_keccak::TranscriptKeccak;
+    use crate::types::{load_json, StarkStruct, PIL};
+    use std::fmt::Debug;
+
+    fn check_bin_ser_der<M: MerkleTree + Default + PartialEq + Debug, T: Transcript>(
+        pil_file: &str,
+        const_file: &str,
+        cm_file: &str,
+        stark_struct_file: &str,
+    ) {
+        let mut pil = load_json::<PIL>(pil_file).unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load(const_file).unwrap();
+        let mut cm_pol = PolsArray::new(&pil, PolKind::Commit);
+        cm_pol.load(cm_file).unwrap();
+        let stark_struct = load_json::<StarkStruct>(stark_struct_file).unwrap();
+
+        let setup = StarkSetup::<M>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        let starkproof = StarkProof::<M>::stark_gen::<T>(
+            cm_pol,
+            const_pol,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "273030697313060285579891744179749754319274977764",
+            None,
+        )
+        .unwrap();
+
+        let serialized = serde_json::to_string(&starkproof).unwrap();
+        let from_json: StarkProof<M> = serde_json::from_str(&serialized).unwrap();
+
+        let mut bin = vec![];
+        from_json.write_bin(&mut bin).unwrap();
+        assert!(bin.len() < serialized.len());
+        let actual = StarkProof::<M>::read_bin(&bin[..]).unwrap();
+
+        assert_eq!(actual, from_json);
+        assert_eq!(serde_json::to_string(&actual).unwrap(), serialized);
+
+        let mut bin = vec![];
+        starkproof.write_bin(&mut bin).unwrap();
+        let actual = StarkProof::<M>::read_bin(&bin[..]).unwrap();
+        assert_eq!(actual, starkproof);
+    }
+
+    #[test]
+    fn test_serialize_stark_proof_bin_gl() {
+        check_bin_ser_der::<MerkleTreeGL, TranscriptGL>(
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            "data/starkStruct.json.gl",
+        );
+    }
+
+    #[test]
+    fn test_serialize_stark_proof_bin_bn128() {
+        check_bin_ser_der::<MerkleTreeBN128, TranscriptBN128>(
+            "data/fib.pil.json",
+            "data/fib.const",
+            "data/fib.cm",
+            "data/starkStruct.json",
+        );
+    }
+
+    #[test]
+    fn test_serialize_stark_proof_bin_bls12381() {
+        check_bin_ser_der::<MerkleTreeBLS12381, TranscriptBLS128>(
+            "data/fib.pil.json",
+            "data/fib.const",
+            "data/fib.cm",
+            "data/starkStruct.json.bls12381",
+        );
+    }
+
+    #[test]
+    fn test_serialize_stark_proof_bin_keccak() {
+        check_bin_ser_der::<MerkleTreeKeccak, TranscriptKeccak>(
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            "data/starkStruct.json.gl",
+        );
+    }
+
+    #[test]
+    fn test_read_bin_with_other_hash_type() {
+        let mut pil = load_json::<PIL>("data/fib.pil.json.gl").unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load("data/fib.const.gl").unwrap();
+        let mut cm_pol = PolsArray::new(&pil, PolKind::Commit);
+        cm_pol.load("data/fib.cm.gl").unwrap();
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        let starkproof = StarkProof::<MerkleTreeGL>::stark_gen::<TranscriptGL>(
+            cm_pol,
+            const_pol,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "",
+            None,
+        )
+        .unwrap();
+
+        let mut bin = vec![];
+        starkproof.write_bin(&mut bin).unwrap();
+        assert!(StarkProof::<MerkleTreeBN128>::read_bin(&bin[..]).is_err());
+        // the KECCAK nodes are goldilocks elements too, but the tag tells them apart
+        assert!(StarkProof::<MerkleTreeKeccak>::read_bin(&bin[..]).is_err());
+
+        let malformed = |bin: &[u8]| {
+            let err = StarkProof::<MerkleTreeGL>::read_bin(bin).unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::MalformedInput(_))
+            ));
+        };
+        // truncated
+        malformed(&bin[..bin.len() / 2]);
+        // a non canonical element of root1, after the header
+        let mut corrupt = bin.clone();
+        corrupt[9..17].copy_from_slice(&u64::MAX.to_le_bytes());
+        malformed(&corrupt);
+        // the length of the evals, after the header, the 4 roots and the rootC, is huge
+        let offset = 4 + 4 + 1 + 4 * 32 + 1 + 32;
+        let mut corrupt = bin.clone();
+        corrupt[offset..(offset + 8)].copy_from_slice(&u64::MAX.to_le_bytes());
+        malformed(&corrupt);
+        // so is the length of the prover address, after no evals nor publics
+        let mut proof = starkproof;
+        proof.evals.clear();
+        proof.publics.clear();
+        let mut corrupt = vec![];
+        proof.write_bin(&mut corrupt).unwrap();
+        corrupt[(offset + 16)..(offset + 24)].copy_from_slice(&(1u64 << 62).to_le_bytes());
+        malformed(&corrupt);
+    }
+}
@@ -2,19 +2,21 @@
 #![allow(clippy::needless_range_loop)]
 
 use crate::constant::{get_max_workers, MAX_OPS_PER_THREAD, MG, MIN_OPS_PER_THREAD, SHIFT};
+use crate::errors::StarkyError;
 use crate::fft::FFT;
 use crate::fft_p::{fft, ifft, interpolate};
 use crate::fri::FRIProof;
 use crate::fri::FRI;
 use crate::helper::pretty_print_array;
 use crate::interpreter::compile_code;
+use crate::memory_plan::{MemoryPlan, Stage};
 use crate::polsarray::PolsArray;
 use crate::polutils::batch_inverse;
 use crate::starkinfo::{Program, StarkInfo};
 use crate::starkinfo_codegen::{Polynom, Segment};
 use crate::traits::{FieldExtension, MTNodeType, MerkleTree, Transcript};
 use crate::types::{StarkStruct, PIL};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use fields::field_gl::Fr as FGL;
 use profiler_macro::time_profiler;
 use rayon::prelude::*;
@@ -137,15 +139,18 @@ impl<F: FieldExtension> Default for StarkContext<F> {
 }
 
 impl<F: FieldExtension> StarkContext<F> {
-    pub fn get_mut_base(&mut self, section: &str) -> &mut Vec<FGL> {
+    pub fn get_mut_base(&mut self, section: &str) -> Result<&mut Vec<FGL>> {
         match section {
-            "xDivXSubXi" => &mut self.xDivXSubXi,
-            "xDivXSubWXi" => &mut self.xDivXSubWXi,
-            _ => panic!("invalid symbol {:?}", section),
+            "xDivXSubXi" => Ok(&mut self.xDivXSubXi),
+            "xDivXSubWXi" => Ok(&mut self.xDivXSubWXi),
+            _ => bail!(StarkyError::MalformedPil(format!(
+                "invalid section {}",
+                section
+            ))),
         }
     }
-    pub fn get_mut(&mut self, section: &str) -> &mut Vec<F> {
-        match section {
+    pub fn get_mut(&mut self, section: &str) -> Result<&mut Vec<F>> {
+        let section = match section {
             "tmp" => &mut self.tmp,
             "cm1_n" => &mut self.cm1_n,
             "cm1_2ns" => &mut self.cm1_2ns,
@@ -167,10 +172,12 @@ impl<F: FieldExtension> StarkContext<F> {
             "tmpexp_n" => &mut self.tmpexp_n,
             "x_n" => &mut self.x_n,
             "x_2ns" => &mut self.x_2ns,
-            _ => {
-                panic!("invalid symbol {:?}", section);
-            }
-        }
+            _ => bail!(StarkyError::MalformedPil(format!(
+                "invalid section {}",
+                section
+            ))),
+        };
+        Ok(section)
     }
 }
 
@@ -199,7 +206,24 @@ impl<M: MerkleTree> StarkProof<M> {
         _pil: &PIL,
         stark_struct: &StarkStruct,
         prover_addr: &str,
+        memory_limit: Option<usize>,
     ) -> Result<StarkProof<M>> {
+        cm_pols.check_shape(stark_struct.nBits)?;
+        const_pols.check_shape(stark_struct.nBits)?;
+        if starkinfo.ext_dim != M::ExtendField::DIM {
+            bail!(StarkyError::MalformedInput(format!(
+                "the setup is built for the extension degree {}, but the prover is of {}",
+                starkinfo.ext_dim,
+                M::ExtendField::DIM
+            )));
+        }
+        let plan = MemoryPlan::fit::<M>(
+            starkinfo,
+            stark_struct,
+            const_tree.element_size(),
+            memory_limit,
+        )?;
+        plan.report();
         let mut ctx = StarkContext::<M::ExtendField>::default();
 
         let mut fftobj = FFT::new();
@@ -211,23 +235,13 @@ impl<M: MerkleTree> StarkProof<M> {
 
         let mut n_cm = starkinfo.n_cm1;
 
+        // the sections are allocated right before their first use and freed after their last
+        // one, following the memory plan
         log::trace!("Alloc context memory");
-        ctx.cm1_n = cm_pols.write_buff();
+        ctx.cm1_n = cm_pols.write_buff()?;
         drop(cm_pols);
-
-        ctx.cm2_n = vec![M::ExtendField::ZERO; (starkinfo.map_sectionsN.cm2_n) * ctx.N];
-        ctx.cm3_n = vec![M::ExtendField::ZERO; (starkinfo.map_sectionsN.cm3_n) * ctx.N];
-        ctx.tmpexp_n = vec![M::ExtendField::ZERO; (starkinfo.map_sectionsN.tmpexp_n) * ctx.N];
-
-        ctx.cm1_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm1_n * ctx.Next];
-        ctx.cm2_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm2_n * ctx.Next];
-        ctx.cm3_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm3_n * ctx.Next];
-        ctx.cm4_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm4_n * ctx.Next];
         ctx.const_2ns = vec![M::ExtendField::ZERO; const_tree.element_size()];
 
-        ctx.q_2ns = vec![M::ExtendField::ZERO; starkinfo.q_dim * ctx.Next];
-        ctx.f_2ns = vec![M::ExtendField::ZERO; 3 * ctx.Next];
-
         ctx.x_n = vec![M::ExtendField::ZERO; ctx.N];
 
         let xx = M::ExtendField::ONE;
@@ -238,18 +252,13 @@ impl<M: MerkleTree> StarkProof<M> {
         });
 
         let extend_bits = ctx.nbits_ext - ctx.nbits;
-        ctx.x_2ns = vec![M::ExtendField::ZERO; ctx.N << extend_bits];
-
         let shift_ext: M::ExtendField = M::ExtendField::from(*SHIFT);
         let w_nbits_ext: M::ExtendField = M::ExtendField::from(MG.0[ctx.nbits_ext]);
-        ctx.x_2ns.par_iter_mut().enumerate().for_each(|(k, xb)| {
-            *xb = shift_ext * w_nbits_ext.exp(k);
-        });
 
         ctx.Zi = build_Zh_Inv::<M::ExtendField>(ctx.nbits, extend_bits, 0);
 
         log::trace!("Convert const pols to array");
-        ctx.const_n = const_pols.write_buff();
+        ctx.const_n = const_pols.write_buff()?;
         const_tree.to_extend(&mut ctx.const_2ns);
         drop(const_pols);
 
@@ -263,9 +272,12 @@ impl<M: MerkleTree> StarkProof<M> {
                     starkinfo,
                     &program.publics_code[i],
                     pe.idx,
-                );
+                )?;
             } else {
-                panic!("Invalid public type {}", pe.polType);
+                bail!(StarkyError::MalformedPil(format!(
+                    "invalid public type {}",
+                    pe.polType
+                )));
             }
         }
 
@@ -283,6 +295,7 @@ impl<M: MerkleTree> StarkProof<M> {
         let mut result = vec![M::ExtendField::ZERO; (1 << stark_struct.nBitsExt) * 8];
         log::trace!("Merkelizing 1....");
         let tree1 = extend_and_merkelize::<M>(&mut ctx, starkinfo, "cm1_n", &mut result)?;
+        ctx.cm1_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm1_n * ctx.Next];
         tree1.to_extend(&mut ctx.cm1_2ns);
 
         log::trace!(
@@ -297,21 +310,33 @@ impl<M: MerkleTree> StarkProof<M> {
 
         log::trace!("challenge[0] {}", ctx.challenge[0]);
         log::trace!("challenge[1] {}", ctx.challenge[1]);
-
-        calculate_exps_parallel(&mut ctx, starkinfo, &program.step2prev, "n", "step2prev");
+        plan.stage_done(Stage::Commit1);
+
+        ctx.cm2_n = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm2_n * ctx.N];
+        ctx.cm3_n = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm3_n * ctx.N];
+        ctx.tmpexp_n = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.tmpexp_n * ctx.N];
+        calculate_exps_parallel(
+            &mut ctx,
+            starkinfo,
+            &program.step2prev,
+            "n",
+            "step2prev",
+            plan.exec_rows,
+        )?;
 
         for pu in starkinfo.pu_ctx.iter() {
-            let f_pol = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.f_exp_id]);
-            let t_pol = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.t_exp_id]);
-            let (h1, h2) = calculate_H1H2(f_pol, t_pol);
-            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], h1);
+            let f_pol = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.f_exp_id])?;
+            let t_pol = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.t_exp_id])?;
+            let (h1, h2) = calculate_H1H2(f_pol, t_pol)?;
+            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], h1)?;
             n_cm += 1;
-            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], h2);
+            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], h2)?;
             n_cm += 1;
         }
 
         log::trace!("Merkelizing 2....");
         let tree2 = extend_and_merkelize::<M>(&mut ctx, starkinfo, "cm2_n", &mut result)?;
+        ctx.cm2_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm2_n * ctx.Next];
         tree2.to_extend(&mut ctx.cm2_2ns);
         transcript.put(&[tree2.root().as_elements().to_vec()])?;
         log::trace!(
@@ -326,40 +351,56 @@ impl<M: MerkleTree> StarkProof<M> {
         // beta
         log::trace!("challenge[2] {}", ctx.challenge[2]);
         log::trace!("challenge[3] {}", ctx.challenge[3]);
+        plan.stage_done(Stage::Commit2);
 
-        calculate_exps_parallel(&mut ctx, starkinfo, &program.step3prev, "n", "step3prev");
+        calculate_exps_parallel(
+            &mut ctx,
+            starkinfo,
+            &program.step3prev,
+            "n",
+            "step3prev",
+            plan.exec_rows,
+        )?;
 
         for (i, pu) in starkinfo.pu_ctx.iter().enumerate() {
             log::trace!("Calculating z for plookup {}", i);
-            let p_num = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.num_id]);
-            let p_den = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.den_id]);
-            let z = calculate_Z(p_num, p_den);
-            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], z);
+            let p_num = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.num_id])?;
+            let p_den = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pu.den_id])?;
+            let z = calculate_Z(p_num, p_den)?;
+            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], z)?;
             n_cm += 1;
         }
 
         for (i, pe) in starkinfo.pe_ctx.iter().enumerate() {
             log::trace!("Calculating z for permutation {}", i);
-            let p_num = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pe.num_id]);
-            let p_den = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pe.den_id]);
-            let z = calculate_Z(p_num, p_den);
-            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], z);
+            let p_num = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pe.num_id])?;
+            let p_den = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&pe.den_id])?;
+            let z = calculate_Z(p_num, p_den)?;
+            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], z)?;
             n_cm += 1;
         }
         for (i, ci) in starkinfo.ci_ctx.iter().enumerate() {
             log::trace!("Calculating z for connection {}", i);
-            let p_num = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&ci.num_id]);
-            let p_den = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&ci.den_id]);
-            let z = calculate_Z(p_num, p_den);
-            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], z);
+            let p_num = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&ci.num_id])?;
+            let p_den = get_pol(&mut ctx, starkinfo, starkinfo.exp2pol[&ci.den_id])?;
+            let z = calculate_Z(p_num, p_den)?;
+            set_pol(&mut ctx, starkinfo, &starkinfo.cm_n[n_cm], z)?;
             n_cm += 1;
         }
 
-        calculate_exps_parallel(&mut ctx, starkinfo, &program.step3, "n", "step3");
+        calculate_exps_parallel(
+            &mut ctx,
+            starkinfo,
+            &program.step3,
+            "n",
+            "step3",
+            plan.exec_rows,
+        )?;
 
         log::trace!("Merkelizing 3....");
 
         let tree3 = extend_and_merkelize::<M>(&mut ctx, starkinfo, "cm3_n", &mut result)?;
+        ctx.cm3_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm3_n * ctx.Next];
         tree3.to_extend(&mut ctx.cm3_2ns);
         transcript.put(&[tree3.root().as_elements().to_vec()])?;
 
@@ -369,15 +410,36 @@ impl<M: MerkleTree> StarkProof<M> {
             tree3.root(),
         );
 
+        plan.stage_done(Stage::Commit3);
+        // the n domain is not used any more
+        drop(result);
+        for section in ["cm1_n", "cm2_n", "cm3_n", "tmpexp_n", "const_n", "x_n"] {
+            *ctx.get_mut(section)? = Vec::new();
+        }
+
         // 4. Compute C Polynomial
         ctx.challenge[4] = transcript.get_field(); // vc
 
-        calculate_exps_parallel(&mut ctx, starkinfo, &program.step42ns, "2ns", "step4");
+        ctx.x_2ns = vec![M::ExtendField::ZERO; ctx.Next];
+        ctx.x_2ns.par_iter_mut().enumerate().for_each(|(k, xb)| {
+            *xb = shift_ext * w_nbits_ext.exp(k);
+        });
+        ctx.q_2ns = vec![M::ExtendField::ZERO; starkinfo.q_dim * ctx.Next];
+        calculate_exps_parallel(
+            &mut ctx,
+            starkinfo,
+            &program.step42ns,
+            "2ns",
+            "step4",
+            plan.exec_rows,
+        )?;
+        ctx.x_2ns = Vec::new();
 
         log::trace!("Calculate c polynomial");
         let mut qq1 = vec![M::ExtendField::ZERO; ctx.q_2ns.len()];
-        let mut qq2 = vec![M::ExtendField::ZERO; starkinfo.q_dim * ctx.Next * starkinfo.q_deg];
         ifft(&ctx.q_2ns, starkinfo.q_dim, ctx.nbits_ext, &mut qq1);
+        ctx.q_2ns = Vec::new();
+        let mut qq2 = vec![M::ExtendField::ZERO; starkinfo.q_dim * ctx.Next * starkinfo.q_deg];
 
         let mut cur_s = M::ExtendField::ONE;
         let shift_inv = (M::ExtendField::inv(&shift_ext)).exp(ctx.N);
@@ -393,6 +455,9 @@ impl<M: MerkleTree> StarkProof<M> {
             cur_s *= shift_inv;
         }
 
+        drop(qq1);
+
+        ctx.cm4_2ns = vec![M::ExtendField::ZERO; starkinfo.map_sectionsN.cm4_n * ctx.Next];
         // powdr may produce constant polynomial only
         if starkinfo.q_deg > 0 {
             fft(
@@ -411,6 +476,8 @@ impl<M: MerkleTree> StarkProof<M> {
             tree4.root(),
         );
         transcript.put(&[tree4.root().as_elements().to_vec()])?;
+        drop(qq2);
+        plan.stage_done(Stage::Quotient);
 
         //if ctx.cm4_2ns.len() > 0 {
         //    log::trace!("tree4[0] {}", ctx.cm4_2ns[0]);
@@ -449,23 +516,22 @@ impl<M: MerkleTree> StarkProof<M> {
                     size: starkinfo.n_constants,
                     dim: 1,
                 },
-                "cm" => get_pol_ref(&mut ctx, starkinfo, starkinfo.cm_2ns[ev.id]),
-                _ => {
-                    panic!("Invalid ev type: {}", ev.type_);
-                }
+                "cm" => get_pol_ref(&mut ctx, starkinfo, starkinfo.cm_2ns[ev.id])?,
+                _ => bail!(StarkyError::MalformedPil(format!(
+                    "invalid ev type {}",
+                    ev.type_
+                ))),
             };
             let l = if ev.prime { &LpEv } else { &LEv };
             let acc = (0..N)
                 .into_par_iter()
                 .map(|k| {
+                    let pos = p.offset + (k << extend_bits) * (p.size);
                     let v = match p.dim {
-                        1 => p.buffer[(k << extend_bits) * (p.size) + (p.offset)],
-                        // TODO: We need to support F5G
-                        _ => M::ExtendField::from_vec(vec![
-                            p.buffer[p.offset + (k << extend_bits) * (p.size)].to_be(),
-                            p.buffer[p.offset + (k << extend_bits) * (p.size) + 1].to_be(),
-                            p.buffer[p.offset + (k << extend_bits) * (p.size) + 2].to_be(),
-                        ]),
+                        1 => p.buffer[pos],
+                        _ => M::ExtendField::from_vec(
+                            (0..p.dim).map(|d| p.buffer[pos + d].to_be()).collect(),
+                        ),
                     };
                     v * l[k]
                 })
@@ -496,8 +562,9 @@ impl<M: MerkleTree> StarkProof<M> {
 
         let extend_size = N << extend_bits;
 
-        ctx.xDivXSubXi = vec![FGL::ZERO; extend_size * 3];
-        ctx.xDivXSubWXi = vec![FGL::ZERO; extend_size * 3];
+        let dim = M::ExtendField::DIM;
+        ctx.xDivXSubXi = vec![FGL::ZERO; extend_size * dim];
+        ctx.xDivXSubWXi = vec![FGL::ZERO; extend_size * dim];
         let mut tmp_den = vec![M::ExtendField::ZERO; extend_size];
         let mut tmp_denw = vec![M::ExtendField::ZERO; extend_size];
 
@@ -520,31 +587,41 @@ impl<M: MerkleTree> StarkProof<M> {
         tmp_den = batch_inverse(&tmp_den);
         tmp_denw = batch_inverse(&tmp_denw);
         ctx.xDivXSubXi
-            .par_chunks_mut(3)
-            .zip_eq(ctx.xDivXSubWXi.par_chunks_mut(3))
+            .par_chunks_mut(dim)
+            .zip_eq(ctx.xDivXSubWXi.par_chunks_mut(dim))
             .enumerate()
             .for_each(|(k, (xxx, xxwx))| {
-                let v = (tmp_den[k] * x_buff[k]).as_elements();
-                xxx[0] = v[0];
-                xxx[1] = v[1];
-                xxx[2] = v[2];
-
-                let vw = (tmp_denw[k] * x_buff[k]).as_elements();
-                xxwx[0] = vw[0];
-                xxwx[1] = vw[1];
-                xxwx[2] = vw[2];
+                xxx.copy_from_slice(&(tmp_den[k] * x_buff[k]).as_elements());
+                xxwx.copy_from_slice(&(tmp_denw[k] * x_buff[k]).as_elements());
             });
-        calculate_exps_parallel(&mut ctx, starkinfo, &program.step52ns, "2ns", "step5");
+        drop(tmp_den);
+        drop(tmp_denw);
+        drop(x_buff);
+        ctx.f_2ns = vec![M::ExtendField::ZERO; dim * ctx.Next];
+        calculate_exps_parallel(
+            &mut ctx,
+            starkinfo,
+            &program.step52ns,
+            "2ns",
+            "step5",
+            plan.exec_rows,
+        )?;
 
         let mut fri_pol = vec![M::ExtendField::ZERO; N << extend_bits];
         fri_pol.par_iter_mut().enumerate().for_each(|(i, o)| {
-            *o = M::ExtendField::from_vec(vec![
-                ctx.f_2ns[i * 3].to_be(),
-                ctx.f_2ns[i * 3 + 1].to_be(),
-                ctx.f_2ns[i * 3 + 2].to_be(),
-            ]);
+            *o = M::ExtendField::from_vec(
+                ctx.f_2ns[(i * dim)..((i + 1) * dim)]
+                    .iter()
+                    .map(|e| e.to_be())
+                    .collect(),
+            );
         });
 
+        // FRI only queries the trees, keep the evaluations and drop the rest of the context
+        let evals = std::mem::take(&mut ctx.evals);
+        let publics = std::mem::take(&mut ctx.publics);
+        drop(ctx);
+
         let query_pol = |idx: usize| -> Vec<(Vec<FGL>, Vec<Vec<M::BaseField>>)> {
             vec![
                 tree1.get_group_proof(idx).unwrap(),
@@ -556,6 +633,7 @@ impl<M: MerkleTree> StarkProof<M> {
         };
         let mut fri = FRI::new(stark_struct);
         let friProof = fri.prove::<M::ExtendField, M, T>(&mut transcript, &fri_pol, query_pol)?;
+        plan.stage_done(Stage::Fri);
 
         Ok(StarkProof {
             rootC: Some(const_tree.root()),
@@ -564,8 +642,8 @@ impl<M: MerkleTree> StarkProof<M> {
             root3: tree3.root(),
             root4: tree4.root(),
             fri_proof: friProof,
-            evals: ctx.evals.clone(),
-            publics: ctx.publics.clone(),
+            evals,
+            publics,
             prover_addr: prover_addr.to_string(),
         })
     }
@@ -575,9 +653,9 @@ impl<M: MerkleTree> StarkProof<M> {
         starkinfo: &StarkInfo,
         seg: &Segment,
         idx: usize,
-    ) -> T {
+    ) -> Result<T> {
         ctx.tmp = vec![T::ZERO; seg.tmp_used];
-        let t = compile_code(ctx, starkinfo, &seg.first, "n", true);
+        let t = compile_code(ctx, starkinfo, &seg.first, "n", true)?;
         //log::trace!("calculate_exp_at_point compile_code ctx.first:\n{}", t);
 
         // just let public codegen run multiple times
@@ -610,33 +688,31 @@ fn set_pol<F: FieldExtension>(
     starkinfo: &StarkInfo,
     id_pol: &usize,
     pol: Vec<F>,
-) {
+) -> Result<()> {
     let id_pol = *id_pol;
-    let p = get_pol_ref(ctx, starkinfo, id_pol);
+    let p = get_pol_ref(ctx, starkinfo, id_pol)?;
     if p.dim == 1 {
         for i in 0..p.deg {
             p.buffer[p.offset + i * p.size] = pol[i];
         }
-    } else if p.dim == 3 {
+    } else if p.dim == F::DIM {
         for i in 0..p.deg {
             let elems = pol[i].as_elements();
-            if elems.len() > 1 {
-                p.buffer[p.offset + i * p.size] = elems[0].into();
-                p.buffer[p.offset + i * p.size + 1] = elems[1].into();
-                p.buffer[p.offset + i * p.size + 2] = elems[2].into();
-            } else {
-                p.buffer[p.offset + i * p.size] = elems[0].into();
-                p.buffer[p.offset + i * p.size + 1] = F::ZERO;
-                p.buffer[p.offset + i * p.size + 2] = F::ZERO;
+            for d in 0..p.dim {
+                p.buffer[p.offset + i * p.size + d] = match elems.get(d) {
+                    Some(e) => (*e).into(),
+                    None => F::ZERO,
+                };
             }
         }
     } else {
-        panic!("Invalid dim {}", p.dim);
+        bail!(StarkyError::MalformedPil(format!("invalid dim {}", p.dim)));
     }
+    Ok(())
 }
 
 #[time_profiler("calculate_H1H2")]
-fn calculate_H1H2<F: FieldExtension>(f: Vec<F>, t: Vec<F>) -> (Vec<F>, Vec<F>) {
+fn calculate_H1H2<F: FieldExtension>(f: Vec<F>, t: Vec<F>) -> Result<(Vec<F>, Vec<F>)> {
     let mut idx_t: HashMap<F, usize> = HashMap::with_capacity(t.len());
     let mut s: Vec<(F, usize)> = vec![(F::ZERO, 0); t.len() + f.len()];
 
@@ -646,11 +722,14 @@ fn calculate_H1H2<F: FieldExtension>(f: Vec<F>, t: Vec<F>) -> (Vec<F>, Vec<F>) {
     }
 
     for (i, e) in f.iter().enumerate() {
-        let idx = idx_t.get(e);
-        if idx.is_none() {
-            panic!("Number not included: {:?}", e);
-        }
-        s[i + t.len()] = (*e, *idx.unwrap());
+        let idx = match idx_t.get(e) {
+            Some(idx) => *idx,
+            None => bail!(StarkyError::UnsatisfiedIdentities(format!(
+                "plookup, {} is not included in the table",
+                e
+            ))),
+        };
+        s[i + t.len()] = (*e, idx);
     }
 
     s.sort_by(|a, b| a.1.cmp(&b.1));
@@ -664,10 +743,10 @@ fn calculate_H1H2<F: FieldExtension>(f: Vec<F>, t: Vec<F>) -> (Vec<F>, Vec<F>) {
             *h1_ = s[2 * i].0;
             *h2_ = s[2 * i + 1].0;
         });
-    (h1, h2)
+    Ok((h1, h2))
 }
 
-fn calculate_Z<F: FieldExtension>(num: Vec<F>, den: Vec<F>) -> Vec<F> {
+fn calculate_Z<F: FieldExtension>(num: Vec<F>, den: Vec<F>) -> Result<Vec<F>> {
     let N = num.len();
     assert_eq!(N, den.len());
     let den_inv = batch_inverse(&den);
@@ -678,49 +757,49 @@ fn calculate_Z<F: FieldExtension>(num: Vec<F>, den: Vec<F>) -> Vec<F> {
     }
 
     let check_val = z[N - 1] * (num[N - 1] * den_inv[N - 1]);
-    assert!(check_val._eq(&F::one()));
-    z
+    if !check_val._eq(&F::one()) {
+        bail!(StarkyError::UnsatisfiedIdentities(
+            "the grand product of the permutation or connection is not one".to_string()
+        ));
+    }
+    Ok(z)
 }
 
 fn get_pol_ref<'a, F: FieldExtension>(
     ctx: &'a mut StarkContext<F>,
     starkinfo: &StarkInfo,
     id_pol: usize,
-) -> Polynom<'a, F> {
+) -> Result<Polynom<'a, F>> {
     let p = &starkinfo.var_pol_map[id_pol];
-    Polynom {
-        buffer: ctx.get_mut(&p.section),
+    Ok(Polynom {
+        buffer: ctx.get_mut(&p.section)?,
         deg: starkinfo.map_deg.get(&p.section),
         offset: p.section_pos,
         size: starkinfo.map_sectionsN.get(&p.section),
         dim: p.dim,
-    }
+    })
 }
 
 pub fn get_pol<F: FieldExtension>(
     ctx: &mut StarkContext<F>,
     starkinfo: &StarkInfo,
     id_pol: usize,
-) -> Vec<F> {
-    let p = get_pol_ref(ctx, starkinfo, id_pol);
+) -> Result<Vec<F>> {
+    let p = get_pol_ref(ctx, starkinfo, id_pol)?;
     let mut res = vec![F::ZERO; p.deg];
-    // TODO: Support F5G
     if p.dim == 1 {
         for i in 0..p.deg {
             res[i] = p.buffer[p.offset + i * p.size];
         }
-    } else if p.dim == 3 {
+    } else if p.dim == F::DIM {
         for i in 0..p.deg {
-            res[i] = F::from_vec(vec![
-                p.buffer[p.offset + i * p.size].to_be(),
-                p.buffer[p.offset + i * p.size + 1].to_be(),
-                p.buffer[p.offset + i * p.size + 2].to_be(),
-            ]);
+            let pos = p.offset + i * p.size;
+            res[i] = F::from_vec((0..p.dim).map(|d| p.buffer[pos + d].to_be()).collect());
         }
     } else {
-        panic!("Invalid dim {}", p.dim);
+        bail!(StarkyError::MalformedPil(format!("invalid dim {}", p.dim)));
     }
-    res
+    Ok(res)
 }
 
 #[time_profiler("extend_and_merkelize")]
@@ -737,7 +816,7 @@ pub fn extend_and_merkelize<M: MerkleTree>(
     let curr_size = (1 << nBitsExt) * n_pols;
     result.resize(curr_size, M::ExtendField::ZERO);
 
-    let p = ctx.get_mut(section_name);
+    let p = ctx.get_mut(section_name)?;
     interpolate(p, n_pols, nBits, result, nBitsExt);
     let mut p_be = vec![FGL::ZERO; result.len()];
     p_be.par_iter_mut()
@@ -758,7 +837,7 @@ pub fn merkelize<M: MerkleTree>(
 ) -> Result<M> {
     let nBitsExt = ctx.nbits_ext;
     let n_pols = starkinfo.map_sectionsN.get(section_name);
-    let p = ctx.get_mut(section_name);
+    let p = ctx.get_mut(section_name)?;
     let mut p_be = vec![FGL::ZERO; p.len()];
     p_be.par_iter_mut().zip(p).for_each(|(be_out, f3g_in)| {
         *be_out = f3g_in.to_be();
@@ -775,9 +854,9 @@ pub fn calculate_exps<F: FieldExtension>(
     dom: &str,
     //step: &str,
     N: usize,
-) {
+) -> Result<()> {
     ctx.tmp = vec![F::ZERO; seg.tmp_used];
-    let c_first = compile_code(ctx, starkinfo, &seg.first, dom, false);
+    let c_first = compile_code(ctx, starkinfo, &seg.first, dom, false)?;
     /*
     log::trace!(
         "calculate_exps compile_code {} ctx.first:\n{}",
@@ -794,11 +873,12 @@ pub fn calculate_exps<F: FieldExtension>(
     // next ~ N-next: c_i
     // N-next ~ N: c_last
     for i in 0..N {
-        c_first.eval(ctx, i);
+        c_first.eval(ctx, i)?;
         if (i % 10000) == 0 {
             log::trace!("Calculating expression.. {}/{}", i, N);
         }
     }
+    Ok(())
 }
 
 #[time_profiler()]
@@ -808,7 +888,8 @@ pub fn calculate_exps_parallel<F: FieldExtension>(
     seg: &Segment,
     _dom: &str,
     step: &str,
-) {
+    exec_rows: Option<usize>,
+) -> Result<()> {
     #[derive(Debug)]
     struct ExecItem {
         name: String,
@@ -974,10 +1055,10 @@ pub fn calculate_exps_parallel<F: FieldExtension>(
             });
             "2ns"
         }
-        _ => panic!("Invalid step {}", step),
+        _ => bail!(StarkyError::Unknown(format!("invalid step {}", step))),
     };
 
-    let set_width = |section: &mut ExecItem| {
+    let set_width = |section: &mut ExecItem| -> Result<()> {
         let name: &str = section.name.as_str();
         if name == "const_n" || name == "const_2ns" {
             section.width = starkinfo.n_constants;
@@ -986,113 +1067,134 @@ pub fn calculate_exps_parallel<F: FieldExtension>(
         } else if ["x_n", "x_2ns"].contains(&name) {
             section.width = 1;
         } else if ["xDivXSubXi", "xDivXSubWXi", "f_2ns"].contains(&name) {
-            section.width = 3;
+            section.width = F::DIM;
         } else if ["q_2ns"].contains(&name) {
             section.width = starkinfo.q_dim;
         } else {
-            panic!("Invalid section name {}", name)
+            bail!(StarkyError::MalformedPil(format!(
+                "invalid section {}",
+                name
+            )));
         }
+        Ok(())
     };
 
     for i in 0..exec_info.input_sections.len() {
-        set_width(&mut exec_info.input_sections[i]);
+        set_width(&mut exec_info.input_sections[i])?;
     }
     for i in 0..exec_info.output_sections.len() {
-        set_width(&mut exec_info.output_sections[i]);
+        set_width(&mut exec_info.output_sections[i])?;
     }
 
     let extend_bits = ctx.nbits_ext - ctx.nbits;
     let n = if dom == "n" { ctx.N } else { ctx.Next };
     let next = if dom == "n" { 1 } else { 1 << extend_bits };
 
-    let mut n_per_thread = (n - 1) / get_max_workers() + 1;
+    let workers = get_max_workers().max(1);
+    let mut n_per_thread = (n - 1) / workers + 1;
     if n_per_thread > MAX_OPS_PER_THREAD {
         n_per_thread = MAX_OPS_PER_THREAD
     };
     if n_per_thread < MIN_OPS_PER_THREAD {
         n_per_thread = MIN_OPS_PER_THREAD
     };
+    if let Some(rows) = exec_rows {
+        n_per_thread = n_per_thread.min(std::cmp::max(rows / workers, 1));
+    }
 
-    let mut ctx_chunks: Vec<StarkContext<F>> = vec![];
-
-    for i in (0..n).step_by(n_per_thread) {
-        let cur_n = std::cmp::min(n_per_thread, n - i);
-        let mut tmp_ctx = StarkContext::<F> {
-            N: n,
-            Next: next,
-            nbits: ctx.nbits,
-            nbits_ext: ctx.nbits_ext,
-            evals: ctx.evals.clone(),
-            publics: ctx.publics.clone(),
-            challenge: ctx.challenge.clone(),
-            ..Default::default()
-        };
+    // the chunks of a batch are copied, evaluated and written back before the next batch
+    let starts: Vec<usize> = (0..n).step_by(n_per_thread).collect();
+    let batch = match exec_rows {
+        Some(rows) => std::cmp::max(rows / n_per_thread, 1),
+        None => starts.len(),
+    };
 
-        for si in &exec_info.input_sections {
-            if si.name.as_str() == "xDivXSubXi" || si.name.as_str() == "xDivXSubWXi" {
-                let tmp = tmp_ctx.get_mut_base(si.name.as_str());
-                // for GL(p)
-                *tmp = vec![FGL::ZERO; (cur_n + next) * si.width];
-                let ori_sec = ctx.get_mut_base(si.name.as_str());
-                for j in 0..(cur_n * si.width) {
-                    tmp[j] = ori_sec[i * si.width + j]
-                }
-                // next
-                for j in 0..(next * si.width) {
-                    tmp[cur_n * si.width + j] = ori_sec[((i + cur_n) % n) * si.width + j]
-                }
-            } else {
-                let tmp = tmp_ctx.get_mut(si.name.as_str());
-                // for field extension GL(p^3)
-                *tmp = vec![F::ZERO; (cur_n + next) * si.width];
-                let ori_sec = ctx.get_mut(si.name.as_str());
-                for j in 0..(cur_n * si.width) {
-                    tmp[j] = ori_sec[i * si.width + j]
-                }
-                // next
-                for j in 0..(next * si.width) {
-                    tmp[cur_n * si.width + j] = ori_sec[((i + cur_n) % n) * si.width + j]
+    for batch_starts in starts.chunks(batch) {
+        let mut ctx_chunks: Vec<StarkContext<F>> = vec![];
+
+        for &i in batch_starts {
+            let cur_n = std::cmp::min(n_per_thread, n - i);
+            let mut tmp_ctx = StarkContext::<F> {
+                N: n,
+                Next: next,
+                nbits: ctx.nbits,
+                nbits_ext: ctx.nbits_ext,
+                evals: ctx.evals.clone(),
+                publics: ctx.publics.clone(),
+                challenge: ctx.challenge.clone(),
+                ..Default::default()
+            };
+
+            for si in &exec_info.input_sections {
+                if si.name.as_str() == "xDivXSubXi" || si.name.as_str() == "xDivXSubWXi" {
+                    let tmp = tmp_ctx.get_mut_base(si.name.as_str())?;
+                    // for GL(p)
+                    *tmp = vec![FGL::ZERO; (cur_n + next) * si.width];
+                    let ori_sec = ctx.get_mut_base(si.name.as_str())?;
+                    for j in 0..(cur_n * si.width) {
+                        tmp[j] = ori_sec[i * si.width + j]
+                    }
+                    // next
+                    for j in 0..(next * si.width) {
+                        tmp[cur_n * si.width + j] = ori_sec[((i + cur_n) % n) * si.width + j]
+                    }
+                } else {
+                    let tmp = tmp_ctx.get_mut(si.name.as_str())?;
+                    // for field extension GL(p^3)
+                    *tmp = vec![F::ZERO; (cur_n + next) * si.width];
+                    let ori_sec = ctx.get_mut(si.name.as_str())?;
+                    for j in 0..(cur_n * si.width) {
+                        tmp[j] = ori_sec[i * si.width + j]
+                    }
+                    // next
+                    for j in 0..(next * si.width) {
+                        tmp[cur_n * si.width + j] = ori_sec[((i + cur_n) % n) * si.width + j]
+                    }
                 }
             }
+            ctx_chunks.push(tmp_ctx);
         }
-        ctx_chunks.push(tmp_ctx);
-    }
 
-    ctx_chunks
-        .par_iter_mut()
-        .enumerate()
-        .for_each(|(i, tmp_ctx)| {
-            let cur_n = std::cmp::min(n_per_thread, n - i * n_per_thread);
-            log::trace!("execute trace LDE {}/{}", i * n_per_thread, n);
-            tmp_ctx.Zi = build_Zh_Inv(ctx.nbits, extend_bits, i * n_per_thread);
-            for so in &exec_info.output_sections {
-                let tmp = tmp_ctx.get_mut(so.name.as_str());
-                if tmp.is_empty() {
-                    *tmp = vec![F::ZERO; so.width * (cur_n + next)];
+        ctx_chunks
+            .par_iter_mut()
+            .zip(batch_starts)
+            .try_for_each(|(tmp_ctx, &i)| {
+                let cur_n = std::cmp::min(n_per_thread, n - i);
+                log::trace!("execute trace LDE {}/{}", i, n);
+                tmp_ctx.Zi = build_Zh_Inv(ctx.nbits, extend_bits, i);
+                for so in &exec_info.output_sections {
+                    let tmp = tmp_ctx.get_mut(so.name.as_str())?;
+                    if tmp.is_empty() {
+                        *tmp = vec![F::ZERO; so.width * (cur_n + next)];
+                    }
                 }
-            }
-            calculate_exps(tmp_ctx, starkinfo, seg, dom, cur_n);
-        });
+                calculate_exps(tmp_ctx, starkinfo, seg, dom, cur_n)
+            })?;
 
-    // write back the output
-    for i in 0..ctx_chunks.len() {
-        for so in &exec_info.output_sections {
-            let tmp = ctx_chunks[i].get_mut(so.name.as_str());
-            let out = ctx.get_mut(so.name.as_str());
-            for k in 0..(tmp.len() - so.width * next) {
-                out[i * n_per_thread * so.width + k] = tmp[k];
+        // write back the output
+        for (tmp_ctx, &i) in ctx_chunks.iter_mut().zip(batch_starts) {
+            for so in &exec_info.output_sections {
+                let tmp = tmp_ctx.get_mut(so.name.as_str())?;
+                let out = ctx.get_mut(so.name.as_str())?;
+                for k in 0..(tmp.len() - so.width * next) {
+                    out[i * so.width + k] = tmp[k];
+                }
             }
         }
     }
+    Ok(())
 }
 
 #[cfg(test)]
 pub mod tests {
+    use crate::errors::StarkyError;
+    use crate::f5g::F5G;
     use crate::field_bn128::Fr;
     use crate::merklehash::MerkleTreeGL;
     use crate::merklehash_bn128::MerkleTreeBN128;
+    use crate::merklehash_ext::MerkleTreeExt;
     use crate::polsarray::{PolKind, PolsArray};
-    use crate::stark_gen::StarkProof;
+    use crate::stark_gen::{calculate_H1H2, calculate_Z, StarkProof};
     use crate::stark_setup::StarkSetup;
     use crate::stark_verify::stark_verify;
     use crate::traits::MTNodeType;
@@ -1102,6 +1204,27 @@ pub mod tests {
     use crate::types::{StarkStruct, PIL};
     use ark_std::{end_timer, start_timer};
 
+    #[test]
+    fn test_unsatisfied_h1h2_and_z() {
+        let t: Vec<F5G> = (1..5u64).map(F5G::from).collect();
+        let f: Vec<F5G> = [1u64, 4, 4, 2].into_iter().map(F5G::from).collect();
+        calculate_H1H2(f.clone(), t.clone()).unwrap();
+        calculate_Z(f.clone(), vec![f[1], f[0], f[3], f[2]]).unwrap();
+
+        let unsatisfied = |err: anyhow::Error| {
+            matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::UnsatisfiedIdentities(_))
+            )
+        };
+        let mut not_in_t = f.clone();
+        not_in_t[2] = F5G::from(5u64);
+        assert!(unsatisfied(
+            calculate_H1H2(not_in_t, t.clone()).unwrap_err()
+        ));
+        assert!(unsatisfied(calculate_Z(f, t).unwrap_err()));
+    }
+
     #[test]
     fn test_stark_gen() {
         let mut pil = load_json::<PIL>("data/fib.pil.json").unwrap();
@@ -1135,6 +1258,7 @@ pub mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
         let ser = serde_json::to_string(&starkproof).unwrap();
@@ -1176,6 +1300,7 @@ pub mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
         let ser = serde_json::to_string(&starkproof).unwrap();
@@ -1214,6 +1339,7 @@ pub mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
         let ser = serde_json::to_string(&starkproof).unwrap();
@@ -1253,6 +1379,7 @@ pub mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
         let ser = serde_json::to_string(&starkproof).unwrap();
@@ -1292,6 +1419,7 @@ pub mod tests {
             &pil,
             &stark_struct,
             "273030697313060285579891744179749754319274977764",
+            None,
         )
         .unwrap();
         let ser = serde_json::to_string(&starkproof).unwrap();
@@ -1317,4 +1445,58 @@ pub mod tests {
         .unwrap();
         assert!(result);
     }
+
+    #[test]
+    fn test_stark_plookup_gl_f5g() {
+        type M = MerkleTreeExt<MerkleTreeGL, F5G>;
+        let mut pil = load_json::<PIL>("data/plookup.pil.json.gl").unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load("data/plookup.const.gl").unwrap();
+        let mut cm_pol = PolsArray::new(&pil, PolKind::Commit);
+        cm_pol.load("data/plookup.cm.gl").unwrap();
+        let mut stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        stark_struct.extensionDegree = 5;
+        let setup = StarkSetup::<M>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+
+        let starkproof = StarkProof::<M>::stark_gen::<TranscriptGL>(
+            cm_pol,
+            const_pol,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "",
+            None,
+        )
+        .unwrap();
+        assert!(starkproof.evals.iter().all(|e| e.dim == 5));
+        let ser = serde_json::to_string(&starkproof).unwrap();
+        let de: StarkProof<M> = serde_json::from_str(&ser).unwrap();
+        let result = stark_verify::<M, TranscriptGL>(
+            &de,
+            &setup.const_root,
+            &setup.starkinfo,
+            &stark_struct,
+            &setup.program,
+        )
+        .unwrap();
+        assert!(result);
+
+        // the setup of the cubic extension can not verify it
+        let mut pil = load_json::<PIL>("data/plookup.pil.json.gl").unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load("data/plookup.const.gl").unwrap();
+        stark_struct.extensionDegree = 3;
+        let cubic =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        assert!(stark_verify::<M, TranscriptGL>(
+            &de,
+            &cubic.const_root,
+            &cubic.starkinfo,
+            &stark_struct,
+            &cubic.program,
+        )
+        .is_err());
+    }
 }
@@ -1,14 +1,22 @@
 #![allow(non_snake_case, dead_code)]
+use crate::errors::StarkyError;
 use crate::fft_p::interpolate;
 use crate::polsarray::PolsArray;
 use crate::starkinfo::{self, Program, StarkInfo};
 use crate::traits::{FieldExtension, MerkleTree};
 use crate::types::{StarkStruct, PIL};
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use fields::field_gl::Fr as FGL;
 use profiler_macro::time_profiler;
 use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use tiny_keccak::{Hasher, Keccak};
+
+/// The version of the persisted setup file, bump it once the layout of `StarkSetup` changes.
+pub const STARK_SETUP_VERSION: u32 = 2;
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct StarkSetup<M: MerkleTree> {
@@ -16,6 +24,93 @@ pub struct StarkSetup<M: MerkleTree> {
     pub const_root: M::MTNode,
     pub starkinfo: StarkInfo,
     pub program: Program,
+    /// the digests of the PIL and the constant polynomials the setup is built from, which a
+    /// loaded setup must match
+    #[serde(default)]
+    pub pil_digest: String,
+    #[serde(default)]
+    pub const_digest: String,
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The keccak256 of the PIL, over its json with the keys in order
+pub fn pil_digest(pil: &PIL) -> Result<String> {
+    fn update(hasher: &mut Keccak, value: &Value) {
+        match value {
+            Value::Object(map) => {
+                let mut keys = map.keys().collect::<Vec<_>>();
+                keys.sort();
+                hasher.update(b"{");
+                for k in keys {
+                    hasher.update(serde_json::to_string(k).unwrap().as_bytes());
+                    hasher.update(b":");
+                    update(hasher, &map[k]);
+                    hasher.update(b",");
+                }
+                hasher.update(b"}");
+            }
+            Value::Array(values) => {
+                hasher.update(b"[");
+                for v in values {
+                    update(hasher, v);
+                    hasher.update(b",");
+                }
+                hasher.update(b"]");
+            }
+            _ => hasher.update(value.to_string().as_bytes()),
+        }
+    }
+    let mut hasher = Keccak::v256();
+    update(&mut hasher, &serde_json::to_value(pil)?);
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+    Ok(to_hex(&digest))
+}
+
+/// The keccak256 of the constant polynomials, over the row-major values in chunks of rows
+pub fn const_digest(const_pol: &PolsArray) -> Result<String> {
+    const ROWS: usize = 1 << 14;
+    let chunks = (0..const_pol.n.div_ceil(ROWS))
+        .into_par_iter()
+        .map(|c| {
+            let mut hasher = Keccak::v256();
+            for j in (c * ROWS)..((c + 1) * ROWS).min(const_pol.n) {
+                for id in 0..const_pol.nPols {
+                    hasher.update(&const_pol.value(id, j)?.as_int().to_le_bytes());
+                }
+            }
+            let mut digest = [0u8; 32];
+            hasher.finalize(&mut digest);
+            Ok(digest)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut hasher = Keccak::v256();
+    hasher.update(&(const_pol.nPols as u64).to_le_bytes());
+    hasher.update(&(const_pol.n as u64).to_le_bytes());
+    for digest in chunks.iter() {
+        hasher.update(digest);
+    }
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+    Ok(to_hex(&digest))
+}
+
+/// The persisted setup, the stark struct is kept to reject a setup built for other parameters.
+#[derive(Serialize)]
+struct StarkSetupFileRef<'a, M: MerkleTree> {
+    version: u32,
+    stark_struct: &'a StarkStruct,
+    setup: &'a StarkSetup<M>,
+}
+
+#[derive(Deserialize)]
+struct StarkSetupFile<M: MerkleTree> {
+    version: u32,
+    stark_struct: StarkStruct,
+    setup: StarkSetup<M>,
 }
 
 /// STARK SETUP
@@ -32,10 +127,19 @@ impl<M: MerkleTree> StarkSetup<M> {
     ) -> Result<StarkSetup<M>> {
         let nBits = stark_struct.nBits;
         let nBitsExt = stark_struct.nBitsExt;
-        assert_eq!(const_pol.nPols, pil.nConstants);
+        if const_pol.nPols != pil.nConstants {
+            bail!(StarkyError::MalformedPil(format!(
+                "got {} constant polynomials, but the PIL has {}",
+                const_pol.nPols, pil.nConstants
+            )));
+        }
+        const_pol.check_shape(nBits)?;
+        // before the starkinfo rewrites the PIL
+        let pil_digest = pil_digest(pil)?;
+        let const_digest = const_digest(const_pol)?;
 
         log::trace!("Write const pol buff and interpolate");
-        let const_buff = const_pol.write_buff();
+        let const_buff = const_pol.write_buff()?;
         //extend and merkelize
         let mut const_pols_array_e = vec![M::ExtendField::ZERO; (1 << nBitsExt) * pil.nConstants];
         let mut const_pols_array_e_be = vec![FGL::ZERO; (1 << nBitsExt) * pil.nConstants];
@@ -69,12 +173,77 @@ impl<M: MerkleTree> StarkSetup<M> {
             const_tree,
             starkinfo: starkinfo.0,
             program: starkinfo.1,
+            pil_digest,
+            const_digest,
         })
     }
+
+    /// Write the const tree, const root, starkinfo and program to `setup_file`.
+    #[time_profiler("save_stark_setup")]
+    pub fn save(&self, stark_struct: &StarkStruct, setup_file: &str) -> Result<()>
+    where
+        M: Serialize,
+    {
+        let file = File::create(setup_file)
+            .map_err(|e| anyhow!("create {} error: {:?}", setup_file, e))?;
+        let setup = StarkSetupFileRef {
+            version: STARK_SETUP_VERSION,
+            stark_struct,
+            setup: self,
+        };
+        serde_json::to_writer(BufWriter::new(file), &setup)?;
+        Ok(())
+    }
+
+    /// Load the setup generated by `save`, which must be built for the same `stark_struct`, PIL
+    /// and constant polynomials.
+    #[time_profiler("load_stark_setup")]
+    pub fn load(
+        stark_struct: &StarkStruct,
+        pil: &PIL,
+        const_pol: &PolsArray,
+        setup_file: &str,
+    ) -> Result<StarkSetup<M>>
+    where
+        M: DeserializeOwned,
+    {
+        let file =
+            File::open(setup_file).map_err(|e| anyhow!("open {} error: {:?}", setup_file, e))?;
+        let setup: StarkSetupFile<M> = serde_json::from_reader(BufReader::new(file))?;
+        if setup.version != STARK_SETUP_VERSION {
+            bail!(
+                "Invalid stark setup version {} in {}, expect {}",
+                setup.version,
+                setup_file,
+                STARK_SETUP_VERSION
+            );
+        }
+        if &setup.stark_struct != stark_struct {
+            bail!(
+                "The stark setup {} is built for another stark struct: {:?}",
+                setup_file,
+                setup.stark_struct
+            );
+        }
+        if setup.setup.pil_digest != pil_digest(pil)? {
+            bail!(StarkyError::MalformedInput(format!(
+                "The stark setup {} is built for another PIL",
+                setup_file
+            )));
+        }
+        if setup.setup.const_digest != const_digest(const_pol)? {
+            bail!(StarkyError::MalformedInput(format!(
+                "The stark setup {} is built for other constant polynomials",
+                setup_file
+            )));
+        }
+        Ok(setup.setup)
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
+    use crate::errors::StarkyError;
     use crate::polsarray::{PolKind, PolsArray};
     use crate::stark_setup::StarkSetup;
     use crate::types::{load_json, StarkStruct, PIL};
@@ -142,4 +311,54 @@ pub mod tests {
             "4658128321472362347225942316135505030498162093259225938328465623672244875764";
         assert_eq!(Fr::from_str(expect_root).unwrap(), root);
     }
+
+    #[test]
+    fn test_stark_setup_save_and_load() {
+        let mut pil = load_json::<PIL>("data/fib.pil.json.gl").unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load("data/fib.const.gl").unwrap();
+
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        setup
+            .save(&stark_struct, "/tmp/test_stark_setup.gl.json")
+            .unwrap();
+
+        let setup_file = "/tmp/test_stark_setup.gl.json";
+        let pil = load_json::<PIL>("data/fib.pil.json.gl").unwrap();
+        let actual =
+            StarkSetup::<MerkleTreeGL>::load(&stark_struct, &pil, &const_pol, setup_file).unwrap();
+        assert_eq!(actual.const_root, setup.const_root);
+        assert_eq!(actual.const_tree, setup.const_tree);
+
+        let mut other_struct = stark_struct.clone();
+        other_struct.nQueries += 1;
+        assert!(
+            StarkSetup::<MerkleTreeGL>::load(&other_struct, &pil, &const_pol, setup_file).is_err()
+        );
+
+        // another constant polynomial
+        let mut other_const = PolsArray::new(&pil, PolKind::Constant);
+        other_const.load("data/fib.const.gl").unwrap();
+        other_const.array[0][1] = other_const.array[0][1] + FGL::ONE;
+        let err = StarkSetup::<MerkleTreeGL>::load(&stark_struct, &pil, &other_const, setup_file)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+
+        // another PIL of the same shape
+        let mut other_pil = pil.clone();
+        other_pil.polIdentities.pop();
+        assert!(StarkSetup::<MerkleTreeGL>::load(
+            &stark_struct,
+            &other_pil,
+            &const_pol,
+            setup_file
+        )
+        .is_err());
+    }
 }
@@ -10,6 +10,7 @@ use crate::starkinfo_codegen::Section;
 use crate::traits::FieldExtension;
 use crate::traits::MTNodeType;
 use crate::types::{StarkStruct, PIL};
+use crate::zkin_join::zkin_prefix;
 use profiler_macro::time_profiler;
 
 fn header(options: &StarkOption) -> String {
@@ -26,7 +27,7 @@ include "evalpol.circom";
 include "treeselector.circom";
 "#
     .to_string();
-    if options.agg_stage {
+    if options.agg_inputs > 0 {
         header += r#"
 include "mux1.circom";
 include "iszero.circom";
@@ -1683,7 +1684,7 @@ template Main() {{
         )
     }
 
-    if options.verkey_input && !options.agg_stage {
+    if options.verkey_input && options.agg_inputs == 0 {
         res.push_str(&format!(
             r#"
 template Main() {{
@@ -1849,300 +1850,222 @@ template Main() {{
     // Aggregation Stage
     ///////
 
-    if options.agg_stage {
-        //let const_roots = const_root.as_elements();
-        res.push_str(&format!(
-            r#"
-template Main() {{
-
-    signal input publics[{}];
-    signal input rootC[4];
-
-    "#,
-            pil.publics.len() - 4,
+    if options.agg_inputs > 0 {
+        res.push_str(&agg_stage_main(
+            starkinfo,
+            pil,
+            stark_struct,
+            const_root,
+            options.agg_inputs,
         ));
-        res.push_str(&format!(
-            r#"
-    signal input a_publics[{}];
-    signal input a_root1[4];
-    signal input a_root2[4];
-    signal input a_root3[4];
-    signal input a_root4[4];
-    signal input a_rootC[4];
-
-    signal input b_publics[{}];
-    signal input b_root1[4];
-    signal input b_root2[4];
-    signal input b_root3[4];
-    signal input b_root4[4];
-    signal input b_rootC[4];
-    "#,
-            pil.publics.len(),
-            pil.publics.len()
-        ));
-
-        res.push_str(&format!(
-            r#"
-    signal input a_evals[{}][3];
-    signal input a_s0_vals1[{}][{}];
-
-    signal input b_evals[{}][3];
-    signal input b_s0_vals1[{}][{}];
-        "#,
-            starkinfo.ev_map.len(),
-            stark_struct.nQueries,
-            starkinfo.map_sectionsN.get("cm1_2ns"),
-            starkinfo.ev_map.len(),
-            stark_struct.nQueries,
-            starkinfo.map_sectionsN.get("cm1_2ns")
-        ));
-
-        if starkinfo.map_sectionsN.get("cm2_2ns") > 0 {
-            res.push_str(&format!(
-                r#"
-    signal input a_s0_vals2[{}][{}];
-    signal input b_s0_vals2[{}][{}];
-            "#,
-                stark_struct.nQueries,
-                starkinfo.map_sectionsN.get("cm2_2ns"),
-                stark_struct.nQueries,
-                starkinfo.map_sectionsN.get("cm2_2ns")
-            ));
-        }
-
-        if starkinfo.map_sectionsN.get("cm3_2ns") > 0 {
-            res.push_str(&format!(
-                r#"
-    signal input a_s0_vals3[{}][{}];
-
-    signal input b_s0_vals3[{}][{}];
-            "#,
-                stark_struct.nQueries,
-                starkinfo.map_sectionsN.get("cm3_2ns"),
-                stark_struct.nQueries,
-                starkinfo.map_sectionsN.get("cm3_2ns")
-            ));
-        }
+    }
 
-        res.push_str(&format!(
+    // generate the main component
+    if !options.skip_main {
+        // if options.agg_inputs > 0 {
+        res.push_str(
             r#"
-    signal input a_s0_vals4[{}][{}];
-    signal input a_s0_valsC[{}][{}];
-    signal input a_s0_siblings1[{}][{}][4];
-
-    signal input b_s0_vals4[{}][{}];
-    signal input b_s0_valsC[{}][{}];
-    signal input b_s0_siblings1[{}][{}][4];
+component main {public [publics, rootC]}= Main();
     "#,
-            stark_struct.nQueries,
-            starkinfo.map_sectionsN.get("cm4_2ns"),
-            stark_struct.nQueries,
-            starkinfo.n_constants,
-            stark_struct.nQueries,
-            stark_struct.steps[0].nBits,
-            stark_struct.nQueries,
-            starkinfo.map_sectionsN.get("cm4_2ns"),
-            stark_struct.nQueries,
-            starkinfo.n_constants,
-            stark_struct.nQueries,
-            stark_struct.steps[0].nBits
-        ));
+        );
+        // }
+    } else {
+        res.push_str(
+            r#"
+component main {public [publics]}= StarkVerifier();
+"#,
+        );
+    }
+    res
+}
 
-        if starkinfo.map_sectionsN.get("cm2_2ns") > 0 {
+/// The signals of the proof verified by the `prefix` input of the aggregation circuit.
+fn agg_input_signals(starkinfo: &StarkInfo, stark_struct: &StarkStruct, prefix: &str) -> String {
+    let n_queries = stark_struct.nQueries;
+    let n_bits0 = stark_struct.steps[0].nBits;
+    let mut res = format!(
+        r#"
+    signal input {prefix}publics[{}];
+    signal input {prefix}root1[4];
+    signal input {prefix}root2[4];
+    signal input {prefix}root3[4];
+    signal input {prefix}root4[4];
+    signal input {prefix}rootC[4];
+    signal input {prefix}evals[{}][3];
+    signal input {prefix}s0_vals1[{}][{}];
+"#,
+        starkinfo.publics.len(),
+        starkinfo.ev_map.len(),
+        n_queries,
+        starkinfo.map_sectionsN.get("cm1_2ns")
+    );
+    for section in ["cm2_2ns", "cm3_2ns"] {
+        if starkinfo.map_sectionsN.get(section) > 0 {
             res.push_str(&format!(
-                r#"
-    signal input a_s0_siblings2[{}][{}][4];
-
-    signal input b_s0_siblings2[{}][{}][4];
-            "#,
-                stark_struct.nQueries,
-                stark_struct.steps[0].nBits,
-                stark_struct.nQueries,
-                stark_struct.steps[0].nBits
+                "    signal input {prefix}s0_vals{}[{}][{}];\n",
+                &section[2..3],
+                n_queries,
+                starkinfo.map_sectionsN.get(section)
             ));
         }
-
-        if starkinfo.map_sectionsN.get("cm3_2ns") > 0 {
+    }
+    res.push_str(&format!(
+        r#"    signal input {prefix}s0_vals4[{}][{}];
+    signal input {prefix}s0_valsC[{}][{}];
+    signal input {prefix}s0_siblings1[{}][{}][4];
+"#,
+        n_queries,
+        starkinfo.map_sectionsN.get("cm4_2ns"),
+        n_queries,
+        starkinfo.n_constants,
+        n_queries,
+        n_bits0
+    ));
+    for section in ["cm2_2ns", "cm3_2ns"] {
+        if starkinfo.map_sectionsN.get(section) > 0 {
             res.push_str(&format!(
-                r#"
-    signal input a_s0_siblings3[{}][{}][4];
-
-    signal input b_s0_siblings3[{}][{}][4];
-            "#,
-                stark_struct.nQueries,
-                stark_struct.steps[0].nBits,
-                stark_struct.nQueries,
-                stark_struct.steps[0].nBits,
+                "    signal input {prefix}s0_siblings{}[{}][{}][4];\n",
+                &section[2..3],
+                n_queries,
+                n_bits0
             ));
         }
-
+    }
+    res.push_str(&format!(
+        r#"    signal input {prefix}s0_siblings4[{}][{}][4];
+    signal input {prefix}s0_siblingsC[{}][{}][4];
+"#,
+        n_queries, n_bits0, n_queries, n_bits0
+    ));
+    for s in 1..stark_struct.steps.len() {
         res.push_str(&format!(
-            r#"
-    signal input a_s0_siblings4[{}][{}][4];
-    signal input a_s0_siblingsC[{}][{}][4];
-
-    signal input b_s0_siblings4[{}][{}][4];
-    signal input b_s0_siblingsC[{}][{}][4];
-            "#,
-            stark_struct.nQueries,
-            stark_struct.steps[0].nBits,
-            stark_struct.nQueries,
-            stark_struct.steps[0].nBits,
-            stark_struct.nQueries,
-            stark_struct.steps[0].nBits,
-            stark_struct.nQueries,
-            stark_struct.steps[0].nBits
+            r#"    signal input {prefix}s{s}_root[4];
+    signal input {prefix}s{s}_vals[{}][{}];
+    signal input {prefix}s{s}_siblings[{}][{}][4];
+"#,
+            n_queries,
+            (1 << (stark_struct.steps[s - 1].nBits - stark_struct.steps[s].nBits)) * 3,
+            n_queries,
+            stark_struct.steps[s].nBits
         ));
+    }
+    res.push_str(&format!(
+        "    signal input {prefix}finalPol[{}][3];\n",
+        1 << stark_struct.steps[stark_struct.steps.len() - 1].nBits
+    ));
+    res
+}
 
-        for s in 0..(stark_struct.steps.len() - 1) {
-            res.push_str(&format!(
-                r#"
-        signal input a_s{}_root[4];
-
-        signal input b_s{}_root[4];
-            "#,
-                s + 1,
-                s + 1
-            ));
-        }
-
-        for s in 1..stark_struct.steps.len() {
+/// Verify the `prefix` input of the aggregation circuit by the component `v`.
+///
+/// The input is a proof of the aggregated circuit, whose rootC is `const_root`, or of the
+/// aggregation circuit itself, whose rootC is the public `rootC`, like a recursive2 proof joined
+/// with a recursive1 proof.
+fn agg_input_verifier(
+    starkinfo: &StarkInfo,
+    stark_struct: &StarkStruct,
+    prefix: &str,
+    v: &str,
+) -> String {
+    let mut res = format!(
+        r#"
+    component {v} = StarkVerifier();
+    {v}.publics <== {prefix}publics;
+    {v}.root1 <== {prefix}root1;
+    {v}.root2 <== {prefix}root2;
+    {v}.root3 <== {prefix}root3;
+    {v}.root4 <== {prefix}root4;
+    {v}.rootC <== {prefix}rootC;
+    {v}.evals <== {prefix}evals;
+    {v}.s0_vals1 <== {prefix}s0_vals1;
+    {v}.s0_siblings1 <== {prefix}s0_siblings1;
+"#
+    );
+    for (section, tree) in [("cm2_2ns", "2"), ("cm3_2ns", "3")] {
+        if starkinfo.map_sectionsN.get(section) > 0 {
             res.push_str(&format!(
-                r#"
-    signal input a_s{}_vals[{}][{}];
-    signal input a_s{}_siblings[{}][{}][4];
-
-    signal input b_s{}_vals[{}][{}];
-    signal input b_s{}_siblings[{}][{}][4];
-            "#,
-                s,
-                stark_struct.nQueries,
-                (1 << (stark_struct.steps[s - 1].nBits - stark_struct.steps[s].nBits)) * 3,
-                s,
-                stark_struct.nQueries,
-                stark_struct.steps[s].nBits,
-                s,
-                stark_struct.nQueries,
-                (1 << (stark_struct.steps[s - 1].nBits - stark_struct.steps[s].nBits)) * 3,
-                s,
-                stark_struct.nQueries,
-                stark_struct.steps[s].nBits
+                r#"    {v}.s0_vals{tree} <== {prefix}s0_vals{tree};
+    {v}.s0_siblings{tree} <== {prefix}s0_siblings{tree};
+"#
             ));
         }
-
+    }
+    res.push_str(&format!(
+        r#"    {v}.s0_vals4 <== {prefix}s0_vals4;
+    {v}.s0_valsC <== {prefix}s0_valsC;
+    {v}.s0_siblings4 <== {prefix}s0_siblings4;
+    {v}.s0_siblingsC <== {prefix}s0_siblingsC;
+"#
+    ));
+    for s in 1..stark_struct.steps.len() {
         res.push_str(&format!(
-            r#"
-    signal input a_finalPol[{}][3];
-
-    signal input b_finalPol[{}][3];
-        "#,
-            1 << stark_struct.steps[stark_struct.steps.len() - 1].nBits,
-            1 << stark_struct.steps[stark_struct.steps.len() - 1].nBits,
+            r#"    {v}.s{s}_root <== {prefix}s{s}_root;
+    {v}.s{s}_vals <== {prefix}s{s}_vals;
+    {v}.s{s}_siblings <== {prefix}s{s}_siblings;
+"#
         ));
-
-        res.push_str(&format!(
-            r#"
-    component vA = StarkVerifier();
-
-    for (var i=0; i<{}; i++) {{
-        vA.publics[i] <== a_publics[i];
+    }
+    res.push_str(&format!(
+        r#"    {v}.finalPol <== {prefix}finalPol;
+
+    // {prefix}rootC is the constant root, or the public rootC otherwise
+    component {prefix}isConst[4];
+    signal {prefix}allConst[4];
+    for (var j=0; j<4; j++) {{
+        {prefix}isConst[j] = IsZero();
+        {prefix}isConst[j].in <== {prefix}rootC[j] - constRoot[j];
     }}
-
-    vA.root1 <== a_root1;
-    vA.root2 <== a_root2;
-    vA.root3 <== a_root3;
-    vA.root4 <== a_root4;
-    vA.rootC <== a_rootC;
-    vA.evals <== a_evals;
-    vA.s0_vals1 <== a_s0_vals1;
-    vA.s0_vals3 <== a_s0_vals3;
-    vA.s0_vals4 <== a_s0_vals4;
-    vA.s0_valsC <== a_s0_valsC;
-    vA.s0_siblings1 <== a_s0_siblings1;
-    vA.s0_siblings3 <== a_s0_siblings3;
-    vA.s0_siblings4 <== a_s0_siblings4;
-    vA.s0_siblingsC <== a_s0_siblingsC;
-
-    vA.finalPol <== a_finalPol;
-            "#,
-            pil.publics.len()
-        ));
-        // component isOneBatchA = IsZero();
-        // isOneBatchA.in  <== a_publics[43] - a_publics[16] - 1; a_publics[43]-> newBatchNum;  a_publics[16]-> oldBatchNum
-        // TODO: "vA.rootC <== rootCSingle;" this need to change!!!
-
-        for s in 1..(stark_struct.steps.len()) {
-            res.push_str(&format!(
-                r#"
-    vA.s{}_root <== a_s{}_root;
-    vA.s{}_vals <== a_s{}_vals;
-    vA.s{}_siblings <== a_s{}_siblings;
-            "#,
-                s, s, s, s, s, s,
-            ));
-        }
-
-        res.push_str(&format!(
-            r#"
-    component vB = StarkVerifier();
-    for (var i=0; i<{}; i++) {{
-        vB.publics[i] <== b_publics[i];
+    {prefix}allConst[0] <== {prefix}isConst[0].out;
+    for (var j=1; j<4; j++) {{
+        {prefix}allConst[j] <== {prefix}allConst[j-1] * {prefix}isConst[j].out;
     }}
-
-    vB.root1 <== b_root1;
-    vB.root2 <== b_root2;
-    vB.root3 <== b_root3;
-    vB.root4 <== b_root4;
-    vB.rootC <== b_rootC;
-    vB.evals <== b_evals;
-    vB.s0_vals1 <== b_s0_vals1;
-    vB.s0_vals3 <== b_s0_vals3;
-    vB.s0_vals4 <== b_s0_vals4;
-    vB.s0_valsC <== b_s0_valsC;
-    vB.s0_siblings1 <== b_s0_siblings1;
-    vB.s0_siblings3 <== b_s0_siblings3;
-    vB.s0_siblings4 <== b_s0_siblings4;
-    vB.s0_siblingsC <== b_s0_siblingsC;
-
-    vB.finalPol <== b_finalPol;
-            "#,
-            pil.publics.len()
-        ));
-
-        for s in 1..(stark_struct.steps.len()) {
-            res.push_str(&format!(
-                r#"
-    vB.s{}_root <== b_s{}_root;
-    vB.s{}_vals <== b_s{}_vals;
-    vB.s{}_siblings <== b_s{}_siblings;
-            "#,
-                s, s, s, s, s, s,
-            ));
-        }
-
-        res.push_str(
-            r#"
+    for (var j=0; j<4; j++) {{
+        (1 - {prefix}allConst[3]) * ({prefix}rootC[j] - rootC[j]) === 0;
+    }}
+"#
+    ));
+    res
 }
-            "#,
-        )
-    }
 
-    // generate the main component
-    if !options.skip_main {
-        // if options.agg_stage {
-        res.push_str(
-            r#"
-component main {public [publics, rootC]}= Main();
-    "#,
-        );
-        // }
-    } else {
-        res.push_str(
-            r#"
-component main {public [publics]}= StarkVerifier();
+/// The main template of the aggregation stage, verifying `n_inputs` proofs prefixed by
+/// `zkin_prefix`, as joined by `join_zkin`.
+fn agg_stage_main<F: ff::PrimeField + Default>(
+    starkinfo: &StarkInfo,
+    pil: &PIL,
+    stark_struct: &StarkStruct,
+    const_root: &ElementDigest<4, F>,
+    n_inputs: usize,
+) -> String {
+    let const_roots = const_root.as_elements();
+    let mut res = format!(
+        r#"
+template Main() {{
+    signal input publics[{}];
+    signal input rootC[4];
+
+    var constRoot[4] = [{}, {}, {}, {}];
 "#,
-        );
+        pil.publics.len() - 4,
+        const_roots[0].as_int(),
+        const_roots[1].as_int(),
+        const_roots[2].as_int(),
+        const_roots[3].as_int()
+    );
+    for i in 0..n_inputs {
+        let prefix = zkin_prefix(i);
+        res.push_str(&agg_input_signals(starkinfo, stark_struct, &prefix));
+        res.push_str(&agg_input_verifier(
+            starkinfo,
+            stark_struct,
+            &prefix,
+            &format!("v{}", (b'A' + i as u8) as char),
+        ));
     }
+    res.push_str(
+        r#"
+}
+"#,
+    );
     res
 }
 
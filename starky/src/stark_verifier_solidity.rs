@@ -0,0 +1,1351 @@
+#![allow(non_snake_case)]
+//! Render a solidity contract verifying the BN128 or KECCAK stark proofs on the EVM directly,
+//! the sibling of the circom verifiers.
+//!
+//! The contract follows `stark_verify`: the transcript, the constraint check at xi, the merkle
+//! proofs of the queries and FRI. An extension element is packed into one word, 64 bits per
+//! coefficient. The BN128 hash is computed by an external `PoseidonEx` contract, since the
+//! poseidon constants of width 17 alone exceed the contract size limit: they are stored as the
+//! code of data contracts, see `poseidon_chunks`.
+use crate::constant::{MG, POSEIDON_BN128_CONSTANTS, SHIFT};
+use crate::errors::StarkyError;
+use crate::field_bn128::Fr;
+use crate::helper::fr_to_biguint;
+use crate::merklehash_bn128::MerkleTreeBN128;
+use crate::merklehash_keccak::{node_to_bytes, MerkleTreeKeccak};
+use crate::stark_gen::StarkProof;
+use crate::starkinfo::{Program, StarkInfo};
+use crate::starkinfo_codegen::{Node, Section};
+use crate::traits::{FieldExtension, MTNodeType, MerkleTree};
+use crate::types::{parse_pil_number, StarkStruct};
+use crate::ElementDigest;
+use anyhow::{bail, Result};
+use fields::field_gl::Fr as FGL;
+use num_bigint::BigUint;
+use std::collections::BTreeSet;
+
+/// The merkle trees whose proofs can be checked by the solidity verifier.
+pub trait SolidityHash: MerkleTree {
+    /// The children of a node.
+    const ARITY: usize;
+    /// The words of a level of siblings in the calldata.
+    const SIBLING_WORDS: usize;
+
+    fn node_word(node: &Self::MTNode) -> BigUint;
+
+    fn sibling_words(level: &[Self::BaseField]) -> Vec<BigUint>;
+}
+
+impl SolidityHash for MerkleTreeBN128 {
+    const ARITY: usize = 16;
+    const SIBLING_WORDS: usize = 16;
+
+    fn node_word(node: &Self::MTNode) -> BigUint {
+        fr_to_biguint(&Fr(node.as_scalar::<Fr>()))
+    }
+
+    fn sibling_words(level: &[Fr]) -> Vec<BigUint> {
+        level.iter().map(fr_to_biguint).collect()
+    }
+}
+
+impl SolidityHash for MerkleTreeKeccak {
+    const ARITY: usize = 2;
+    const SIBLING_WORDS: usize = 1;
+
+    fn node_word(node: &Self::MTNode) -> BigUint {
+        BigUint::from_bytes_be(&node_to_bytes(node))
+    }
+
+    fn sibling_words(level: &[FGL]) -> Vec<BigUint> {
+        vec![Self::node_word(&ElementDigest::new(level))]
+    }
+}
+
+/// The levels of siblings in the merkle proof of a tree with `height` leaves.
+fn merkle_levels(height: usize, arity: usize) -> usize {
+    let mut n = height;
+    let mut levels = 0;
+    while n > 1 {
+        n = (n - 1) / arity + 1;
+        levels += 1;
+    }
+    levels
+}
+
+/// The word offsets of the flat proof taken by `verifyProof`.
+///
+/// root1..root4, the evals, the FRI roots of the steps 1.., the last FRI polynomial, then per
+/// query the values and siblings of tree1..tree4 and the constant tree followed by the FRI
+/// step trees. The trees without columns are left out.
+#[derive(Debug, PartialEq)]
+struct Layout {
+    evals: usize,
+    fri_roots: usize,
+    last: usize,
+    queries: usize,
+    query0_size: usize,
+    query_size: usize,
+    size: usize,
+}
+
+impl Layout {
+    fn new<M: SolidityHash>(starkinfo: &StarkInfo, stark_struct: &StarkStruct) -> Self {
+        let steps = &stark_struct.steps;
+        let levels0 = merkle_levels(1 << stark_struct.nBitsExt, M::ARITY);
+        let query0_size = tree_widths(starkinfo)
+            .iter()
+            .filter(|w| **w > 0)
+            .map(|w| w + levels0 * M::SIBLING_WORDS)
+            .sum::<usize>();
+        let query_size = query0_size
+            + (1..steps.len())
+                .map(|s| {
+                    let width = 3 << (steps[s - 1].nBits - steps[s].nBits);
+                    width + merkle_levels(1 << steps[s].nBits, M::ARITY) * M::SIBLING_WORDS
+                })
+                .sum::<usize>();
+
+        let evals = 4;
+        let fri_roots = evals + 3 * starkinfo.ev_map.len();
+        let last = fri_roots + steps.len() - 1;
+        let queries = last + 3 * (1 << steps[steps.len() - 1].nBits);
+        Layout {
+            evals,
+            fri_roots,
+            last,
+            queries,
+            query0_size,
+            query_size,
+            size: queries + stark_struct.nQueries * query_size,
+        }
+    }
+}
+
+/// The row widths of tree1..tree4 and the constant tree.
+fn tree_widths(starkinfo: &StarkInfo) -> [usize; 5] {
+    [
+        starkinfo.map_sectionsN.get("cm1_2ns"),
+        starkinfo.map_sectionsN.get("cm2_2ns"),
+        starkinfo.map_sectionsN.get("cm3_2ns"),
+        starkinfo.map_sectionsN.get("cm4_2ns"),
+        starkinfo.n_constants,
+    ]
+}
+
+/// Flatten the proof into the `proof` and `publics` arguments of `verifyProof`.
+pub fn proof_words<M: SolidityHash>(proof: &StarkProof<M>) -> (Vec<BigUint>, Vec<BigUint>) {
+    let e3 = |e: &M::ExtendField| -> Vec<BigUint> {
+        let mut elems = e.as_elements();
+        elems.resize(3, FGL::ZERO);
+        elems.iter().map(|v| BigUint::from(v.as_int())).collect()
+    };
+
+    let mut words = vec![];
+    for root in [&proof.root1, &proof.root2, &proof.root3, &proof.root4] {
+        words.push(M::node_word(root));
+    }
+    proof.evals.iter().for_each(|e| words.extend(e3(e)));
+
+    let queries = &proof.fri_proof.queries;
+    for q in queries.iter().skip(1) {
+        words.push(M::node_word(&q.root));
+    }
+    proof
+        .fri_proof
+        .last
+        .iter()
+        .for_each(|e| words.extend(e3(e)));
+
+    for i in 0..queries[0].pol_queries.len() {
+        for q in queries.iter() {
+            for (vals, siblings) in q.pol_queries[i].iter() {
+                if vals.is_empty() {
+                    continue;
+                }
+                words.extend(vals.iter().map(|v| BigUint::from(v.as_int())));
+                for level in siblings.iter() {
+                    words.extend(M::sibling_words(level));
+                }
+            }
+        }
+    }
+
+    let publics = proof
+        .publics
+        .iter()
+        .map(|p| BigUint::from(p.as_elements()[0].as_int()))
+        .collect();
+    (words, publics)
+}
+
+fn ref_(r: &Node) -> Result<String> {
+    let tree = |name: &str| -> String {
+        match r.dim {
+            1 => format!("c.{}[{}]", name, r.tree_pos),
+            _ => format!(
+                "e3(c.{}[{}], c.{}[{}], c.{}[{}])",
+                name,
+                r.tree_pos,
+                name,
+                r.tree_pos + 1,
+                name,
+                r.tree_pos + 2
+            ),
+        }
+    };
+    let res = match r.type_.as_str() {
+        "tmp" => format!("t[{}]", r.id),
+        "eval" => format!("c.evals[{}]", r.id),
+        "challenge" => format!("c.challenges[{}]", r.id),
+        "public" => format!("c.publics[{}]", r.id),
+        "x" => "c.challenges[7]".to_string(),
+        "Z" if r.prime => "c.Zp".to_string(),
+        "Z" => "c.Z".to_string(),
+        "xDivXSubXi" => "c.xDivXSubXi".to_string(),
+        "xDivXSubWXi" => "c.xDivXSubWXi".to_string(),
+        "tree1" | "tree2" | "tree3" | "tree4" => tree(&r.type_),
+        "const" => format!("c.consts[{}]", r.id),
+        "number" => FGL::from(parse_pil_number(r.value.as_deref().unwrap_or_default())?)
+            .as_int()
+            .to_string(),
+        _ => bail!(StarkyError::MalformedInput(format!(
+            "invalid reference {} in the verifier code",
+            r.type_
+        ))),
+    };
+    Ok(res)
+}
+
+/// Unroll the code into a function over the packed extension elements.
+fn unroll_code(name: &str, code: &[Section]) -> Result<String> {
+    let mut n_tmp = 0;
+    let mut body = String::new();
+    for inst in code {
+        if inst.dest.type_ != "tmp" {
+            bail!(StarkyError::MalformedInput(format!(
+                "invalid destination {} in the verifier code",
+                inst.dest.type_
+            )));
+        }
+        n_tmp = n_tmp.max(inst.dest.id + 1);
+        let src = inst.src.iter().map(ref_).collect::<Result<Vec<_>>>()?;
+        let expr = match (inst.op.as_str(), src.len()) {
+            ("add", 2) => format!("e3add({}, {})", src[0], src[1]),
+            ("sub", 2) => format!("e3sub({}, {})", src[0], src[1]),
+            ("mul", 2) => format!("e3mul({}, {})", src[0], src[1]),
+            ("muladd", 3) => format!("e3add(e3mul({}, {}), {})", src[0], src[1], src[2]),
+            ("copy", 1) => src[0].clone(),
+            _ => bail!(StarkyError::MalformedInput(format!(
+                "invalid op {} in the verifier code",
+                inst.op
+            ))),
+        };
+        body.push_str(&format!("\n        t[{}] = {};", inst.dest.id, expr));
+    }
+    let res = match code.last() {
+        Some(inst) => ref_(&inst.dest)?,
+        None => bail!(StarkyError::MalformedInput(
+            "empty verifier code".to_string()
+        )),
+    };
+    Ok(format!(
+        r#"
+    function {}(Ctx memory c) internal pure returns (uint256) {{
+        uint256[] memory t = new uint256[]({});{}
+        return {};
+    }}
+"#,
+        name, n_tmp, body, res
+    ))
+}
+
+/// A function returning the inverse of the `2^bits`-th root of unity for the given bits.
+fn roots_of_unity_inv(bits: &BTreeSet<usize>) -> String {
+    let mut res = String::from(
+        r#"
+    function rootInv(uint256 bits) internal pure returns (uint256) {"#,
+    );
+    for b in bits {
+        res.push_str(&format!(
+            r#"
+        if (bits == {}) return {};"#,
+            b,
+            MG.1[*b].as_int()
+        ));
+    }
+    res.push_str(
+        r#"
+        revert("no root of unity");
+    }
+"#,
+    );
+    res
+}
+
+/// The goldilocks and extension field arithmetic, FRI folding and the transcript helpers.
+const FIELD_LIB: &str = r#"
+    uint256 constant P = 0xFFFFFFFF00000001;
+    uint256 constant MASK = 0xFFFFFFFFFFFFFFFF;
+
+    struct Ctx {
+        uint256[8] challenges;
+        uint256[] evals;
+        uint256[] publics;
+        uint256 Z;
+        uint256 Zp;
+        uint256 xDivXSubXi;
+        uint256 xDivXSubWXi;
+        uint256[] tree1;
+        uint256[] tree2;
+        uint256[] tree3;
+        uint256[] tree4;
+        uint256[] consts;
+    }
+
+    // an element of the cubic extension by x^3 - x - 1 is packed as a0 | a1 << 64 | a2 << 128
+    function e3(uint256 a0, uint256 a1, uint256 a2) internal pure returns (uint256) {
+        return a0 | (a1 << 64) | (a2 << 128);
+    }
+
+    function e3add(uint256 a, uint256 b) internal pure returns (uint256) {
+        return e3(
+            addmod(a & MASK, b & MASK, P),
+            addmod((a >> 64) & MASK, (b >> 64) & MASK, P),
+            addmod(a >> 128, b >> 128, P)
+        );
+    }
+
+    function e3sub(uint256 a, uint256 b) internal pure returns (uint256) {
+        return e3(
+            addmod(a & MASK, P - (b & MASK), P),
+            addmod((a >> 64) & MASK, P - ((b >> 64) & MASK), P),
+            addmod(a >> 128, P - (b >> 128), P)
+        );
+    }
+
+    function e3mul(uint256 a, uint256 b) internal pure returns (uint256) {
+        uint256 a0 = a & MASK;
+        uint256 a1 = (a >> 64) & MASK;
+        uint256 a2 = a >> 128;
+        uint256 b0 = b & MASK;
+        uint256 b1 = (b >> 64) & MASK;
+        uint256 b2 = b >> 128;
+        // x^3 = x + 1 and x^4 = x^2 + x
+        uint256 c3 = a1 * b2 + a2 * b1;
+        uint256 c4 = a2 * b2;
+        return e3(
+            (a0 * b0 + c3) % P,
+            (a0 * b1 + a1 * b0 + c3 + c4) % P,
+            (a0 * b2 + a1 * b1 + a2 * b0 + c4) % P
+        );
+    }
+
+    function e3scale(uint256 a, uint256 s) internal pure returns (uint256) {
+        return e3(mulmod(a & MASK, s, P), mulmod((a >> 64) & MASK, s, P), mulmod(a >> 128, s, P));
+    }
+
+    // a^(2^bits)
+    function e3pow2(uint256 a, uint256 bits) internal pure returns (uint256) {
+        for (uint256 i = 0; i < bits; i++) {
+            a = e3mul(a, a);
+        }
+        return a;
+    }
+
+    function e3norm(uint256 a0, uint256 a1, uint256 a2) internal pure returns (uint256) {
+        uint256 aa = mulmod(a0, a0, P);
+        uint256 bb = mulmod(a1, a1, P);
+        uint256 cc = mulmod(a2, a2, P);
+        uint256 abc = mulmod(mulmod(a0, a1, P), a2, P);
+        // -aaa - 2aac + 3abc + abb - acc - bbb + bcc - ccc
+        return (3 * abc + mulmod(a0, bb, P) + mulmod(a1, cc, P) + 6 * P - mulmod(aa, a0, P)
+            - 2 * mulmod(aa, a2, P) - mulmod(a0, cc, P) - mulmod(bb, a1, P) - mulmod(cc, a2, P)) % P;
+    }
+
+    function e3inv(uint256 a) internal pure returns (uint256) {
+        uint256 a0 = a & MASK;
+        uint256 a1 = (a >> 64) & MASK;
+        uint256 a2 = a >> 128;
+        uint256 tinv = glinv(e3norm(a0, a1, a2));
+        uint256 bb = mulmod(a1, a1, P);
+        uint256 cc = mulmod(a2, a2, P);
+        uint256 ac = mulmod(a0, a2, P);
+        return e3(
+            mulmod((mulmod(a1, a2, P) + bb + 4 * P - mulmod(a0, a0, P) - 2 * ac - cc) % P, tinv, P),
+            mulmod((mulmod(a0, a1, P) + P - cc) % P, tinv, P),
+            mulmod((ac + cc + P - bb) % P, tinv, P)
+        );
+    }
+
+    function glpow(uint256 a, uint256 e) internal pure returns (uint256 r) {
+        r = 1;
+        while (e > 0) {
+            if (e & 1 == 1) r = mulmod(r, a, P);
+            a = mulmod(a, a, P);
+            e >>= 1;
+        }
+    }
+
+    function glinv(uint256 a) internal pure returns (uint256) {
+        require(a != 0, "inverse of zero");
+        return glpow(a, P - 2);
+    }
+
+    function readValues(uint256[] calldata proof, uint256 o, uint256 n)
+        internal
+        pure
+        returns (uint256[] memory vals)
+    {
+        vals = new uint256[](n);
+        for (uint256 i = 0; i < n; i++) {
+            require(proof[o + i] < P, "non canonical value");
+            vals[i] = proof[o + i];
+        }
+    }
+
+    // the coefficient m of the polynomial interpolating the 2^bits values on the roots of
+    // unity, multiplied by 2^bits
+    function coefficient(uint256[] memory vals, uint256 bits, uint256 m) internal pure returns (uint256 coef) {
+        uint256 wm = glpow(rootInv(bits), m);
+        uint256 w = 1;
+        for (uint256 j = 0; j < (1 << bits); j++) {
+            coef = e3add(coef, e3scale(e3(vals[3 * j], vals[3 * j + 1], vals[3 * j + 2]), w));
+            w = mulmod(w, wm, P);
+        }
+    }
+
+    // evaluate at x the polynomial interpolating the 2^bits values on the roots of unity
+    function fold(uint256[] memory vals, uint256 bits, uint256 x) internal pure returns (uint256 ev) {
+        for (uint256 m = 1 << bits; m > 0; m--) {
+            ev = e3add(e3mul(ev, x), coefficient(vals, bits, m - 1));
+        }
+        return e3scale(ev, glinv(1 << bits));
+    }
+
+    function checkDegree(uint256[] memory vals, uint256 bits, uint256 maxDeg) internal pure returns (bool) {
+        for (uint256 m = maxDeg + 1; m < (1 << bits); m++) {
+            if (coefficient(vals, bits, m) != 0) return false;
+        }
+        return true;
+    }
+
+    function getField(Transcript memory t) internal <%mutability%> returns (uint256) {
+        uint256 a0 = getFields1(t);
+        uint256 a1 = getFields1(t);
+        uint256 a2 = getFields1(t);
+        return e3(a0, a1, a2);
+    }
+
+    function getPermutations(Transcript memory t, uint256 n, uint256 nBits)
+        internal
+        <%mutability%>
+        returns (uint256[] memory res)
+    {
+        res = new uint256[](n);
+        uint256 field = nextPermField(t);
+        uint256 curBit = 0;
+        for (uint256 i = 0; i < n; i++) {
+            for (uint256 j = 0; j < nBits; j++) {
+                if (curBit == FIELD_BITS) {
+                    field = nextPermField(t);
+                    curBit = 0;
+                }
+                res[i] |= ((field >> curBit) & 1) << j;
+                curBit++;
+            }
+        }
+    }
+"#;
+
+/// The keccak transcript and merkle tree, see `TranscriptKeccak` and `MerkleTreeKeccak`.
+const KECCAK_LIB: &str = r#"
+    uint256 constant FIELD_BITS = 63;
+    uint256 constant SIBLING_WORDS = 1;
+
+    struct Transcript {
+        bytes32 state;
+        bytes pending;
+        uint256 out;
+        uint256 outLeft;
+    }
+
+    function newTranscript() internal pure returns (Transcript memory t) {}
+
+    // the 4 big-endian words of the digest reduced by P
+    function reduceDigest(bytes32 h) internal pure returns (uint256 r) {
+        for (uint256 i = 0; i < 4; i++) {
+            uint256 w = (uint256(h) >> (192 - 64 * i)) & MASK;
+            if (w >= P) w -= P;
+            r |= w << (192 - 64 * i);
+        }
+    }
+
+    function tPut(Transcript memory t, uint256 v) internal pure {
+        t.pending = abi.encodePacked(t.pending, uint64(v));
+        t.outLeft = 0;
+    }
+
+    function tPutRoot(Transcript memory t, uint256 root) internal pure {
+        t.pending = abi.encodePacked(t.pending, root);
+        t.outLeft = 0;
+    }
+
+    function tUpdate(Transcript memory t) internal pure {
+        t.state = keccak256(abi.encodePacked(t.state, t.pending));
+        t.pending = "";
+        t.out = reduceDigest(t.state);
+        t.outLeft = 4;
+    }
+
+    function getFields1(Transcript memory t) internal pure returns (uint256) {
+        if (t.outLeft == 0) tUpdate(t);
+        t.outLeft--;
+        return (t.out >> (64 * t.outLeft)) & MASK;
+    }
+
+    function nextPermField(Transcript memory t) internal pure returns (uint256) {
+        return getFields1(t);
+    }
+
+    function hashLeaf(uint256[] memory vals) internal pure returns (uint256) {
+        bytes memory buf = new bytes(8 * vals.length);
+        for (uint256 i = 0; i < vals.length; i++) {
+            uint256 v = vals[i];
+            // the following values overwrite the zero tail of the word
+            assembly {
+                mstore(add(add(buf, 32), mul(i, 8)), shl(192, v))
+            }
+        }
+        return reduceDigest(keccak256(buf));
+    }
+
+    function hashNode(uint256 left, uint256 right) internal pure returns (uint256) {
+        return reduceDigest(keccak256(abi.encodePacked(left, right)));
+    }
+
+    function verifyMerkle(
+        uint256[] calldata proof,
+        uint256 o,
+        uint256 root,
+        uint256 idx,
+        uint256[] memory vals,
+        uint256 levels
+    ) internal pure returns (bool) {
+        uint256 value = hashLeaf(vals);
+        for (uint256 i = 0; i < levels; i++) {
+            if (idx & 1 == 0) {
+                value = hashNode(value, proof[o + i]);
+            } else {
+                value = hashNode(proof[o + i], value);
+            }
+            idx >>= 1;
+        }
+        return value == root;
+    }
+"#;
+
+/// The poseidon transcript and merkle tree, see `TranscriptBN128` and `MerkleTreeBN128`.
+const BN128_LIB: &str = r#"
+    uint256 constant FIELD_BITS = 253;
+    uint256 constant SIBLING_WORDS = 16;
+    uint256 constant Q = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+
+    IPoseidonEx public immutable poseidon;
+
+    constructor(IPoseidonEx poseidon_) {
+        poseidon = poseidon_;
+    }
+
+    struct Transcript {
+        uint256 state;
+        uint256[] pending;
+        uint256 nPending;
+        uint256[] out;
+        uint256 outPos;
+        uint256[3] out3;
+        uint256 out3Left;
+    }
+
+    function newTranscript() internal pure returns (Transcript memory t) {
+        t.pending = new uint256[](16);
+        t.out = new uint256[](0);
+    }
+
+    function tPut(Transcript memory t, uint256 v) internal view {
+        t.outPos = t.out.length;
+        t.pending[t.nPending] = v;
+        t.nPending++;
+        if (t.nPending == 16) tUpdate(t);
+    }
+
+    function tPutRoot(Transcript memory t, uint256 root) internal view {
+        require(root < Q, "non canonical root");
+        tPut(t, root);
+    }
+
+    function tUpdate(Transcript memory t) internal view {
+        t.out = poseidon.poseidonEx(t.pending, t.state);
+        t.outPos = 0;
+        t.out3Left = 0;
+        t.pending = new uint256[](16);
+        t.nPending = 0;
+        t.state = t.out[0];
+    }
+
+    // each output is split into 3 elements by its 64-bit limbs
+    function getFields1(Transcript memory t) internal view returns (uint256) {
+        if (t.out3Left == 0) {
+            if (t.outPos == t.out.length) tUpdate(t);
+            uint256 v = t.out[t.outPos];
+            t.outPos++;
+            t.out3 = [(v & MASK) % P, ((v >> 64) & MASK) % P, ((v >> 128) & MASK) % P];
+            t.out3Left = 3;
+        }
+        t.out3Left--;
+        return t.out3[2 - t.out3Left];
+    }
+
+    function nextPermField(Transcript memory t) internal view returns (uint256) {
+        if (t.outPos == t.out.length) tUpdate(t);
+        t.outPos++;
+        return t.out[t.outPos - 1];
+    }
+
+    // up to 4 values are packed into one word, otherwise 3 values per input of a chain of
+    // poseidon hashes over 16 inputs
+    function hashLeaf(uint256[] memory vals) internal view returns (uint256) {
+        uint256 n = vals.length;
+        if (n <= 4) {
+            uint256 r = 0;
+            for (uint256 i = 0; i < n; i++) {
+                r |= vals[i] << (64 * i);
+            }
+            return r % Q;
+        }
+        uint256 nPacked = (n - 1) / 3 + 1;
+        uint256 digest = 0;
+        for (uint256 i = 0; i < nPacked; i += 16) {
+            uint256 size = nPacked - i < 16 ? nPacked - i : 16;
+            uint256[] memory inputs = new uint256[](size);
+            for (uint256 j = 0; j < size; j++) {
+                for (uint256 k = 0; k < 3 && 3 * (i + j) + k < n; k++) {
+                    inputs[j] |= vals[3 * (i + j) + k] << (64 * k);
+                }
+            }
+            digest = poseidon.poseidonEx(inputs, digest)[0];
+        }
+        return digest;
+    }
+
+    // a level holds the 16 children of the parent, the current node included
+    function verifyMerkle(
+        uint256[] calldata proof,
+        uint256 o,
+        uint256 root,
+        uint256 idx,
+        uint256[] memory vals,
+        uint256 levels
+    ) internal view returns (bool) {
+        uint256 value = hashLeaf(vals);
+        uint256[] memory siblings = new uint256[](16);
+        for (uint256 i = 0; i < levels; i++) {
+            for (uint256 k = 0; k < 16; k++) {
+                siblings[k] = proof[o + 16 * i + k];
+                require(siblings[k] < Q, "non canonical sibling");
+            }
+            if (siblings[idx & 15] != value) return false;
+            value = poseidon.poseidonEx(siblings, 0)[0];
+            idx >>= 4;
+        }
+        return value == root;
+    }
+"#;
+
+const POSEIDON_INTERFACE: &str = r#"
+/// Poseidon over BN128 like circomlib's `PoseidonEx`, returning the whole state after the
+/// permutation of `initialState || inputs`.
+interface IPoseidonEx {
+    function poseidonEx(uint256[] memory inputs, uint256 initialState) external view returns (uint256[] memory);
+}
+"#;
+
+/// The data bytes of a constant contract, below the contract size limit with the leading STOP.
+const POSEIDON_CHUNK_SIZE: usize = 24544;
+
+/// The round constants and the MDS matrix of each width from 2 to 17 as big-endian words, the
+/// ones of a width following the ones of the previous width, and the word offset of each width.
+fn poseidon_constants() -> (Vec<u8>, Vec<usize>) {
+    let consts = &*POSEIDON_BN128_CONSTANTS;
+    let mut data = vec![];
+    let mut offsets = vec![];
+    for (c, m) in consts.c.iter().zip(consts.m.iter()) {
+        offsets.push(data.len() / 32);
+        for v in c.iter().chain(m.iter().flatten()) {
+            let word = fr_to_biguint(v).to_bytes_be();
+            data.resize(data.len() + 32 - word.len(), 0);
+            data.extend(word);
+        }
+    }
+    (data, offsets)
+}
+
+/// The poseidon constants split into the hex data taken by `PoseidonEx.load`, in order.
+pub fn poseidon_chunks() -> Vec<String> {
+    let (data, _) = poseidon_constants();
+    data.chunks(POSEIDON_CHUNK_SIZE)
+        .map(|chunk| {
+            let hex: String = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("0x{}", hex)
+        })
+        .collect()
+}
+
+/// circomlib's poseidon of 1 to 16 inputs, see `poseidon_bn128::Poseidon`.
+fn poseidon_contract() -> String {
+    let consts = &*POSEIDON_BN128_CONSTANTS;
+    let (data, offsets) = poseidon_constants();
+    let mut layout = String::new();
+    for (i, (offset, n_rounds_p)) in offsets.iter().zip(consts.n_rounds_p.iter()).enumerate() {
+        layout.push_str(&format!(
+            r#"
+        if (t == {}) return ({}, {});"#,
+            i + 2,
+            offset,
+            n_rounds_p
+        ));
+    }
+    format!(
+        r#"
+/// The round constants and the MDS matrices of all the widths exceed the contract size limit, so
+/// the deployer stores them as the code of data contracts with `load`, in the order of the
+/// poseidon json generated with the verifier, and each hash copies the ones of its width.
+contract PoseidonEx is IPoseidonEx {{
+    uint256 constant Q = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    uint256 constant N_ROUNDS_F = {};
+    uint256 constant N_CHUNKS = {};
+    uint256 constant CHUNK_SIZE = {};
+
+    address public immutable owner;
+    address[] public chunks;
+
+    constructor() {{
+        owner = msg.sender;
+    }}
+
+    function load(bytes calldata data) external {{
+        require(msg.sender == owner, "only the owner loads the constants");
+        require(chunks.length < N_CHUNKS, "the constants are loaded");
+        require(
+            data.length == CHUNK_SIZE || (chunks.length == N_CHUNKS - 1 && data.length < CHUNK_SIZE),
+            "invalid chunk size"
+        );
+        // the init code returns the code following it, a STOP then the data
+        bytes memory init = abi.encodePacked(hex"61", uint16(data.length + 1), hex"80600c6000396000f300", data);
+        address a;
+        assembly {{
+            a := create(0, add(init, 32), mload(init))
+        }}
+        require(a != address(0), "failed to store the constants");
+        chunks.push(a);
+    }}
+
+    // the word offset of the constants of the width and its partial rounds
+    function widthLayout(uint256 t) internal pure returns (uint256, uint256) {{{}
+        revert("invalid width");
+    }}
+
+    function readConstants(uint256 offset, uint256 n) internal view returns (uint256[] memory words) {{
+        words = new uint256[](n);
+        uint256 pos = offset * 32;
+        uint256 end = pos + n * 32;
+        uint256 dst;
+        assembly {{
+            dst := add(words, 32)
+        }}
+        while (pos < end) {{
+            uint256 start = pos % CHUNK_SIZE;
+            uint256 size = CHUNK_SIZE - start;
+            if (size > end - pos) size = end - pos;
+            address a = chunks[pos / CHUNK_SIZE];
+            assembly {{
+                extcodecopy(a, dst, add(start, 1), size)
+            }}
+            dst += size;
+            pos += size;
+        }}
+    }}
+
+    function pow5(uint256 x) internal pure returns (uint256) {{
+        uint256 x2 = mulmod(x, x, Q);
+        return mulmod(mulmod(x2, x2, Q), x, Q);
+    }}
+
+    function mix(uint256[] memory state, uint256[] memory m, uint256[] memory res, uint256 t) internal pure {{
+        uint256 q = Q;
+        assembly {{
+            let s := add(state, 32)
+            for {{ let i := 0 }} lt(i, t) {{ i := add(i, 1) }} {{
+                let row := add(add(m, 32), mul(mul(i, t), 32))
+                let acc := 0
+                for {{ let j := 0 }} lt(j, t) {{ j := add(j, 1) }} {{
+                    acc := addmod(acc, mulmod(mload(add(row, mul(j, 32))), mload(add(s, mul(j, 32))), q), q)
+                }}
+                mstore(add(add(res, 32), mul(i, 32)), acc)
+            }}
+        }}
+    }}
+
+    function poseidonEx(uint256[] memory inputs, uint256 initialState)
+        external
+        view
+        returns (uint256[] memory state)
+    {{
+        require(chunks.length == N_CHUNKS, "the constants are not loaded");
+        uint256 t = inputs.length + 1;
+        (uint256 offset, uint256 nRoundsP) = widthLayout(t);
+        uint256 nRounds = N_ROUNDS_F + nRoundsP;
+        uint256[] memory c = readConstants(offset, nRounds * t);
+        uint256[] memory m = readConstants(offset + nRounds * t, t * t);
+
+        state = new uint256[](t);
+        state[0] = initialState;
+        for (uint256 i = 1; i < t; i++) {{
+            state[i] = inputs[i - 1];
+        }}
+        uint256[] memory next = new uint256[](t);
+        for (uint256 r = 0; r < nRounds; r++) {{
+            for (uint256 i = 0; i < t; i++) {{
+                state[i] = addmod(state[i], c[r * t + i], Q);
+            }}
+            if (r < N_ROUNDS_F / 2 || r >= N_ROUNDS_F / 2 + nRoundsP) {{
+                for (uint256 i = 0; i < t; i++) {{
+                    state[i] = pow5(state[i]);
+                }}
+            }} else {{
+                state[0] = pow5(state[0]);
+            }}
+            mix(state, m, next, t);
+            (state, next) = (next, state);
+        }}
+    }}
+}}
+"#,
+        consts.n_rounds_f,
+        (data.len() - 1) / POSEIDON_CHUNK_SIZE + 1,
+        POSEIDON_CHUNK_SIZE,
+        layout
+    )
+}
+
+fn verify_proof(starkinfo: &StarkInfo, stark_struct: &StarkStruct) -> Result<String> {
+    let mut q = String::new();
+    for i in 0..starkinfo.q_deg {
+        let idx = match starkinfo.ev_idx.get("cm", 0, starkinfo.qs[i]) {
+            Some(idx) => idx,
+            None => bail!(StarkyError::MalformedInput(format!(
+                "no evaluation of the quotient polynomial {}",
+                starkinfo.qs[i]
+            ))),
+        };
+        q.push_str(&format!(
+            r#"
+        q = e3add(q, e3mul(xAcc, c.evals[{}]));
+        xAcc = e3mul(xAcc, xN);"#,
+            idx
+        ));
+    }
+
+    Ok(format!(
+        r#"
+    function verifyProof(uint256[] calldata proof, uint256[] calldata publics)
+        external
+        <%mutability%>
+        returns (bool)
+    {{
+        require(proof.length == PROOF_SIZE, "invalid proof size");
+        require(publics.length == N_PUBLICS, "invalid publics size");
+        Ctx memory c;
+        Transcript memory t = newTranscript();
+        c.publics = new uint256[](N_PUBLICS);
+        for (uint256 i = 0; i < N_PUBLICS; i++) {{
+            require(publics[i] < P, "non canonical public");
+            c.publics[i] = publics[i];
+            tPut(t, publics[i]);
+        }}
+
+        tPutRoot(t, proof[0]);
+        c.challenges[0] = getField(t);
+        c.challenges[1] = getField(t);
+        tPutRoot(t, proof[1]);
+        c.challenges[2] = getField(t);
+        c.challenges[3] = getField(t);
+        tPutRoot(t, proof[2]);
+        c.challenges[4] = getField(t);
+        tPutRoot(t, proof[3]);
+        c.challenges[7] = getField(t);
+
+        c.evals = new uint256[](N_EVALS);
+        for (uint256 i = 0; i < N_EVALS; i++) {{
+            uint256[] memory e = readValues(proof, EVALS_OFFSET + 3 * i, 3);
+            c.evals[i] = e3(e[0], e[1], e[2]);
+            tPut(t, e[0]);
+            tPut(t, e[1]);
+            tPut(t, e[2]);
+        }}
+        c.challenges[5] = getField(t);
+        c.challenges[6] = getField(t);
+
+        if (!checkConstraints(c)) return false;
+        return verifyFri(c, t, proof);
+    }}
+
+    // the constraint polynomial at xi must be the quotient times Z
+    function checkConstraints(Ctx memory c) internal pure returns (bool) {{
+        uint256 xN = e3pow2(c.challenges[7], N_BITS);
+        c.Z = e3sub(xN, 1);
+        c.Zp = e3sub(e3pow2(e3scale(c.challenges[7], W_N), N_BITS), 1);
+        uint256 res = evalConstraints(c);
+
+        uint256 q = 0;
+        uint256 xAcc = 1;{}
+        return res == e3mul(q, c.Z);
+    }}
+
+    function verifyFri(Ctx memory c, Transcript memory t, uint256[] calldata proof)
+        internal
+        <%mutability%>
+        returns (bool)
+    {{
+        uint256[] memory specialX = new uint256[](N_STEPS);
+        for (uint256 s = 0; s < N_STEPS; s++) {{
+            specialX[s] = getField(t);
+            if (s < N_STEPS - 1) tPutRoot(t, proof[FRI_ROOTS_OFFSET + s]);
+        }}
+        uint256[] memory last = readValues(proof, LAST_OFFSET, 3 << LAST_BITS);
+        for (uint256 i = 0; i < last.length; i++) {{
+            tPut(t, last[i]);
+        }}
+
+        uint256[] memory ys = getPermutations(t, N_QUERIES, {});
+        for (uint256 i = 0; i < N_QUERIES; i++) {{
+            if (!verifyQuery(c, proof, QUERIES_OFFSET + i * QUERY_SIZE, ys[i], specialX, last)) {{
+                return false;
+            }}
+        }}
+        return checkDegree(last, LAST_BITS, MAX_DEG);
+    }}
+"#,
+        q, stark_struct.steps[0].nBits
+    ))
+}
+
+fn verify_query0<M: SolidityHash>(starkinfo: &StarkInfo, stark_struct: &StarkStruct) -> String {
+    let levels = merkle_levels(1 << stark_struct.nBitsExt, M::ARITY);
+    let mut res = String::from(
+        r#"
+    function verifyQuery0(Ctx memory c, uint256[] calldata proof, uint256 o, uint256 idx)
+        internal
+        <%mutability%>
+        returns (bool, uint256)
+    {"#,
+    );
+    let names = ["tree1", "tree2", "tree3", "tree4", "consts"];
+    let roots = ["proof[0]", "proof[1]", "proof[2]", "proof[3]", "ROOT_C"];
+    for (i, width) in tree_widths(starkinfo).iter().enumerate() {
+        if *width == 0 {
+            continue;
+        }
+        res.push_str(&format!(
+            r#"
+        c.{} = readValues(proof, o, {});
+        if (!verifyMerkle(proof, o + {}, {}, idx, c.{}, {})) return (false, 0);
+        o += {};"#,
+            names[i],
+            width,
+            width,
+            roots[i],
+            names[i],
+            levels,
+            width + levels * M::SIBLING_WORDS
+        ));
+    }
+    res.push_str(
+        r#"
+
+        uint256 x = mulmod(SHIFT, glpow(W_EXT, idx), P);
+        c.xDivXSubXi = e3mul(x, e3inv(e3sub(x, c.challenges[7])));
+        c.xDivXSubWXi = e3mul(x, e3inv(e3sub(x, e3scale(c.challenges[7], W_N))));
+        return (true, evalQuery(c));
+    }
+"#,
+    );
+    res
+}
+
+/// Check the FRI steps of a query, `idx` is the position of the query in the current domain.
+fn verify_query<M: SolidityHash>(stark_struct: &StarkStruct) -> String {
+    let steps = &stark_struct.steps;
+    let mut res = String::from(
+        r#"
+    function verifyQuery(
+        Ctx memory c,
+        uint256[] calldata proof,
+        uint256 o,
+        uint256 idx,
+        uint256[] memory specialX,
+        uint256[] memory last
+    ) internal <%mutability%> returns (bool) {
+        (bool ok, uint256 ev) = verifyQuery0(c, proof, o, idx);
+        if (!ok) return false;
+        o += QUERY0_SIZE;
+        uint256[] memory vals;
+        uint256 g;"#,
+    );
+
+    let mut shift = *SHIFT;
+    for _ in 0..(stark_struct.nBitsExt - steps[0].nBits) {
+        shift = shift * shift;
+    }
+    for s in 1..steps.len() {
+        let pol_bits = steps[s - 1].nBits;
+        let bits = steps[s].nBits;
+        let width = 3 << (pol_bits - bits);
+        let levels = merkle_levels(1 << bits, M::ARITY);
+        res.push_str(&format!(
+            r#"
+
+        // step {s}, the folded value of the last step is the element of the group
+        g = idx >> {bits};
+        idx &= {mask};
+        vals = readValues(proof, o, {width});
+        if (!verifyMerkle(proof, o + {width}, proof[FRI_ROOTS_OFFSET + {root}], idx, vals, {levels})) return false;
+        if (e3(vals[3 * g], vals[3 * g + 1], vals[3 * g + 2]) != ev) return false;
+        ev = fold(vals, {reduction}, e3scale(specialX[{s}], glinv(mulmod({shift}, glpow({w}, idx), P))));
+        o += {size};"#,
+            s = s,
+            bits = bits,
+            mask = (1u64 << bits) - 1,
+            width = width,
+            root = s - 1,
+            levels = levels,
+            reduction = pol_bits - bits,
+            shift = shift.as_int(),
+            w = MG.0[pol_bits].as_int(),
+            size = width + levels * M::SIBLING_WORDS,
+        ));
+        for _ in 0..(pol_bits - bits) {
+            shift = shift * shift;
+        }
+    }
+    res.push_str(
+        r#"
+
+        return ev == e3(last[3 * idx], last[3 * idx + 1], last[3 * idx + 2]);
+    }
+"#,
+    );
+    res
+}
+
+/// The solidity library only implements the cubic extension field.
+pub fn check_extension_degree(stark_struct: &StarkStruct) -> Result<()> {
+    if stark_struct.extensionDegree != 3 {
+        bail!(StarkyError::MalformedInput(format!(
+            "the solidity verifier only supports the extension degree 3, got {}",
+            stark_struct.extensionDegree
+        )));
+    }
+    Ok(())
+}
+
+/// Render the solidity verifier of the stark proofs hashed by `M`.
+pub fn render<M: SolidityHash>(
+    starkinfo: &StarkInfo,
+    program: &Program,
+    stark_struct: &StarkStruct,
+    const_root: &M::MTNode,
+) -> Result<String> {
+    if stark_struct.verificationHashType != M::HASH_TYPE {
+        bail!(StarkyError::InvalidHashType(
+            stark_struct.verificationHashType.clone()
+        ));
+    }
+    check_extension_degree(stark_struct)?;
+    let steps = &stark_struct.steps;
+    if steps[0].nBits != stark_struct.nBitsExt {
+        bail!(StarkyError::MalformedInput(format!(
+            "the first FRI step must be of {} bits",
+            stark_struct.nBitsExt
+        )));
+    }
+    let layout = Layout::new::<M>(starkinfo, stark_struct);
+    let last_bits = steps[steps.len() - 1].nBits;
+    let blowup_bits = stark_struct.nBitsExt - stark_struct.nBits;
+    let max_deg = if last_bits < blowup_bits {
+        0
+    } else {
+        1 << (last_bits - blowup_bits)
+    };
+
+    let (interface, hash_lib, mutability) = match M::HASH_TYPE {
+        "BN128" => (POSEIDON_INTERFACE, BN128_LIB, "view"),
+        _ => ("", KECCAK_LIB, "pure"),
+    };
+
+    let mut res = format!(
+        r#"// SPDX-License-Identifier: MIT
+// Generated by starky, verifies the {} stark proofs of 2^{} rows.
+pragma solidity ^0.8.16;
+{}
+/// The proof is flattened by `zkit stark_calldata`: root1..root4, the evals, the FRI roots of
+/// the steps 1.., the last FRI polynomial, then per query the values and siblings of the
+/// committed trees, the constant tree and the FRI steps.
+contract StarkVerifier {{
+    uint256 constant N_BITS = {};
+    uint256 constant N_QUERIES = {};
+    uint256 constant N_STEPS = {};
+    uint256 constant N_PUBLICS = {};
+    uint256 constant N_EVALS = {};
+    uint256 constant LAST_BITS = {};
+    uint256 constant MAX_DEG = {};
+    uint256 constant ROOT_C = {};
+    uint256 constant SHIFT = {};
+    uint256 constant W_N = {};
+    uint256 constant W_EXT = {};
+
+    uint256 constant EVALS_OFFSET = {};
+    uint256 constant FRI_ROOTS_OFFSET = {};
+    uint256 constant LAST_OFFSET = {};
+    uint256 constant QUERIES_OFFSET = {};
+    uint256 constant QUERY0_SIZE = {};
+    uint256 constant QUERY_SIZE = {};
+    uint256 constant PROOF_SIZE = {};
+"#,
+        M::HASH_TYPE,
+        stark_struct.nBits,
+        interface,
+        stark_struct.nBits,
+        stark_struct.nQueries,
+        steps.len(),
+        starkinfo.publics.len(),
+        starkinfo.ev_map.len(),
+        last_bits,
+        max_deg,
+        M::node_word(const_root),
+        SHIFT.as_int(),
+        MG.0[stark_struct.nBits].as_int(),
+        MG.0[stark_struct.nBitsExt].as_int(),
+        layout.evals,
+        layout.fri_roots,
+        layout.last,
+        layout.queries,
+        layout.query0_size,
+        layout.query_size,
+        layout.size,
+    );
+    res.push_str(FIELD_LIB);
+    res.push_str(hash_lib);
+
+    let inv_bits = (1..steps.len())
+        .map(|s| steps[s - 1].nBits - steps[s].nBits)
+        .chain(std::iter::once(last_bits))
+        .collect::<BTreeSet<_>>();
+    res.push_str(&roots_of_unity_inv(&inv_bits));
+    res.push_str(&verify_proof(starkinfo, stark_struct)?);
+    res.push_str(&unroll_code(
+        "evalConstraints",
+        &program.verifier_code.first,
+    )?);
+    res.push_str(&verify_query0::<M>(starkinfo, stark_struct));
+    res.push_str(&unroll_code(
+        "evalQuery",
+        &program.verifier_query_code.first,
+    )?);
+    res.push_str(&verify_query::<M>(stark_struct));
+    res.push_str("}\n");
+    if M::HASH_TYPE == "BN128" {
+        res.push_str(&poseidon_contract());
+    }
+    Ok(res.replace("<%mutability%>", mutability))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polsarray::{PolKind, PolsArray};
+    use crate::prove::{stark_prove, stark_solidity_calldata, stark_solidity_verifier};
+    use crate::stark_setup::StarkSetup;
+    use crate::types::{load_json, PIL};
+
+    #[test]
+    fn test_merkle_levels() {
+        assert_eq!(merkle_levels(1, 16), 0);
+        assert_eq!(merkle_levels(16, 16), 1);
+        assert_eq!(merkle_levels(2048, 16), 3);
+        assert_eq!(merkle_levels(2048, 2), 11);
+        assert_eq!(merkle_levels(3, 2), 2);
+    }
+
+    #[test]
+    fn test_stark_solidity_verifier_bn128() {
+        stark_prove(
+            "data/starkStruct.json",
+            "data/fib.pil.json",
+            false,
+            false,
+            0,
+            "data/fib.const",
+            "data/fib.cm",
+            None,
+            None,
+            "/tmp/test_stark_solidity_verifier.bn128.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+        stark_solidity_verifier(
+            "data/starkStruct.json",
+            "data/fib.pil.json",
+            "data/fib.const",
+            None,
+            "/tmp/test_stark_solidity_verifier.bn128.sol",
+        )
+        .unwrap();
+        stark_solidity_calldata(
+            "data/starkStruct.json",
+            "/tmp/test_stark_solidity_verifier.bn128.zkin.json",
+            "/tmp/test_stark_solidity_verifier.bn128.calldata.json",
+        )
+        .unwrap();
+
+        let proof = load_json::<StarkProof<MerkleTreeBN128>>(
+            "/tmp/test_stark_solidity_verifier.bn128.zkin.json",
+        )
+        .unwrap();
+        let (words, publics) = proof_words(&proof);
+        let contract =
+            std::fs::read_to_string("/tmp/test_stark_solidity_verifier.bn128.sol").unwrap();
+        assert!(contract.contains(&format!("PROOF_SIZE = {};", words.len())));
+        assert!(contract.contains(&format!("N_PUBLICS = {};", publics.len())));
+        assert!(contract.contains("interface IPoseidonEx"));
+        assert!(contract.contains("contract PoseidonEx is IPoseidonEx"));
+        assert!(!contract.contains("<%"));
+
+        let (calldata, _): (Vec<String>, Vec<String>) =
+            load_json("/tmp/test_stark_solidity_verifier.bn128.calldata.json").unwrap();
+        assert_eq!(calldata.len(), words.len());
+
+        let chunks: Vec<String> =
+            load_json("/tmp/test_stark_solidity_verifier.bn128.poseidon.json").unwrap();
+        assert_eq!(chunks, poseidon_chunks());
+        assert!(contract.contains(&format!("N_CHUNKS = {};", chunks.len())));
+    }
+
+    #[test]
+    fn test_poseidon_constants() {
+        let (data, offsets) = poseidon_constants();
+        let consts = &*POSEIDON_BN128_CONSTANTS;
+        assert_eq!(offsets.len(), 16);
+        // the width t holds (8 + nRoundsP) * t round constants and a t * t matrix
+        for (i, offset) in offsets.iter().enumerate() {
+            let t = i + 2;
+            assert_eq!(consts.c[i].len(), (8 + consts.n_rounds_p[i]) * t);
+            let word = &data[32 * offset..32 * (offset + 1)];
+            assert_eq!(BigUint::from_bytes_be(word), fr_to_biguint(&consts.c[i][0]));
+            let m = offset + consts.c[i].len();
+            let word = &data[32 * m..32 * (m + 1)];
+            assert_eq!(
+                BigUint::from_bytes_be(word),
+                fr_to_biguint(&consts.m[i][0][0])
+            );
+        }
+        let chunks = poseidon_chunks();
+        assert_eq!(chunks.len(), (data.len() - 1) / POSEIDON_CHUNK_SIZE + 1);
+        assert_eq!(chunks[0].len(), 2 + 2 * POSEIDON_CHUNK_SIZE);
+        // a data contract is a STOP followed by the chunk
+        assert!(POSEIDON_CHUNK_SIZE < 24576);
+    }
+
+    #[test]
+    fn test_stark_solidity_verifier_layout_keccak() {
+        let mut stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        stark_struct.verificationHashType = "KECCAK".to_string();
+        let stark_struct_file = "/tmp/test_stark_solidity_verifier.keccak.starkStruct.json";
+        std::fs::write(
+            stark_struct_file,
+            serde_json::to_string(&stark_struct).unwrap(),
+        )
+        .unwrap();
+        stark_prove(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "data/fib.const.gl",
+            "data/fib.cm.gl",
+            None,
+            None,
+            "/tmp/test_stark_solidity_verifier.keccak.zkin.json",
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        let mut pil = load_json::<PIL>("data/fib.pil.json.gl").unwrap();
+        let const_pol = PolsArray::load_mmap(&pil, PolKind::Constant, "data/fib.const.gl").unwrap();
+        let setup =
+            StarkSetup::<MerkleTreeKeccak>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        let proof = load_json::<StarkProof<MerkleTreeKeccak>>(
+            "/tmp/test_stark_solidity_verifier.keccak.zkin.json",
+        )
+        .unwrap();
+        let (words, publics) = proof_words(&proof);
+        let layout = Layout::new::<MerkleTreeKeccak>(&setup.starkinfo, &stark_struct);
+        assert_eq!(layout.size, words.len());
+
+        let contract = render::<MerkleTreeKeccak>(
+            &setup.starkinfo,
+            &setup.program,
+            &stark_struct,
+            &setup.const_root,
+        )
+        .unwrap();
+        assert!(contract.contains("keccak256"));
+        assert!(!contract.contains("IPoseidonEx"));
+
+        // the contract only verifies the proofs of its own hash
+        assert!(render::<MerkleTreeBN128>(
+            &setup.starkinfo,
+            &setup.program,
+            &stark_struct,
+            &ElementDigest::default(),
+        )
+        .is_err());
+
+        stark_solidity_verifier(
+            stark_struct_file,
+            "data/fib.pil.json.gl",
+            "data/fib.const.gl",
+            None,
+            "/tmp/test_stark_solidity_verifier.keccak.sol",
+        )
+        .unwrap();
+        stark_solidity_calldata(
+            stark_struct_file,
+            "/tmp/test_stark_solidity_verifier.keccak.zkin.json",
+            "/tmp/test_stark_solidity_verifier.keccak.calldata.json",
+        )
+        .unwrap();
+        let contract =
+            std::fs::read_to_string("/tmp/test_stark_solidity_verifier.keccak.sol").unwrap();
+        assert!(contract.contains(&format!("PROOF_SIZE = {};", words.len())));
+        assert!(contract.contains(&format!("N_PUBLICS = {};", publics.len())));
+        let (calldata, _): (Vec<String>, Vec<String>) =
+            load_json("/tmp/test_stark_solidity_verifier.keccak.calldata.json").unwrap();
+        assert_eq!(calldata.len(), words.len());
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(dead_code, clippy::type_complexity)]
 use crate::constant::{MG, SHIFT};
+use crate::errors::StarkyError;
 use crate::fri::FRI;
 use crate::stark_gen::StarkContext;
 use crate::stark_gen::StarkProof;
@@ -24,6 +25,14 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
     stark_struct: &StarkStruct,
     program: &Program,
 ) -> Result<bool> {
+    if starkinfo.ext_dim != M::ExtendField::DIM {
+        bail!(StarkyError::MalformedInput(format!(
+            "the setup is built for the extension degree {}, but the proof is of {}",
+            starkinfo.ext_dim,
+            M::ExtendField::DIM
+        )));
+    }
+    check_proof_shape(proof, starkinfo)?;
     let mut transcript = T::new();
 
     let mut ctx = StarkContext::default();
@@ -73,7 +82,7 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
         (ctx.challenge[7] * M::ExtendField::from(MG.0[ctx.nbits])).exp(ctx.N) - M::ExtendField::ONE;
 
     log::trace!("verifier_code {}", program.verifier_code);
-    let res = execute_code(&ctx, &program.verifier_code.first);
+    let res = execute_code(&ctx, &program.verifier_code.first)?;
     log::trace!("starkinfo: {}", starkinfo);
 
     let mut x_acc = M::ExtendField::ONE;
@@ -95,6 +104,19 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
                        idx: usize|
      -> Result<Vec<M::ExtendField>> {
         log::trace!("Query: {}", idx);
+        let widths = [
+            starkinfo.map_sectionsN.cm1_n,
+            starkinfo.map_sectionsN.cm2_n,
+            starkinfo.map_sectionsN.cm3_n,
+            starkinfo.map_sectionsN.cm4_2ns,
+            starkinfo.n_constants,
+        ];
+        if query.len() != widths.len() || query.iter().zip(widths).any(|(q, w)| q.0.len() != w) {
+            bail!(StarkyError::InvalidProof(format!(
+                "the query {} doesn't open the widths {:?} of the committed trees",
+                idx, widths
+            )));
+        }
         let tree = M::new();
         let res = tree.verify_group_proof(&proof.root1, &query[0].1, idx, &query[0].0)?;
         if !res {
@@ -135,7 +157,10 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
             / (x - (ctx_query.challenge[7] * M::ExtendField::from(MG.0[ctx.nbits]))))
         .as_elements();
 
-        let vals = vec![execute_code(&ctx_query, &program.verifier_query_code.first)];
+        let vals = vec![execute_code(
+            &ctx_query,
+            &program.verifier_query_code.first,
+        )?];
 
         Ok(vals)
     };
@@ -143,44 +168,80 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
     fri.verify(&mut transcript, &proof.fri_proof, check_query)
 }
 
-fn execute_code<F: FieldExtension>(ctx: &StarkContext<F>, code: &Vec<Section>) -> F {
+/// Check the number of publics, evaluations and FRI queries of an untrusted proof against the
+/// stark info, so that a malformed proof is rejected instead of indexed out of bounds.
+fn check_proof_shape<M: MerkleTree>(proof: &StarkProof<M>, starkinfo: &StarkInfo) -> Result<()> {
+    if proof.publics.len() != starkinfo.n_publics {
+        bail!(StarkyError::InvalidProof(format!(
+            "{} publics, expected {}",
+            proof.publics.len(),
+            starkinfo.n_publics
+        )));
+    }
+    if proof.evals.len() != starkinfo.ev_map.len() {
+        bail!(StarkyError::InvalidProof(format!(
+            "{} evaluations, expected {}",
+            proof.evals.len(),
+            starkinfo.ev_map.len()
+        )));
+    }
+    Ok(())
+}
+
+/// The `i`-th value of `arr`, failing instead of panicking on a short array.
+fn checked_get<T: Copy>(arr: &[T], i: usize, name: &str) -> Result<T> {
+    match arr.get(i) {
+        Some(v) => Ok(*v),
+        None => bail!(StarkyError::InvalidProof(format!(
+            "{} has no value {}",
+            name, i
+        ))),
+    }
+}
+
+fn execute_code<F: FieldExtension>(ctx: &StarkContext<F>, code: &Vec<Section>) -> Result<F> {
     let mut tmp: HashMap<usize, F> = HashMap::new();
 
-    let extract_val = |arr: &Vec<FGL>, pos: usize, dim: usize| -> F {
+    let extract_val = |arr: &Vec<FGL>, pos: usize, dim: usize| -> Result<F> {
         match dim {
-            1 => F::from(arr[pos]),
-            3 => {
-                let r = &arr[pos..(pos + 3)];
-                F::from_vec(vec![r[0], r[1], r[2]])
-            }
-            // TODO: Support F5G
-            _ => panic!("Invalid dimension"),
+            1 => Ok(F::from(checked_get(arr, pos, "the query")?)),
+            _ if dim == F::DIM => match arr.get(pos..(pos + dim)) {
+                Some(v) => Ok(F::from_vec(v.to_vec())),
+                None => bail!(StarkyError::InvalidProof(format!(
+                    "the query has no value {}",
+                    pos + dim - 1
+                ))),
+            },
+            _ => bail!(StarkyError::MalformedInput(format!(
+                "invalid dimension {}",
+                dim
+            ))),
         }
     };
 
-    let get_ref = |r: &Node, tmp: &HashMap<usize, F>| -> F {
+    let get_ref = |r: &Node, tmp: &HashMap<usize, F>| -> Result<F> {
         let t = match r.type_.as_str() {
-            "tmp" => *tmp.get(&r.id).unwrap(),
-            "tree1" => extract_val(&ctx.tree1, r.tree_pos, r.dim),
-            "tree2" => extract_val(&ctx.tree2, r.tree_pos, r.dim),
-            "tree3" => extract_val(&ctx.tree3, r.tree_pos, r.dim),
-            "tree4" => extract_val(&ctx.tree4, r.tree_pos, r.dim),
-            "const" => ctx.consts[r.id].into(),
-            "eval" => ctx.evals[r.id],
-            "number" => F::from(parse_pil_number(r.value.as_ref().unwrap())),
-            "public" => ctx.publics[r.id],
-            "challenge" => ctx.challenge[r.id],
-            // TODO: Support F5G
-            "xDivXSubXi" => F::from_vec(vec![
-                ctx.xDivXSubXi[0],
-                ctx.xDivXSubXi[1],
-                ctx.xDivXSubXi[2],
-            ]),
-            "xDivXSubWXi" => F::from_vec(vec![
-                ctx.xDivXSubWXi[0],
-                ctx.xDivXSubWXi[1],
-                ctx.xDivXSubWXi[2],
-            ]),
+            "tmp" => match tmp.get(&r.id) {
+                Some(v) => *v,
+                None => bail!(StarkyError::MalformedInput(format!(
+                    "the tmp {} is read before set",
+                    r.id
+                ))),
+            },
+            "tree1" => extract_val(&ctx.tree1, r.tree_pos, r.dim)?,
+            "tree2" => extract_val(&ctx.tree2, r.tree_pos, r.dim)?,
+            "tree3" => extract_val(&ctx.tree3, r.tree_pos, r.dim)?,
+            "tree4" => extract_val(&ctx.tree4, r.tree_pos, r.dim)?,
+            "const" => checked_get(&ctx.consts, r.id, "the constant query")?.into(),
+            "eval" => checked_get(&ctx.evals, r.id, "the evaluations")?,
+            "number" => match &r.value {
+                Some(v) => F::from(parse_pil_number(v)?),
+                None => bail!(StarkyError::MalformedInput("a number without value".into())),
+            },
+            "public" => checked_get(&ctx.publics, r.id, "the publics")?,
+            "challenge" => checked_get(&ctx.challenge, r.id, "the challenges")?,
+            "xDivXSubXi" => F::from_vec(ctx.xDivXSubXi.clone()),
+            "xDivXSubWXi" => F::from_vec(ctx.xDivXSubWXi.clone()),
             "x" => ctx.challenge[7],
             "Z" => {
                 if r.prime {
@@ -189,37 +250,62 @@ fn execute_code<F: FieldExtension>(ctx: &StarkContext<F>, code: &Vec<Section>) -
                     ctx.Z
                 }
             }
-            _ => panic!("Invalid reference type, get: {}", r.type_),
+            _ => bail!(StarkyError::MalformedInput(format!(
+                "invalid reference type, get: {}",
+                r.type_
+            ))),
         };
         //log::trace!("verify get ref {}", t);
-        t
+        Ok(t)
     };
 
-    let set_ref = |r: &Node, val: F, tmp: &mut HashMap<usize, F>| match r.type_.as_str() {
-        "tmp" => {
-            //log::trace!("verify set ref {} {}", r.id, val);
-            tmp.insert(r.id, val);
-        }
-        _ => {
-            panic!("Invalid reference type set: {}", r.type_);
+    let set_ref = |r: &Node, val: F, tmp: &mut HashMap<usize, F>| -> Result<()> {
+        match r.type_.as_str() {
+            "tmp" => {
+                //log::trace!("verify set ref {} {}", r.id, val);
+                tmp.insert(r.id, val);
+                Ok(())
+            }
+            _ => bail!(StarkyError::MalformedInput(format!(
+                "invalid reference type set: {}",
+                r.type_
+            ))),
         }
     };
-    let sz = code.len() - 1;
-    let dest = code[sz].dest.clone();
+    let dest = match code.last() {
+        Some(section) => section.dest.clone(),
+        None => bail!(StarkyError::MalformedInput("empty verifier code".into())),
+    };
     for ci in code {
         let mut src: Vec<F> = vec![];
         for s in ci.src.iter() {
-            src.push(get_ref(s, &tmp));
+            src.push(get_ref(s, &tmp)?);
+        }
+        let arity = match ci.op.as_str() {
+            "copy" => 1,
+            "add" | "sub" | "mul" => 2,
+            "muladd" => 3,
+            _ => bail!(StarkyError::MalformedInput(format!(
+                "invalid op: {}",
+                ci.op
+            ))),
+        };
+        if src.len() != arity {
+            bail!(StarkyError::MalformedInput(format!(
+                "the op {} takes {} operands, not {}",
+                ci.op,
+                arity,
+                src.len()
+            )));
         }
         let res = match ci.op.as_str() {
             "add" => src[0] + src[1],
             "sub" => src[0] - src[1],
             "mul" => src[0] * src[1],
             "muladd" => (src[0] * src[1]) + src[2],
-            "copy" => src[0],
-            _ => panic!("Invalid op: {}", ci.op),
+            _ => src[0],
         };
-        set_ref(&ci.dest, res, &mut tmp);
+        set_ref(&ci.dest, res, &mut tmp)?;
     }
     get_ref(&dest, &tmp)
 }
@@ -93,6 +93,13 @@ pub struct StarkInfo {
 
     pub publics: Vec<Public>,
     pub ev_idx: EVIdx,
+    /// The dim of the extension field, `StarkStruct::extensionDegree`.
+    #[serde(default = "default_ext_dim")]
+    pub ext_dim: usize,
+}
+
+fn default_ext_dim() -> usize {
+    3
 }
 
 impl fmt::Display for StarkInfo {
@@ -273,6 +280,7 @@ impl StarkInfo {
             map_total_n: 0,
             publics: Vec::new(),
             ev_idx: EVIdx::new(),
+            ext_dim: stark_struct.extensionDegree,
         };
 
         let mut program = Program {
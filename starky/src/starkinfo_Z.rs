@@ -1,12 +1,13 @@
 #![allow(non_snake_case, dead_code)]
 use crate::constant::GLOBAL_L1;
+use crate::errors::StarkyError;
 use crate::expressionops::ExpressionOps as E;
 use crate::helper::get_ks;
 use crate::starkinfo::PCCTX;
 use crate::starkinfo::{Program, StarkInfo};
 use crate::starkinfo_codegen::{build_code, pil_code_gen, Context};
 use crate::types::{PolIdentity, PIL};
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 impl StarkInfo {
     pub fn generate_step3(
@@ -39,7 +40,10 @@ impl StarkInfo {
             let mut t_exp = E::nop();
             let u = E::challenge("u".to_string());
             let def_val = E::challenge("defVal".to_string());
-            for j in pi.t.as_ref().unwrap().iter() {
+            let pi_t = pi.t.as_ref().ok_or_else(|| {
+                StarkyError::MalformedPil("permutation identity without t".to_string())
+            })?;
+            for j in pi_t.iter() {
                 let e = E::exp(*j, None);
                 if E::is_nop(&t_exp) {
                     t_exp = e;
@@ -57,14 +61,17 @@ impl StarkInfo {
             }
 
             if E::is_nop(&t_exp) {
-                panic!("nop {:?}", t_exp);
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", t_exp)));
             }
 
             let t_exp_id = pil.expressions.len();
             pil.expressions.push(t_exp);
 
             let mut f_exp = E::nop();
-            for j in pi.f.as_ref().unwrap().iter() {
+            let pi_f = pi.f.as_ref().ok_or_else(|| {
+                StarkyError::MalformedPil("permutation identity without f".to_string())
+            })?;
+            for j in pi_f.iter() {
                 let e = E::exp(*j, None);
                 if E::is_nop(&f_exp) {
                     f_exp = e;
@@ -83,7 +90,7 @@ impl StarkInfo {
 
             let f_exp_id = pil.expressions.len();
             if E::is_nop(&f_exp) {
-                panic!("nop {:?}", f_exp);
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", f_exp)));
             }
 
             pil.expressions.push(f_exp);
@@ -125,7 +132,10 @@ impl StarkInfo {
             let zp = E::cm(pu_ctx.z_id, Some(true));
 
             if !pil.references.contains_key(global_l1) {
-                panic!("{} must be defined: {:?}", global_l1, pil.references);
+                bail!(StarkyError::MalformedPil(format!(
+                    "{} must be defined",
+                    global_l1
+                )));
             }
 
             let l1 = E::const_(pil.references[global_l1].id, None);
@@ -220,7 +230,10 @@ impl StarkInfo {
             let zp = E::cm(self.pe_ctx[i].z_id, Some(true));
 
             if !pil.references.contains_key(global_l1) {
-                panic!("{} must be defined", global_l1);
+                bail!(StarkyError::MalformedPil(format!(
+                    "{} must be defined",
+                    global_l1
+                )));
             }
             let l1 = E::const_(pil.references[global_l1].id, None);
             let mut c1 = E::mul(&l1, &E::sub(&z, &E::number("1".to_string())));
@@ -228,7 +241,7 @@ impl StarkInfo {
 
             self.pe_ctx[i].c1_id = pil.expressions.len();
             if E::is_nop(&c1) {
-                panic!("nop {:?}", format!("{:?}", c1));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", c1)));
             }
 
             pil.expressions.push(c1);
@@ -244,7 +257,7 @@ impl StarkInfo {
             self.pe_ctx[i].num_id = pil.expressions.len();
             num_exp.keep = Some(true);
             if E::is_nop(&num_exp) {
-                panic!("nop {:?}", format!("{:?}", num_exp));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", num_exp)));
             }
 
             pil.expressions.push(num_exp);
@@ -253,7 +266,7 @@ impl StarkInfo {
             self.pe_ctx[i].den_id = pil.expressions.len();
             den_exp.keep = Some(true);
             if E::is_nop(&den_exp) {
-                panic!("nop {:?}", format!("{:?}", den_exp));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", den_exp)));
             }
 
             pil.expressions.push(den_exp);
@@ -265,7 +278,7 @@ impl StarkInfo {
             c2.deg = 2;
             self.pe_ctx[i].c2_id = pil.expressions.len();
             if E::is_nop(&c2) {
-                panic!("nop {:?}", format!("{:?}", c2));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", c2)));
             }
             pil.expressions.push(c2);
             pil.polIdentities.push(PolIdentity {
@@ -295,11 +308,13 @@ impl StarkInfo {
         for ci in cii.iter() {
             let ci_pols = match &ci.pols {
                 Some(x) => x.clone(),
-                _ => panic!("ci.pols is empty"),
+                _ => bail!(StarkyError::MalformedPil("ci.pols is empty".to_string())),
             };
             let ci_connections = match &ci.connections {
                 Some(x) => x.clone(),
-                _ => panic!("ci.connections is empty"),
+                _ => bail!(StarkyError::MalformedPil(
+                    "ci.connections is empty".to_string()
+                )),
             };
 
             let mut ci_ctx = PCCTX {
@@ -326,14 +341,14 @@ impl StarkInfo {
             ci_ctx.num_id = pil.expressions.len();
             num_exp.keep = Some(true);
             if E::is_nop(&num_exp) {
-                panic!("nop {:?}", format!("{:?}", num_exp));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", num_exp)));
             }
             pil.expressions.push(num_exp);
 
             ci_ctx.den_id = pil.expressions.len();
             den_exp.keep = Some(true);
             if E::is_nop(&den_exp) {
-                panic!("nop {:?}", format!("{:?}", den_exp));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", den_exp)));
             }
             pil.expressions.push(den_exp);
 
@@ -369,13 +384,13 @@ impl StarkInfo {
                 pil.nQ += 1;
 
                 if E::is_nop(&num_exp) {
-                    panic!("nop {:?}", format!("{:?}", num_exp));
+                    bail!(StarkyError::MalformedPil(format!("nop {:?}", num_exp)));
                 }
                 ci_ctx.num_id = pil.expressions.len();
                 pil.expressions.push(num_exp);
                 ci_ctx.den_id = pil.expressions.len();
                 if E::is_nop(&den_exp) {
-                    panic!("nop {:?}", format!("{:?}", den_exp));
+                    bail!(StarkyError::MalformedPil(format!("nop {:?}", den_exp)));
                 }
                 pil.expressions.push(den_exp);
             }
@@ -384,7 +399,10 @@ impl StarkInfo {
             let zp = E::cm(ci_ctx.z_id, Some(true));
 
             if !pil.references.contains_key(global_l1) {
-                panic!("{} must be defined", global_l1);
+                bail!(StarkyError::MalformedPil(format!(
+                    "{} must be defined",
+                    global_l1
+                )));
             }
             let l1 = E::const_(pil.references[global_l1].id, None);
             let mut c1 = E::mul(&l1, &E::sub(&z, &E::number("1".to_string())));
@@ -392,7 +410,7 @@ impl StarkInfo {
 
             ci_ctx.c1_id = pil.expressions.len();
             if E::is_nop(&c1) {
-                panic!("nop {:?}", format!("{:?}", c1));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", c1)));
             }
             pil.expressions.push(c1);
 
@@ -410,7 +428,7 @@ impl StarkInfo {
             ci_ctx.c2_id = pil.expressions.len();
 
             if E::is_nop(&c2) {
-                panic!("nop {:?}", format!("{:?}", c2));
+                bail!(StarkyError::MalformedPil(format!("nop {:?}", c2)));
             }
 
             pil.expressions.push(c2);
@@ -1,4 +1,5 @@
 #![allow(dead_code, non_snake_case)]
+use crate::errors::StarkyError;
 use crate::expressionops::ExpressionOps;
 use crate::starkinfo::StarkInfo;
 use crate::traits::FieldExtension;
@@ -333,7 +334,11 @@ pub fn pil_code_gen(
         return Ok(());
     }
 
-    let exp = pil.expressions[exp_id].clone();
+    let exp = pil
+        .expressions
+        .get(exp_id)
+        .ok_or_else(|| StarkyError::MalformedPil(format!("expression {} is not defined", exp_id)))?
+        .clone();
     calculate_deps(ctx, pil, &exp, prime, exp_id, false)?;
 
     let mut code_ctx = ContextC {
@@ -361,7 +366,10 @@ pub fn pil_code_gen(
     }
     if !res_type.is_empty() {
         if prime {
-            panic!("Prime in retType");
+            bail!(StarkyError::MalformedPil(format!(
+                "prime in the result of expression {}",
+                exp_id
+            )));
         }
 
         let dest = Node::new(res_type.to_string(), res_id, None, 0, prime, 0);
@@ -388,30 +396,36 @@ pub fn pil_code_gen(
     Ok(())
 }
 
+// a malformed add or mul is kept as it is, and reported by eval_exp
 fn find_muladd(exp: &Expression) -> Expression {
-    if exp.values.is_some() {
-        let values = exp.values.as_ref().unwrap();
-        if exp.op.as_str() == "add" && values[0].op.as_str() == "mul" {
-            let value_of_values = values[0].values.as_ref().unwrap();
-            let a = find_muladd(&value_of_values[0]);
-            let b = find_muladd(&value_of_values[1]);
-            let c = find_muladd(&values[1]);
-            return Expression::new("muladd".to_string(), 0, None, None, Some(vec![a, b, c]));
-        } else if exp.op.as_str() == "add" && values[1].op.as_str() == "mul" {
-            let value_of_values = values[1].values.as_ref().unwrap();
-            let a = find_muladd(&value_of_values[0]);
-            let b = find_muladd(&value_of_values[1]);
-            let c = find_muladd(&values[0]);
-            return Expression::new("muladd".to_string(), 0, None, None, Some(vec![a, b, c]));
-        } else {
-            let mut r = exp.clone();
-            let mut_values: Vec<Expression> =
-                (0..values.len()).map(|i| find_muladd(&values[i])).collect();
-            if !mut_values.is_empty() {
-                r.values = Some(mut_values);
+    if let Some(values) = exp.values.as_ref() {
+        if exp.op.as_str() == "add" && values.len() == 2 {
+            for (m, c) in [(0, 1), (1, 0)] {
+                match values[m].values.as_ref() {
+                    Some(value_of_values)
+                        if values[m].op.as_str() == "mul" && value_of_values.len() == 2 =>
+                    {
+                        let a = find_muladd(&value_of_values[0]);
+                        let b = find_muladd(&value_of_values[1]);
+                        let c = find_muladd(&values[c]);
+                        return Expression::new(
+                            "muladd".to_string(),
+                            0,
+                            None,
+                            None,
+                            Some(vec![a, b, c]),
+                        );
+                    }
+                    _ => {}
+                }
             }
-            return r;
         }
+        let mut r = exp.clone();
+        let mut_values: Vec<Expression> = values.iter().map(find_muladd).collect();
+        if !mut_values.is_empty() {
+            r.values = Some(mut_values);
+        }
+        return r;
     }
     exp.clone()
 }
@@ -461,7 +475,10 @@ pub fn eval_exp(
 ) -> Result<Node> {
     //log::trace!("eval, expression {}", exp);
     if ExpressionOps::is_nop(exp) {
-        panic!("exp: {:?}", exp);
+        bail!(StarkyError::MalformedPil(format!(
+            "nop in expression {}",
+            code_ctx.exp_id
+        )));
     }
 
     let default = vec![];
@@ -486,7 +503,7 @@ fn eval_single_op(
             let c = Section {
                 op: "add".to_string(),
                 dest: r.clone(),
-                src: values.collect(),
+                src: operands(exp, values, 2)?,
             };
             code_ctx.code.push(c);
             Ok(r)
@@ -497,7 +514,7 @@ fn eval_single_op(
             let c = Section {
                 op: "sub".to_string(),
                 dest: r.clone(),
-                src: values.collect(),
+                src: operands(exp, values, 2)?,
             };
             code_ctx.code.push(c);
             Ok(r)
@@ -508,7 +525,7 @@ fn eval_single_op(
             let c = Section {
                 op: "mul".to_string(),
                 dest: r.clone(),
-                src: values.collect(),
+                src: operands(exp, values, 2)?,
             };
             code_ctx.code.push(c);
             Ok(r)
@@ -519,17 +536,17 @@ fn eval_single_op(
             let c = Section {
                 op: "muladd".to_string(),
                 dest: r.clone(),
-                src: values.collect(),
+                src: operands(exp, values, 3)?,
             };
             code_ctx.code.push(c);
             Ok(r)
         }
         "addc" => {
-            let a = values.next().unwrap();
+            let a = values.next().ok_or_else(|| missing(exp, "operand"))?;
             let b = Node::new(
                 "number".to_string(),
                 0,
-                Some(exp.const_.unwrap().to_string()),
+                Some(exp.const_.ok_or_else(|| missing(exp, "const"))?.to_string()),
                 0,
                 false,
                 0,
@@ -545,11 +562,11 @@ fn eval_single_op(
             Ok(r)
         }
         "mulc" => {
-            let a = values.next().unwrap();
+            let a = values.next().ok_or_else(|| missing(exp, "operand"))?;
             let b = Node::new(
                 "number".to_string(),
                 0,
-                Some(exp.const_.unwrap().to_string()),
+                Some(exp.const_.ok_or_else(|| missing(exp, "const"))?.to_string()),
                 0,
                 false,
                 0,
@@ -567,7 +584,7 @@ fn eval_single_op(
         }
         "neg" => {
             let a = Node::new("number".to_string(), 0, Some("0".to_string()), 0, false, 0);
-            let b = values.next().unwrap();
+            let b = values.next().ok_or_else(|| missing(exp, "operand"))?;
 
             let r = Node::new("tmp".to_string(), code_ctx.tmp_used, None, 0, false, 0);
             code_ctx.tmp_used += 1;
@@ -586,7 +603,7 @@ fn eval_single_op(
             }
             Ok(Node::new(
                 "cm".to_string(),
-                exp.id.unwrap(),
+                exp.id.ok_or_else(|| missing(exp, "id"))?,
                 None,
                 0,
                 exp.next() || prime,
@@ -599,7 +616,7 @@ fn eval_single_op(
             }
             Ok(Node::new(
                 "const".to_string(),
-                exp.id.unwrap(),
+                exp.id.ok_or_else(|| missing(exp, "id"))?,
                 None,
                 0,
                 exp.next() || prime,
@@ -612,7 +629,7 @@ fn eval_single_op(
             }
             Ok(Node::new(
                 "exp".to_string(),
-                exp.id.unwrap(),
+                exp.id.ok_or_else(|| missing(exp, "id"))?,
                 None,
                 0,
                 exp.next() || prime,
@@ -625,7 +642,7 @@ fn eval_single_op(
             }
             Ok(Node::new(
                 "q".to_string(),
-                exp.id.unwrap(),
+                exp.id.ok_or_else(|| missing(exp, "id"))?,
                 None,
                 0,
                 exp.next() || prime,
@@ -642,7 +659,7 @@ fn eval_single_op(
         )),
         "public" => Ok(Node::new(
             "public".to_string(),
-            exp.id.unwrap(),
+            exp.id.ok_or_else(|| missing(exp, "id"))?,
             None,
             0,
             false,
@@ -650,7 +667,7 @@ fn eval_single_op(
         )),
         "challenge" => Ok(Node::new(
             "challenge".to_string(),
-            exp.id.unwrap(),
+            exp.id.ok_or_else(|| missing(exp, "id"))?,
             None,
             0,
             false,
@@ -658,7 +675,7 @@ fn eval_single_op(
         )),
         "eval" => Ok(Node::new(
             "eval".to_string(),
-            exp.id.unwrap(),
+            exp.id.ok_or_else(|| missing(exp, "id"))?,
             None,
             0,
             false,
@@ -667,7 +684,10 @@ fn eval_single_op(
         "xDivXSubXi" => Ok(Node::new("xDivXSubXi".to_string(), 0, None, 0, false, 0)),
         "xDivXSubWXi" => Ok(Node::new("xDivXSubWXi".to_string(), 0, None, 0, false, 0)),
         "x" => Ok(Node::new("x".to_string(), 0, None, 0, false, 0)),
-        _ => bail!(format!("InvalidOperator: eval_exp: {}", exp.op)),
+        _ => bail!(StarkyError::MalformedPil(format!(
+            "invalid operator {} in expression {}",
+            exp.op, code_ctx.exp_id
+        ))),
     }
 }
 
@@ -681,23 +701,42 @@ pub fn calculate_deps(
 ) -> Result<()> {
     //log::trace!("calculate_deps: {}", expr);
     if expr.op == "exp" {
-        let id = expr.id.unwrap();
+        let id = expr.id.ok_or_else(|| missing(expr, "id"))?;
         if prime && expr.next() {
             expression_error(pil, "Double prime".to_string(), exp_id, id)?;
         }
         pil_code_gen(ctx, pil, id, prime || expr.next(), "", 0, muladd)?;
     }
-    if expr.values.is_some() {
-        for e in expr.values.as_ref().unwrap().iter() {
+    if let Some(values) = expr.values.as_ref() {
+        for e in values.iter() {
             calculate_deps(ctx, pil, e, prime, exp_id, muladd)?;
         }
     }
     Ok(())
 }
 
-pub fn expression_error(_pil: &PIL, strerr: String, _e1: usize, _e2: usize) -> Result<()> {
-    //TODO
-    bail!(strerr);
+pub fn expression_error(_pil: &PIL, strerr: String, e1: usize, e2: usize) -> Result<()> {
+    bail!(StarkyError::MalformedPil(format!(
+        "{} in expression {} {}",
+        strerr, e1, e2
+    )));
+}
+
+fn operands(exp: &Expression, values: impl Iterator<Item = Node>, n: usize) -> Result<Vec<Node>> {
+    let values: Vec<Node> = values.collect();
+    if values.len() != n {
+        bail!(StarkyError::MalformedPil(format!(
+            "{} with {} operands, expect {}",
+            exp.op,
+            values.len(),
+            n
+        )));
+    }
+    Ok(values)
+}
+
+fn missing(exp: &Expression, what: &str) -> anyhow::Error {
+    StarkyError::MalformedPil(format!("{} without {}", exp.op, what)).into()
 }
 
 pub fn build_code(ctx: &mut Context, pil: &PIL) -> Segment {
@@ -783,3 +822,48 @@ pub fn iterate_code(
     iterate(&mut code.i, f);
     iterate(&mut code.last, f);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::load_json;
+
+    fn code_gen(pil: &mut PIL, exp_id: usize) -> Result<()> {
+        let mut ctx = Context {
+            exp_id: 0,
+            tmp_used: 0,
+            code: vec![],
+            calculated: HashMap::new(),
+        };
+        pil_code_gen(&mut ctx, pil, exp_id, false, "", 0, false)
+    }
+
+    fn assert_malformed_pil(err: anyhow::Error) {
+        assert!(
+            matches!(
+                err.downcast_ref::<StarkyError>(),
+                Some(StarkyError::MalformedPil(_))
+            ),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_pil_code_gen_malformed() {
+        let pil = load_json::<PIL>("data/fib.pil.json").unwrap();
+        let e = pil.polIdentities[0].e;
+        code_gen(&mut pil.clone(), e).unwrap();
+
+        let n = pil.expressions.len();
+        assert_malformed_pil(code_gen(&mut pil.clone(), n).unwrap_err());
+
+        let mut bad = pil.clone();
+        bad.expressions[e].op = "pow".to_string();
+        assert_malformed_pil(code_gen(&mut bad, e).unwrap_err());
+
+        let mut bad = pil.clone();
+        bad.expressions[e].values.as_mut().unwrap().pop();
+        assert_malformed_pil(code_gen(&mut bad, e).unwrap_err());
+    }
+}
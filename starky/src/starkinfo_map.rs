@@ -46,8 +46,8 @@ impl StarkInfo {
         //log::trace!("pu: {:?}", self.pu_ctx);
         for (i, pu) in self.pu_ctx.iter().enumerate() {
             let dim = std::cmp::max(
-                Self::get_exp_dim(pil, &pil.expressions[pu.f_exp_id]),
-                Self::get_exp_dim(pil, &pil.expressions[pu.t_exp_id]),
+                Self::get_exp_dim(pil, &pil.expressions[pu.f_exp_id], self.ext_dim),
+                Self::get_exp_dim(pil, &pil.expressions[pu.t_exp_id], self.ext_dim),
             );
 
             let pph1_n = add_pol(PolType {
@@ -127,13 +127,13 @@ impl StarkInfo {
 
             let ppz_n = add_pol(PolType {
                 section: "cm3_n".to_string(),
-                dim: 3,
+                dim: self.ext_dim,
                 exp_id: 0,
                 section_pos: 0,
             });
             let ppz_2ns = add_pol(PolType {
                 section: "cm3_2ns".to_string(),
-                dim: 3,
+                dim: self.ext_dim,
                 exp_id: 0,
                 section_pos: 0,
             });
@@ -141,13 +141,13 @@ impl StarkInfo {
             self.cm_2ns.push(ppz_2ns);
             self.map_sections.cm3_n.push(ppz_n);
             self.map_sections.cm3_2ns.push(ppz_2ns);
-            pil.cm_dims[self.n_cm1 + self.n_cm2 + i] = 3;
+            pil.cm_dims[self.n_cm1 + self.n_cm2 + i] = self.ext_dim;
 
             if im_exps_none(&o.num_id) && !tmpexps.contains_key(&o.num_id) {
                 tmpexps.insert(o.num_id, self.tmpexp_n.len());
                 let pp_num_n = add_pol(PolType {
                     section: "tmpexp_n".to_string(),
-                    dim: 3,
+                    dim: self.ext_dim,
                     exp_id: 0,
                     section_pos: 0,
                 });
@@ -161,7 +161,7 @@ impl StarkInfo {
                 tmpexps.insert(o.den_id, self.tmpexp_n.len());
                 let pp_den_n = add_pol(PolType {
                     section: "tmpexp_n".to_string(),
-                    dim: 3,
+                    dim: self.ext_dim,
                     exp_id: 0,
                     section_pos: 0,
                 });
@@ -173,7 +173,7 @@ impl StarkInfo {
         }
 
         for i in 0..self.im_exps_list.len() {
-            let dim = Self::get_exp_dim(pil, &pil.expressions[self.im_exps_list[i]]);
+            let dim = Self::get_exp_dim(pil, &pil.expressions[self.im_exps_list[i]], self.ext_dim);
 
             let ppz_n = add_pol(PolType {
                 section: "cm3_n".to_string(),
@@ -197,7 +197,7 @@ impl StarkInfo {
             self.exp2pol.insert(self.im_exps_list[i], ppz_n);
         }
 
-        self.q_dim = Self::get_exp_dim(pil, &pil.expressions[self.c_exp]);
+        self.q_dim = Self::get_exp_dim(pil, &pil.expressions[self.c_exp], self.ext_dim);
 
         for i in 0..self.q_deg {
             let ppz_n = add_pol(PolType {
@@ -231,7 +231,7 @@ impl StarkInfo {
 
         let ppf_2ns = add_pol(PolType {
             section: "f_2ns".to_string(),
-            dim: 3,
+            dim: self.ext_dim,
             exp_id: 0,
             section_pos: 0,
         });
@@ -325,7 +325,7 @@ impl StarkInfo {
         self.set_code_dimensions(&mut program.step3, 1);
         self.set_code_dimensions(&mut program.step42ns, 1);
         self.set_code_dimensions(&mut program.step52ns, 1);
-        self.set_code_dimensions(&mut program.verifier_code, 3);
+        self.set_code_dimensions(&mut program.verifier_code, self.ext_dim);
         self.set_code_dimensions(&mut program.verifier_query_code, 1);
 
         Ok(())
@@ -375,7 +375,7 @@ impl StarkInfo {
                 d = 1;
             }
             "eval" | "challenge" | "Z" => {
-                d = 3;
+                d = self.ext_dim;
             }
             "xDivXSubXi" | "xDivXSubWXi" | "x" => {
                 d = dim_x;
@@ -447,7 +447,7 @@ impl StarkInfo {
 
     pub fn set_code_dimensions_first(&mut self, segment: &mut Segment) -> Result<()> {
         let mut tmp_dim: HashMap<usize, usize> = HashMap::new();
-        let dim_x = 3;
+        let dim_x = self.ext_dim;
         self._set_code_dimensions(&mut segment.first, &mut tmp_dim, dim_x);
         Ok(())
     }
@@ -487,7 +487,8 @@ impl StarkInfo {
                         r.id = ctx.starkinfo.im_exp2cm[&ctx.starkinfo.im_exps_list[idx]];
                     } else if ctx.tmpexps.get(&r.id).is_some() && ctx.dom == "n" {
                         r.type_ = "tmpExp".to_string();
-                        r.dim = Self::get_exp_dim(pil, &pil.expressions[r.id]);
+                        r.dim =
+                            Self::get_exp_dim(pil, &pil.expressions[r.id], ctx.starkinfo.ext_dim);
                         r.id = ctx.tmpexps[&r.id];
                     } else {
                         let p = if r.prime { 1 } else { 0 };
@@ -523,7 +524,7 @@ impl StarkInfo {
 
         for s in names.iter() {
             let mut p = 0;
-            for e in 1..=3 {
+            for e in 1..=self.ext_dim {
                 for pp in self.var_pol_map.iter_mut() {
                     if pp.section.as_str() == *s && pp.dim == e {
                         pp.section_pos = p;
@@ -533,24 +534,25 @@ impl StarkInfo {
                 if e == 1 {
                     self.map_sectionsN1.set(s, p);
                 }
-                if e == 3 {
+                if e == self.ext_dim {
                     self.map_sectionsN.set(s, p);
                 }
             }
-            let t = (self.map_sectionsN.get(s) - self.map_sectionsN1.get(s)) / 3;
+            let t = (self.map_sectionsN.get(s) - self.map_sectionsN1.get(s)) / self.ext_dim;
             //log::trace!("map_sectionN3 set {} = {}", s, t);
             self.map_sectionsN3.set(s, t);
         }
         Ok(())
     }
 
-    pub fn get_exp_dim(pil: &PIL, exp: &Expression) -> usize {
+    /// The dim of the expression, `ext_dim` once it takes an element of the extension field.
+    pub fn get_exp_dim(pil: &PIL, exp: &Expression, ext_dim: usize) -> usize {
         match exp.op.as_str() {
             "add" | "sub" | "mul" | "muladd" | "addc" | "mulc" | "neg" => {
                 let mut md = 1;
                 let values = exp.values.as_ref().unwrap();
                 for v in values.iter() {
-                    let d = Self::get_exp_dim(pil, v);
+                    let d = Self::get_exp_dim(pil, v, ext_dim);
                     if d > md {
                         md = d
                     }
@@ -559,10 +561,10 @@ impl StarkInfo {
             }
             "cm" => pil.cm_dims[exp.id.unwrap()],
             "const" => 1,
-            "exp" => Self::get_exp_dim(pil, &pil.expressions[exp.id.unwrap()]),
-            "q" => Self::get_exp_dim(pil, &pil.expressions[pil.q2exp[exp.id.unwrap()]]),
+            "exp" => Self::get_exp_dim(pil, &pil.expressions[exp.id.unwrap()], ext_dim),
+            "q" => Self::get_exp_dim(pil, &pil.expressions[pil.q2exp[exp.id.unwrap()]], ext_dim),
             "number" | "public" => 1,
-            "challenge" | "eval" | "xDivXSubXi" | "xDivXSubWXi" => 3,
+            "challenge" | "eval" | "xDivXSubXi" | "xDivXSubWXi" => ext_dim,
             "x" => 1,
             _ => panic!("Exp op not defined: {}", exp.op),
         }
@@ -0,0 +1,248 @@
+//! A typed trace builder on top of `PolsArray`, the Rust counterpart of filling `constPols` and
+//! `cmPols` in the starkjs executors.
+//!
+//! The columns are resolved from the `PIL` once, by their `Namespace.name`, and the rows are then
+//! set through the handles instead of the `(namespace, name, i, j)` strings of `set_matrix`.
+use crate::constant::MG;
+use crate::errors::StarkyError;
+use crate::helper::{get_ks, log2_any};
+use crate::polsarray::{PolKind, PolsArray};
+use crate::types::PIL;
+use anyhow::{bail, Result};
+use fields::field_gl::Fr as FGL;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+
+/// A polynomial of the trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column(usize);
+
+impl Column {
+    /// The id of the polynomial in the `.const` or `.cm` file
+    pub fn id(&self) -> usize {
+        self.0
+    }
+}
+
+/// The polynomials of an array column, like `pol commit a[4]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArrayColumn {
+    id: usize,
+    len: usize,
+}
+
+impl ArrayColumn {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The i-th polynomial of the array
+    pub fn at(&self, i: usize) -> Column {
+        assert!(i < self.len, "index {} out of the array of {}", i, self.len);
+        Column(self.id + i)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Column> {
+        (self.id..(self.id + self.len)).map(Column)
+    }
+}
+
+/// The constant or committed polynomials of a `PIL` under construction
+pub struct Trace {
+    pub pols: PolsArray,
+    kind: &'static str,
+    // name => (id, the array length if it's an array)
+    columns: HashMap<String, (usize, Option<usize>)>,
+}
+
+impl Trace {
+    pub fn new(pil: &PIL, kind: PolKind) -> Self {
+        let (ref_type, name) = match kind {
+            PolKind::Commit => ("cmP", "committed"),
+            PolKind::Constant => ("constP", "constant"),
+        };
+        let columns = pil
+            .references
+            .iter()
+            .filter(|(_, r)| r.type_ == ref_type)
+            .map(|(n, r)| (n.clone(), (r.id, if r.isArray { r.len } else { None })))
+            .collect();
+        Trace {
+            pols: PolsArray::new(pil, kind),
+            kind: name,
+            columns,
+        }
+    }
+
+    /// The number of rows
+    pub fn n(&self) -> usize {
+        self.pols.n
+    }
+
+    /// Resolve the polynomial `Namespace.name`
+    pub fn column(&self, name: &str) -> Result<Column> {
+        match self.columns.get(name) {
+            Some((id, None)) => Ok(Column(*id)),
+            Some((_, Some(len))) => bail!(StarkyError::MalformedPil(format!(
+                "{} is an array of {} polynomials",
+                name, len
+            ))),
+            None => bail!(StarkyError::MalformedPil(format!(
+                "{} is not a {} polynomial",
+                name, self.kind
+            ))),
+        }
+    }
+
+    /// Resolve the array polynomial `Namespace.name`
+    pub fn array_column(&self, name: &str) -> Result<ArrayColumn> {
+        match self.columns.get(name) {
+            Some((id, Some(len))) => Ok(ArrayColumn { id: *id, len: *len }),
+            Some((_, None)) => bail!(StarkyError::MalformedPil(format!(
+                "{} is not an array polynomial",
+                name
+            ))),
+            None => bail!(StarkyError::MalformedPil(format!(
+                "{} is not a {} polynomial",
+                name, self.kind
+            ))),
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, col: Column, row: usize) -> FGL {
+        self.pols.array[col.0][row]
+    }
+
+    #[inline(always)]
+    pub fn set<V: Into<FGL>>(&mut self, col: Column, row: usize, value: V) {
+        self.pols.array[col.0][row] = value.into();
+    }
+
+    /// Set the columns of one row
+    pub fn set_row(&mut self, row: usize, values: &[(Column, FGL)]) {
+        for (col, value) in values.iter() {
+            self.set(*col, row, *value);
+        }
+    }
+
+    /// Set every row of the column to `f(row)`
+    pub fn fill<F: FnMut(usize) -> FGL>(&mut self, col: Column, f: F) {
+        let n = self.n();
+        self.pols.array[col.0] = (0..n).map(f).collect();
+    }
+
+    /// 1 on the first row, 0 elsewhere
+    pub fn fill_l1(&mut self, col: Column) {
+        self.fill(col, |i| if i == 0 { FGL::ONE } else { FGL::ZERO });
+    }
+
+    /// 1 on the last row, 0 elsewhere
+    pub fn fill_llast(&mut self, col: Column) {
+        let n = self.n();
+        self.fill(col, |i| if i == n - 1 { FGL::ONE } else { FGL::ZERO });
+    }
+
+    /// The row number, 0, 1, 2...
+    pub fn fill_step(&mut self, col: Column) {
+        self.fill(col, |i| FGL::from(i as u64));
+    }
+
+    /// The identity permutation of a `connect` identity, `cols[j][i] = k_j * w^i` with `k_0 = 1`,
+    /// which the cells are then swapped in by `connect`.
+    pub fn fill_connection(&mut self, cols: &[Column]) {
+        let n = self.n();
+        let ks = if cols.len() > 1 {
+            get_ks(cols.len() - 1)
+        } else {
+            vec![]
+        };
+        let w = MG.0[log2_any(n)];
+        let mut x = FGL::ONE;
+        for i in 0..n {
+            self.set(cols[0], i, x);
+            for (j, k) in ks.iter().enumerate() {
+                self.set(cols[j + 1], i, x * *k);
+            }
+            x = x * w;
+        }
+    }
+
+    /// Connect the cell `(c1, r1)` to `(c2, r2)` by swapping them
+    pub fn connect(&mut self, (c1, r1): (Column, usize), (c2, r2): (Column, usize)) {
+        let v1 = self.get(c1, r1);
+        let v2 = self.get(c2, r2);
+        self.set(c1, r1, v2);
+        self.set(c2, r2, v1);
+    }
+
+    pub fn save(&self, file_name: &str) -> Result<()> {
+        let writer = BufWriter::new(File::create(file_name)?);
+        self.pols.save(writer)
+    }
+
+    pub fn into_pols(self) -> PolsArray {
+        self.pols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::load_json;
+
+    #[test]
+    fn test_trace_columns() {
+        let pil = load_json::<PIL>("data/arrays.pil.json").unwrap();
+        let mut cm = Trace::new(&pil, PolKind::Commit);
+        let arr = cm.array_column("Arrays1.b").unwrap();
+        assert_eq!(arr.len(), 3);
+        assert_eq!(arr.at(1).id(), 3);
+        cm.set(arr.at(1), 3, 7u64);
+        assert_eq!(cm.get(arr.at(1), 3), FGL::from(7u64));
+        assert_eq!(arr.iter().count(), arr.len());
+
+        assert_eq!(cm.column("Arrays1.c").unwrap().id(), 5);
+        assert!(cm.array_column("Arrays1.c").is_err());
+        let err = cm.column("Arrays1.b").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedPil(_))
+        ));
+        assert!(cm.column("Arrays2.d").is_err());
+    }
+
+    #[test]
+    fn test_trace_constants() {
+        let pil = load_json::<PIL>("data/connection.pil.json").unwrap();
+        let mut consts = Trace::new(&pil, PolKind::Constant);
+        // the committed polynomials are not resolved from the constant trace
+        assert!(consts.column("Connection.a").is_err());
+        let l1 = consts.column("Global.L1").unwrap();
+        let s1 = consts.column("Connection.S1").unwrap();
+        let s2 = consts.column("Connection.S2").unwrap();
+        let n = consts.n();
+
+        consts.fill_llast(l1);
+        assert_eq!(consts.get(l1, n - 1), FGL::ONE);
+        assert_eq!(consts.get(l1, 0), FGL::ZERO);
+        consts.fill_step(l1);
+        assert_eq!(consts.get(l1, 5), FGL::from(5u64));
+        consts.fill_l1(l1);
+        assert_eq!(consts.get(l1, 0), FGL::ONE);
+        assert_eq!(consts.get(l1, 1), FGL::ZERO);
+
+        consts.fill_connection(&[s1, s2]);
+        assert_eq!(consts.get(s1, 0), FGL::ONE);
+        assert_eq!(consts.get(s2, 0), get_ks(1)[0]);
+        assert_eq!(consts.get(s1, 1), MG.0[log2_any(n)]);
+        let (a, b) = (consts.get(s1, 2), consts.get(s2, 3));
+        consts.connect((s1, 2), (s2, 3));
+        assert_eq!((consts.get(s1, 2), consts.get(s2, 3)), (b, a));
+    }
+}
@@ -0,0 +1,302 @@
+//! The executors of the starkjs examples ported to `Trace`, they produce the same `.const` and
+//! `.cm` files as `node starkjs/<example>/<example>.js`.
+//!
+//! `simple_vm` is not ported, its executor is a copy of `connection.js` over columns which
+//! `simple_vm.pil` doesn't declare, and `simple_vm.pil` itself is not accepted by pilcom.
+use crate::errors::StarkyError;
+use crate::polsarray::PolKind;
+use crate::trace::Trace;
+use crate::types::{load_json, PIL};
+use anyhow::{bail, Result};
+use fields::field_gl::Fr as FGL;
+
+pub const EXAMPLES: [&str; 4] = ["fibonacci", "plookup", "permutation", "connection"];
+
+/// `buildConstants` and `execute` of a starkjs executor
+pub trait Executor {
+    fn build_constants(&self, consts: &mut Trace) -> Result<()>;
+    fn execute(&self, cm: &mut Trace) -> Result<()>;
+}
+
+/// `starkjs/fibonacci`, the inputs are `l2(0)` and `l1(0)`
+pub struct Fibonacci {
+    pub input: [u64; 2],
+}
+
+impl Executor for Fibonacci {
+    fn build_constants(&self, consts: &mut Trace) -> Result<()> {
+        let l1 = consts.column("Fibonacci.L1")?;
+        let llast = consts.column("Fibonacci.LLAST")?;
+        consts.fill_l1(l1);
+        consts.fill_llast(llast);
+        Ok(())
+    }
+
+    fn execute(&self, cm: &mut Trace) -> Result<()> {
+        let l1 = cm.column("Fibonacci.l1")?;
+        let l2 = cm.column("Fibonacci.l2")?;
+        cm.set(l2, 0, self.input[0]);
+        cm.set(l1, 0, self.input[1]);
+        for i in 1..cm.n() {
+            let (a, b) = (cm.get(l1, i - 1), cm.get(l2, i - 1));
+            cm.set(l2, i, a);
+            cm.set(l1, i, b * b + a * a);
+        }
+        Ok(())
+    }
+}
+
+/// `starkjs/plookup`
+pub struct Plookup;
+
+impl Executor for Plookup {
+    fn build_constants(&self, consts: &mut Trace) -> Result<()> {
+        let l1 = consts.column("Global.L1")?;
+        let a = consts.column("Plookup.A")?;
+        let b = consts.column("Plookup.B")?;
+        let sel = consts.column("Plookup.SEL")?;
+        consts.fill_l1(l1);
+        // the 16x16 table, then zeros
+        consts.fill(a, |p| FGL::from(if p < 256 { p as u64 / 16 } else { 0 }));
+        consts.fill(b, |p| FGL::from(if p < 256 { p as u64 % 16 } else { 0 }));
+        consts.fill(sel, |p| FGL::from((p < 256) as u64));
+        Ok(())
+    }
+
+    fn execute(&self, cm: &mut Trace) -> Result<()> {
+        let cc = cm.column("Plookup.cc")?;
+        let sel = cm.column("Plookup.sel")?;
+        let a = cm.column("Plookup.a")?;
+        let b = cm.column("Plookup.b")?;
+        cm.fill(cc, |p| {
+            let p = p as u64;
+            FGL::from(if p < 256 { (p / 16) * (p % 16) } else { p })
+        });
+
+        for i in 0..10u64 {
+            let b_i = if i == 0 { 55 } else { i + 3 };
+            cm.set_row(
+                i as usize,
+                &[(sel, FGL::ONE), (a, FGL::from(i)), (b, FGL::from(b_i))],
+            );
+        }
+        cm.set_row(
+            10,
+            &[(sel, FGL::ZERO), (a, 55u64.into()), (b, 10u64.into())],
+        );
+        for p in 11..cm.n() {
+            cm.set_row(p, &[(sel, FGL::ZERO), (a, 55u64.into()), (b, 55u64.into())]);
+        }
+        Ok(())
+    }
+}
+
+/// `starkjs/permutation`
+pub struct Permutation;
+
+impl Executor for Permutation {
+    fn build_constants(&self, consts: &mut Trace) -> Result<()> {
+        let l1 = consts.column("Global.L1")?;
+        consts.fill_l1(l1);
+        Ok(())
+    }
+
+    fn execute(&self, cm: &mut Trace) -> Result<()> {
+        let a = cm.column("Permutation.a")?;
+        let b = cm.column("Permutation.b")?;
+        let c = cm.column("Permutation.c")?;
+        let d = cm.column("Permutation.d")?;
+        let sel_c = cm.column("Permutation.selC")?;
+        let sel_d = cm.column("Permutation.selD")?;
+        let n = cm.n();
+        for i in 0..n {
+            let a_i = FGL::from((i * i + i + 1) as u64);
+            cm.set(a, i, a_i);
+            cm.set(b, n - i - 1, a_i);
+            if i % 2 == 0 {
+                cm.set_row(i, &[(sel_c, FGL::ONE), (c, a_i)]);
+                cm.set_row(i / 2, &[(sel_d, FGL::ONE), (d, a_i)]);
+            } else {
+                cm.set_row(i, &[(sel_c, FGL::ZERO), (c, 44u64.into())]);
+                cm.set_row(
+                    n / 2 + (i - 1) / 2,
+                    &[(sel_d, FGL::ZERO), (d, 55u64.into())],
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `starkjs/connection`
+pub struct Connection;
+
+impl Executor for Connection {
+    fn build_constants(&self, consts: &mut Trace) -> Result<()> {
+        let l1 = consts.column("Global.L1")?;
+        let s1 = consts.column("Connection.S1")?;
+        let s2 = consts.column("Connection.S2")?;
+        let s3 = consts.column("Connection.S3")?;
+        consts.fill_l1(l1);
+        consts.fill_connection(&[s1, s2, s3]);
+
+        let n = consts.n();
+        for i in 0..n {
+            let j = if i % 2 == 0 {
+                i / 2
+            } else {
+                n / 2 + (i - 1) / 2
+            };
+            consts.connect((s1, i), (s2, j));
+            consts.connect((s2, i), (s3, j));
+        }
+        Ok(())
+    }
+
+    fn execute(&self, cm: &mut Trace) -> Result<()> {
+        let a = cm.column("Connection.a")?;
+        let b = cm.column("Connection.b")?;
+        let c = cm.column("Connection.c")?;
+        let n = cm.n();
+        // the even rows of the previous column, then the odd ones
+        let interleave = |i: usize| {
+            if i < n / 2 {
+                i * 2
+            } else {
+                (i - n / 2) * 2 + 1
+            }
+        };
+        cm.fill(a, |i| FGL::from(i as u64));
+        for i in 0..n {
+            let v = cm.get(a, interleave(i));
+            cm.set(b, i, v);
+        }
+        for i in 0..n {
+            let v = cm.get(b, interleave(i));
+            cm.set(c, i, v);
+        }
+        Ok(())
+    }
+}
+
+/// Build the constant and committed polynomials of the PIL by the executor
+pub fn generate<E: Executor>(
+    executor: &E,
+    pil: &PIL,
+    const_pol_file: &str,
+    cm_pol_file: &str,
+) -> Result<()> {
+    let mut consts = Trace::new(pil, PolKind::Constant);
+    executor.build_constants(&mut consts)?;
+    consts.save(const_pol_file)?;
+
+    let mut cm = Trace::new(pil, PolKind::Commit);
+    executor.execute(&mut cm)?;
+    cm.save(cm_pol_file)
+}
+
+/// Generate the traces of the starkjs example, one of `EXAMPLES`, the fibonacci one takes the two
+/// inputs, 1 and 2 by default.
+pub fn trace_gen(
+    example: &str,
+    pil_file: &str,
+    input: &[u64],
+    const_pol_file: &str,
+    cm_pol_file: &str,
+) -> Result<()> {
+    let pil = load_json::<PIL>(pil_file)?;
+    match example {
+        "fibonacci" => {
+            let input = match input {
+                [] => [1, 2],
+                [a, b] => [*a, *b],
+                _ => bail!(StarkyError::MalformedInput(format!(
+                    "fibonacci takes 2 inputs, got {}",
+                    input.len()
+                ))),
+            };
+            generate(&Fibonacci { input }, &pil, const_pol_file, cm_pol_file)
+        }
+        "plookup" => generate(&Plookup, &pil, const_pol_file, cm_pol_file),
+        "permutation" => generate(&Permutation, &pil, const_pol_file, cm_pol_file),
+        "connection" => generate(&Connection, &pil, const_pol_file, cm_pol_file),
+        _ => bail!(StarkyError::MalformedInput(format!(
+            "unknown example {}, expect one of {}",
+            example,
+            EXAMPLES.join(", ")
+        ))),
+    }?;
+    log::info!("{} traces: {}, {}", example, const_pol_file, cm_pol_file);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pil_verify::pil_verify;
+
+    fn check(example: &str, data: &str, input: &[u64]) {
+        let const_file = format!("/tmp/test_trace_gen.{}.const", data);
+        let cm_file = format!("/tmp/test_trace_gen.{}.cm", data);
+        let pil_file = format!("data/{}.pil.json", data);
+        trace_gen(example, &pil_file, input, &const_file, &cm_file).unwrap();
+        assert_eq!(
+            std::fs::read(&const_file).unwrap(),
+            std::fs::read(format!("data/{}.const", data)).unwrap(),
+            "{}",
+            example
+        );
+        assert_eq!(
+            std::fs::read(&cm_file).unwrap(),
+            std::fs::read(format!("data/{}.cm", data)).unwrap(),
+            "{}",
+            example
+        );
+        pil_verify(&pil_file, &const_file, &cm_file).unwrap();
+    }
+
+    #[test]
+    fn test_trace_gen_examples() {
+        check("fibonacci", "fib", &[1, 2]);
+        check("plookup", "plookup", &[]);
+        check("permutation", "pe", &[]);
+        check("connection", "connection", &[]);
+    }
+
+    #[test]
+    fn test_trace_gen_invalid() {
+        let err = trace_gen(
+            "simple_vm",
+            "data/fib.pil.json",
+            &[],
+            "/tmp/test_trace_gen_invalid.const",
+            "/tmp/test_trace_gen_invalid.cm",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+        // the executor doesn't match the PIL
+        let err = trace_gen(
+            "plookup",
+            "data/fib.pil.json",
+            &[],
+            "/tmp/test_trace_gen_invalid.const",
+            "/tmp/test_trace_gen_invalid.cm",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedPil(_))
+        ));
+        assert!(trace_gen(
+            "fibonacci",
+            "data/fib.pil.json",
+            &[1],
+            "/tmp/test_trace_gen_invalid.const",
+            "/tmp/test_trace_gen_invalid.cm",
+        )
+        .is_err());
+    }
+}
@@ -35,6 +35,8 @@ where
         + DeserializeOwned;
     type ExtendField: FieldExtension;
     type BaseField: Clone + Default + Debug + PartialEq + Serialize + DeserializeOwned;
+    /// The `verificationHashType` of the stark struct committed by this tree.
+    const HASH_TYPE: &'static str;
     fn new() -> Self;
     fn to_extend(&self, p_be: &mut Vec<Self::ExtendField>);
     fn to_basefield(node: &Self::MTNode) -> Vec<Self::BaseField>;
@@ -56,7 +58,10 @@ where
 
 pub trait Transcript {
     fn new() -> Self;
-    fn get_field<F: FieldExtension>(&mut self) -> F;
+    /// Squeeze an element of the extension field, `F::DIM` base field elements in order.
+    fn get_field<F: FieldExtension>(&mut self) -> F {
+        F::from_vec((0..F::DIM).map(|_| self.get_fields1().unwrap()).collect())
+    }
     fn get_fields1(&mut self) -> Result<FGL>;
     fn put(&mut self, es: &[Vec<FGL>]) -> Result<()>;
     fn get_permutations(&mut self, n: usize, nbits: usize) -> Result<Vec<usize>>;
@@ -93,6 +98,8 @@ pub trait FieldExtension:
 {
     const ELEMENT_BYTES: usize;
     const IS_CANONICAL: bool = false;
+    /// The degree of the extension, the elements of the extension field are of this dim.
+    const DIM: usize;
     const ZERO: Self;
     const ONE: Self;
 
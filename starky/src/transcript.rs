@@ -1,5 +1,4 @@
 use crate::poseidon_opt::Poseidon;
-use crate::traits::FieldExtension;
 use crate::traits::Transcript;
 use anyhow::Result;
 use fields::field_gl::Fr as FGL;
@@ -34,7 +33,6 @@ impl TranscriptGL {
 }
 
 impl Transcript for TranscriptGL {
-    // TODO:Check the type F is equal to F3G after we support F5G.
     fn new() -> Self {
         Self {
             state: [FGL::ZERO; 4],
@@ -44,13 +42,6 @@ impl Transcript for TranscriptGL {
         }
     }
 
-    fn get_field<F: FieldExtension>(&mut self) -> F {
-        let a = self.get_fields1().unwrap();
-        let b = self.get_fields1().unwrap();
-        let c = self.get_fields1().unwrap();
-        F::from_vec(vec![a, b, c])
-    }
-
     fn get_fields1(&mut self) -> Result<FGL> {
         if !self.out.is_empty() {
             let v = self.out[0];
@@ -3,8 +3,8 @@ use crate::digest::ElementDigest;
 use crate::field_bn128::{Fr, FrRepr};
 use crate::helper::{biguint_to_be, fr_to_biguint};
 use crate::poseidon_bn128_opt::Poseidon;
+use crate::traits::MTNodeType;
 use crate::traits::Transcript;
-use crate::traits::{FieldExtension, MTNodeType};
 use anyhow::Result;
 use ff::*;
 use fields::field_gl::Fr as FGL;
@@ -60,13 +60,6 @@ impl Transcript for TranscriptBN128 {
         }
     }
 
-    fn get_field<F: FieldExtension>(&mut self) -> F {
-        let a = self.get_fields1().unwrap();
-        let b = self.get_fields1().unwrap();
-        let c = self.get_fields1().unwrap();
-        F::from_vec(vec![a, b, c])
-    }
-
     fn get_fields1(&mut self) -> Result<FGL> {
         if !self.out3.is_empty() {
             log::trace!("get_fields1 {},", self.out3[0]);
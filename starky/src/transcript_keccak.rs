@@ -0,0 +1,124 @@
+//! Fiat-Shamir transcript over Keccak-256, the counterpart of `MerkleTreeKeccak`.
+//!
+//! The absorbed elements are appended as big-endian `uint64`, a squeeze sets
+//! `state = keccak256(state || pending)` and outputs its 4 words as Goldilocks elements.
+use crate::merklehash_keccak::digest_to_node;
+use crate::traits::{MTNodeType, Transcript};
+use anyhow::Result;
+use fields::field_gl::Fr as FGL;
+use num_bigint::BigUint;
+use std::collections::VecDeque;
+use tiny_keccak::{Hasher, Keccak};
+
+pub struct TranscriptKeccak {
+    state: [u8; 32],
+    pending: Vec<u8>,
+    out: VecDeque<FGL>,
+}
+
+impl TranscriptKeccak {
+    fn update_state(&mut self) {
+        let mut keccak = Keccak::v256();
+        keccak.update(&self.state);
+        keccak.update(&self.pending);
+        keccak.finalize(&mut self.state);
+        self.pending = vec![];
+        self.out = digest_to_node(&self.state)
+            .as_elements()
+            .iter()
+            .copied()
+            .collect();
+    }
+
+    fn add_1(&mut self, e: &FGL) {
+        self.out = VecDeque::new();
+        self.pending.extend_from_slice(&e.as_int().to_be_bytes());
+    }
+}
+
+impl Transcript for TranscriptKeccak {
+    fn new() -> Self {
+        Self {
+            state: [0u8; 32],
+            pending: Vec::new(),
+            out: VecDeque::new(),
+        }
+    }
+
+    fn get_fields1(&mut self) -> Result<FGL> {
+        if let Some(v) = self.out.pop_front() {
+            return Ok(v);
+        }
+        self.update_state();
+        self.get_fields1()
+    }
+
+    fn put(&mut self, es: &[Vec<FGL>]) -> Result<()> {
+        for e in es.iter() {
+            for t in e {
+                self.add_1(t);
+            }
+        }
+        Ok(())
+    }
+
+    fn get_permutations(&mut self, n: usize, nbits: usize) -> Result<Vec<usize>> {
+        let total_bits = n * nbits;
+        let n_fields = (total_bits - 1) / 63 + 1;
+        let mut fields: Vec<BigUint> = Vec::new();
+        for _i in 0..n_fields {
+            let e = self.get_fields1()?;
+            fields.push(BigUint::from(e.as_int()));
+        }
+        let mut res: Vec<usize> = vec![];
+        let mut cur_field = 0;
+        let mut cur_bit = 0usize;
+        let one = BigUint::from(1u32);
+        for _i in 0..n {
+            let mut a = 0usize;
+            for j in 0..nbits {
+                let shift = &fields[cur_field] >> cur_bit;
+                let bit = shift & &one;
+                if bit == one {
+                    a += 1 << j;
+                }
+                cur_bit += 1;
+                if cur_bit == 63 {
+                    cur_bit = 0;
+                    cur_field += 1;
+                }
+            }
+            res.push(a);
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript_keccak() {
+        let mut t1 = TranscriptKeccak::new();
+        let mut t2 = TranscriptKeccak::new();
+        t1.put(&[vec![FGL::from(1u64), FGL::from(2u64)]]).unwrap();
+        t2.put(&[vec![FGL::from(1u64)], vec![FGL::from(2u64)]])
+            .unwrap();
+        let f1 = t1.get_fields1().unwrap();
+        assert_eq!(f1, t2.get_fields1().unwrap());
+        assert_ne!(f1, t1.get_fields1().unwrap());
+
+        // the pending outputs are dropped by a new input
+        let mut t3 = TranscriptKeccak::new();
+        t3.put(&[vec![FGL::from(1u64), FGL::from(2u64)]]).unwrap();
+        t3.get_fields1().unwrap();
+        t3.put(&[vec![FGL::from(3u64)]]).unwrap();
+        t2.get_fields1().unwrap();
+        assert_ne!(t3.get_fields1().unwrap(), t2.get_fields1().unwrap());
+
+        let perms = t1.get_permutations(8, 10).unwrap();
+        assert_eq!(perms.len(), 8);
+        assert!(perms.iter().all(|p| *p < 1 << 10));
+    }
+}
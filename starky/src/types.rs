@@ -6,7 +6,8 @@ use std::fmt;
 use std::fs::File;
 use std::io::Read;
 
-use anyhow::{anyhow, Result};
+use crate::errors::StarkyError;
+use anyhow::{Context, Result};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Public {
@@ -191,45 +192,67 @@ pub struct Step {
     pub nBits: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct StarkStruct {
     pub nBits: usize,
     pub nBitsExt: usize,
     pub nQueries: usize,
     pub verificationHashType: String,
     pub steps: Vec<Step>,
+    /// The degree of the extension field, 3 for F3G or 5 for F5G.
+    #[serde(default = "default_extension_degree")]
+    pub extensionDegree: usize,
+}
+
+fn default_extension_degree() -> usize {
+    3
+}
+
+impl Default for StarkStruct {
+    fn default() -> Self {
+        StarkStruct {
+            nBits: 0,
+            nBitsExt: 0,
+            nQueries: 0,
+            verificationHashType: String::new(),
+            steps: vec![],
+            extensionDegree: default_extension_degree(),
+        }
+    }
 }
 
 pub fn load_json<T>(filename: &str) -> Result<T>
 where
     T: serde::de::DeserializeOwned,
 {
-    let mut file = File::open(filename).map_err(|e| anyhow!("open {} error: {:?}", filename, e))?;
+    let mut file = File::open(filename).with_context(|| format!("open {} error", filename))?;
     let mut data = String::new();
     file.read_to_string(&mut data)?;
-    read_json(data)
+    serde_json::from_str(&data)
+        .map_err(|e| StarkyError::MalformedInput(format!("{}, {}", filename, e)).into())
 }
 
 pub fn read_json<T>(data: String) -> Result<T>
 where
     T: serde::de::DeserializeOwned,
 {
-    Ok(serde_json::from_str(&data)?)
+    serde_json::from_str(&data).map_err(|e| StarkyError::MalformedInput(e.to_string()).into())
 }
 
 #[inline(always)]
-pub fn parse_pil_number(raw_val: &str) -> u64 {
-    //let raw_val = r.value.as_ref().unwrap();
-    let mut n_val: i128 = match raw_val.starts_with("0x") {
-        true => i128::from_str_radix(&raw_val[2..], 16).unwrap(),
-        _ => raw_val.parse::<i128>().unwrap(),
+pub fn parse_pil_number(raw_val: &str) -> Result<u64> {
+    let n_val = match raw_val.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => raw_val.parse::<i128>(),
     };
+    let mut n_val = n_val
+        .map_err(|e| StarkyError::MalformedPil(format!("invalid number `{}`: {}", raw_val, e)))?;
     // FIXME: Goldilocks modular, try to fetch it from FieldExtension
     if n_val < 0 {
         n_val += 18446744069414584321;
     }
     n_val %= 18446744069414584321;
-    n_val as u64
+    Ok(n_val as u64)
 }
 
 #[cfg(test)]
@@ -273,4 +296,16 @@ mod test {
     }"#;
         read_json::<StarkStruct>(json_str.to_string()).unwrap();
     }
+
+    #[test]
+    fn test_parse_pil_number() {
+        assert_eq!(parse_pil_number("12").unwrap(), 12);
+        assert_eq!(parse_pil_number("0x10").unwrap(), 16);
+        assert_eq!(parse_pil_number("-1").unwrap(), 18446744069414584320);
+        let err = parse_pil_number("1a").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedPil(_))
+        ));
+    }
 }
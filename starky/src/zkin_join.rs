@@ -1,55 +1,290 @@
-use anyhow::Result;
+use crate::errors::StarkyError;
+use anyhow::{bail, Result};
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
+use std::str::FromStr;
 
-/// Combine the `input1.zkin.json` and `input1.zkin.json` into one(`out.zkin.json`)
-// ../../target/release/eigen-zkit join_zkin --zkin1 0/fibonacci.recursive1/input.zkin.json  --zkin2 1/fibonacci.recursive1/input.zkin.json  --zkinout 0/fibonacci.recursive1/r1_input-rs.zkin.json
-pub fn join_zkin(
-    // stark_setup_file: &String,
-    zkin1: &String,
-    zkin2: &String,
-    zkout: &String,
-) -> Result<()> {
-    // 1. load files.
-    let inputs_str = std::fs::read_to_string(zkin1)?;
-    let zkin1_map: BTreeMap<String, serde_json::Value> = serde_json::from_str(&inputs_str)?;
+/// How the `publics` of the joined zkin are taken from the publics of the inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublicsSpec {
+    /// `drop_last:k`, the publics of the first input without its last `k` entries, which are
+    /// the root of the verification key appended by the recursive stage for `k = 4`.
+    DropLast(usize),
+    /// `concat`, the publics of all the inputs in order.
+    Concat,
+    /// `i:start..end,...`, the given ranges of the publics of the input `i` in order.
+    Slices(Vec<(usize, usize, usize)>),
+}
 
-    let inputs_str = std::fs::read_to_string(zkin2)?;
-    let zkin2_map: BTreeMap<String, serde_json::Value> = serde_json::from_str(&inputs_str)?;
+impl Default for PublicsSpec {
+    fn default() -> Self {
+        PublicsSpec::DropLast(4)
+    }
+}
 
-    // 2. construct zkout
-    let mut zkout_map = BTreeMap::new();
+impl FromStr for PublicsSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || StarkyError::MalformedInput(format!("invalid publics spec {}", s));
+        if s == "concat" {
+            return Ok(PublicsSpec::Concat);
+        }
+        if let Some(k) = s.strip_prefix("drop_last:") {
+            return Ok(PublicsSpec::DropLast(k.parse().map_err(|_| invalid())?));
+        }
+        let mut slices = vec![];
+        for slice in s.split(',') {
+            let (input, range) = slice.split_once(':').ok_or_else(invalid)?;
+            let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+            let slice = (
+                input.parse().map_err(|_| invalid())?,
+                start.parse().map_err(|_| invalid())?,
+                end.parse().map_err(|_| invalid())?,
+            );
+            if slice.1 > slice.2 {
+                bail!(invalid());
+            }
+            slices.push(slice);
+        }
+        Ok(PublicsSpec::Slices(slices))
+    }
+}
+
+impl PublicsSpec {
+    fn apply(&self, publics: &[&Vec<Value>]) -> Result<Vec<Value>> {
+        let res = match self {
+            PublicsSpec::DropLast(k) => {
+                if publics[0].len() < *k {
+                    bail!(StarkyError::MalformedInput(format!(
+                        "can not drop the last {} of {} publics",
+                        k,
+                        publics[0].len()
+                    )));
+                }
+                publics[0][..(publics[0].len() - k)].to_vec()
+            }
+            PublicsSpec::Concat => publics.iter().flat_map(|p| p.iter().cloned()).collect(),
+            PublicsSpec::Slices(slices) => {
+                let mut res = vec![];
+                for (input, start, end) in slices {
+                    match publics.get(*input) {
+                        Some(p) if *end <= p.len() => res.extend_from_slice(&p[*start..*end]),
+                        _ => bail!(StarkyError::MalformedInput(format!(
+                            "the publics {}:{}..{} are out of the inputs",
+                            input, start, end
+                        ))),
+                    }
+                }
+                res
+            }
+        };
+        Ok(res)
+    }
+}
+
+/// The prefix of the signals of the `i`-th proof in the aggregation circuit: `a_`, `b_`, ...
+pub fn zkin_prefix(i: usize) -> String {
+    format!("{}_", (b'a' + i as u8) as char)
+}
+
+/// Combine the zkin files into one(`out.zkin.json`) for the aggregation circuit.
+///
+/// The signals of the `i`-th input are prefixed by `zkin_prefix(i)`. The inputs listed in `agg`
+/// are proofs of the aggregation circuit itself, like the recursive2 proof joined with a
+/// recursive1 proof, the others are proofs of the aggregated circuit. The inputs of a circuit
+/// must share its `rootC`, and the `rootC` of the aggregation circuit, or of the aggregated one
+/// if `agg` is empty, is kept unprefixed along with the publics selected by `spec`.
+// ../../target/release/eigen-zkit join_zkin --zkin 0/fibonacci.recursive1/input.zkin.json --zkin 1/fibonacci.recursive1/input.zkin.json --zkinout 0/fibonacci.recursive1/r1_input-rs.zkin.json
+pub fn join_zkin(zkins: &[String], agg: &[usize], spec: &PublicsSpec, zkout: &str) -> Result<()> {
+    if zkins.len() < 2 || zkins.len() > 26 {
+        bail!(StarkyError::MalformedInput(format!(
+            "join 2 to 26 zkin files, got {}",
+            zkins.len()
+        )));
+    }
+
+    // 1. load files.
+    let mut zkin_maps = vec![];
+    for zkin in zkins {
+        let inputs_str = std::fs::read_to_string(zkin)?;
+        let zkin_map: BTreeMap<String, Value> = serde_json::from_str(&inputs_str)?;
+        zkin_maps.push(zkin_map);
+    }
 
-    for (k, v) in zkin1_map {
-        // TODO: remove this
-        zkout_map.insert(format!("a_{k}"), v.clone());
-        if k == "publics" {
-            if let Value::Array(ref arr) = v {
-                if arr.len() >= 4 {
-                    let exclude_last_four = &arr[..(arr.len() - 4)];
-                    zkout_map.insert(
-                        "publics".to_string(),
-                        Value::Array(exclude_last_four.to_vec()),
-                    );
-                } else {
-                    zkout_map.insert("publics".to_string(), v.clone());
+    // 2. check the rootC of each circuit and the publics
+    if let Some(i) = agg.iter().find(|i| **i >= zkins.len()) {
+        bail!(StarkyError::MalformedInput(format!(
+            "the aggregation input {} is out of the {} zkin files",
+            i,
+            zkins.len()
+        )));
+    }
+    let mut root_c = None;
+    for is_agg in [false, true] {
+        let mut first = None;
+        for (i, (zkin, zkin_map)) in zkins.iter().zip(zkin_maps.iter()).enumerate() {
+            if agg.contains(&i) != is_agg {
+                continue;
+            }
+            match first {
+                None => first = Some((zkin, zkin_map.get("rootC"))),
+                Some((first_zkin, first_root_c)) if zkin_map.get("rootC") != first_root_c => {
+                    bail!(StarkyError::MalformedInput(format!(
+                        "the rootC of {} differs from {} of the same circuit",
+                        zkin, first_zkin
+                    )))
                 }
+                _ => {}
             }
         }
-        if k == "rootC" {
-            zkout_map.insert(k.to_string(), v.clone());
+        if let Some((_, first_root_c)) = first {
+            root_c = first_root_c;
         }
     }
-    for (k, v) in zkin2_map {
-        zkout_map.insert(format!("b_{k}"), v);
+    let mut publics = vec![];
+    for (zkin, zkin_map) in zkins.iter().zip(zkin_maps.iter()) {
+        match zkin_map.get("publics") {
+            Some(Value::Array(arr)) => publics.push(arr),
+            _ => bail!(StarkyError::MalformedInput(format!(
+                "no publics in {}",
+                zkin
+            ))),
+        }
     }
 
-    // 3. save zkout to file
+    // 3. construct zkout
+    let mut zkout_map = BTreeMap::new();
+    zkout_map.insert("publics".to_string(), Value::Array(spec.apply(&publics)?));
+    if let Some(root_c) = root_c {
+        zkout_map.insert("rootC".to_string(), root_c.clone());
+    }
+    for (i, zkin_map) in zkin_maps.iter().enumerate() {
+        let prefix = zkin_prefix(i);
+        for (k, v) in zkin_map {
+            zkout_map.insert(format!("{prefix}{k}"), v.clone());
+        }
+    }
+
+    // 4. save zkout to file
     let input = serde_json::to_string(&zkout_map)?;
     let mut file = File::create(zkout)?;
     write!(file, "{}", input)?;
     log::trace!("zkout file Generated Correctly");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_zkin(file: &str, publics: &[u64], root_c: &[u64]) -> String {
+        let zkin = json!({"publics": publics, "rootC": root_c, "root1": [1, 2, 3, 4]});
+        std::fs::write(file, zkin.to_string()).unwrap();
+        file.to_string()
+    }
+
+    #[test]
+    fn test_publics_spec() {
+        assert_eq!(
+            "drop_last:4".parse::<PublicsSpec>().unwrap(),
+            PublicsSpec::default()
+        );
+        assert_eq!(
+            "concat".parse::<PublicsSpec>().unwrap(),
+            PublicsSpec::Concat
+        );
+        assert_eq!(
+            "0:0..2,2:1..3".parse::<PublicsSpec>().unwrap(),
+            PublicsSpec::Slices(vec![(0, 0, 2), (2, 1, 3)])
+        );
+        assert!("0:2..1".parse::<PublicsSpec>().is_err());
+        assert!("first".parse::<PublicsSpec>().is_err());
+    }
+
+    #[test]
+    fn test_join_zkin() {
+        let zkins = vec![
+            write_zkin(
+                "/tmp/test_join_zkin.0.json",
+                &[1, 2, 3, 4, 5, 6],
+                &[7, 7, 7, 7],
+            ),
+            write_zkin(
+                "/tmp/test_join_zkin.1.json",
+                &[8, 9, 10, 11, 12, 13],
+                &[7, 7, 7, 7],
+            ),
+            write_zkin(
+                "/tmp/test_join_zkin.2.json",
+                &[14, 15, 16, 17, 18, 19],
+                &[7, 7, 7, 7],
+            ),
+        ];
+        let out = "/tmp/test_join_zkin.out.json";
+
+        join_zkin(&zkins, &[], &PublicsSpec::default(), out).unwrap();
+        let joined: BTreeMap<String, Value> =
+            serde_json::from_str(&std::fs::read_to_string(out).unwrap()).unwrap();
+        assert_eq!(joined["publics"], json!([1, 2]));
+        assert_eq!(joined["rootC"], json!([7, 7, 7, 7]));
+        assert_eq!(joined["c_publics"], json!([14, 15, 16, 17, 18, 19]));
+        assert_eq!(joined["b_root1"], json!([1, 2, 3, 4]));
+        assert_eq!(joined.len(), 2 + 3 * 3);
+
+        let spec = "0:0..2,2:4..6".parse::<PublicsSpec>().unwrap();
+        join_zkin(&zkins, &[], &spec, out).unwrap();
+        let joined: BTreeMap<String, Value> =
+            serde_json::from_str(&std::fs::read_to_string(out).unwrap()).unwrap();
+        assert_eq!(joined["publics"], json!([1, 2, 18, 19]));
+
+        // the publics out of the inputs
+        let spec = "3:0..1".parse::<PublicsSpec>().unwrap();
+        assert!(join_zkin(&zkins, &[], &spec, out).is_err());
+
+        // the proofs of another circuit
+        let other = write_zkin("/tmp/test_join_zkin.3.json", &[1, 2, 3, 4], &[6, 6, 6, 6]);
+        assert!(join_zkin(&[zkins[0].clone(), other], &[], &PublicsSpec::Concat, out).is_err());
+        assert!(join_zkin(&zkins[..1], &[], &PublicsSpec::Concat, out).is_err());
+        assert!(join_zkin(&zkins, &[3], &PublicsSpec::Concat, out).is_err());
+    }
+
+    #[test]
+    fn test_join_zkin_recursive2_with_recursive1() {
+        // the recursive2 proof publishes the rootC of the recursive2 circuit last
+        let recursive2 = write_zkin(
+            "/tmp/test_join_zkin_r2.0.json",
+            &[1, 2, 5, 5, 5, 5],
+            &[5, 5, 5, 5],
+        );
+        let recursive1 = write_zkin(
+            "/tmp/test_join_zkin_r2.1.json",
+            &[3, 4, 7, 7, 7, 7],
+            &[7, 7, 7, 7],
+        );
+        let zkins = vec![recursive2.clone(), recursive1.clone()];
+        let out = "/tmp/test_join_zkin_r2.out.json";
+
+        join_zkin(&zkins, &[0], &PublicsSpec::default(), out).unwrap();
+        let joined: BTreeMap<String, Value> =
+            serde_json::from_str(&std::fs::read_to_string(out).unwrap()).unwrap();
+        assert_eq!(joined["publics"], json!([1, 2]));
+        assert_eq!(joined["rootC"], json!([5, 5, 5, 5]));
+        assert_eq!(joined["a_rootC"], json!([5, 5, 5, 5]));
+        assert_eq!(joined["b_rootC"], json!([7, 7, 7, 7]));
+
+        // the recursive1 proofs of another circuit
+        let other = write_zkin(
+            "/tmp/test_join_zkin_r2.2.json",
+            &[3, 4, 6, 6, 6, 6],
+            &[6, 6, 6, 6],
+        );
+        let zkins = vec![recursive2, recursive1, other];
+        assert!(join_zkin(&zkins, &[0], &PublicsSpec::default(), out).is_err());
+        // the recursive2 proof is not a proof of the aggregated circuit
+        assert!(join_zkin(&zkins[..2], &[], &PublicsSpec::default(), out).is_err());
+    }
+}
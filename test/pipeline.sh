@@ -0,0 +1,21 @@
+#!/bin/bash
+set -ex
+
+# The flow of stark_aggregation.sh by `eigen-zkit pipeline`, rerun it to resume from the last
+# completed stage, or with --force to start over.
+
+CUR_DIR=$(cd $(dirname $0);pwd)
+ZKIT="${CUR_DIR}/../target/release/eigen-zkit"
+WORKSPACE=/tmp/pipeline_fibonacci
+
+cd ${CUR_DIR}/../zkit && cargo build --release && cd ${CUR_DIR}
+cd ${CUR_DIR}/../starkjs && npm i && cd ${CUR_DIR}
+
+# the traces of the 4 proofs to aggregate
+for i in 0 1 2 3; do
+    mkdir -p $WORKSPACE/$i
+    ${ZKIT} stark_trace -e fibonacci -p ../starky/data/fib.pil.json --input $((2*i+1)),$((2*i+2)) \
+        --o $WORKSPACE/fib.const --m $WORKSPACE/$i/fib.cm
+done
+
+RUST_LOG=info ${ZKIT} pipeline -c pipeline.json $@
@@ -34,12 +34,12 @@ mkdir -p $RUNDIR/circuits && node $RUNDIR/$PILEXECJS -w $RUNDIR/circuits -i $TAS
 # generate .circom file.
 # input files :  .pil json & starkStruct.json.gl
 # output files : .circom
-../target/release/eigen-zkit stark_prove -s ../starky/data/starkStruct.json.gl \
+../target/release/eigen-zkit stark_prove --min_security_bits 0 -s ../starky/data/starkStruct.json.gl \
     -p $WORKSPACE/$TASK_NO/$CIRCUIT.pil.json \
     --o $WORKSPACE/$TASK_NO/$CIRCUIT.const \
     --m $WORKSPACE/$TASK_NO/$CIRCUIT.cm -c $WORKSPACE/circuits/$TASK_NO/${BATCH_VERIFIER}.circom --i $WORKSPACE/circuits/$TASK_NO/${BATCH_VERIFIER}.zkin.json --skip_main
 
-../target/release/eigen-zkit compile -p goldilocks -i $WORKSPACE/circuits/$TASK_NO/${BATCH_VERIFIER}.circom -l $RUNDIR/node_modules/pil-stark/circuits.gl --O2=full -o $WORKSPACE/$TASK_NO
+../target/release/eigen-zkit compile -p goldilocks -i $WORKSPACE/circuits/$TASK_NO/${BATCH_VERIFIER}.circom --O2=full -o $WORKSPACE/$TASK_NO
 
 # generate the pil files and  const constant polynomial files
 # input files :  $C12_VERIFIER.r1cs
@@ -65,12 +65,12 @@ mkdir -p $RUNDIR/circuits && node $RUNDIR/$PILEXECJS -w $RUNDIR/circuits -i $TAS
 # generate the stark proof and the circom circuits to verify stark proof.
 # input files : $C12_VERIFIER.pil.json(stark proof)  $C12_VERIFIER.const(const polynomials)  $C12_VERIFIER.cm (commit polynomials)
 # output files :  $RECURSIVE1_CIRCUIT.circom  $RECURSIVE1_CIRCUIT/input.json
-../target/release/eigen-zkit stark_prove -s ../starky/data/c12.starkStruct.json \
+../target/release/eigen-zkit stark_prove --min_security_bits 0 -s ../starky/data/c12.starkStruct.json \
     -p $WORKSPACE/$C12_VERIFIER.pil.json \
     --o $WORKSPACE/$C12_VERIFIER.const \
     --m $WORKSPACE/$C12_VERIFIER.cm -c $WORKSPACE/circuits/$TASK_NO/$RECURSIVE1_CIRCUIT.circom --i $WORKSPACE/circuits/$TASK_NO/c12a.zkin.json
 
-../target/release/eigen-zkit compile -p goldilocks -i $WORKSPACE/circuits/$TASK_NO/$RECURSIVE1_CIRCUIT.circom -l $RUNDIR/node_modules/pil-stark/circuits.gl --O2=full -o $WORKSPACE/$TASK_NO
+../target/release/eigen-zkit compile -p goldilocks -i $WORKSPACE/circuits/$TASK_NO/$RECURSIVE1_CIRCUIT.circom --O2=full -o $WORKSPACE/$TASK_NO
 # generate the pil files and  const polynomicals files
 # input files :  $RECURSIVE1_CIRCUIT.r1cs
 # output files :  $RECURSIVE1_CIRCUIT.const  $RECURSIVE1_CIRCUIT.pil  $RECURSIVE1_CIRCUIT.exec
@@ -95,14 +95,14 @@ mkdir -p $WORKSPACE/aggregation/$TASK_NO
 
 if [ "$GENERATE_PROOF_TYPE" = "stark" ]; then 
     echo "Generate stark proof"
-    ../target/release/eigen-zkit stark_prove -s ../starky/data/r1.starkStruct.json \
+    ../target/release/eigen-zkit stark_prove --min_security_bits 0 -s ../starky/data/r1.starkStruct.json \
         -p $WORKSPACE/$RECURSIVE1_CIRCUIT.pil.json \
         --o $WORKSPACE/$RECURSIVE1_CIRCUIT.const \
         --m $WORKSPACE/$RECURSIVE1_CIRCUIT.cm -c $WORKSPACE/circuits/$TASK_NO/$RECURSIVE2_CIRCUIT.circom \
         --i $WORKSPACE/aggregation/$TASK_NO/$RECURSIVE1_CIRCUIT.zkin.json --norm_stage --agg_stage
 else 
     echo "Generate snark proof"
-    ../target/release/eigen-zkit stark_prove -s ../starky/data/r1.starkStruct.bn128.json \
+    ../target/release/eigen-zkit stark_prove --min_security_bits 0 -s ../starky/data/r1.starkStruct.bn128.json \
         -p $WORKSPACE/$RECURSIVE1_CIRCUIT.pil.json \
         --o $WORKSPACE/$RECURSIVE1_CIRCUIT.const \
         --m $WORKSPACE/$RECURSIVE1_CIRCUIT.cm -c $WORKSPACE/circuits/$TASK_NO/$RECURSIVE2_CIRCUIT.circom \
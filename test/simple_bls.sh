@@ -4,12 +4,12 @@ cd ../starkjs
 CIRCUIT=fib
 npm run $CIRCUIT
 
-../target/release/eigen-zkit stark_prove -s ../starky/data/starkStruct.json.gl \
+../target/release/eigen-zkit stark_prove --min_security_bits 0 -s ../starky/data/starkStruct.json.gl \
     -p /tmp/$CIRCUIT.pil.json \
     --o /tmp/$CIRCUIT.const \
     --m /tmp/$CIRCUIT.cm -c circuits/$CIRCUIT.verifier.circom --i circuits/$CIRCUIT.verifier.zkin.json
 
-../target/release/eigen-zkit compile -p goldilocks -i circuits/$CIRCUIT.verifier.circom -l node_modules/pil-stark/circuits.gl --O2=full -o /tmp/
+../target/release/eigen-zkit compile -p goldilocks -i circuits/$CIRCUIT.verifier.circom --O2=full -o /tmp/
 
 # Circom to Stark
 ../target/release/eigen-zkit compressor12_setup \
@@ -25,7 +25,7 @@ npm run $CIRCUIT
     --e /tmp/c12.exec \
     --m /tmp/c12.cm
 
-../target/release/eigen-zkit stark_prove -s ../starky/data/c12.starkStruct.bls12381.json \
+../target/release/eigen-zkit stark_prove --min_security_bits 0 -s ../starky/data/c12.starkStruct.bls12381.json \
     -p /tmp/c12.pil.json \
     --o /tmp/c12.const \
     --m /tmp/c12.cm -c circuits/c12a.verifier.circom --i circuits/c12a.verifier.zkin.json --norm_stage
@@ -29,4 +29,10 @@ module.exports = {
   mocha: {
     timeout: 10000000,
   },
+  networks: {
+    hardhat: {
+      // the BN128 stark verifier calls the poseidon of the PoseidonEx contract for every leaf
+      blockGasLimit: 10000000000,
+    },
+  },
 };
@@ -0,0 +1,46 @@
+const { expect } = require("chai");
+const { ethers } = require("hardhat");
+import * as fs from 'fs';
+
+// the Goldilocks prime
+const P_GL = BigInt("0xFFFFFFFF00000001");
+
+describe("Stark verifier test", function() {
+  it("Test KECCAK stark verifier", async () => {
+    let F = await ethers.getContractFactory("contracts/stark_verifier.sol:StarkVerifier");
+    let contract = await F.deploy();
+    await contract.deployed();
+    const [proof, publics] = JSON.parse(fs.readFileSync("../input/stark_calldata.json", "utf8"));
+
+    expect(await contract.verifyProof(proof, publics)).to.eq(true);
+
+    // the first coefficient of the first evaluation, following root1..root4
+    let tampered = [...proof];
+    tampered[4] = ((BigInt(proof[4]) + 1n) % P_GL).toString();
+    expect(await contract.verifyProof(tampered, publics)).to.eq(false);
+  })
+
+  it("Test BN128 stark verifier", async () => {
+    let PF = await ethers.getContractFactory("contracts/stark_verifier_bn128.sol:PoseidonEx");
+    let poseidon = await PF.deploy();
+    await poseidon.deployed();
+    const chunks = JSON.parse(fs.readFileSync("../contracts/stark_verifier_bn128.poseidon.json", "utf8"));
+    for (const chunk of chunks) {
+      await (await poseidon.load(chunk)).wait();
+    }
+    // circomlib's poseidon([1, 2])
+    const out = await poseidon.poseidonEx([1, 2], 0);
+    expect(out[0].toString()).to.eq("7853200120776062878684798364095072458815029376092732009249414926327459813530");
+
+    let F = await ethers.getContractFactory("contracts/stark_verifier_bn128.sol:StarkVerifier");
+    let contract = await F.deploy(poseidon.address);
+    await contract.deployed();
+    const [proof, publics] = JSON.parse(fs.readFileSync("../input/stark_calldata_bn128.json", "utf8"));
+
+    expect(await contract.verifyProof(proof, publics)).to.eq(true);
+
+    let tampered = [...proof];
+    tampered[4] = ((BigInt(proof[4]) + 1n) % P_GL).toString();
+    expect(await contract.verifyProof(tampered, publics)).to.eq(false);
+  })
+});
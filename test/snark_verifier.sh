@@ -28,11 +28,7 @@ ZKIT="${CUR_DIR}/../target/release/eigen-zkit"
 
 if [ $first_run = "true" ]; then 
     echo "compile circom and generate wasm and r1cs"
-    if [ $CURVE = "BN128" ]; then
-        $ZKIT compile -i $SNARK_CIRCOM -p $CURVE  -l "../starkjs/node_modules/pil-stark/circuits.bn128" -l "../starkjs/node_modules/circomlib/circuits" --O2=full -o $WORK_DIR
-    elif [ $CURVE = "BLS12381" ]; then
-        $ZKIT compile -i $SNARK_CIRCOM -p $CURVE -l "../stark-circuits/circuits" -l "../starkjs/node_modules/circomlib/circuits" --O2=full -o $WORK_DIR
-    fi
+    $ZKIT compile -i $SNARK_CIRCOM -p $CURVE -l "../starkjs/node_modules/circomlib/circuits" --O2=full -o $WORK_DIR
     # cp $WORK_DIR/$CIRCUIT_NAME"_js"/$CIRCUIT_NAME.wasm /tmp/aggregation/circuits.wasm
 fi 
 
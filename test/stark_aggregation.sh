@@ -81,8 +81,8 @@ echo " ==> aggregation proof 0 and 1 <== "
 
 echo "1. combine input1.zkin.json with input2.zkin.json "
 ${ZKIT} join_zkin \
-    --zkin1 $input0/$RECURSIVE1_CIRCUIT.zkin.json \
-    --zkin2 $input1/$RECURSIVE1_CIRCUIT.zkin.json \
+    --zkin $input0/$RECURSIVE1_CIRCUIT.zkin.json \
+    --zkin $input1/$RECURSIVE1_CIRCUIT.zkin.json \
     --zkinout $WORKSPACE/aggregation/r01_input.zkin.json
 
 if [ $ARG_FIRST_RUN = "yes" ]; then
@@ -90,8 +90,6 @@ if [ $ARG_FIRST_RUN = "yes" ]; then
     ${ZKIT} compile \
         -p goldilocks \
         -i $WORKSPACE/circuits/0/$RECURSIVE2_CIRCUIT.circom \
-        -l "../starkjs/node_modules/pil-stark/circuits.gl" \
-        -l "../starkjs/node_modules/circomlib/circuits" \
         --O2=full \
         -o $WORKSPACE
 else
@@ -117,7 +115,7 @@ ${ZKIT} compressor12_exec \
     --m $WORKSPACE/$RECURSIVE2_CIRCUIT.cm
 
 echo "5. generate recursive2 proof "
-${ZKIT} stark_prove \
+${ZKIT} stark_prove --min_security_bits 0 \
     -s ../starky/data/r1.starkStruct.json \
     -p $WORKSPACE/$RECURSIVE2_CIRCUIT.pil.json \
     --o $WORKSPACE/$RECURSIVE2_CIRCUIT.const \
@@ -134,8 +132,9 @@ do
     input="$WORKSPACE/aggregation/$((i + 2))"
 
     ${ZKIT} join_zkin \
-        --zkin1 $WORKSPACE/aggregation/$RECURSIVE2_CIRCUIT$suffix.zkin.json \
-        --zkin2 $input/$RECURSIVE1_CIRCUIT.zkin.json \
+        --zkin $WORKSPACE/aggregation/$RECURSIVE2_CIRCUIT$suffix.zkin.json \
+        --zkin $input/$RECURSIVE1_CIRCUIT.zkin.json \
+        --agg 0 \
         --zkinout $WORKSPACE/aggregation/r$suffix"_input".zkin.json
 
     ${ZKIT} compressor12_exec \
@@ -145,7 +144,7 @@ do
         --e $WORKSPACE/$RECURSIVE2_CIRCUIT.exec \
         --m $WORKSPACE/$RECURSIVE2_CIRCUIT$suffix.cm
 
-    ${ZKIT} stark_prove \
+    ${ZKIT} stark_prove --min_security_bits 0 \
         -s ../starky/data/r1.starkStruct.json \
         -p $WORKSPACE/$RECURSIVE2_CIRCUIT.pil.json \
         --o $WORKSPACE/$RECURSIVE2_CIRCUIT.const \
@@ -167,8 +166,6 @@ if [ $ARG_FIRST_RUN = "yes" ]; then
     ${ZKIT} compile \
         -p goldilocks \
         -i $WORKSPACE/aggregation/$FINAL_CIRCUIT.circom \
-        -l "../starkjs/node_modules/pil-stark/circuits.gl" \
-        -l "../starkjs/node_modules/circomlib/circuits" \
         --O2=full \
         -o $WORKSPACE
 else
@@ -201,7 +198,7 @@ if [ $ARG_CURVE = "BN128" ]; then
     STARK_STRUCT=$CURRENT_DIR/../starky/data/final.starkStruct.bn128.json
 fi
 echo "4. generate final recursivef proof "
-${ZKIT} stark_prove \
+${ZKIT} stark_prove --min_security_bits 0 \
     -s $STARK_STRUCT \
     -p $WORKSPACE/$FINAL_CIRCUIT.pil.json \
     --o $WORKSPACE/$FINAL_CIRCUIT.const \
@@ -0,0 +1,39 @@
+#!/bin/bash
+set -ex
+
+CUR_DIR=$(cd $(dirname $0);pwd)
+ZKIT="${CUR_DIR}/../target/release/eigen-zkit"
+DATA_DIR=${CUR_DIR}/../starky/data
+WORK_DIR=/tmp/stark_verifier
+mkdir -p $WORK_DIR
+
+echo "1. KECCAK stark struct of the fib example"
+sed 's/"GL"/"KECCAK"/' $DATA_DIR/starkStruct.json.gl > $WORK_DIR/starkStruct.json
+
+echo "2. stark prove"
+$ZKIT stark_prove --min_security_bits 0 -s $WORK_DIR/starkStruct.json -p $DATA_DIR/fib.pil.json.gl \
+    --o $DATA_DIR/fib.const.gl --m $DATA_DIR/fib.cm.gl --i $WORK_DIR/fib.zkin.json
+
+echo "3. generate verifier contract and calldata"
+$ZKIT generate_verifier -p stark --stark_stuct $WORK_DIR/starkStruct.json \
+    --piljson $DATA_DIR/fib.pil.json.gl --o $DATA_DIR/fib.const.gl \
+    -s ${CUR_DIR}/single/contracts/stark_verifier.sol
+$ZKIT stark_calldata -s $WORK_DIR/starkStruct.json --i $WORK_DIR/fib.zkin.json \
+    --o ${CUR_DIR}/single/input/stark_calldata.json
+
+echo "4. BN128 proof of the fib example, its verifier, poseidon constants and calldata"
+$ZKIT stark_prove --min_security_bits 0 -s $DATA_DIR/starkStruct.json -p $DATA_DIR/fib.pil.json \
+    --o $DATA_DIR/fib.const --m $DATA_DIR/fib.cm --i $WORK_DIR/fib.bn128.zkin.json
+$ZKIT generate_verifier -p stark --stark_stuct $DATA_DIR/starkStruct.json \
+    --piljson $DATA_DIR/fib.pil.json --o $DATA_DIR/fib.const \
+    -s ${CUR_DIR}/single/contracts/stark_verifier_bn128.sol
+$ZKIT stark_calldata -s $DATA_DIR/starkStruct.json --i $WORK_DIR/fib.bn128.zkin.json \
+    --o ${CUR_DIR}/single/input/stark_calldata_bn128.json
+
+echo "5. test verifier contracts"
+cd ${CUR_DIR}/single
+if [ ! -d node_modules ]; then
+    npm install
+fi
+cd test
+npx hardhat test stark.test.ts
@@ -1,8 +1,19 @@
 extern crate clap;
+use algebraic::bellman_ce::pairing::{bls12_381::Bls12, bn256::Bn256};
+use algebraic::errors::AlgebraicError;
+use algebraic::r1cs_analysis::analyse;
+use algebraic::r1cs_check::r1cs_check;
 use clap::{command, Parser};
 use dsl_compile::circom_compiler;
+use dsl_compile::errors::DslError;
+use fields::field_gl::GL;
 use groth16::api::*;
-use starky::prove::stark_prove;
+use groth16::errors::Groth16Error;
+use recursion::errors::RecursionError;
+use starky::errors::StarkyError;
+use starky::prove::{
+    stark_prove, stark_setup, stark_solidity_calldata, stark_solidity_verifier, stark_verify,
+};
 use std::time::Instant;
 
 /// Trust setup for Plonk
@@ -59,15 +70,92 @@ struct CalculateWitnessOpt {
     output: String,
 }
 
+/// Report the constraint statistics and the unused or under-constrained signals of the r1cs
+#[derive(Parser, Debug)]
+struct AnalyseOpt {
+    /// BN128, BLS12381 or GL
+    #[arg(short, default_value = "BN128")]
+    curve_type: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    /// the symbols of the compiler, like circuit.sym
+    #[arg(short)]
+    sym_file: Option<String>,
+    /// [output] the analysis in json
+    #[arg(short)]
+    output: Option<String>,
+}
+
+/// Check the witness against every constraint of the r1cs, naming the failing signals
+#[derive(Parser, Debug)]
+struct R1csCheckOpt {
+    /// BN128, BLS12381 or GL
+    #[arg(short, default_value = "BN128")]
+    curve_type: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    /// the witness, in wtns or json
+    #[arg(short, required = true, default_value = "witness.wtns")]
+    witness: String,
+    /// the symbols of the compiler, like circuit.sym
+    #[arg(short)]
+    sym_file: Option<String>,
+}
+
 /// Generate solidity verifier
 #[derive(Debug, Parser)]
 struct GenerateVerifierOpt {
     #[arg(short, default_value = "vk.bin")]
     vk_file: String,
+    /// groth16, plonk or stark
     #[arg(short, default_value = "groth16")]
     protocal: String,
     #[arg(short, default_value = "verifier.sol")]
     sol: String,
+    /// the stark struct of the BN128 or KECCAK proofs, only for stark
+    #[arg(long = "stark_stuct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    /// the stark setup generated by stark_setup, rebuilt from the const pols if absent
+    #[arg(long = "stark_setup")]
+    stark_setup: Option<String>,
+}
+
+/// Prove by Plonk
+#[derive(Debug, Parser)]
+struct ProveOpt {
+    #[arg(short, required = true)]
+    srs_monomial_form: String,
+    #[arg(short, default_value = "circuit.r1cs")]
+    circuit_file: String,
+    #[arg(short, default_value = "witness.wtns")]
+    witness: String,
+    #[arg(long = "b", default_value = "proof.bin")]
+    output_proof: String,
+    /// the serialized proof for the solidity verifier
+    #[arg(long = "j", default_value = "proof.json")]
+    output_proof_json: String,
+    /// the public inputs for the solidity verifier
+    #[arg(long = "i")]
+    output_public_json: Option<String>,
+    /// transcript, keccak or rescue
+    #[arg(short, default_value = "keccak")]
+    transcript: String,
+}
+
+/// Verify the Plonk proof
+#[derive(Debug, Parser)]
+struct VerifyOpt {
+    #[arg(short, default_value = "proof.bin")]
+    proof: String,
+    #[arg(short, default_value = "vk.bin")]
+    vk: String,
+    /// transcript, keccak or rescue
+    #[arg(short, default_value = "keccak")]
+    transcript: String,
 }
 
 /// Export proof's verification key
@@ -94,6 +182,82 @@ struct ExportAggregationVerificationKeyOpt {
     output_vk: String,
 }
 
+/// Proof aggregation for plonk
+#[derive(Parser, Debug)]
+struct AggregationProveOpt {
+    #[arg(short)]
+    srs_monomial_form: String,
+    /// the file listing the old proofs, one path per line
+    #[arg(long = "f")]
+    old_proof_list: String,
+    #[arg(long = "v", default_value = "vk.bin")]
+    old_vk: String,
+    #[arg(long = "n", default_value = "aggregation_proof.bin")]
+    new_proof: String,
+    /// the serialized proof for the solidity verifier
+    #[arg(long = "j", default_value = "aggregation_proof.json")]
+    proof_json: String,
+}
+
+/// Verify aggregation proof
+#[derive(Parser, Debug)]
+struct AggregationVerifyOpt {
+    #[arg(long = "p", default_value = "aggregation_proof.bin")]
+    proof: String,
+    #[arg(long = "v", default_value = "aggregation_vk.bin")]
+    vk: String,
+}
+
+/// A subcommand for generating a Solidity aggregation verifier smart contract
+#[derive(Parser, Debug)]
+struct GenerateAggregationVerifierOpt {
+    #[arg(short, default_value = "vk.bin")]
+    old_vk: String,
+    #[arg(long = "n", default_value = "aggregation_vk.bin")]
+    new_vk: String,
+    #[arg(long = "num_inputs")]
+    num_inputs: usize,
+    #[arg(short, default_value = "aggregation_verifier.sol")]
+    sol: String,
+}
+
+/// Derive the stark struct reaching the given bits of security
+#[derive(Parser, Debug)]
+struct StarkStructOpt {
+    /// log2 of the trace length
+    #[arg(long = "n_bits")]
+    n_bits: usize,
+    /// log2 of the blowup factor
+    #[arg(long = "blowup_bits", default_value = "1")]
+    blowup_bits: usize,
+    #[arg(long = "security_bits", default_value = "128")]
+    security_bits: usize,
+    /// verificationHashType, GL, BN128, BLS12381 or KECCAK
+    #[arg(short = 't', long = "hash_type", default_value = "GL")]
+    hash_type: String,
+    /// the degree of the extension field of the challenges and the FRI, 3 or 5
+    #[arg(long = "extension_degree", default_value = "3")]
+    extension_degree: usize,
+    #[arg(long = "o", default_value = "stark_struct.json")]
+    output: String,
+}
+
+/// Build the stark setup once for proving the same PIL repeatedly
+#[derive(Parser, Debug)]
+struct StarkSetupOpt {
+    #[arg(short, long = "stark_stuct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(short, long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    #[arg(long = "stark_setup", default_value = "stark_setup.json")]
+    stark_setup: String,
+    /// refuse the stark struct below these bits of security, 128 by default
+    #[arg(long = "min_security_bits")]
+    min_security_bits: Option<usize>,
+}
+
 /// Stark proving and verifying all in one
 #[derive(Parser, Debug)]
 struct StarkProveOpt {
@@ -107,12 +271,19 @@ struct StarkProveOpt {
     skip_main: bool,
     #[arg(short, long = "agg_stage", action= clap::ArgAction::SetTrue)]
     agg_stage: bool,
+    /// the proofs verified by the circom verifier of the agg_stage
+    #[arg(long = "agg_inputs", default_value_t = 2)]
+    agg_inputs: usize,
     #[arg(long = "o", default_value = "pols.const")]
     const_pols: String,
     #[arg(long = "m", default_value = "pols.cm")]
     cm_pols: String,
-    #[arg(short, long = "circom", default_value = "stark_verfier.circom")]
-    circom_file: String,
+    /// the stark setup generated by stark_setup, rebuilt from the const pols if absent
+    #[arg(long = "stark_setup")]
+    stark_setup: Option<String>,
+    /// the circom verifier of the proof, only for the extension degree 3 of GL, BN128 and BLS12381
+    #[arg(short, long = "circom")]
+    circom_file: Option<String>,
     #[arg(long = "i", default_value = "zkin.json")]
     zkin: String,
     #[arg(
@@ -120,6 +291,82 @@ struct StarkProveOpt {
         default_value = "273030697313060285579891744179749754319274977764"
     )]
     prover_addr: String,
+    /// the memory ceiling of the prover in MiB, the expressions are evaluated in batches of rows
+    /// to fit it, or the proof is refused before allocating
+    #[arg(long = "memory_limit")]
+    memory_limit: Option<usize>,
+    /// refuse the stark struct below these bits of security, 128 by default
+    #[arg(long = "min_security_bits")]
+    min_security_bits: Option<usize>,
+}
+
+/// Check the committed polynomials against the PIL identities row by row
+#[derive(Parser, Debug)]
+struct PilVerifyOpt {
+    #[arg(short, long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    #[arg(long = "m", default_value = "pols.cm")]
+    cm_pols: String,
+}
+
+/// Generate the constant and committed polynomials of a starkjs example without node
+#[derive(Parser, Debug)]
+struct StarkTraceOpt {
+    /// fibonacci, plookup, permutation or connection
+    #[arg(short, long = "example")]
+    example: String,
+    #[arg(short, long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    /// the inputs of the example, like 1,2 of fibonacci
+    #[arg(long = "input", value_delimiter = ',')]
+    input: Vec<u64>,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    #[arg(long = "m", default_value = "pols.cm")]
+    cm_pols: String,
+}
+
+/// Verify the stark proof generated by stark_prove
+#[derive(Parser, Debug)]
+struct StarkVerifyOpt {
+    #[arg(short, long = "stark_stuct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(short, long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    /// the stark setup generated by stark_setup, rebuilt from the const pols if absent
+    #[arg(long = "stark_setup")]
+    stark_setup: Option<String>,
+    #[arg(long = "i", default_value = "zkin.json")]
+    zkin: String,
+}
+
+/// Flatten the stark proof into the arguments of the solidity verifier
+#[derive(Parser, Debug)]
+struct StarkCalldataOpt {
+    #[arg(short, long = "stark_stuct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(long = "i", default_value = "zkin.json")]
+    zkin: String,
+    /// the json `[proof, publics]` taken by verifyProof
+    #[arg(long = "o", default_value = "stark_calldata.json")]
+    output: String,
+}
+
+/// Convert the stark proof between the zkin json and the binary form
+#[derive(Parser, Debug)]
+struct StarkProofConvertOpt {
+    /// verificationHashType of the proof, GL, BN128, BLS12381 or KECCAK
+    #[arg(short = 't', long = "hash_type", default_value = "GL")]
+    hash_type: String,
+    /// the zkin json or binary proof, the format is detected from the content
+    #[arg(long = "i", default_value = "zkin.json")]
+    input: String,
+    #[arg(long = "o", default_value = "zkin.bin")]
+    output: String,
 }
 
 /// Check aggregation proof
@@ -165,17 +412,34 @@ struct Compressor12ExecOpt {
     exec_file: String,
     #[arg(long = "m", default_value = "mycircuit.c12.cm")]
     commit_file: String,
+    /// run the wasm by wasmer, or interpret it natively (experimental)
+    #[arg(long = "witness-backend", default_value = "wasmer")]
+    witness_backend: algebraic::witness::WitnessBackend,
 }
 
-/// generate the input1.zkin.json and input2.zkin.json into out.zkin.json
+/// Run the recursive proving flow of the config, resuming from the last completed stage
+#[derive(Parser, Debug)]
+struct PipelineOpt {
+    /// the layers, stark structs and inputs of the flow, see eigen_zkit::pipeline
+    #[arg(short, long = "config", default_value = "pipeline.json")]
+    config: String,
+    /// run every stage again instead of resuming
+    #[arg(long = "force", action = clap::ArgAction::SetTrue)]
+    force: bool,
+}
+
+/// Join the zkin files of the proofs to aggregate into out.zkin.json
 #[derive(Parser, Debug)]
 struct JoinZkinExecOpt {
-    // #[arg(long = "starksetup", default_value = "starksetup.json")]
-    // starksetup: String,
-    #[arg(long = "zkin1", default_value = "input1.zkin.json")]
-    zkin1: String,
-    #[arg(long = "zkin2", default_value = "input2.zkin.json")]
-    zkin2: String,
+    /// the zkin files in the order of the a_, b_, c_, ... inputs of the aggregation circuit
+    #[arg(long = "zkin", required = true, num_args = 2..)]
+    zkins: Vec<String>,
+    /// the inputs which are proofs of the aggregation circuit itself, like a recursive2 proof
+    #[arg(long = "agg", num_args = 1..)]
+    agg: Vec<usize>,
+    /// the publics of the output, drop_last:k of the first input, concat, or i:start..end,...
+    #[arg(long = "publics", default_value = "drop_last:4")]
+    publics: String,
     #[arg(long = "zkinout", default_value = "out.zkin.json")]
     zkinout: String,
 }
@@ -204,6 +468,7 @@ pub struct Groth16ProveOpt {
     circuit_file: String,
     #[arg(short, required = true)]
     wasm_file: String,
+    /// the proving key of groth16_setup, or a snarkjs zkey
     #[arg(short, required = true, default_value = "g16.zkey")]
     pk_file: String,
     #[arg(short, required = true)]
@@ -218,6 +483,9 @@ pub struct Groth16ProveOpt {
     proof_file: String,
     #[arg(short, action= clap::ArgAction::SetTrue)]
     to_hex: bool,
+    /// run the wasm by wasmer, or interpret it natively (experimental)
+    #[arg(long = "witness-backend", default_value = "wasmer")]
+    witness_backend: groth16::api::WitnessBackend,
 }
 
 /// Verify with groth16
@@ -237,22 +505,169 @@ pub struct Groth16VerifyOpt {
     proof_file: String,
 }
 
+/// Verify the groth16 proofs of the same verification key at once
+#[derive(Parser, Debug)]
+pub struct Groth16VerifyBatchOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    /// the public inputs of the proofs, in the same order as the proofs
+    #[arg(long = "public-input", required = true, num_args = 1..)]
+    public_input_files: Vec<String>,
+    #[arg(long = "proof", required = true, num_args = 1..)]
+    proof_files: Vec<String>,
+}
+
+/// Export the groth16 proof and public inputs as the calldata of the solidity verifier
+#[derive(Parser, Debug)]
+pub struct Groth16CalldataOpt {
+    #[arg(long = "proof", required = true, default_value = "proof.json")]
+    proof_file: String,
+    #[arg(
+        long = "public-input",
+        required = true,
+        default_value = "public_input.json"
+    )]
+    public_input_file: String,
+    /// the hex ABI-encoded calldata of verifyTx
+    #[arg(short, default_value = "calldata.txt")]
+    output: String,
+    /// the JSON argument array of verifyTx
+    #[arg(long = "args", default_value = "calldata.json")]
+    args_file: String,
+}
+
+/// Initialize the groth16 phase-2 ceremony from the circuit and a snarkjs powers of tau file
+#[derive(Parser, Debug)]
+pub struct Groth16CeremonyInitOpt {
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(long = "ptau", required = true)]
+    ptau_file: String,
+    #[arg(short, default_value = "circuit_0000.zkey")]
+    output: String,
+}
+
+/// Contribute to the delta of the groth16 ceremony
+#[derive(Parser, Debug)]
+pub struct Groth16CeremonyContributeOpt {
+    #[arg(short, required = true, default_value = "circuit_0000.zkey")]
+    input: String,
+    #[arg(short, required = true, default_value = "circuit_0001.zkey")]
+    output: String,
+    /// extra entropy mixed into the system randomness
+    #[arg(short, default_value = "")]
+    entropy: String,
+}
+
+/// Verify the contribution chain of the groth16 ceremony
+#[derive(Parser, Debug)]
+pub struct Groth16CeremonyVerifyOpt {
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(long = "ptau", required = true)]
+    ptau_file: String,
+    #[arg(short, required = true, default_value = "circuit_0001.zkey")]
+    zkey_file: String,
+}
+
+/// Export the proving key and verification key of the groth16 ceremony
+#[derive(Parser, Debug)]
+pub struct Groth16CeremonyExportOpt {
+    #[arg(short, required = true, default_value = "circuit_0001.zkey")]
+    zkey_file: String,
+    #[arg(short, required = true, default_value = "g16.zkey")]
+    pk_file: String,
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    #[arg(short, action= clap::ArgAction::SetTrue)]
+    to_hex: bool,
+}
+
+/// Export the groth16 proving key as a snarkjs zkey
+#[derive(Parser, Debug)]
+pub struct Groth16ExportZkeyOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(short, required = true, default_value = "g16.zkey")]
+    pk_file: String,
+    #[arg(short, required = true, default_value = "circuit_final.zkey")]
+    zkey_file: String,
+}
+
+/// Export the verification key of a snarkjs zkey
+#[derive(Parser, Debug)]
+pub struct Groth16ZkeyExportVkOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(short, required = true, default_value = "circuit_final.zkey")]
+    zkey_file: String,
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    #[arg(short, action= clap::ArgAction::SetTrue)]
+    to_hex: bool,
+}
+
 #[derive(Parser, Debug)]
 enum Command {
+    #[command(name = "setup")]
+    Setup(SetupOpt),
     /// Compile circom circuits to r1cs, and generate witness
     #[command(name = "compile")]
     Compile(CompilierOpt),
+    #[command(name = "calculate_witness")]
+    CalculateWitness(CalculateWitnessOpt),
+    #[command(name = "analyse")]
+    Analyse(AnalyseOpt),
+    #[command(name = "r1cs_check")]
+    R1csCheck(R1csCheckOpt),
+    #[command(name = "prove")]
+    Prove(ProveOpt),
+    #[command(name = "verify")]
+    Verify(VerifyOpt),
+    #[command(name = "export_verification_key")]
+    ExportVerificationKey(ExportVerificationKeyOpt),
     #[command(name = "generate_verifier")]
     GenerateVerifier(GenerateVerifierOpt),
 
+    #[command(name = "export_aggregation_verification_key")]
+    ExportAggregationVerificationKey(ExportAggregationVerificationKeyOpt),
+    #[command(name = "aggregation_prove")]
+    AggregationProve(AggregationProveOpt),
+    #[command(name = "aggregation_verify")]
+    AggregationVerify(AggregationVerifyOpt),
+    #[command(name = "aggregation_check")]
+    AggregationCheck(AggregationCheckOpt),
+    #[command(name = "generate_aggregation_verifier")]
+    GenerateAggregationVerifier(GenerateAggregationVerifierOpt),
+
+    #[command(name = "stark_setup")]
+    StarkSetup(StarkSetupOpt),
+    #[command(name = "stark_struct")]
+    StarkStruct(StarkStructOpt),
     #[command(name = "stark_prove")]
     StarkProve(StarkProveOpt),
+    #[command(name = "stark_verify")]
+    StarkVerify(StarkVerifyOpt),
+    #[command(name = "pil_verify")]
+    PilVerify(PilVerifyOpt),
+    #[command(name = "stark_trace")]
+    StarkTrace(StarkTraceOpt),
+    #[command(name = "stark_proof_convert")]
+    StarkProofConvert(StarkProofConvertOpt),
+    #[command(name = "stark_calldata")]
+    StarkCalldata(StarkCalldataOpt),
     #[command(name = "compressor12_setup")]
     Compressor12Setup(Compressor12SetupOpt),
     #[command(name = "compressor12_exec")]
     Compressor12Exec(Compressor12ExecOpt),
     #[command(name = "join_zkin")]
     JoinZkin(JoinZkinExecOpt),
+    #[command(name = "pipeline")]
+    Pipeline(PipelineOpt),
 
     #[command(name = "groth16_setup")]
     Groth16Setup(Groth16SetupOpt),
@@ -260,6 +675,28 @@ enum Command {
     Groth16Prove(Groth16ProveOpt),
     #[command(name = "groth16_verify")]
     Groth16Verify(Groth16VerifyOpt),
+    #[command(name = "groth16_verify_batch")]
+    Groth16VerifyBatch(Groth16VerifyBatchOpt),
+    #[command(name = "groth16_calldata")]
+    Groth16Calldata(Groth16CalldataOpt),
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[command(name = "groth16_ceremony_init")]
+    Groth16CeremonyInit(Groth16CeremonyInitOpt),
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[command(name = "groth16_ceremony_contribute")]
+    Groth16CeremonyContribute(Groth16CeremonyContributeOpt),
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[command(name = "groth16_ceremony_verify")]
+    Groth16CeremonyVerify(Groth16CeremonyVerifyOpt),
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[command(name = "groth16_ceremony_export")]
+    Groth16CeremonyExport(Groth16CeremonyExportOpt),
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[command(name = "groth16_export_zkey")]
+    Groth16ExportZkey(Groth16ExportZkeyOpt),
+    #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+    #[command(name = "groth16_zkey_export_vk")]
+    Groth16ZkeyExportVk(Groth16ZkeyExportVkOpt),
 }
 
 #[derive(Parser, Debug)]
@@ -269,11 +706,71 @@ struct Cli {
     command: Command,
 }
 
+// the exit codes, so that the scripts can tell the failures apart
+const EXIT_ERROR: i32 = 1;
+// the same as clap for the usage error
+const EXIT_INVALID_ARGUMENT: i32 = 2;
+const EXIT_MISSING_FILE: i32 = 3;
+const EXIT_MALFORMED_INPUT: i32 = 4;
+const EXIT_INVALID_PROOF: i32 = 5;
+const EXIT_UNSATISFIED: i32 = 6;
+const EXIT_MEMORY_LIMIT: i32 = 7;
+
+fn exit_code(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<StarkyError>() {
+            return match e {
+                StarkyError::InvalidHashType(_) => EXIT_INVALID_ARGUMENT,
+                StarkyError::MalformedInput(_) | StarkyError::MalformedPil(_) => {
+                    EXIT_MALFORMED_INPUT
+                }
+                StarkyError::UnsatisfiedIdentities(_) => EXIT_UNSATISFIED,
+                StarkyError::InvalidProof(_) => EXIT_INVALID_PROOF,
+                StarkyError::MemoryLimit(_) => EXIT_MEMORY_LIMIT,
+                StarkyError::InsufficientSecurity(_) => EXIT_INVALID_ARGUMENT,
+                StarkyError::Unknown(_) => EXIT_ERROR,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<Groth16Error>() {
+            return match e {
+                Groth16Error::UnknownCurveType(_) => EXIT_INVALID_ARGUMENT,
+                Groth16Error::MalformedInput(_) => EXIT_MALFORMED_INPUT,
+                Groth16Error::InvalidProof(_) => EXIT_INVALID_PROOF,
+                Groth16Error::Unknown(_) => EXIT_ERROR,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<RecursionError>() {
+            return match e {
+                RecursionError::MalformedExecFile(_) => EXIT_MALFORMED_INPUT,
+                RecursionError::InvalidWitness(_) => EXIT_UNSATISFIED,
+                RecursionError::Unknown(_) => EXIT_ERROR,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<AlgebraicError>() {
+            return match e {
+                AlgebraicError::MalformedInput(_) => EXIT_MALFORMED_INPUT,
+                AlgebraicError::UnsatisfiedConstraints(_) => EXIT_UNSATISFIED,
+                AlgebraicError::Unknown(_) => EXIT_ERROR,
+            };
+        }
+        if let Some(DslError::CircomCompileError(_)) = cause.downcast_ref::<DslError>() {
+            return EXIT_MALFORMED_INPUT;
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                return EXIT_MISSING_FILE;
+            }
+        }
+    }
+    EXIT_ERROR
+}
+
 fn main() {
     let args = Cli::parse();
     env_logger::init();
     let start = Instant::now();
     let exec_result = match args.command {
+        Command::Setup(args) => plonky::api::setup(args.power, &args.srs_monomial_form),
         Command::Compile(args) => circom_compiler(
             args.input,
             args.prime.to_lowercase(),
@@ -283,25 +780,143 @@ fn main() {
             args.no_simplification,
             args.reduced_simplification,
         ),
+        Command::CalculateWitness(args) => {
+            plonky::api::calculate_witness(&args.wasm_file, &args.input_json, &args.output)
+        }
+        Command::Analyse(args) => {
+            let (r1cs, sym, json) = (
+                &args.circuit_file,
+                args.sym_file.as_deref(),
+                args.output.as_deref(),
+            );
+            match args.curve_type.as_str() {
+                "BN128" => analyse::<Bn256>(r1cs, sym, json),
+                "BLS12381" => analyse::<Bls12>(r1cs, sym, json),
+                "GL" => analyse::<GL>(r1cs, sym, json),
+                _ => Err(Groth16Error::UnknownCurveType(args.curve_type).into()),
+            }
+            .map(|analysis| println!("{}", analysis))
+        }
+        Command::R1csCheck(args) => {
+            let (r1cs, witness, sym) =
+                (&args.circuit_file, &args.witness, args.sym_file.as_deref());
+            match args.curve_type.as_str() {
+                "BN128" => r1cs_check::<Bn256>(r1cs, witness, sym),
+                "BLS12381" => r1cs_check::<Bls12>(r1cs, witness, sym),
+                "GL" => r1cs_check::<GL>(r1cs, witness, sym),
+                _ => Err(Groth16Error::UnknownCurveType(args.curve_type).into()),
+            }
+        }
+        Command::Prove(args) => plonky::api::prove(
+            &args.circuit_file,
+            &args.witness,
+            &args.srs_monomial_form,
+            &args.transcript,
+            &args.output_proof,
+            &args.output_proof_json,
+            args.output_public_json.as_deref(),
+        ),
+        Command::Verify(args) => plonky::api::verify(&args.vk, &args.proof, &args.transcript),
+        Command::ExportVerificationKey(args) => plonky::api::export_verification_key(
+            &args.srs_monomial_form,
+            &args.circuit_file,
+            &args.output_vk,
+        ),
         Command::GenerateVerifier(args) => match args.protocal.as_str() {
             "groth16" => groth16::api::generate_verifier(&args.vk_file, &args.sol),
-            _ => {
-                panic!("unknown protocol")
-            }
+            "plonk" => plonky::api::generate_verifier(&args.vk_file, &args.sol),
+            "stark" => stark_solidity_verifier(
+                &args.stark_struct,
+                &args.piljson,
+                &args.const_pols,
+                args.stark_setup.as_deref(),
+                &args.sol,
+            ),
+            _ => Err(anyhow::anyhow!("unknown protocol {}", args.protocal)),
         },
 
-        Command::StarkProve(args) => stark_prove(
+        Command::ExportAggregationVerificationKey(args) => {
+            plonky::api::export_aggregation_verification_key(
+                args.num_proofs_to_check,
+                args.num_inputs,
+                &args.srs_monomial_form,
+                &args.output_vk,
+            )
+        }
+        Command::AggregationProve(args) => plonky::api::aggregation_prove(
+            &args.srs_monomial_form,
+            &args.old_proof_list,
+            &args.old_vk,
+            &args.new_proof,
+            &args.proof_json,
+        ),
+        Command::AggregationVerify(args) => plonky::api::aggregation_verify(&args.proof, &args.vk),
+        Command::AggregationCheck(args) => {
+            plonky::api::aggregation_check(&args.old_proof_list, &args.old_vk, &args.new_proof)
+        }
+        Command::GenerateAggregationVerifier(args) => plonky::api::generate_aggregation_verifier(
+            &args.old_vk,
+            &args.new_vk,
+            args.num_inputs,
+            &args.sol,
+        ),
+
+        Command::StarkStruct(args) => starky::security::generate_stark_struct(
+            args.n_bits,
+            args.blowup_bits,
+            args.security_bits,
+            &args.hash_type,
+            args.extension_degree,
+            &args.output,
+        ),
+        Command::StarkSetup(args) => stark_setup(
             &args.stark_struct,
             &args.piljson,
-            args.norm_stage,
-            args.skip_main,
-            args.agg_stage,
             &args.const_pols,
-            &args.cm_pols,
-            &args.circom_file,
+            &args.stark_setup,
+            args.min_security_bits,
+        ),
+        Command::StarkProve(args) => eigen_zkit::pipeline::memory_limit_bytes(args.memory_limit)
+            .and_then(|memory_limit| {
+                stark_prove(
+                    &args.stark_struct,
+                    &args.piljson,
+                    args.norm_stage,
+                    args.skip_main,
+                    if args.agg_stage { args.agg_inputs } else { 0 },
+                    &args.const_pols,
+                    &args.cm_pols,
+                    args.stark_setup.as_deref(),
+                    args.circom_file.as_deref(),
+                    &args.zkin,
+                    &args.prover_addr,
+                    memory_limit,
+                    args.min_security_bits,
+                )
+            }),
+        Command::StarkVerify(args) => stark_verify(
+            &args.stark_struct,
+            &args.piljson,
+            &args.const_pols,
+            args.stark_setup.as_deref(),
             &args.zkin,
-            &args.prover_addr,
         ),
+        Command::PilVerify(args) => {
+            starky::pil_verify::pil_verify(&args.piljson, &args.const_pols, &args.cm_pols)
+        }
+        Command::StarkTrace(args) => starky::trace_examples::trace_gen(
+            &args.example,
+            &args.piljson,
+            &args.input,
+            &args.const_pols,
+            &args.cm_pols,
+        ),
+        Command::StarkProofConvert(args) => {
+            starky::serializer_bin::convert_proof(&args.hash_type, &args.input, &args.output)
+        }
+        Command::StarkCalldata(args) => {
+            stark_solidity_calldata(&args.stark_struct, &args.zkin, &args.output)
+        }
         Command::Compressor12Setup(args) => recursion::compressor12_setup::setup(
             &args.r1cs_file,
             &args.pil_file,
@@ -315,10 +930,15 @@ fn main() {
             &args.pil_file,
             &args.exec_file,
             &args.commit_file,
+            args.witness_backend,
         ),
-        Command::JoinZkin(args) => {
-            starky::zkin_join::join_zkin(&args.zkin1, &args.zkin2, &args.zkinout)
-        }
+        Command::JoinZkin(args) => args
+            .publics
+            .parse::<starky::zkin_join::PublicsSpec>()
+            .and_then(|spec| {
+                starky::zkin_join::join_zkin(&args.zkins, &args.agg, &spec, &args.zkinout)
+            }),
+        Command::Pipeline(args) => eigen_zkit::pipeline::pipeline(&args.config, args.force),
         Command::Groth16Setup(args) => groth16_setup(
             &args.curve_type,
             &args.circuit_file,
@@ -335,6 +955,7 @@ fn main() {
             &args.public_input_file,
             &args.proof_file,
             args.to_hex,
+            args.witness_backend,
         ),
         Command::Groth16Verify(args) => groth16_verify(
             &args.curve_type,
@@ -342,11 +963,60 @@ fn main() {
             &args.public_input_file,
             &args.proof_file,
         ),
+        Command::Groth16VerifyBatch(args) => groth16_verify_batch(
+            &args.curve_type,
+            &args.vk_file,
+            &args.public_input_files,
+            &args.proof_files,
+        ),
+        Command::Groth16Calldata(args) => groth16_export_calldata(
+            &args.proof_file,
+            &args.public_input_file,
+            &args.output,
+            &args.args_file,
+        ),
+        #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+        Command::Groth16CeremonyInit(args) => {
+            groth16_ceremony_init(&args.circuit_file, &args.ptau_file, &args.output)
+        }
+        #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+        Command::Groth16CeremonyContribute(args) => {
+            groth16_ceremony_contribute(&args.input, &args.output, &args.entropy)
+                .map(|hash| println!("contribution hash: {}", hash))
+        }
+        #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+        Command::Groth16CeremonyVerify(args) => {
+            groth16_ceremony_verify(&args.circuit_file, &args.ptau_file, &args.zkey_file).map(
+                |hashes| {
+                    for (i, hash) in hashes.iter().enumerate() {
+                        println!("contribution #{}: {}", i + 1, hash);
+                    }
+                },
+            )
+        }
+        #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+        Command::Groth16CeremonyExport(args) => {
+            groth16_ceremony_export(&args.zkey_file, &args.pk_file, &args.vk_file, args.to_hex)
+        }
+        #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+        Command::Groth16ExportZkey(args) => groth16_export_zkey(
+            &args.curve_type,
+            &args.circuit_file,
+            &args.pk_file,
+            &args.zkey_file,
+        ),
+        #[cfg(not(any(feature = "cuda", feature = "opencl")))]
+        Command::Groth16ZkeyExportVk(args) => groth16_zkey_export_vk(
+            &args.curve_type,
+            &args.zkey_file,
+            &args.vk_file,
+            args.to_hex,
+        ),
     };
     match exec_result {
         Err(x) => {
-            println!("execute error: {}", x);
-            std::process::exit(400)
+            println!("execute error: {:#}", x);
+            std::process::exit(exit_code(&x))
         }
         _ => println!("time cost: {}", start.elapsed().as_secs_f64()),
     };
@@ -0,0 +1,1217 @@
+//! The stark -> c12 -> recursive1 -> recursive2 -> final -> groth16 flow of
+//! `test/stark_aggregation.sh`, planned from a declarative config and run stage by stage.
+//!
+//! The stark setup of each layer is built once by its `stark_setup` stage and loaded by its
+//! provers. The aggregation folds `fan_in` proofs at a time, the joined proof being the first
+//! input of the next fold.
+//!
+//! Each stage records the keccak256 fingerprint of its inputs and of the settings of the config
+//! (`min_security_bits`, `memory_limit`, `prover_addr` and `witness_backend`) in
+//! `<workspace>/pipeline.state.json` once it's done. A stage whose fingerprint still matches and
+//! whose outputs exist is skipped, so a failed run resumes from the last completed stage, and the
+//! setups of the circuits (compile, compressor12_setup and groth16_setup) are kept between runs
+//! until the circuits change.
+use algebraic::witness::WitnessBackend;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use starky::errors::StarkyError;
+use starky::types::{load_json, StarkStruct};
+use starky::zkin_join::PublicsSpec;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use tiny_keccak::{Hasher, Keccak};
+
+const STATE_FILE: &str = "pipeline.state.json";
+
+/// The config of `zkit pipeline`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    /// the directory of the generated files
+    pub workspace: String,
+    /// the prefix of the generated files, like `fibonacci`
+    pub name: String,
+    pub base: BaseLayer,
+    /// the recursive layers proving each base proof in order, like c12 and recursive1
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    /// fold the proofs into one, the last of `layers` must be of `agg_stage`
+    pub aggregation: Option<Aggregation>,
+    #[serde(rename = "final")]
+    pub final_layer: Option<Layer>,
+    pub snark: Option<Snark>,
+    /// the circom libraries of the goldilocks circuits, searched before the embedded ones
+    #[serde(default)]
+    pub circom_libs: Vec<String>,
+    #[serde(default)]
+    pub witness_backend: WitnessBackend,
+    #[serde(default = "default_prover_addr")]
+    pub prover_addr: String,
+    /// the memory ceiling of the stark provers in MiB
+    pub memory_limit: Option<usize>,
+    /// refuse the stark structs below these bits of security, 128 by default
+    pub min_security_bits: Option<usize>,
+}
+
+/// The proofs of the PIL, they share the constant polynomials
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaseLayer {
+    pub pil: String,
+    #[serde(rename = "const")]
+    pub const_pols: String,
+    /// the committed polynomials, one per proof
+    #[serde(rename = "cm")]
+    pub cm_pols: Vec<String>,
+    pub stark_struct: String,
+    /// overrides the `verificationHashType` of the stark struct
+    pub hash_type: Option<String>,
+    #[serde(default)]
+    pub skip_main: bool,
+}
+
+/// A circuit verifying the proof of the previous layer, proven by c12
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    pub stark_struct: String,
+    /// overrides the `verificationHashType` of the stark struct
+    pub hash_type: Option<String>,
+    /// the `--force-n-bits` of compressor12_setup, 0 for the minimal one
+    #[serde(default)]
+    pub force_n_bits: usize,
+    #[serde(default)]
+    pub norm_stage: bool,
+    #[serde(default)]
+    pub agg_stage: bool,
+    #[serde(default)]
+    pub skip_main: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Aggregation {
+    #[serde(flatten)]
+    pub layer: Layer,
+    /// the proofs verified by one aggregation proof, 2 to 26. The first fold takes `fan_in`
+    /// proofs, each next one the previous fold and `fan_in - 1` proofs.
+    #[serde(default = "default_fan_in")]
+    pub fan_in: usize,
+    /// the publics of the joined zkin, see `join_zkin --publics`
+    #[serde(default = "default_publics")]
+    pub publics: String,
+}
+
+/// The groth16 proof of the last stark proof
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snark {
+    /// BN128 or BLS12381, the hash type of the last stark proof
+    pub curve: String,
+    #[serde(default)]
+    pub circom_libs: Vec<String>,
+}
+
+fn default_prover_addr() -> String {
+    "273030697313060285579891744179749754319274977764".to_string()
+}
+
+fn default_fan_in() -> usize {
+    2
+}
+
+fn default_publics() -> String {
+    "drop_last:4".to_string()
+}
+
+/// A step of the pipeline, one zkit command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    StarkSetup {
+        stark_struct: String,
+        pil: String,
+        const_pols: String,
+        setup: String,
+    },
+    StarkProve {
+        stark_struct: String,
+        pil: String,
+        const_pols: String,
+        cm_pols: String,
+        setup: String,
+        circom: String,
+        zkin: String,
+        norm_stage: bool,
+        skip_main: bool,
+        agg_inputs: usize,
+    },
+    Compile {
+        circom: String,
+        prime: String,
+        libs: Vec<String>,
+        output: String,
+    },
+    Compressor12Setup {
+        r1cs: String,
+        pil: String,
+        const_pols: String,
+        exec: String,
+        force_n_bits: usize,
+    },
+    Compressor12Exec {
+        zkin: String,
+        wasm: String,
+        pil: String,
+        exec: String,
+        cm_pols: String,
+    },
+    JoinZkin {
+        zkins: Vec<String>,
+        /// the zkins which are proofs of the aggregation circuit itself
+        agg: Vec<usize>,
+        publics: String,
+        zkin_out: String,
+    },
+    Groth16Setup {
+        curve: String,
+        r1cs: String,
+        pk: String,
+        vk: String,
+    },
+    Groth16Prove {
+        curve: String,
+        r1cs: String,
+        wasm: String,
+        pk: String,
+        zkin: String,
+        public_input: String,
+        proof: String,
+    },
+}
+
+/// The r1cs and wasm written by `compile` for the circom file
+fn compiled(circom: &str, output: &str) -> (String, String) {
+    let stem = Path::new(circom).file_stem().unwrap().to_string_lossy();
+    (
+        format!("{}/{}.r1cs", output, stem),
+        format!("{}/{}_js/{}.wasm", output, stem, stem),
+    )
+}
+
+impl Action {
+    pub fn inputs(&self) -> Vec<String> {
+        match self {
+            Action::StarkSetup {
+                stark_struct,
+                pil,
+                const_pols,
+                ..
+            } => vec![stark_struct.clone(), pil.clone(), const_pols.clone()],
+            Action::StarkProve {
+                stark_struct,
+                pil,
+                const_pols,
+                cm_pols,
+                setup,
+                ..
+            } => vec![
+                stark_struct.clone(),
+                pil.clone(),
+                const_pols.clone(),
+                cm_pols.clone(),
+                setup.clone(),
+            ],
+            Action::Compile { circom, .. } => vec![circom.clone()],
+            Action::Compressor12Setup { r1cs, .. } => vec![r1cs.clone()],
+            Action::Compressor12Exec {
+                zkin,
+                wasm,
+                pil,
+                exec,
+                ..
+            } => vec![zkin.clone(), wasm.clone(), pil.clone(), exec.clone()],
+            Action::JoinZkin { zkins, .. } => zkins.clone(),
+            Action::Groth16Setup { r1cs, .. } => vec![r1cs.clone()],
+            Action::Groth16Prove {
+                r1cs,
+                wasm,
+                pk,
+                zkin,
+                ..
+            } => vec![r1cs.clone(), wasm.clone(), pk.clone(), zkin.clone()],
+        }
+    }
+
+    pub fn outputs(&self) -> Vec<String> {
+        match self {
+            Action::StarkSetup { setup, .. } => vec![setup.clone()],
+            Action::StarkProve { circom, zkin, .. } => vec![circom.clone(), zkin.clone()],
+            Action::Compile { circom, output, .. } => {
+                let (r1cs, wasm) = compiled(circom, output);
+                vec![r1cs, wasm]
+            }
+            Action::Compressor12Setup {
+                pil,
+                const_pols,
+                exec,
+                ..
+            } => vec![pil.clone(), const_pols.clone(), exec.clone()],
+            Action::Compressor12Exec { pil, cm_pols, .. } => {
+                vec![format!("{}.json", pil), cm_pols.clone()]
+            }
+            Action::JoinZkin { zkin_out, .. } => vec![zkin_out.clone()],
+            Action::Groth16Setup { pk, vk, .. } => vec![pk.clone(), vk.clone()],
+            Action::Groth16Prove {
+                public_input,
+                proof,
+                ..
+            } => vec![public_input.clone(), proof.clone()],
+        }
+    }
+
+    fn run(&self, config: &PipelineConfig) -> Result<()> {
+        for output in self.outputs() {
+            if let Some(dir) = Path::new(&output).parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+        }
+        match self {
+            Action::StarkSetup {
+                stark_struct,
+                pil,
+                const_pols,
+                setup,
+            } => starky::prove::stark_setup(
+                stark_struct,
+                pil,
+                const_pols,
+                setup,
+                config.min_security_bits,
+            ),
+            Action::StarkProve {
+                stark_struct,
+                pil,
+                const_pols,
+                cm_pols,
+                setup,
+                circom,
+                zkin,
+                norm_stage,
+                skip_main,
+                agg_inputs,
+            } => starky::prove::stark_prove(
+                stark_struct,
+                pil,
+                *norm_stage,
+                *skip_main,
+                *agg_inputs,
+                const_pols,
+                cm_pols,
+                Some(setup),
+                Some(circom),
+                zkin,
+                &config.prover_addr,
+                memory_limit_bytes(config.memory_limit)?,
+                config.min_security_bits,
+            ),
+            Action::Compile {
+                circom,
+                prime,
+                libs,
+                output,
+            } => dsl_compile::circom_compiler(
+                circom.clone(),
+                prime.clone(),
+                "full".to_string(),
+                libs.clone(),
+                output.clone(),
+                false,
+                false,
+            ),
+            Action::Compressor12Setup {
+                r1cs,
+                pil,
+                const_pols,
+                exec,
+                force_n_bits,
+            } => recursion::compressor12_setup::setup(r1cs, pil, const_pols, exec, *force_n_bits),
+            Action::Compressor12Exec {
+                zkin,
+                wasm,
+                pil,
+                exec,
+                cm_pols,
+            } => recursion::compressor12_exec::exec(
+                zkin,
+                wasm,
+                pil,
+                exec,
+                cm_pols,
+                config.witness_backend,
+            ),
+            Action::JoinZkin {
+                zkins,
+                agg,
+                publics,
+                zkin_out,
+            } => {
+                starky::zkin_join::join_zkin(zkins, agg, &publics.parse::<PublicsSpec>()?, zkin_out)
+            }
+            Action::Groth16Setup {
+                curve,
+                r1cs,
+                pk,
+                vk,
+            } => groth16::api::groth16_setup(curve, r1cs, pk, vk, false),
+            Action::Groth16Prove {
+                curve,
+                r1cs,
+                wasm,
+                pk,
+                zkin,
+                public_input,
+                proof,
+            } => groth16::api::groth16_prove(
+                curve,
+                r1cs,
+                wasm,
+                pk,
+                zkin,
+                public_input,
+                proof,
+                false,
+                // of algebraic-gpu once groth16 runs on the gpu
+                config.witness_backend.to_string().parse()?,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    /// unique in the pipeline, like `0/c12/prove`
+    pub name: String,
+    pub action: Action,
+}
+
+/// The circuit of a layer and the files of its setup
+struct Circuit {
+    circom: String,
+    r1cs: String,
+    wasm: String,
+    pil: String,
+    const_pols: String,
+    exec: String,
+}
+
+/// A proof flowing through the layers, the zkin is the input of the next circuit
+struct Proof {
+    /// the index of the base proof, or `aggregation`
+    name: String,
+    dir: String,
+    zkin: String,
+}
+
+impl PipelineConfig {
+    pub fn validate(&self) -> Result<()> {
+        let malformed = |msg: String| -> Result<()> { bail!(StarkyError::MalformedInput(msg)) };
+        if self.workspace.is_empty() || self.name.is_empty() {
+            return malformed("the workspace and name of the pipeline are required".to_string());
+        }
+        if self.base.cm_pols.is_empty() {
+            return malformed("the base layer has no committed polynomials".to_string());
+        }
+        let mut names = HashSet::new();
+        for layer in self.prover_layers().iter().skip(1) {
+            if ["", "base", "snark"].contains(&layer.name.as_str())
+                || layer.name.contains('/')
+                || !names.insert(&layer.name)
+            {
+                return malformed(format!("invalid or duplicated layer name {:?}", layer.name));
+            }
+        }
+        if let Some(agg) = &self.aggregation {
+            // the inputs of the aggregation circuit are prefixed by a_ to z_
+            if agg.fan_in < 2 || agg.fan_in > 26 {
+                return malformed(format!(
+                    "the aggregation circuit verifies 2 to 26 proofs, got the fan_in {}",
+                    agg.fan_in
+                ));
+            }
+            let n = self.base.cm_pols.len();
+            if n < agg.fan_in || (n - agg.fan_in) % (agg.fan_in - 1) != 0 {
+                return malformed(format!(
+                    "{} proofs can't be folded {} at a time, the first fold takes {} and each next \
+                     one {} more",
+                    n,
+                    agg.fan_in,
+                    agg.fan_in,
+                    agg.fan_in - 1
+                ));
+            }
+            if !self.layers.last().map(|l| l.agg_stage).unwrap_or(false) {
+                return malformed(
+                    "the layer before the aggregation must be of agg_stage".to_string(),
+                );
+            }
+            agg.publics.parse::<PublicsSpec>()?;
+        }
+        if let Some(snark) = &self.snark {
+            if snark.curve != "BN128" && snark.curve != "BLS12381" {
+                bail!(StarkyError::InvalidHashType(snark.curve.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings the stages run with besides their inputs, a change of them reruns the stages
+    pub fn settings(&self) -> String {
+        format!(
+            "min_security_bits={:?} memory_limit={:?} prover_addr={} witness_backend={}",
+            self.min_security_bits, self.memory_limit, self.prover_addr, self.witness_backend
+        )
+    }
+
+    /// The base layer, the recursive layers, the aggregation and the final one, in order
+    fn prover_layers(&self) -> Vec<Layer> {
+        let base = Layer {
+            name: "base".to_string(),
+            stark_struct: self.base.stark_struct.clone(),
+            hash_type: self.base.hash_type.clone(),
+            force_n_bits: 0,
+            norm_stage: false,
+            agg_stage: false,
+            skip_main: self.base.skip_main,
+        };
+        std::iter::once(base)
+            .chain(self.layers.iter().cloned())
+            .chain(self.aggregation.iter().map(|a| a.layer.clone()))
+            .chain(self.final_layer.iter().cloned())
+            .collect()
+    }
+
+    fn file(&self, dir: &str, circuit: &str, ext: &str) -> String {
+        format!("{}/{}.{}.{}", dir, self.name, circuit, ext)
+    }
+
+    /// The stark struct of the layer, with the hash type overridden
+    pub fn stark_struct_file(&self, layer: &str) -> String {
+        self.file(&self.workspace, layer, "starkStruct.json")
+    }
+
+    /// The stark setup of the layer, built by its `stark_setup` stage
+    pub fn setup_file(&self, layer: &str) -> String {
+        self.file(&self.workspace, layer, "stark_setup.json")
+    }
+
+    /// Write the stark structs of the layers into the workspace, checking that every proof is
+    /// hashed for the field of the circuit verifying it.
+    pub fn prepare(&self) -> Result<()> {
+        self.validate()?;
+        std::fs::create_dir_all(&self.workspace)?;
+        let layers = self.prover_layers();
+        for (i, layer) in layers.iter().enumerate() {
+            let mut stark_struct = load_json::<StarkStruct>(&layer.stark_struct)
+                .with_context(|| format!("load the stark struct of {}", layer.name))?;
+            if let Some(hash_type) = &layer.hash_type {
+                stark_struct.verificationHashType = hash_type.clone();
+            }
+            let expected = if i + 1 < layers.len() {
+                "GL"
+            } else {
+                self.snark.as_ref().map(|s| s.curve.as_str()).unwrap_or("")
+            };
+            if !expected.is_empty() && stark_struct.verificationHashType != expected {
+                bail!(StarkyError::MalformedInput(format!(
+                    "the proof of {} is verified in {}, its hash type must be {}, got {}",
+                    layer.name,
+                    if i + 1 < layers.len() {
+                        &layers[i + 1].name
+                    } else {
+                        "snark"
+                    },
+                    expected,
+                    stark_struct.verificationHashType
+                )));
+            }
+            std::fs::write(
+                self.stark_struct_file(&layer.name),
+                serde_json::to_string_pretty(&stark_struct)?,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn circuit(&self, name: &str) -> Circuit {
+        let ws = &self.workspace;
+        let circom = self.file(ws, name, "circom");
+        let (r1cs, wasm) = compiled(&circom, ws);
+        Circuit {
+            circom,
+            r1cs,
+            wasm,
+            pil: self.file(ws, name, "pil"),
+            const_pols: self.file(ws, name, "const"),
+            exec: self.file(ws, name, "exec"),
+        }
+    }
+
+    /// The stages of the pipeline in order
+    pub fn plan(&self) -> Result<Vec<Stage>> {
+        self.validate()?;
+        let ws = &self.workspace;
+        let layers = self.prover_layers();
+        // the circuit verifying the proof of layers[i] is named by layers[i + 1]
+        let mut circuits: Vec<String> = layers.iter().skip(1).map(|l| l.name.clone()).collect();
+        circuits.push(match self.snark {
+            Some(_) => "snark".to_string(),
+            None => format!("{}.verifier", layers.last().unwrap().name),
+        });
+
+        let mut stages = vec![];
+        let mut stage = |name: String, action: Action| stages.push(Stage { name, action });
+
+        // the base proofs
+        let base_setup = self.setup_file("base");
+        stage(
+            "base/stark_setup".to_string(),
+            Action::StarkSetup {
+                stark_struct: self.stark_struct_file("base"),
+                pil: self.base.pil.clone(),
+                const_pols: self.base.const_pols.clone(),
+                setup: base_setup.clone(),
+            },
+        );
+        let mut proofs: Vec<Proof> = vec![];
+        for (i, cm) in self.base.cm_pols.iter().enumerate() {
+            let dir = format!("{}/{}", ws, i);
+            let zkin = self.file(&dir, &circuits[0], "zkin.json");
+            stage(
+                format!("{}/base/prove", i),
+                Action::StarkProve {
+                    stark_struct: self.stark_struct_file("base"),
+                    pil: self.base.pil.clone(),
+                    const_pols: self.base.const_pols.clone(),
+                    cm_pols: cm.clone(),
+                    setup: base_setup.clone(),
+                    circom: self.circuit(&circuits[0]).circom,
+                    zkin: zkin.clone(),
+                    norm_stage: false,
+                    skip_main: self.base.skip_main,
+                    agg_inputs: 0,
+                },
+            );
+            proofs.push(Proof {
+                name: i.to_string(),
+                dir,
+                zkin,
+            });
+        }
+
+        let fan_in = self
+            .aggregation
+            .as_ref()
+            .map(|a| a.fan_in)
+            .unwrap_or_else(default_fan_in);
+        for (k, layer) in layers.iter().enumerate().skip(1) {
+            let circuit = self.circuit(&layer.name);
+            let next = self.circuit(&circuits[k]).circom;
+            let setup = self.setup_file(&layer.name);
+            stage(
+                format!("{}/compile", layer.name),
+                Action::Compile {
+                    circom: circuit.circom.clone(),
+                    prime: "goldilocks".to_string(),
+                    libs: self.circom_libs.clone(),
+                    output: ws.clone(),
+                },
+            );
+            stage(
+                format!("{}/setup", layer.name),
+                Action::Compressor12Setup {
+                    r1cs: circuit.r1cs.clone(),
+                    pil: circuit.pil.clone(),
+                    const_pols: circuit.const_pols.clone(),
+                    exec: circuit.exec.clone(),
+                    force_n_bits: layer.force_n_bits,
+                },
+            );
+            // the pil json is written by the first exec, the stark setup follows it
+            let mut stark_setup = true;
+            let mut prove =
+                |stage: &mut dyn FnMut(String, Action), prefix: &str, dir: &str, zkin: &str| {
+                    let cm_pols = self.file(dir, &layer.name, "cm");
+                    let zkin_out = self.file(dir, &circuits[k], "zkin.json");
+                    stage(
+                        format!("{}/{}/exec", prefix, layer.name),
+                        Action::Compressor12Exec {
+                            zkin: zkin.to_string(),
+                            wasm: circuit.wasm.clone(),
+                            pil: circuit.pil.clone(),
+                            exec: circuit.exec.clone(),
+                            cm_pols: cm_pols.clone(),
+                        },
+                    );
+                    if stark_setup {
+                        stark_setup = false;
+                        stage(
+                            format!("{}/stark_setup", layer.name),
+                            Action::StarkSetup {
+                                stark_struct: self.stark_struct_file(&layer.name),
+                                pil: format!("{}.json", circuit.pil),
+                                const_pols: circuit.const_pols.clone(),
+                                setup: setup.clone(),
+                            },
+                        );
+                    }
+                    stage(
+                        format!("{}/{}/prove", prefix, layer.name),
+                        Action::StarkProve {
+                            stark_struct: self.stark_struct_file(&layer.name),
+                            pil: format!("{}.json", circuit.pil),
+                            const_pols: circuit.const_pols.clone(),
+                            cm_pols,
+                            setup: setup.clone(),
+                            circom: next.clone(),
+                            zkin: zkin_out.clone(),
+                            norm_stage: layer.norm_stage,
+                            skip_main: layer.skip_main,
+                            agg_inputs: if layer.agg_stage { fan_in } else { 0 },
+                        },
+                    );
+                    zkin_out
+                };
+
+            let is_aggregation = self
+                .aggregation
+                .as_ref()
+                .map(|a| a.layer.name == layer.name)
+                .unwrap_or(false);
+            if is_aggregation {
+                // fold fan_in proofs, then the fold and the next fan_in - 1 proofs, and so on,
+                // like the aggregation stage of the script
+                let publics = self.aggregation.as_ref().unwrap().publics.clone();
+                let mut acc: Option<String> = None;
+                let mut dir = String::new();
+                let mut rest = &proofs[..];
+                let mut j = 0;
+                while !rest.is_empty() {
+                    let take = if acc.is_some() { fan_in - 1 } else { fan_in };
+                    let (batch, tail) = rest.split_at(take);
+                    dir = format!("{}/aggregation/{}", ws, j);
+                    let joined = self.file(&dir, &layer.name, "zkin.json");
+                    stage(
+                        format!("aggregation/{}/join", j),
+                        Action::JoinZkin {
+                            zkins: acc
+                                .iter()
+                                .cloned()
+                                .chain(batch.iter().map(|p| p.zkin.clone()))
+                                .collect(),
+                            // the previous fold is a proof of the aggregation circuit itself
+                            agg: if acc.is_some() { vec![0] } else { vec![] },
+                            publics: publics.clone(),
+                            zkin_out: joined.clone(),
+                        },
+                    );
+                    acc = Some(prove(
+                        &mut stage,
+                        &format!("aggregation/{}", j),
+                        &dir,
+                        &joined,
+                    ));
+                    rest = tail;
+                    j += 1;
+                }
+                proofs = vec![Proof {
+                    name: "aggregation".to_string(),
+                    dir,
+                    zkin: acc.unwrap(),
+                }];
+            } else {
+                for p in proofs.iter_mut() {
+                    p.zkin = prove(&mut stage, &p.name, &p.dir, &p.zkin);
+                }
+            }
+        }
+
+        if let Some(snark) = &self.snark {
+            let circuit = self.circuit("snark");
+            let pk = self.file(ws, "snark", "zkey");
+            stage(
+                "snark/compile".to_string(),
+                Action::Compile {
+                    circom: circuit.circom.clone(),
+                    prime: snark.curve.to_lowercase(),
+                    libs: snark.circom_libs.clone(),
+                    output: ws.clone(),
+                },
+            );
+            stage(
+                "snark/setup".to_string(),
+                Action::Groth16Setup {
+                    curve: snark.curve.clone(),
+                    r1cs: circuit.r1cs.clone(),
+                    pk: pk.clone(),
+                    vk: self.file(ws, "snark", "vk.json"),
+                },
+            );
+            for p in proofs.iter() {
+                stage(
+                    format!("{}/snark/prove", p.name),
+                    Action::Groth16Prove {
+                        curve: snark.curve.clone(),
+                        r1cs: circuit.r1cs.clone(),
+                        wasm: circuit.wasm.clone(),
+                        pk: pk.clone(),
+                        zkin: p.zkin.clone(),
+                        public_input: format!("{}/public_input.json", p.dir),
+                        proof: format!("{}/proof.json", p.dir),
+                    },
+                );
+            }
+        }
+        Ok(stages)
+    }
+}
+
+/// The memory ceiling in bytes of a ceiling in MiB
+pub fn memory_limit_bytes(mib: Option<usize>) -> Result<Option<usize>> {
+    mib.map(|mib| {
+        mib.checked_mul(1 << 20).ok_or_else(|| {
+            StarkyError::MalformedInput(format!("the memory limit of {} MiB overflows", mib)).into()
+        })
+    })
+    .transpose()
+}
+
+/// The fingerprint of the stage, keccak256 over the settings, the action and the content of
+/// its inputs
+fn fingerprint(stage: &Stage, settings: &str) -> Result<String> {
+    let mut hasher = Keccak::v256();
+    hasher.update(settings.as_bytes());
+    hasher.update(format!("{:?}", stage.action).as_bytes());
+    let mut buf = vec![0u8; 1 << 20];
+    for input in stage.action.inputs() {
+        let mut f =
+            File::open(&input).with_context(|| format!("{} requires {}", stage.name, input))?;
+        loop {
+            let n = f.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+    }
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Run the stages by `run`, skipping the ones completed with the same inputs and `settings`,
+/// unless `force`. Returns the names of the stages run.
+pub fn run_stages<F: FnMut(&Stage) -> Result<()>>(
+    workspace: &str,
+    stages: &[Stage],
+    settings: &str,
+    force: bool,
+    mut run: F,
+) -> Result<Vec<String>> {
+    let state_file = format!("{}/{}", workspace, STATE_FILE);
+    let mut state: BTreeMap<String, String> = if Path::new(&state_file).exists() && !force {
+        load_json(&state_file)?
+    } else {
+        BTreeMap::new()
+    };
+    let mut done = vec![];
+    for stage in stages.iter() {
+        let fp = fingerprint(stage, settings)?;
+        let completed = state.get(&stage.name) == Some(&fp)
+            && stage.action.outputs().iter().all(|o| Path::new(o).exists());
+        if completed {
+            log::info!("skip {}, completed", stage.name);
+            continue;
+        }
+        log::info!("run {}", stage.name);
+        state.remove(&stage.name);
+        run(stage).with_context(|| format!("stage {}", stage.name))?;
+        state.insert(stage.name.clone(), fp);
+        std::fs::write(&state_file, serde_json::to_string_pretty(&state)?)?;
+        done.push(stage.name.clone());
+    }
+    Ok(done)
+}
+
+/// Run the pipeline of the config file, resuming from the last completed stage unless `force`
+pub fn pipeline(config_file: &str, force: bool) -> Result<()> {
+    let config = load_json::<PipelineConfig>(config_file)?;
+    config.prepare()?;
+    let stages = config.plan()?;
+    let done = run_stages(
+        &config.workspace,
+        &stages,
+        &config.settings(),
+        force,
+        |stage| stage.action.run(&config),
+    )?;
+    log::info!(
+        "pipeline done, {} of {} stages run",
+        done.len(),
+        stages.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(workspace: &str) -> PipelineConfig {
+        serde_json::from_value(serde_json::json!({
+            "workspace": workspace,
+            "name": "fibonacci",
+            "base": {
+                "pil": "../starky/data/fib.pil.json",
+                "const": "../starky/data/fib.const",
+                "cm": ["0.cm", "1.cm", "2.cm"],
+                "stark_struct": "../starky/data/starkStruct.json.gl",
+                "skip_main": true
+            },
+            "layers": [
+                {"name": "c12", "stark_struct": "../starky/data/c12.starkStruct.json"},
+                {
+                    "name": "recursive1",
+                    "stark_struct": "../starky/data/r1.starkStruct.json",
+                    "force_n_bits": 18,
+                    "norm_stage": true,
+                    "agg_stage": true
+                }
+            ],
+            "aggregation": {
+                "name": "recursive2",
+                "stark_struct": "../starky/data/r1.starkStruct.json",
+                "force_n_bits": 18,
+                "norm_stage": true
+            },
+            "final": {"name": "final", "stark_struct": "../starky/data/final.starkStruct.bn128.json"},
+            "snark": {"curve": "BN128"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pipeline_plan() {
+        let ws = "/tmp/test_pipeline_plan";
+        let config = config(ws);
+        config.prepare().unwrap();
+        let stages = config.plan().unwrap();
+        let names: Vec<&str> = stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "base/stark_setup",
+                "0/base/prove",
+                "1/base/prove",
+                "2/base/prove",
+                "c12/compile",
+                "c12/setup",
+                "0/c12/exec",
+                "c12/stark_setup",
+                "0/c12/prove",
+                "1/c12/exec",
+                "1/c12/prove",
+                "2/c12/exec",
+                "2/c12/prove",
+                "recursive1/compile",
+                "recursive1/setup",
+                "0/recursive1/exec",
+                "recursive1/stark_setup",
+                "0/recursive1/prove",
+                "1/recursive1/exec",
+                "1/recursive1/prove",
+                "2/recursive1/exec",
+                "2/recursive1/prove",
+                "recursive2/compile",
+                "recursive2/setup",
+                "aggregation/0/join",
+                "aggregation/0/recursive2/exec",
+                "recursive2/stark_setup",
+                "aggregation/0/recursive2/prove",
+                "aggregation/1/join",
+                "aggregation/1/recursive2/exec",
+                "aggregation/1/recursive2/prove",
+                "final/compile",
+                "final/setup",
+                "aggregation/final/exec",
+                "final/stark_setup",
+                "aggregation/final/prove",
+                "snark/compile",
+                "snark/setup",
+                "aggregation/snark/prove",
+            ]
+        );
+
+        // every input is either given or the output of an earlier stage
+        let mut produced: HashSet<String> = HashSet::new();
+        for s in stages.iter() {
+            for input in s.action.inputs() {
+                assert!(
+                    produced.contains(&input)
+                        || !input.starts_with(ws)
+                        || input.ends_with(".starkStruct.json"),
+                    "{} of {}",
+                    input,
+                    s.name
+                );
+            }
+            produced.extend(s.action.outputs());
+        }
+        // the second aggregation joins the first one, a recursive2 proof, with the third proof
+        let join = stages
+            .iter()
+            .find(|s| s.name == "aggregation/1/join")
+            .unwrap();
+        match &join.action {
+            Action::JoinZkin { zkins, agg, .. } => {
+                assert_eq!(
+                    zkins,
+                    &vec![
+                        format!("{}/aggregation/0/fibonacci.final.zkin.json", ws),
+                        format!("{}/2/fibonacci.recursive2.zkin.json", ws),
+                    ]
+                );
+                assert_eq!(agg, &vec![0]);
+            }
+            a => panic!("{:?}", a),
+        }
+        // the provers load the setup of their layer
+        match &stages[8].action {
+            Action::StarkProve {
+                setup, agg_inputs, ..
+            } => {
+                assert_eq!(setup, &config.setup_file("c12"));
+                assert_eq!(*agg_inputs, 0);
+            }
+            a => panic!("{:?}", a),
+        }
+        match &stages[17].action {
+            Action::StarkProve { agg_inputs, .. } => assert_eq!(*agg_inputs, 2),
+            a => panic!("{:?}", a),
+        }
+        let s: StarkStruct = load_json(&config.stark_struct_file("final")).unwrap();
+        assert_eq!(s.verificationHashType, "BN128");
+    }
+
+    /// Run the plan with fake provers, the recursive1 and recursive2 proofs differ by rootC,
+    /// and return the zkin joined by the last aggregation.
+    fn run_joins(ws: &str, n_proofs: usize, fan_in: usize) -> BTreeMap<String, serde_json::Value> {
+        let _ = std::fs::remove_dir_all(ws);
+        let mut config = config(ws);
+        config.base.cm_pols = (0..n_proofs).map(|i| format!("{}/{}.cm", ws, i)).collect();
+        config.aggregation.as_mut().unwrap().fan_in = fan_in;
+        config.prepare().unwrap();
+        for cm in config.base.cm_pols.iter() {
+            std::fs::write(cm, "").unwrap();
+        }
+        let stages = config.plan().unwrap();
+        let mut last_join = String::new();
+        run_stages(ws, &stages, &config.settings(), false, |s| {
+            for o in s.action.outputs() {
+                std::fs::create_dir_all(Path::new(&o).parent().unwrap())?;
+            }
+            match &s.action {
+                Action::JoinZkin { zkin_out, .. } => {
+                    last_join = zkin_out.clone();
+                    s.action.run(&config)
+                }
+                Action::StarkProve { setup, zkin, .. } => {
+                    let r = if setup.contains("recursive2") { 5 } else { 7 };
+                    let proof =
+                        serde_json::json!({"publics": [1, 2, r, r, r, r], "rootC": [r, r, r, r]});
+                    std::fs::write(zkin, proof.to_string())?;
+                    Ok(std::fs::write(&s.action.outputs()[0], "")?)
+                }
+                _ => {
+                    for o in s.action.outputs() {
+                        std::fs::write(o, "")?;
+                    }
+                    Ok(())
+                }
+            }
+        })
+        .unwrap();
+        load_json(&last_join).unwrap()
+    }
+
+    #[test]
+    fn test_pipeline_run_joins() {
+        // the last fold joins the previous one, a recursive2 proof, with the fourth proof
+        let joined = run_joins("/tmp/test_pipeline_run_joins.2", 4, 2);
+        assert_eq!(joined["rootC"], serde_json::json!([5, 5, 5, 5]));
+        assert_eq!(joined["a_rootC"], serde_json::json!([5, 5, 5, 5]));
+        assert_eq!(joined["b_rootC"], serde_json::json!([7, 7, 7, 7]));
+        assert_eq!(joined["publics"], serde_json::json!([1, 2]));
+
+        let joined = run_joins("/tmp/test_pipeline_run_joins.3", 5, 3);
+        assert_eq!(joined["a_rootC"], serde_json::json!([5, 5, 5, 5]));
+        assert_eq!(joined["c_rootC"], serde_json::json!([7, 7, 7, 7]));
+        assert!(!joined.contains_key("d_rootC"));
+    }
+
+    #[test]
+    fn test_pipeline_invalid_config() {
+        let ws = "/tmp/test_pipeline_invalid_config";
+        // 3 proofs are folded by 3 at once, or by 2 twice
+        let mut c = config(ws);
+        c.aggregation.as_mut().unwrap().fan_in = 3;
+        c.validate().unwrap();
+        for fan_in in [1, 4, 27] {
+            c.aggregation.as_mut().unwrap().fan_in = fan_in;
+            assert!(c.validate().is_err());
+        }
+        // 4 proofs can't be folded by 3
+        c.aggregation.as_mut().unwrap().fan_in = 3;
+        c.base.cm_pols.push("3.cm".to_string());
+        assert!(c.validate().is_err());
+
+        let mut c = config(ws);
+        c.layers[1].agg_stage = false;
+        assert!(c.validate().is_err());
+
+        // the final proof is hashed for BN128, not BLS12381
+        let mut c = config(ws);
+        c.snark.as_mut().unwrap().curve = "BLS12381".to_string();
+        let err = c.prepare().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+
+        let mut c = config(ws);
+        c.layers[0].name = "recursive1".to_string();
+        assert!(c.validate().is_err());
+
+        assert_eq!(memory_limit_bytes(Some(3)).unwrap(), Some(3 << 20));
+        let err = memory_limit_bytes(Some(usize::MAX)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_pipeline_resume() {
+        let ws = "/tmp/test_pipeline_resume";
+        let _ = std::fs::remove_dir_all(ws);
+        std::fs::create_dir_all(ws).unwrap();
+        let input = format!("{}/input.zkin.json", ws);
+        std::fs::write(&input, "{}").unwrap();
+        let join = |i: usize, from: &str| Stage {
+            name: format!("join{}", i),
+            action: Action::JoinZkin {
+                zkins: vec![from.to_string()],
+                agg: vec![],
+                publics: "concat".to_string(),
+                zkin_out: format!("{}/{}.zkin.json", ws, i),
+            },
+        };
+        let stages = vec![join(0, &input), join(1, &format!("{}/0.zkin.json", ws))];
+        let touch = |s: &Stage| -> Result<()> {
+            for o in s.action.outputs() {
+                std::fs::write(o, "{}")?;
+            }
+            Ok(())
+        };
+
+        // the second stage fails, the first one is not run again
+        let mut n = 0;
+        assert!(run_stages(ws, &stages, "", false, |s| {
+            n += 1;
+            if n == 2 {
+                bail!("failed");
+            }
+            touch(s)
+        })
+        .is_err());
+        let done = run_stages(ws, &stages, "", false, touch).unwrap();
+        assert_eq!(done, vec!["join1"]);
+        assert!(run_stages(ws, &stages, "", false, touch)
+            .unwrap()
+            .is_empty());
+
+        // a changed input reruns its stage, a deleted output too
+        std::fs::write(&input, "{\"a\": 1}").unwrap();
+        assert_eq!(
+            run_stages(ws, &stages, "", false, touch).unwrap(),
+            vec!["join0"]
+        );
+        std::fs::remove_file(format!("{}/1.zkin.json", ws)).unwrap();
+        assert_eq!(
+            run_stages(ws, &stages, "", false, touch).unwrap(),
+            vec!["join1"]
+        );
+        assert_eq!(run_stages(ws, &stages, "", true, touch).unwrap().len(), 2);
+
+        // so do changed settings
+        let mut config = config(ws);
+        assert_eq!(
+            run_stages(ws, &stages, &config.settings(), false, touch)
+                .unwrap()
+                .len(),
+            2
+        );
+        let changes: [fn(&mut PipelineConfig); 4] = [
+            |c| c.min_security_bits = Some(100),
+            |c| c.memory_limit = Some(1024),
+            |c| c.prover_addr = "0x0".to_string(),
+            |c| c.witness_backend = WitnessBackend::Native,
+        ];
+        for change in changes {
+            change(&mut config);
+            assert_eq!(
+                run_stages(ws, &stages, &config.settings(), false, touch)
+                    .unwrap()
+                    .len(),
+                2
+            );
+            assert!(run_stages(ws, &stages, &config.settings(), false, touch)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn test_compile_gl_verifier_without_libs() {
+        let ws = "/tmp/test_compile_gl_verifier_without_libs";
+        let _ = std::fs::remove_dir_all(ws);
+        std::fs::create_dir_all(ws).unwrap();
+        let circom = format!("{}/fib.verifier.circom", ws);
+        starky::prove::stark_prove(
+            "../starky/data/starkStruct.json.gl",
+            "../starky/data/fib.pil.json.gl",
+            false,
+            false,
+            0,
+            "../starky/data/fib.const.gl",
+            "../starky/data/fib.cm.gl",
+            None,
+            Some(&circom),
+            &format!("{}/fib.verifier.zkin.json", ws),
+            "",
+            None,
+            Some(0),
+        )
+        .unwrap();
+
+        // the templates of the verifier are all embedded
+        dsl_compile::circom_compiler(
+            circom,
+            "goldilocks".to_string(),
+            "full".to_string(),
+            vec![],
+            ws.to_string(),
+            false,
+            false,
+        )
+        .unwrap();
+        assert!(Path::new(ws).join("fib.verifier.r1cs").exists());
+    }
+}
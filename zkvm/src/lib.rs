@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use powdr::backend::{
     composite::{split, CompositeProof, CompositeVerificationKey},
     BackendType,
@@ -10,10 +10,12 @@ use powdr::riscv::{compile_rust, Runtime};
 use powdr::Pipeline;
 use recursion::pilcom::export as pil_export;
 use starky::{
+    errors::StarkyError,
     merklehash::MerkleTreeGL,
     pil2circom,
+    security::{derive_stark_struct, validate_stark_struct, DEFAULT_SECURITY_BITS},
     stark_setup::StarkSetup,
-    types::{StarkStruct, Step},
+    types::StarkStruct,
 };
 use std::fs::{self, create_dir_all /*, remove_dir_all*/};
 use std::io::{BufWriter, Write};
@@ -22,6 +24,31 @@ use std::time::Instant;
 
 const TEST_CHANNEL: u32 = 1;
 
+/// The eSTARK backend of powdr proves the machines with a blowup factor of 2, the verifier is
+/// generated for a stark struct of the same blowup reaching the required security.
+const POWDR_BLOWUP_BITS: usize = 1;
+
+/// Check the proof has the queries of `params`, fewer queries don't reach its security, and take
+/// the number of queries of the proof, as the verifier must be generated for the same one.
+fn check_proof_queries(proof: &[u8], params: &mut StarkStruct) -> Result<()> {
+    let proof: serde_json::Value = serde_json::from_slice(proof)
+        .map_err(|e| StarkyError::MalformedInput(format!("the powdr proof, {}", e)))?;
+    let n_queries = match proof["s0_vals1"].as_array() {
+        Some(queries) => queries.len(),
+        None => bail!(StarkyError::MalformedInput(
+            "the powdr proof has no s0_vals1".to_string()
+        )),
+    };
+    if n_queries < params.nQueries {
+        bail!(StarkyError::InsufficientSecurity(format!(
+            "the powdr proof has {} queries, {} are required for the security",
+            n_queries, params.nQueries
+        )));
+    }
+    params.nQueries = n_queries;
+    Ok(())
+}
+
 fn generate_witness_and_prove<F: FieldElement>(
     mut pipeline: Pipeline<F>,
 ) -> Result<Pipeline<F>, Vec<String>> {
@@ -71,6 +98,7 @@ fn generate_verifier<F: FieldElement>(
     output_path: &str,
     task: &str,
     chunk_idx: usize,
+    min_security_bits: Option<usize>,
 ) -> Result<Vec<usize>> {
     let buf = Vec::new();
     let mut vw = BufWriter::new(buf);
@@ -108,7 +136,7 @@ fn generate_verifier<F: FieldElement>(
         ));
 
         log::debug!("Running proof generation to {:?}...", proof_file);
-        fs::write(proof_file, machine_proof.proof)?;
+        fs::write(proof_file, &machine_proof.proof)?;
 
         let verifier_file = Path::new(output_path).join(format!(
             "{}_chunk_{}_submachine_{}.circom",
@@ -142,28 +170,18 @@ fn generate_verifier<F: FieldElement>(
 
         assert!(degree > 1);
         let n_bits = (DegreeType::BITS - (degree - 1).leading_zeros()) as usize;
-        let n_bits_ext = n_bits + 1;
-
-        let steps = (2..=n_bits_ext)
-            .rev()
-            .step_by(4)
-            .map(|b| Step { nBits: b })
-            .collect();
-
-        let params = StarkStruct {
-            nBits: n_bits,
-            nBitsExt: n_bits_ext,
-            nQueries: 2,
-            verificationHashType: "GL".to_owned(),
-            steps,
-        };
+        let security_bits = min_security_bits.unwrap_or(DEFAULT_SECURITY_BITS);
+        let mut params = derive_stark_struct(n_bits, POWDR_BLOWUP_BITS, security_bits, "GL", 3)?;
+        // the proof is already generated by powdr, refuse to verify it below the security
+        check_proof_queries(&machine_proof.proof, &mut params)?;
+        validate_stark_struct(&params, min_security_bits)?;
 
         // generate circom
         let opt = pil2circom::StarkOption {
             enable_input: false,
             verkey_input: false,
             skip_main: true,
-            agg_stage: false,
+            agg_inputs: 0,
         };
         if !setup.starkinfo.qs.is_empty() {
             let pil_json = pil_export::<F>(pil);
@@ -308,6 +326,7 @@ pub fn zkvm_prove_only(
     start_of_shutdown_routine: u64,
     i: usize,
     output_path: &str,
+    min_security_bits: Option<usize>,
 ) -> Result<Vec<usize>> {
     log::debug!("Compiling Rust...");
     let asm_file_path = Path::new(output_path).join(format!("{}.asm", task));
@@ -332,7 +351,7 @@ pub fn zkvm_prove_only(
     )
     .unwrap();
 
-    let ids = generate_verifier(pipeline, output_path, task, i)?;
+    let ids = generate_verifier(pipeline, output_path, task, i, min_security_bits)?;
 
     let duration = start.elapsed();
     log::debug!(
@@ -415,6 +434,37 @@ mod tests {
         zkvm_execute_and_prove("evm", suite_json, "/tmp/test_evm").unwrap();
     }
 
+    #[test]
+    fn test_powdr_stark_struct() {
+        let mut params =
+            derive_stark_struct(10, POWDR_BLOWUP_BITS, DEFAULT_SECURITY_BITS, "GL", 3).unwrap();
+        assert_eq!((params.nBitsExt, params.nQueries), (11, 128));
+        let steps = params.steps.iter().map(|s| s.nBits).collect::<Vec<_>>();
+        assert_eq!(steps, vec![11, 7, 3]);
+        validate_stark_struct(&params, None).unwrap();
+
+        let proof = |n: usize| serde_json::json!({ "s0_vals1": vec![[1]; n] }).to_string();
+        // the 2 queries of a default powdr proof are refused
+        let err = check_proof_queries(proof(2).as_bytes(), &mut params).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::InsufficientSecurity(_))
+        ));
+        check_proof_queries(proof(130).as_bytes(), &mut params).unwrap();
+        assert_eq!(params.nQueries, 130);
+        validate_stark_struct(&params, None).unwrap();
+
+        // unless the caller accepts 2 bits of security
+        let mut params = derive_stark_struct(10, POWDR_BLOWUP_BITS, 2, "GL", 3).unwrap();
+        check_proof_queries(proof(2).as_bytes(), &mut params).unwrap();
+        validate_stark_struct(&params, Some(2)).unwrap();
+        let err = check_proof_queries(b"{}", &mut params).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<StarkyError>(),
+            Some(StarkyError::MalformedInput(_))
+        ));
+    }
+
     #[test]
     fn test_zkvm_lr_prove() {
         env_logger::try_init().unwrap_or_default();
@@ -474,6 +524,8 @@ mod tests {
                 start_of_shutdown_routine,
                 i,
                 output_path,
+                // powdr proves with 2 queries
+                Some(2),
             )
             .unwrap();
             log::info!("submachine ids: {:?}", submachine_ids);